@@ -16,12 +16,14 @@ use std::{fmt, io, task};
 use heph::actor::{self, NewActor, SyncContext};
 use heph::actor_ref::ActorRef;
 use heph::supervisor::Supervisor;
-use mio::{event, Interest};
+use mio::{event, Interest, Registry};
 
 use crate::process::ProcessId;
-use crate::spawn::{ActorOptions, AddActorError, FutureOptions, PrivateSpawn, Spawn};
+use crate::spawn::{
+    ActorOptions, AddActorError, FutureOptions, JoinHandle, PrivateSpawn, Spawn, SpawnBlocking,
+};
 use crate::trace::{self, Trace};
-use crate::{shared, RuntimeRef};
+use crate::{metrics, shared, RuntimeRef};
 
 /// Trait to indicate an API needs access to the Heph runtime.
 ///
@@ -38,13 +40,14 @@ use crate::{shared, RuntimeRef};
 pub trait Access: PrivateAccess {}
 
 mod private {
+    use std::sync::Arc;
     use std::time::Instant;
     use std::{io, task};
 
-    use mio::{event, Interest};
+    use mio::{event, Interest, Registry};
 
     use crate::process::ProcessId;
-    use crate::{trace, RuntimeRef};
+    use crate::{shared, trace, RuntimeRef};
 
     /// Actual trait behind [`rt::Access`].
     ///
@@ -66,6 +69,9 @@ mod private {
         where
             S: event::Source + ?Sized;
 
+        /// Returns a clone of the registry used by [`PrivateAccess::register`].
+        fn try_clone_registry(&self) -> io::Result<Registry>;
+
         /// Add a deadline.
         fn add_deadline(&mut self, deadline: Instant);
 
@@ -81,6 +87,9 @@ mod private {
         /// Returns the CPU the thread is bound to, if any.
         fn cpu(&self) -> Option<usize>;
 
+        /// Returns the shared runtime internals.
+        fn shared_internals(&self) -> &Arc<shared::RuntimeInternals>;
+
         /// Start timing an event if tracing is enabled, see [`trace::start`].
         fn start_trace(&self) -> Option<trace::EventTiming>;
 
@@ -162,6 +171,10 @@ impl PrivateAccess for ThreadLocal {
         self.rt.reregister(source, self.pid.into(), interest)
     }
 
+    fn try_clone_registry(&self) -> io::Result<Registry> {
+        self.rt.try_clone_registry()
+    }
+
     fn add_deadline(&mut self, deadline: Instant) {
         self.rt.add_deadline(self.pid, deadline)
     }
@@ -182,6 +195,10 @@ impl PrivateAccess for ThreadLocal {
         self.rt.cpu()
     }
 
+    fn shared_internals(&self) -> &Arc<shared::RuntimeInternals> {
+        &self.rt.internals.shared
+    }
+
     fn start_trace(&self) -> Option<trace::EventTiming> {
         self.rt.start_trace()
     }
@@ -288,12 +305,42 @@ impl ThreadSafe {
     /// Spawn a thread-safe [`Future`].
     ///
     /// See [`RuntimeRef::spawn_future`] for more documentation.
-    pub fn spawn_future<Fut>(&mut self, future: Fut, options: FutureOptions)
+    pub fn spawn_future<Fut>(
+        &mut self,
+        future: Fut,
+        options: FutureOptions,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + Send + std::marker::Sync + 'static,
+        Fut: Future + Send + std::marker::Sync + 'static,
+        Fut::Output: Send,
     {
         self.rt.spawn_future(future, options)
     }
+
+    /// Run the blocking function `f` on the pool of blocking threads.
+    ///
+    /// See [`RuntimeRef::spawn_blocking`] for more documentation.
+    pub fn spawn_blocking<F, T>(&mut self, f: F) -> SpawnBlocking<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        SpawnBlocking::new(self.rt.blocking(), f)
+    }
+
+    /// Returns the registry for user defined metrics.
+    ///
+    /// See [`RuntimeRef::metrics_registry`] for more documentation.
+    pub fn metrics_registry(&self) -> &metrics::Registry {
+        self.rt.metrics_registry()
+    }
+
+    /// Returns a snapshot of the metrics of the runtime.
+    ///
+    /// See [`RuntimeRef::metrics`] for more documentation.
+    pub fn metrics(&self) -> metrics::RuntimeMetrics {
+        metrics::RuntimeMetrics::collect(&self.rt)
+    }
 }
 
 impl Access for ThreadSafe {}
@@ -321,6 +368,10 @@ impl PrivateAccess for ThreadSafe {
         self.rt.reregister(source, self.pid.into(), interest)
     }
 
+    fn try_clone_registry(&self) -> io::Result<Registry> {
+        self.rt.try_clone_registry()
+    }
+
     fn add_deadline(&mut self, deadline: Instant) {
         self.rt.add_deadline(self.pid, deadline)
     }
@@ -341,6 +392,10 @@ impl PrivateAccess for ThreadSafe {
         None
     }
 
+    fn shared_internals(&self) -> &Arc<shared::RuntimeInternals> {
+        &self.rt
+    }
+
     fn start_trace(&self) -> Option<trace::EventTiming> {
         self.rt.start_trace()
     }
@@ -437,9 +492,14 @@ impl Sync {
     /// Spawn a thread-safe [`Future`].
     ///
     /// See [`RuntimeRef::spawn_future`] for more documentation.
-    pub fn spawn_future<Fut>(&mut self, future: Fut, options: FutureOptions)
+    pub fn spawn_future<Fut>(
+        &mut self,
+        future: Fut,
+        options: FutureOptions,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + Send + std::marker::Sync + 'static,
+        Fut: Future + Send + std::marker::Sync + 'static,
+        Fut::Output: Send,
     {
         self.rt.spawn_future(future, options)
     }
@@ -0,0 +1,243 @@
+//! Module with the pool of threads used to run blocking operations.
+//!
+//! Some operations, such as resolving a hostname using `getaddrinfo(3)`, have
+//! no non-blocking alternative. Running them on a worker thread would block all
+//! actors running on that thread, so instead they're run on a separate pool of
+//! threads. The result is returned using a [`Handle`], which is a [`Future`].
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{self, Poll};
+use std::{fmt, io, mem, thread};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use log::{error, trace};
+
+use crate::process::panic_message;
+
+#[cfg(test)]
+#[path = "blocking_tests.rs"]
+mod blocking_tests;
+
+/// Default maximum number of threads in the pool.
+pub(crate) const DEFAULT_MAX_THREADS: usize = 4;
+/// Default maximum number of jobs waiting for a thread.
+pub(crate) const DEFAULT_QUEUE_SIZE: usize = 1024;
+
+/// Function run on the pool.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Pool of threads to run blocking operations on.
+///
+/// Threads are started lazily, up to `max_threads`, when there are no idle
+/// threads available to run a new job.
+///
+/// At most `queue_size` jobs wait for a thread. If the queue is full the job
+/// is kept in the [`Handle`], which adds it to the queue once a thread picks up
+/// a job, applying backpressure to the caller.
+pub(crate) struct Pool {
+    sender: Sender<Job>,
+    receiver: Receiver<Job>,
+    /// Maximum number of threads to start.
+    max_threads: usize,
+    /// Number of threads started.
+    started: AtomicUsize,
+    /// Number of threads waiting for a job.
+    idle: Arc<AtomicUsize>,
+    /// Wakers of the [`Handle`]s waiting for space in the queue.
+    waiting: Arc<Mutex<Vec<task::Waker>>>,
+}
+
+impl Pool {
+    /// Create a new pool with at most `max_threads` threads and `queue_size`
+    /// jobs waiting for a thread.
+    pub(crate) fn new(max_threads: usize, queue_size: usize) -> Pool {
+        debug_assert!(max_threads >= 1);
+        debug_assert!(queue_size >= 1);
+        let (sender, receiver) = bounded(queue_size);
+        Pool {
+            sender,
+            receiver,
+            max_threads,
+            started: AtomicUsize::new(0),
+            idle: Arc::new(AtomicUsize::new(0)),
+            waiting: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Run `f` on the pool, returning a [`Handle`] to retrieve the result.
+    ///
+    /// If the queue is full the job is added once the returned `Handle` is
+    /// polled and there is space in the queue.
+    pub(crate) fn spawn<F, T>(self: &Arc<Self>, f: F) -> Handle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            waker: None,
+        }));
+        let job_shared = shared.clone();
+        let job = Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(f));
+            let mut shared = job_shared.lock().unwrap();
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+        let queued = match self.try_queue(job) {
+            Ok(()) => None,
+            Err(job) => Some((self.clone(), job)),
+        };
+        Handle { queued, shared }
+    }
+
+    /// Try to add `job` to the queue, returning it if the queue is full.
+    fn try_queue(&self, job: Job) -> Result<(), Job> {
+        let res = match self.sender.try_send(job) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(job)) => Err(job),
+            // This can't happen as we're holding the receiving side.
+            Err(TrySendError::Disconnected(_)) => unreachable!(),
+        };
+        // Even if the queue is full we might be able to start another thread
+        // to empty it.
+        self.maybe_start_thread();
+        res
+    }
+
+    /// Start a new thread if no threads are idle and we haven't reached the
+    /// maximum number of threads yet.
+    fn maybe_start_thread(&self) {
+        if self.idle.load(Ordering::Acquire) != 0 {
+            return;
+        }
+
+        let mut started = self.started.load(Ordering::Relaxed);
+        loop {
+            if started >= self.max_threads {
+                return;
+            }
+            match self.started.compare_exchange_weak(
+                started,
+                started + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(s) => started = s,
+            }
+        }
+
+        let receiver = self.receiver.clone();
+        let idle = self.idle.clone();
+        let waiting = self.waiting.clone();
+        let res = thread::Builder::new()
+            .name(format!("Heph blocking {}", started))
+            .spawn(move || run(receiver, idle, waiting));
+        if let Err(err) = res {
+            error!("failed to start blocking thread: {}", err);
+            let _ = self.started.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("max_threads", &self.max_threads)
+            .field("started", &self.started.load(Ordering::Relaxed))
+            .field("idle", &self.idle.load(Ordering::Relaxed))
+            .field("queued", &self.receiver.len())
+            .field("queue_size", &self.receiver.capacity())
+            .finish()
+    }
+}
+
+/// Run jobs received on `receiver` until all senders are dropped.
+fn run(receiver: Receiver<Job>, idle: Arc<AtomicUsize>, waiting: Arc<Mutex<Vec<task::Waker>>>) {
+    trace!("starting blocking thread");
+    loop {
+        let _ = idle.fetch_add(1, Ordering::AcqRel);
+        let job = receiver.recv();
+        let _ = idle.fetch_sub(1, Ordering::AcqRel);
+        match job {
+            Ok(job) => {
+                // Made space in the queue, wake the handles waiting for it.
+                let wakers = mem::take(&mut *waiting.lock().unwrap());
+                wakers.into_iter().for_each(task::Waker::wake);
+                job();
+            }
+            Err(_) => break,
+        }
+    }
+    trace!("stopping blocking thread");
+}
+
+/// State shared between the [`Handle`] and the thread running the job.
+struct Shared<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<task::Waker>,
+}
+
+/// Handle to a job running on the [`Pool`].
+///
+/// The future returns an error if the job panicked.
+pub(crate) struct Handle<T> {
+    /// Job waiting for space in the queue of the pool.
+    queued: Option<(Arc<Pool>, Job)>,
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Future for Handle<T> {
+    type Output = thread::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        if let Some((pool, job)) = self.queued.take() {
+            // Register our waker before trying again, otherwise we could miss
+            // the wake-up if a thread makes space in between.
+            pool.waiting.lock().unwrap().push(ctx.waker().clone());
+            match pool.try_queue(job) {
+                Ok(()) => {}
+                Err(job) => {
+                    self.queued = Some((pool, job));
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                match &mut shared.waker {
+                    Some(waker) if waker.will_wake(ctx.waker()) => {}
+                    waker => *waker = Some(ctx.waker().clone()),
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("queued", &self.queued.is_some())
+            .finish()
+    }
+}
+
+/// Convert the panic payload of a job into an I/O error.
+pub(crate) fn panic_to_io_error(panic: Box<dyn Any + Send>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!("blocking operation panicked: {}", panic_message(&*panic)),
+    )
+}
@@ -0,0 +1,87 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::task::{self, Poll, Wake};
+use std::time::Duration;
+
+use super::Pool;
+
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+#[test]
+fn full_queue_applies_backpressure() {
+    let pool = Arc::new(Pool::new(1, 1));
+    let (sender, receiver) = mpsc::channel::<()>();
+    let (started_sender, started) = mpsc::channel();
+
+    // Occupy the only thread.
+    let mut running = pool.spawn(move || {
+        started_sender.send(()).unwrap();
+        receiver.recv().unwrap();
+        1
+    });
+    started.recv_timeout(Duration::from_secs(1)).unwrap();
+    // Fill the queue.
+    let mut queued = pool.spawn(|| 2);
+    assert!(queued.queued.is_none());
+    // The queue is full, so the handle keeps the job.
+    let mut waiting = pool.spawn(|| 3);
+    assert!(waiting.queued.is_some());
+
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = task::Waker::from(woken.clone());
+    let mut ctx = task::Context::from_waker(&waker);
+    assert!(Pin::new(&mut waiting).poll(&mut ctx).is_pending());
+    assert!(waiting.queued.is_some());
+    assert!(!woken.0.load(Ordering::Acquire));
+
+    // Once the thread picks up the next job there is room in the queue.
+    sender.send(()).unwrap();
+    let mut results = Vec::new();
+    for handle in [&mut running, &mut queued, &mut waiting] {
+        loop {
+            match Pin::new(&mut *handle).poll(&mut ctx) {
+                Poll::Ready(result) => {
+                    results.push(result.unwrap());
+                    break;
+                }
+                Poll::Pending => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+    assert!(woken.0.load(Ordering::Acquire));
+    assert_eq!(results, [1, 2, 3]);
+}
+
+#[test]
+fn dropped_waiting_handle_doesnt_run_job() {
+    let pool = Arc::new(Pool::new(1, 1));
+    let (sender, receiver) = mpsc::channel::<()>();
+    let (started_sender, started) = mpsc::channel();
+    let _running = pool.spawn(move || {
+        started_sender.send(()).unwrap();
+        receiver.recv().unwrap();
+    });
+    started.recv_timeout(Duration::from_secs(1)).unwrap();
+    let _queued = pool.spawn(|| {});
+
+    let ran = Arc::new(AtomicBool::new(false));
+    let r = ran.clone();
+    let waiting = pool.spawn(move || r.store(true, Ordering::Release));
+    assert!(waiting.queued.is_some());
+    drop(waiting);
+
+    sender.send(()).unwrap();
+    // Queue a job after the dropped one to know when the pool is done.
+    let (done_sender, done) = mpsc::channel();
+    let _last = pool.spawn(move || done_sender.send(()).unwrap());
+    let _ = done.recv_timeout(Duration::from_secs(1));
+    assert!(!ran.load(Ordering::Acquire));
+}
@@ -0,0 +1,427 @@
+//! Child processes.
+//!
+//! See [`Command`] to spawn a child process, which returns a [`Child`] that can
+//! be waited on without blocking.
+//!
+//! # Notes
+//!
+//! Child processes are only supported on Linux, as it uses process file
+//! descriptors (see `pidfd_open(2)`).
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::future::Future;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::process;
+use std::task::{self, Poll};
+use std::{fmt, io, ptr};
+
+use heph::actor;
+use mio::unix::SourceFd;
+use mio::Interest;
+
+use crate::{self as rt, pipe, Bound, Signal};
+
+#[doc(no_inline)]
+pub use std::process::{ExitStatus, Stdio};
+
+/// Builder for a child process.
+///
+/// This is a wrapper around [`std::process::Command`], which can also be
+/// converted into this type. The difference is that [`Command::spawn`] returns
+/// a [`Child`] that is [bound] to an actor, and waiting for the child process
+/// to exit doesn't block.
+///
+/// [bound]: crate::Bound
+///
+/// # Examples
+///
+/// ```
+/// #![feature(never_type)]
+///
+/// use heph::actor;
+/// use heph_rt::child::{ChildError, Command, Stdio};
+/// use heph_rt::ThreadLocal;
+///
+/// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> Result<(), ChildError> {
+///     let mut child = Command::new("echo")
+///         .arg("Hello world")
+///         .stdout(Stdio::piped())
+///         .spawn(&mut ctx)?;
+///
+///     let mut output = Vec::with_capacity(128);
+///     let stdout = child.stdout.as_mut().unwrap();
+///     stdout.read(&mut output).await?;
+///     assert_eq!(output, b"Hello world\n");
+///
+///     // Returns an error if the child process doesn't exit successfully.
+///     child.wait_success().await
+/// }
+/// #
+/// # let actor_ref = heph_rt::test::try_spawn_local(
+/// #     heph_rt::test::PanicSupervisor,
+/// #     actor as fn(_) -> _,
+/// #     (),
+/// #     heph_rt::spawn::ActorOptions::default(),
+/// # ).unwrap();
+/// # heph_rt::test::join(&actor_ref, std::time::Duration::from_secs(1)).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Command {
+    inner: process::Command,
+}
+
+impl Command {
+    /// Create a new `Command` for launching `program`.
+    ///
+    /// See [`std::process::Command::new`].
+    pub fn new<S>(program: S) -> Command
+    where
+        S: AsRef<OsStr>,
+    {
+        Command {
+            inner: process::Command::new(program),
+        }
+    }
+
+    /// Add an argument to pass to the program.
+    pub fn arg<S>(mut self, arg: S) -> Command
+    where
+        S: AsRef<OsStr>,
+    {
+        let _ = self.inner.arg(arg);
+        self
+    }
+
+    /// Add multiple arguments to pass to the program.
+    pub fn args<I, S>(mut self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let _ = self.inner.args(args);
+        self
+    }
+
+    /// Set an environment variable for the child process.
+    pub fn env<K, V>(mut self, key: K, value: V) -> Command
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let _ = self.inner.env(key, value);
+        self
+    }
+
+    /// Remove an environment variable for the child process.
+    pub fn env_remove<K>(mut self, key: K) -> Command
+    where
+        K: AsRef<OsStr>,
+    {
+        let _ = self.inner.env_remove(key);
+        self
+    }
+
+    /// Clear all environment variables for the child process.
+    pub fn env_clear(mut self) -> Command {
+        let _ = self.inner.env_clear();
+        self
+    }
+
+    /// Set the working directory for the child process.
+    pub fn current_dir<P>(mut self, dir: P) -> Command
+    where
+        P: AsRef<Path>,
+    {
+        let _ = self.inner.current_dir(dir);
+        self
+    }
+
+    /// Configuration for the child process's standard input.
+    ///
+    /// If set to [`Stdio::piped`] it's available as [`Child::stdin`].
+    pub fn stdin(mut self, cfg: Stdio) -> Command {
+        let _ = self.inner.stdin(cfg);
+        self
+    }
+
+    /// Configuration for the child process's standard output.
+    ///
+    /// If set to [`Stdio::piped`] it's available as [`Child::stdout`].
+    pub fn stdout(mut self, cfg: Stdio) -> Command {
+        let _ = self.inner.stdout(cfg);
+        self
+    }
+
+    /// Configuration for the child process's standard error.
+    ///
+    /// If set to [`Stdio::piped`] it's available as [`Child::stderr`].
+    pub fn stderr(mut self, cfg: Stdio) -> Command {
+        let _ = self.inner.stderr(cfg);
+        self
+    }
+
+    /// Spawn the child process.
+    ///
+    /// The child and its standard I/O pipes (if any) are [bound] to the actor
+    /// that owns `ctx`.
+    ///
+    /// By default the child process inherits the standard I/O of the parent
+    /// process.
+    ///
+    /// [bound]: crate::Bound
+    pub fn spawn<M, RT>(mut self, ctx: &mut actor::Context<M, RT>) -> io::Result<Child>
+    where
+        RT: rt::Access,
+    {
+        let mut child = self.inner.spawn()?;
+        match Child::setup(ctx, &mut child) {
+            Ok((pidfd, stdin, stdout, stderr)) => Ok(Child {
+                inner: child,
+                pidfd,
+                stdin,
+                stdout,
+                stderr,
+            }),
+            Err(err) => {
+                // Don't leave a child process we can't control behind.
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(err)
+            }
+        }
+    }
+}
+
+impl From<process::Command> for Command {
+    fn from(inner: process::Command) -> Command {
+        Command { inner }
+    }
+}
+
+/// A child process.
+///
+/// Created by [`Command::spawn`].
+///
+/// # Notes
+///
+/// Like [`std::process::Child`] the child process is not killed or waited on
+/// when `Child` is dropped.
+#[derive(Debug)]
+pub struct Child {
+    inner: process::Child,
+    /// Process file descriptor, see `pidfd_open(2)`. Becomes readable once the
+    /// child process exits.
+    pidfd: File,
+    /// Writing end of the child's standard input, if piped.
+    pub stdin: Option<pipe::Sender>,
+    /// Reading end of the child's standard output, if piped.
+    pub stdout: Option<pipe::Receiver>,
+    /// Reading end of the child's standard error, if piped.
+    pub stderr: Option<pipe::Receiver>,
+}
+
+impl Child {
+    /// Register the child process and convert the standard I/O into pipes.
+    #[allow(clippy::type_complexity)]
+    fn setup<M, RT>(
+        ctx: &mut actor::Context<M, RT>,
+        child: &mut process::Child,
+    ) -> io::Result<(
+        File,
+        Option<pipe::Sender>,
+        Option<pipe::Receiver>,
+        Option<pipe::Receiver>,
+    )>
+    where
+        RT: rt::Access,
+    {
+        let pidfd = pidfd_open(child.id())?;
+        ctx.runtime()
+            .register(&mut SourceFd(&pidfd.as_raw_fd()), Interest::READABLE)?;
+        let stdin = match child.stdin.take() {
+            Some(stdin) => Some(pipe::Sender::from_child_stdin(ctx, stdin)?),
+            None => None,
+        };
+        let stdout = match child.stdout.take() {
+            Some(stdout) => Some(pipe::Receiver::from_child_stdout(ctx, stdout)?),
+            None => None,
+        };
+        let stderr = match child.stderr.take() {
+            Some(stderr) => Some(pipe::Receiver::from_child_stderr(ctx, stderr)?),
+            None => None,
+        };
+        Ok((pidfd, stdin, stdout, stderr))
+    }
+
+    /// Returns the OS-assigned process identifier of the child process.
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Send `signal` to the child process.
+    ///
+    /// This uses `pidfd_send_signal(2)`, so the signal can't be send to another
+    /// process that reused the process id of the child.
+    pub fn kill(&mut self, signal: Signal) -> io::Result<()> {
+        pidfd_send_signal(self.pidfd.as_raw_fd(), signal.to_signo())
+    }
+
+    /// Forcefully kill the child process, sending it `SIGKILL`.
+    pub fn force_kill(&mut self) -> io::Result<()> {
+        pidfd_send_signal(self.pidfd.as_raw_fd(), libc::SIGKILL)
+    }
+
+    /// Attempt to collect the exit status of the child process, if it has
+    /// exited.
+    ///
+    /// Returns `Ok(None)` if the child process is still running. Most users
+    /// should prefer to use [`Child::wait`].
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.inner.try_wait()
+    }
+
+    /// Wait for the child process to exit, returning its exit status.
+    ///
+    /// This closes the standard input of the child (if piped) before waiting,
+    /// to prevent the child from waiting on input forever.
+    pub fn wait(&mut self) -> Wait<'_> {
+        drop(self.stdin.take());
+        Wait { child: self }
+    }
+
+    /// Wait for the child process to exit, returning an error if it didn't
+    /// exit successfully.
+    ///
+    /// The returned [`ChildError`] is meant to be returned by the actor, so its
+    /// supervisor can decide what to do, e.g. restart the child process.
+    pub fn wait_success(&mut self) -> WaitSuccess<'_> {
+        drop(self.stdin.take());
+        WaitSuccess { child: self }
+    }
+}
+
+impl<RT: rt::Access> Bound<RT> for Child {
+    type Error = io::Error;
+
+    fn bind_to<M>(&mut self, ctx: &mut actor::Context<M, RT>) -> io::Result<()> {
+        ctx.runtime()
+            .reregister(&mut SourceFd(&self.pidfd.as_raw_fd()), Interest::READABLE)?;
+        if let Some(stdin) = self.stdin.as_mut() {
+            stdin.bind_to(ctx)?;
+        }
+        if let Some(stdout) = self.stdout.as_mut() {
+            stdout.bind_to(ctx)?;
+        }
+        if let Some(stderr) = self.stderr.as_mut() {
+            stderr.bind_to(ctx)?;
+        }
+        Ok(())
+    }
+}
+
+/// The [`Future`] behind [`Child::wait`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Wait<'a> {
+    child: &'a mut Child,
+}
+
+impl<'a> Future for Wait<'a> {
+    type Output = io::Result<ExitStatus>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Wait { child } = Pin::into_inner(self);
+        match child.try_wait() {
+            Ok(Some(status)) => Poll::Ready(Ok(status)),
+            // We'll be woken once the process file descriptor becomes
+            // readable, i.e. when the child exits.
+            Ok(None) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+/// The [`Future`] behind [`Child::wait_success`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitSuccess<'a> {
+    child: &'a mut Child,
+}
+
+impl<'a> Future for WaitSuccess<'a> {
+    type Output = Result<(), ChildError>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let WaitSuccess { child } = Pin::into_inner(self);
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => Poll::Ready(Ok(())),
+            Ok(Some(status)) => Poll::Ready(Err(ChildError::Failed(status))),
+            Ok(None) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(ChildError::Io(err))),
+        }
+    }
+}
+
+/// Error returned by [`Child::wait_success`].
+///
+/// This can be returned by an actor to let its supervisor handle a failed
+/// child process.
+#[derive(Debug)]
+pub enum ChildError {
+    /// I/O error, e.g. when waiting on the child process.
+    Io(io::Error),
+    /// The child process exited unsuccessfully, either by returning a non-zero
+    /// exit code or by being killed by a signal.
+    Failed(ExitStatus),
+}
+
+impl From<io::Error> for ChildError {
+    fn from(err: io::Error) -> ChildError {
+        ChildError::Io(err)
+    }
+}
+
+impl fmt::Display for ChildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildError::Io(err) => write!(f, "I/O error with child process: {}", err),
+            ChildError::Failed(status) => write!(f, "child process failed: {}", status),
+        }
+    }
+}
+
+impl Error for ChildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChildError::Io(err) => Some(err),
+            ChildError::Failed(..) => None,
+        }
+    }
+}
+
+/// Call `pidfd_open(2)`.
+fn pidfd_open(pid: u32) -> io::Result<File> {
+    let flags: libc::c_uint = 0;
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, flags) };
+    if fd == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        // Safety: `pidfd_open` returned a valid file descriptor, which we own.
+        Ok(unsafe { File::from_raw_fd(fd as RawFd) })
+    }
+}
+
+/// Call `pidfd_send_signal(2)`.
+fn pidfd_send_signal(pidfd: RawFd, signal: libc::c_int) -> io::Result<()> {
+    let info: *const libc::siginfo_t = ptr::null();
+    let flags: libc::c_uint = 0;
+    if unsafe { libc::syscall(libc::SYS_pidfd_send_signal, pidfd, signal, info, flags) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
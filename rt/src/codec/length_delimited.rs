@@ -0,0 +1,115 @@
+//! Module with the [`LengthDelimited`] codec.
+
+use std::io;
+
+use crate::codec::{frame_too_large, Decoder, Encoder, DEFAULT_MAX_FRAME_LENGTH};
+
+/// Length of the header.
+const HEADER_LENGTH: usize = 4;
+
+/// Codec for frames prefixed with their length.
+///
+/// Each frame is prefixed with the length of the frame (excluding the prefix
+/// itself) as a 32 bit big-endian unsigned integer.
+///
+/// Decodes frames into a `Vec<u8>`, and encodes anything that can be
+/// referenced as a slice of bytes.
+///
+/// # Examples
+///
+/// ```
+/// use heph_rt::codec::{Decoder, Encoder, LengthDelimited};
+///
+/// let mut codec = LengthDelimited::new();
+///
+/// let mut buf = Vec::new();
+/// codec.encode(b"Hello world", &mut buf).unwrap();
+/// assert_eq!(buf, b"\0\0\0\x0BHello world");
+///
+/// let (frame, n) = codec.decode(&buf).unwrap().unwrap();
+/// assert_eq!(frame, b"Hello world");
+/// assert_eq!(n, buf.len());
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct LengthDelimited {
+    max_frame_length: usize,
+}
+
+impl LengthDelimited {
+    /// Create a new `LengthDelimited` codec, with a maximum frame length of
+    /// [`DEFAULT_MAX_FRAME_LENGTH`].
+    pub const fn new() -> LengthDelimited {
+        LengthDelimited {
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Set the maximum length of a frame, excluding the length prefix.
+    ///
+    /// Decoding or encoding a larger frame returns an error with the [kind]
+    /// set to [`ErrorKind::InvalidData`].
+    ///
+    /// # Notes
+    ///
+    /// The maximum length is capped at `u32::MAX`, the maximum length that can
+    /// be encoded.
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::InvalidData`]: io::ErrorKind::InvalidData
+    pub const fn max_frame_length(mut self, max: usize) -> LengthDelimited {
+        self.max_frame_length = if max > u32::MAX as usize {
+            u32::MAX as usize
+        } else {
+            max
+        };
+        self
+    }
+}
+
+impl Default for LengthDelimited {
+    fn default() -> LengthDelimited {
+        LengthDelimited::new()
+    }
+}
+
+impl Decoder for LengthDelimited {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &[u8]) -> io::Result<Option<(Vec<u8>, usize)>> {
+        if buf.len() < HEADER_LENGTH {
+            return Ok(None);
+        }
+
+        let mut header = [0; HEADER_LENGTH];
+        header.copy_from_slice(&buf[..HEADER_LENGTH]);
+        let length = u32::from_be_bytes(header) as usize;
+        if length > self.max_frame_length {
+            return Err(frame_too_large(length, self.max_frame_length));
+        }
+
+        let end = HEADER_LENGTH + length;
+        if buf.len() < end {
+            return Ok(None);
+        }
+        Ok(Some((buf[HEADER_LENGTH..end].to_vec(), end)))
+    }
+}
+
+impl<B> Encoder<B> for LengthDelimited
+where
+    B: AsRef<[u8]>,
+{
+    type Error = io::Error;
+
+    fn encode(&mut self, item: B, buf: &mut Vec<u8>) -> io::Result<()> {
+        let frame = item.as_ref();
+        if frame.len() > self.max_frame_length {
+            return Err(frame_too_large(frame.len(), self.max_frame_length));
+        }
+        buf.reserve(HEADER_LENGTH + frame.len());
+        buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(frame);
+        Ok(())
+    }
+}
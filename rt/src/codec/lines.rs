@@ -0,0 +1,152 @@
+//! Module with the [`Lines`] codec.
+
+use std::{io, str};
+
+use crate::codec::{frame_too_large, Decoder, Encoder, DEFAULT_MAX_FRAME_LENGTH};
+
+/// Codec for lines of UTF-8 text.
+///
+/// Lines are delimited by a new line (`\n`), optionally preceded by a carriage
+/// return (`\r`). The delimiter is not part of the decoded line. If the last
+/// line of the stream isn't terminated by a new line it's still returned.
+///
+/// Decodes lines into a `String`, and encodes anything that can be referenced
+/// as a `str`, adding a new line. Encoding a line that contains a new line
+/// returns an error with the [kind] set to [`ErrorKind::InvalidInput`].
+///
+/// [kind]: io::Error::kind
+/// [`ErrorKind::InvalidInput`]: io::ErrorKind::InvalidInput
+///
+/// # Examples
+///
+/// ```
+/// use heph_rt::codec::{Decoder, Encoder, Lines};
+///
+/// let mut codec = Lines::new();
+///
+/// let mut buf = Vec::new();
+/// codec.encode("Hello world", &mut buf).unwrap();
+/// assert_eq!(buf, b"Hello world\n");
+///
+/// let (line, n) = codec.decode(b"Hello\r\nworld").unwrap().unwrap();
+/// assert_eq!(line, "Hello");
+/// assert_eq!(n, 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lines {
+    max_line_length: usize,
+    /// Number of bytes at the start of the buffer already searched for a new
+    /// line, so we don't search the same bytes again when more are read.
+    next_index: usize,
+}
+
+impl Lines {
+    /// Create a new `Lines` codec, with a maximum line length of
+    /// [`DEFAULT_MAX_FRAME_LENGTH`].
+    pub const fn new() -> Lines {
+        Lines {
+            max_line_length: DEFAULT_MAX_FRAME_LENGTH,
+            next_index: 0,
+        }
+    }
+
+    /// Set the maximum length of a line, excluding the delimiter.
+    ///
+    /// Decoding or encoding a longer line returns an error with the [kind] set
+    /// to [`ErrorKind::InvalidData`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::InvalidData`]: io::ErrorKind::InvalidData
+    pub const fn max_line_length(mut self, max: usize) -> Lines {
+        self.max_line_length = max;
+        self
+    }
+}
+
+impl Default for Lines {
+    fn default() -> Lines {
+        Lines::new()
+    }
+}
+
+/// Convert `line` into a `String`, removing a trailing carriage return.
+fn to_string(line: &[u8]) -> io::Result<String> {
+    let line = match line.last() {
+        Some(b'\r') => &line[..line.len() - 1],
+        _ => line,
+    };
+    match str::from_utf8(line) {
+        Ok(line) => Ok(line.to_owned()),
+        Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+    }
+}
+
+impl Decoder for Lines {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &[u8]) -> io::Result<Option<(String, usize)>> {
+        // Allow for the carriage return and new line.
+        let end = buf.len().min(self.max_line_length + 2);
+        let start = self.next_index.min(end);
+        match buf[start..end].iter().position(|b| *b == b'\n') {
+            Some(idx) => {
+                self.next_index = 0;
+                let idx = start + idx;
+                let line = to_string(&buf[..idx])?;
+                if line.len() > self.max_line_length {
+                    return Err(frame_too_large(line.len(), self.max_line_length));
+                }
+                Ok(Some((line, idx + 1)))
+            }
+            None if buf.len() > self.max_line_length + 1 => {
+                self.next_index = 0;
+                Err(frame_too_large(buf.len(), self.max_line_length))
+            }
+            None => {
+                self.next_index = end;
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &[u8]) -> io::Result<Option<(String, usize)>> {
+        match self.decode(buf)? {
+            Some(line) => Ok(Some(line)),
+            None if buf.is_empty() => Ok(None),
+            // Last line without a new line.
+            None => {
+                self.next_index = 0;
+                let line = to_string(buf)?;
+                if line.len() > self.max_line_length {
+                    return Err(frame_too_large(line.len(), self.max_line_length));
+                }
+                Ok(Some((line, buf.len())))
+            }
+        }
+    }
+}
+
+impl<S> Encoder<S> for Lines
+where
+    S: AsRef<str>,
+{
+    type Error = io::Error;
+
+    fn encode(&mut self, item: S, buf: &mut Vec<u8>) -> io::Result<()> {
+        let line = item.as_ref();
+        if line.len() > self.max_line_length {
+            return Err(frame_too_large(line.len(), self.max_line_length));
+        }
+        if line.contains('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line contains a new line",
+            ));
+        }
+        buf.reserve(line.len() + 1);
+        buf.extend_from_slice(line.as_bytes());
+        buf.push(b'\n');
+        Ok(())
+    }
+}
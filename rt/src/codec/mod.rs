@@ -0,0 +1,443 @@
+//! Framing of byte streams.
+//!
+//! Most protocols send messages, or frames, over a stream of bytes. This module
+//! provides the [`Framed`] type, which handles the buffering of the bytes and
+//! uses a codec to convert the bytes into frames, and frames into bytes.
+//!
+//! A codec consists of two traits:
+//!  * [`Decoder`] to decode frames from bytes, and
+//!  * [`Encoder`] to encode frames into bytes.
+//!
+//! The following codecs are provided:
+//!  * [`LengthDelimited`]: frames prefixed with their length as a 32 bit
+//!    big-endian integer.
+//!  * [`Lines`]: UTF-8 lines delimited by a new line (`\n`).
+//!  * [`Varint`]: frames prefixed with their length as a variable length
+//!    integer (the same encoding as used by Protocol Buffers).
+//!
+//! All provided codecs limit the maximum size of a frame, protecting against
+//! a peer sending a (possibly malicious) large frame.
+//!
+//! `Framed` can be used with any type that implements [`TryRead`] and/or
+//! [`TryWrite`], e.g. [`TcpStream`] or the [`pipe`] types.
+//!
+//! [`TcpStream`]: crate::net::TcpStream
+//! [`pipe`]: crate::pipe
+//!
+//! # Examples
+//!
+//! An actor that echos lines back to the peer.
+//!
+//! ```
+//! #![feature(never_type)]
+//!
+//! use std::io;
+//!
+//! use heph::actor;
+//! use heph_rt::codec::{Framed, Lines};
+//! use heph_rt::net::TcpStream;
+//! use heph_rt::ThreadLocal;
+//!
+//! async fn echo_lines(_: actor::Context<!, ThreadLocal>, stream: TcpStream) -> io::Result<()> {
+//!     let mut framed = Framed::new(stream, Lines::new());
+//!     while let Some(line) = framed.read_frame().await? {
+//!         framed.send(line).await?;
+//!     }
+//!     Ok(())
+//! }
+//! #
+//! # drop(echo_lines); // Silent dead code warnings.
+//! ```
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+use crate::bytes::Bytes;
+use crate::net::tcp::stream::{ReadHalf, WriteHalf};
+use crate::net::TcpStream;
+use crate::pipe;
+
+mod length_delimited;
+mod lines;
+mod varint;
+
+pub use length_delimited::LengthDelimited;
+pub use lines::Lines;
+pub use varint::Varint;
+
+/// Default maximum length of a frame used by the provided codecs, 8 MB.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Minimum number of bytes read from the underlying I/O source at a time.
+const MIN_READ_SIZE: usize = 4 * 1024;
+
+/// Decoding of frames from bytes.
+pub trait Decoder {
+    /// The decoded frame.
+    type Item;
+    /// Error returned when decoding fails.
+    ///
+    /// Must be convertible from an [`io::Error`] as I/O errors are returned
+    /// using the same type by [`Framed`].
+    type Error: From<io::Error>;
+
+    /// Attempt to decode a frame from `buf`.
+    ///
+    /// If `buf` contains a complete frame this returns the frame and the number
+    /// of bytes used by the frame, which will be removed from the buffer. If
+    /// `buf` doesn't contain a complete frame (yet) this should return
+    /// `Ok(None)`, in which case more bytes are read and this is called again.
+    /// In that case `buf` starts with the same bytes as before, which allows
+    /// the decoder to keep track of the bytes it already processed.
+    fn decode(&mut self, buf: &[u8]) -> Result<Option<(Self::Item, usize)>, Self::Error>;
+
+    /// Attempt to decode a frame from `buf` after the end of the stream has
+    /// been reached.
+    ///
+    /// This is called instead of [`Decoder::decode`] once no more bytes can be
+    /// read. The default implementation calls `decode` and returns an
+    /// [`io::ErrorKind::UnexpectedEof`] error if there are remaining bytes
+    /// which don't form a complete frame.
+    fn decode_eof(&mut self, buf: &[u8]) -> Result<Option<(Self::Item, usize)>, Self::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.is_empty() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes remaining on stream that don't form a complete frame",
+            )
+            .into()),
+        }
+    }
+}
+
+/// Encoding of frames into bytes.
+pub trait Encoder<Item> {
+    /// Error returned when encoding fails.
+    ///
+    /// Must be convertible from an [`io::Error`] as I/O errors are returned
+    /// using the same type by [`Framed`].
+    type Error: From<io::Error>;
+
+    /// Encode `item` appending the bytes to `buf`.
+    fn encode(&mut self, item: Item, buf: &mut Vec<u8>) -> Result<(), Self::Error>;
+}
+
+/// I/O source that can be read from without blocking.
+pub trait TryRead {
+    /// Attempt to read bytes into `buf`, returning the number of bytes read.
+    ///
+    /// If no bytes can currently be read this must return an error with the
+    /// [kind] set to [`ErrorKind::WouldBlock`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    fn try_read<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes;
+}
+
+/// I/O sink that can be written to without blocking.
+pub trait TryWrite {
+    /// Attempt to write the bytes in `buf`, returning the number of bytes
+    /// written.
+    ///
+    /// If no bytes can currently be written this must return an error with the
+    /// [kind] set to [`ErrorKind::WouldBlock`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize>;
+}
+
+impl TryRead for TcpStream {
+    fn try_read<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        self.try_recv(buf)
+    }
+}
+
+impl TryWrite for TcpStream {
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.try_send(buf)
+    }
+}
+
+impl TryRead for ReadHalf {
+    fn try_read<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        self.try_recv(buf)
+    }
+}
+
+impl TryWrite for WriteHalf {
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.try_send(buf)
+    }
+}
+
+impl TryRead for pipe::Receiver {
+    fn try_read<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        pipe::Receiver::try_read(self, buf)
+    }
+}
+
+impl TryWrite for pipe::Sender {
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        pipe::Sender::try_write(self, buf)
+    }
+}
+
+/// Wrapper around an I/O source and/or sink that reads and writes frames
+/// using a codec.
+///
+/// See the [module documentation] for more information.
+///
+/// [module documentation]: crate::codec
+#[derive(Debug)]
+pub struct Framed<IO, C> {
+    io: IO,
+    codec: C,
+    /// Bytes read, but not yet decoded, start at `read_pos`.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    /// Set once the I/O source returned zero bytes.
+    eof: bool,
+    /// Encoded bytes not yet written.
+    write_buf: Vec<u8>,
+}
+
+impl<IO, C> Framed<IO, C> {
+    /// Create a new `Framed`.
+    pub const fn new(io: IO, codec: C) -> Framed<IO, C> {
+        Framed {
+            io,
+            codec,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            eof: false,
+            write_buf: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying I/O source.
+    pub const fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying I/O source.
+    ///
+    /// # Notes
+    ///
+    /// Reading from or writing to the I/O source directly will likely corrupt
+    /// the stream of frames.
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Returns a reference to the codec.
+    pub const fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Returns the bytes read but not yet decoded.
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.read_buf[self.read_pos..]
+    }
+
+    /// Returns the underlying I/O source and codec.
+    ///
+    /// Any bytes read but not yet decoded, or encoded but not yet written,
+    /// are lost. Use [`Framed::read_buffer`] and [`Framed::flush`] to retrieve
+    /// or write them first.
+    pub fn into_parts(self) -> (IO, C) {
+        (self.io, self.codec)
+    }
+
+    /// Read the next frame.
+    ///
+    /// Returns `Ok(None)` once the end of the stream is reached and all frames
+    /// are decoded.
+    pub fn read_frame<'a>(&'a mut self) -> ReadFrame<'a, IO, C>
+    where
+        IO: TryRead,
+        C: Decoder,
+    {
+        ReadFrame { framed: self }
+    }
+
+    /// Encode `item` into the write buffer, without writing it.
+    ///
+    /// Use [`Framed::flush`] to write the buffered frames.
+    pub fn feed<Item>(&mut self, item: Item) -> Result<(), <C as Encoder<Item>>::Error>
+    where
+        C: Encoder<Item>,
+    {
+        self.codec.encode(item, &mut self.write_buf)
+    }
+
+    /// Encode and write `item`.
+    ///
+    /// This also writes any previously [fed] frames.
+    ///
+    /// [fed]: Framed::feed
+    pub fn send<'a, Item>(&'a mut self, item: Item) -> Flush<'a, IO, C, <C as Encoder<Item>>::Error>
+    where
+        IO: TryWrite,
+        C: Encoder<Item>,
+    {
+        let error = self.feed(item).err();
+        Flush {
+            framed: self,
+            error,
+        }
+    }
+
+    /// Write all frames in the write buffer.
+    pub fn flush<'a>(&'a mut self) -> Flush<'a, IO, C, io::Error>
+    where
+        IO: TryWrite,
+    {
+        Flush {
+            framed: self,
+            error: None,
+        }
+    }
+
+    /// Attempt to decode a frame from the read buffer.
+    fn decode(&mut self) -> Result<Option<C::Item>, C::Error>
+    where
+        C: Decoder,
+    {
+        let buf = &self.read_buf[self.read_pos..];
+        let res = if self.eof {
+            self.codec.decode_eof(buf)?
+        } else {
+            self.codec.decode(buf)?
+        };
+        match res {
+            Some((item, n)) => {
+                debug_assert!(n <= buf.len(), "decoder consumed more bytes than available");
+                self.read_pos += n;
+                if self.read_pos == self.read_buf.len() {
+                    self.read_buf.clear();
+                    self.read_pos = 0;
+                }
+                Ok(Some(item))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Ensure the read buffer has at least `MIN_READ_SIZE` spare capacity,
+    /// removing already decoded bytes if possible.
+    fn reserve_read_buf(&mut self) {
+        if self.read_buf.capacity() - self.read_buf.len() >= MIN_READ_SIZE {
+            return;
+        }
+        if self.read_pos != 0 {
+            drop(self.read_buf.drain(..self.read_pos));
+            self.read_pos = 0;
+        }
+        self.read_buf.reserve(MIN_READ_SIZE);
+    }
+}
+
+/// The [`Future`] behind [`Framed::read_frame`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadFrame<'a, IO, C> {
+    framed: &'a mut Framed<IO, C>,
+}
+
+impl<'a, IO, C> Future for ReadFrame<'a, IO, C>
+where
+    IO: TryRead,
+    C: Decoder,
+{
+    type Output = Result<Option<C::Item>, C::Error>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let ReadFrame { framed } = Pin::into_inner(self);
+        loop {
+            // First try to decode a frame from the bytes we already have.
+            match framed.decode() {
+                Ok(Some(item)) => return Poll::Ready(Ok(Some(item))),
+                Ok(None) if framed.eof => return Poll::Ready(Ok(None)),
+                Ok(None) => {}
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+
+            // Need more bytes.
+            framed.reserve_read_buf();
+            match framed.io.try_read(&mut framed.read_buf) {
+                Ok(0) => framed.eof = true,
+                Ok(_) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Poll::Ready(Err(err.into())),
+            }
+        }
+    }
+}
+
+/// The [`Future`] behind [`Framed::send`] and [`Framed::flush`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Flush<'a, IO, C, E> {
+    framed: &'a mut Framed<IO, C>,
+    /// Error returned by encoding the frame, if any.
+    error: Option<E>,
+}
+
+impl<'a, IO, C, E> Future for Flush<'a, IO, C, E>
+where
+    IO: TryWrite,
+    E: From<io::Error> + Unpin,
+{
+    type Output = Result<(), E>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Flush { framed, error } = Pin::into_inner(self);
+        if let Some(err) = error.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        while !framed.write_buf.is_empty() {
+            match framed.io.try_write(&framed.write_buf) {
+                Ok(0) => return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into())),
+                Ok(n) => drop(framed.write_buf.drain(..n)),
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Poll::Ready(Err(err.into())),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Returns an error indicating a frame of `length` bytes is larger than the
+/// allowed maximum.
+fn frame_too_large(length: usize, max: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "frame of {} bytes exceeds the maximum frame length of {} bytes",
+            length, max
+        ),
+    )
+}
@@ -0,0 +1,137 @@
+//! Module with the [`Varint`] codec.
+
+use std::io;
+
+use crate::codec::{frame_too_large, Decoder, Encoder, DEFAULT_MAX_FRAME_LENGTH};
+
+/// Maximum length of an encoded `u64`.
+const MAX_VARINT_LENGTH: usize = 10;
+
+/// Codec for frames prefixed with their length as a variable length integer.
+///
+/// Each frame is prefixed with the length of the frame (excluding the prefix
+/// itself) encoded as an unsigned LEB128 integer, the same encoding as used by
+/// Protocol Buffers for length-delimited messages.
+///
+/// Decodes frames into a `Vec<u8>`, and encodes anything that can be
+/// referenced as a slice of bytes.
+///
+/// # Examples
+///
+/// ```
+/// use heph_rt::codec::{Decoder, Encoder, Varint};
+///
+/// let mut codec = Varint::new();
+///
+/// let frame = vec![1; 300];
+/// let mut buf = Vec::new();
+/// codec.encode(&frame, &mut buf).unwrap();
+/// assert_eq!(&buf[..2], &[0b1010_1100, 0b0000_0010]);
+///
+/// let (decoded, n) = codec.decode(&buf).unwrap().unwrap();
+/// assert_eq!(decoded, frame);
+/// assert_eq!(n, 302);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Varint {
+    max_frame_length: usize,
+}
+
+impl Varint {
+    /// Create a new `Varint` codec, with a maximum frame length of
+    /// [`DEFAULT_MAX_FRAME_LENGTH`].
+    pub const fn new() -> Varint {
+        Varint {
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Set the maximum length of a frame, excluding the length prefix.
+    ///
+    /// Decoding or encoding a larger frame returns an error with the [kind]
+    /// set to [`ErrorKind::InvalidData`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::InvalidData`]: io::ErrorKind::InvalidData
+    pub const fn max_frame_length(mut self, max: usize) -> Varint {
+        self.max_frame_length = max;
+        self
+    }
+}
+
+impl Default for Varint {
+    fn default() -> Varint {
+        Varint::new()
+    }
+}
+
+/// Decode a variable length integer from `buf`, returning the value and the
+/// number of bytes used.
+fn decode_varint(buf: &[u8]) -> io::Result<Option<(u64, usize)>> {
+    let mut value: u64 = 0;
+    for (i, byte) in buf.iter().take(MAX_VARINT_LENGTH).enumerate() {
+        let bits = u64::from(byte & 0x7F);
+        if i == MAX_VARINT_LENGTH - 1 && bits > 1 {
+            break; // Overflow.
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+
+    if buf.len() < MAX_VARINT_LENGTH {
+        Ok(None)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid variable length integer",
+        ))
+    }
+}
+
+impl Decoder for Varint {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &[u8]) -> io::Result<Option<(Vec<u8>, usize)>> {
+        let (length, header_length) = match decode_varint(buf)? {
+            Some(res) => res,
+            None => return Ok(None),
+        };
+        // NOTE: we only support 64 bit architectures, so this cast is lossless.
+        let length = length as usize;
+        if length > self.max_frame_length {
+            return Err(frame_too_large(length, self.max_frame_length));
+        }
+
+        let end = header_length + length;
+        if buf.len() < end {
+            return Ok(None);
+        }
+        Ok(Some((buf[header_length..end].to_vec(), end)))
+    }
+}
+
+impl<B> Encoder<B> for Varint
+where
+    B: AsRef<[u8]>,
+{
+    type Error = io::Error;
+
+    fn encode(&mut self, item: B, buf: &mut Vec<u8>) -> io::Result<()> {
+        let frame = item.as_ref();
+        if frame.len() > self.max_frame_length {
+            return Err(frame_too_large(frame.len(), self.max_frame_length));
+        }
+        buf.reserve(MAX_VARINT_LENGTH + frame.len());
+        let mut length = frame.len() as u64;
+        while length >= 0x80 {
+            buf.push((length as u8 & 0x7F) | 0x80);
+            length >>= 7;
+        }
+        buf.push(length as u8);
+        buf.extend_from_slice(frame);
+        Ok(())
+    }
+}
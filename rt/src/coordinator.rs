@@ -12,32 +12,48 @@
 //! * A (sync) worker thread stopping because all actors have finished running,
 //!   the worker hit an error or the thread panicked.
 //!
+//! If the watchdog is enabled (see [`Setup::watchdog`]) the coordinator also
+//! periodically checks that the worker threads are making progress.
+//!
 //! [worker threads]: crate::worker
 //! [sync worker threads]: crate::sync_worker
+//! [`Setup::watchdog`]: crate::Setup::watchdog
 
+use std::backtrace::Backtrace;
 use std::env::consts::ARCH;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::os::unix::net::UnixDatagram;
 use std::os::unix::process::parent_id;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
-use std::{fmt, io, process};
+use std::time::{Duration, Instant};
+use std::{fmt, io, mem, process, ptr};
 
-use heph::actor_ref::{ActorGroup, Delivery};
-use log::{as_debug, as_display, debug, error, info, trace};
+use log::{as_debug, as_display, debug, error, info, trace, warn};
 use mio::event::Event;
 use mio::{Events, Interest, Poll, Registry, Token};
-use mio_signals::{SignalSet, Signals};
 
-use crate::setup::{host_id, host_info, Uuid};
+use crate::setup::set_thread_cpu_affinity;
 use crate::shared::waker;
+use crate::signal::{self, Signals};
 use crate::thread_waker::ThreadWaker;
 use crate::trace;
 use crate::{
-    self as rt, cpu_usage, shared, worker, Signal, SyncWorker, SYNC_WORKER_ID_END,
-    SYNC_WORKER_ID_START,
+    self as rt, blocking, cpu_usage, shared, worker, CpuSet, Signal, SignalSet, SyncWorker,
+    SYNC_WORKER_ID_END, SYNC_WORKER_ID_START,
 };
 
+#[cfg(test)]
+#[path = "coordinator_tests.rs"]
+mod coordinator_tests;
+
 /// Token used to receive process signals.
 const SIGNAL: Token = Token(usize::MAX);
+/// Token used by [`Shutdown`] to shut down the runtime.
+const SHUTDOWN: Token = Token(usize::MAX - 1);
+/// Interval at which the shutdown message is resend to the worker threads that
+/// haven't stopped yet.
+const SHUTDOWN_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Coordinator responsible for coordinating the Heph runtime.
 #[derive(Debug)]
@@ -47,20 +63,19 @@ pub(super) struct Coordinator {
     poll: Poll,
     /// Process signal notifications.
     signals: Signals,
+    /// The signals `signals` receives.
+    signal_set: SignalSet,
     /// Internals shared between the coordinator and all workers.
     internals: Arc<shared::RuntimeInternals>,
-
-    // Data used in [`Metrics`].
-    /// Start time, used to calculate [`Metrics`]'s uptime.
-    start: Instant,
-    /// Name of the application.
-    app_name: Box<str>,
-    /// OS name and version, from `uname(2)`.
-    host_os: Box<str>,
-    /// Name of the host. `nodename` field from `uname(2)`.
-    host_name: Box<str>,
-    /// Id of the host.
-    host_id: Uuid,
+    /// CPUs to run the coordinator on, set when the coordinator starts running.
+    cpus: Option<CpuSet>,
+    /// Watchdog for the worker threads, if enabled.
+    watchdog: Option<Watchdog>,
+    /// Waker used by [`Shutdown`], see [`Coordinator::shutdown_handle`].
+    ///
+    /// We keep a reference to the waker to ensure the file descriptor isn't
+    /// closed before we've received the event, which would remove the event.
+    shutdown_waker: Option<Arc<mio::Waker>>,
 }
 
 impl Coordinator {
@@ -70,33 +85,49 @@ impl Coordinator {
     ///
     /// This must be called before creating the worker threads to properly catch
     /// process signals.
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn init(
-        app_name: Box<str>,
+        host_info: shared::HostInfo,
         worker_wakers: Box<[&'static ThreadWaker]>,
+        cpus: Option<CpuSet>,
+        blocking: blocking::Pool,
+        slow_poll_threshold: Option<Duration>,
+        watchdog: Option<(Duration, bool)>,
+        watchdog_backtraces: bool,
+        signal_set: SignalSet,
         trace_log: Option<Arc<trace::SharedLog>>,
     ) -> io::Result<Coordinator> {
         let poll = Poll::new()?;
         // NOTE: on Linux this MUST be created before starting the worker
         // threads.
-        let signals = setup_signals(poll.registry())?;
+        let signals = setup_signals(poll.registry(), signal_set)?;
 
         let setup = shared::RuntimeInternals::setup()?;
         let internals = Arc::new_cyclic(|shared_internals| {
             let waker_id = waker::init(shared_internals.clone());
-            setup.complete(waker_id, worker_wakers, trace_log)
+            setup.complete(
+                waker_id,
+                worker_wakers,
+                blocking,
+                slow_poll_threshold,
+                watchdog.is_some(),
+                trace_log,
+                host_info,
+            )
         });
+        let watchdog = match watchdog {
+            Some((timeout, abort)) => Some(Watchdog::new(timeout, abort, watchdog_backtraces)?),
+            None => None,
+        };
 
-        let (host_os, host_name) = host_info()?;
-        let host_id = host_id()?;
         Ok(Coordinator {
-            host_os,
-            host_name,
-            host_id,
-            app_name,
             poll,
             signals,
+            signal_set,
             internals,
-            start: Instant::now(),
+            cpus,
+            watchdog,
+            shutdown_waker: None,
         })
     }
 
@@ -105,6 +136,19 @@ impl Coordinator {
         &self.internals
     }
 
+    /// Create a [`Shutdown`] handle.
+    ///
+    /// # Notes
+    ///
+    /// This can only be called once.
+    pub(super) fn shutdown_handle(&mut self) -> Result<Shutdown, rt::Error> {
+        let waker = mio::Waker::new(self.poll.registry(), SHUTDOWN)
+            .map_err(|err| rt::Error::coordinator(Error::RegisteringShutdown(err)))?;
+        let waker = Arc::new(waker);
+        self.shutdown_waker = Some(waker.clone());
+        Ok(Shutdown { waker })
+    }
+
     /// Run the coordinator.
     ///
     /// # Notes
@@ -114,20 +158,52 @@ impl Coordinator {
         mut self,
         mut workers: Vec<worker::Handle>,
         mut sync_workers: Vec<SyncWorker>,
-        mut signal_refs: ActorGroup<Signal>,
+        mut signal_refs: signal::Receivers,
         mut trace_log: Option<trace::CoordinatorLog>,
     ) -> Result<(), rt::Error> {
         self.pre_run(&mut workers, &mut sync_workers, &mut trace_log)?;
 
         let mut events = Events::with_capacity(16);
+        let watchdog_interval = self.watchdog.as_ref().map(Watchdog::interval);
+        // Whether or not we're shutting down, see `Shutdown`.
+        let mut shutting_down = false;
         loop {
+            let timeout = if shutting_down {
+                Some(watchdog_interval.map_or(SHUTDOWN_INTERVAL, |i| i.min(SHUTDOWN_INTERVAL)))
+            } else {
+                watchdog_interval
+            };
+
             let timing = trace::start(&trace_log);
             // Process OS events.
-            self.poll
-                .poll(&mut events, None)
-                .map_err(|err| rt::Error::coordinator(Error::Polling(err)))?;
+            match self.poll.poll(&mut events, timeout) {
+                Ok(()) => {}
+                // Interrupted by a signal (`epoll_wait` is never restarted),
+                // we'll handle any events in the next poll.
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => events.clear(),
+                Err(err) => return Err(rt::Error::coordinator(Error::Polling(err))),
+            }
             trace::finish_rt(trace_log.as_mut(), timing, "Polling for OS events", &[]);
 
+            if shutting_down && events.is_empty() {
+                // Not all workers stopped yet, keep sending them the shutdown
+                // message until they do.
+                let timing = trace::start(&trace_log);
+                shutdown(&mut workers, &mut sync_workers);
+                trace::finish_rt(
+                    trace_log.as_mut(),
+                    timing,
+                    "Resending shutdown to worker threads",
+                    &[],
+                );
+            }
+
+            if let Some(watchdog) = self.watchdog.as_mut() {
+                let timing = trace::start(&trace_log);
+                watchdog.check(&self.internals, &workers);
+                trace::finish_rt(trace_log.as_mut(), timing, "Checking worker threads", &[]);
+            }
+
             let timing = trace::start(&trace_log);
             for event in events.iter() {
                 trace!("got OS event: {:?}", event);
@@ -135,8 +211,12 @@ impl Coordinator {
                 match event.token() {
                     SIGNAL => {
                         let timing = trace::start(&trace_log);
-                        let log_metrics =
-                            relay_signals(&mut self.signals, &mut workers, &mut signal_refs);
+                        let log_metrics = relay_signals(
+                            &mut self.signals,
+                            &mut workers,
+                            &mut signal_refs,
+                            trace_log.as_ref(),
+                        );
                         trace::finish_rt(
                             trace_log.as_mut(),
                             timing,
@@ -147,6 +227,17 @@ impl Coordinator {
                             self.log_metrics(&workers, &sync_workers, &signal_refs, &mut trace_log);
                         }
                     }
+                    SHUTDOWN => {
+                        let timing = trace::start(&trace_log);
+                        shutting_down = true;
+                        shutdown(&mut workers, &mut sync_workers);
+                        trace::finish_rt(
+                            trace_log.as_mut(),
+                            timing,
+                            "Shutting down the runtime",
+                            &[],
+                        );
+                    }
                     token if token.0 < SYNC_WORKER_ID_START => {
                         let timing = trace::start(&trace_log);
                         handle_worker_event(&mut workers, event)?;
@@ -191,6 +282,13 @@ impl Coordinator {
         debug_assert!(workers.is_sorted_by_key(worker::Handle::id));
         debug_assert!(sync_workers.is_sorted_by_key(SyncWorker::id));
 
+        if let Some(cpus) = &self.cpus {
+            match set_thread_cpu_affinity(cpus) {
+                Ok(()) => debug!(cpus = as_debug!(cpus); "coordinator CPU affinity set"),
+                Err(err) => warn!("failed to set CPU affinity of the coordinator: {}", err),
+            }
+        }
+
         // Register various sources of OS events that need to wake us from
         // polling events.
         let timing = trace::start(&*trace_log);
@@ -229,34 +327,35 @@ impl Coordinator {
         &'c self,
         workers: &[worker::Handle],
         sync_workers: &[SyncWorker],
-        signal_refs: &ActorGroup<Signal>,
+        signal_refs: &signal::Receivers,
         trace_log: &'l mut Option<trace::CoordinatorLog>,
     ) {
         let timing = trace::start(trace_log);
         let shared_metrics = self.internals.metrics();
+        let host_info = self.internals.host_info();
         let trace_metrics = trace_log.as_ref().map(trace::CoordinatorLog::metrics);
         info!(
             target: "metrics",
             heph_version = as_display!(concat!("v", env!("CARGO_PKG_VERSION"))),
-            host_os = self.host_os,
+            host_os = host_info.os,
             host_arch = ARCH,
-            host_name = self.host_name,
-            host_id = as_display!(self.host_id),
-            app_name = self.app_name,
+            host_name = host_info.name,
+            host_id = as_display!(host_info.id),
+            app_name = host_info.app_name,
             process_id = process::id(),
             parent_process_id = parent_id(),
-            uptime = as_debug!(self.start.elapsed()),
+            uptime = as_debug!(self.internals.uptime()),
             worker_threads = workers.len(),
             sync_actors = sync_workers.len(),
             shared_scheduler_ready = shared_metrics.scheduler_ready,
             shared_scheduler_inactive = shared_metrics.scheduler_inactive,
             shared_timers_total = shared_metrics.timers_total,
             shared_timers_next = as_debug!(shared_metrics.timers_next),
-            process_signals = as_debug!(SIGNAL_SET),
+            process_signals = as_debug!(self.signal_set),
             process_signal_receivers = signal_refs.len(),
             cpu_time = as_debug!(cpu_usage(libc::CLOCK_THREAD_CPUTIME_ID)),
             total_cpu_time = as_debug!(cpu_usage(libc::CLOCK_PROCESS_CPUTIME_ID)),
-            trace_file = as_debug!(trace_metrics.as_ref().map(|m| m.file)),
+            trace_file = as_debug!(trace_metrics.as_ref().and_then(|m| m.file)),
             trace_counter = trace_metrics.map_or(0, |m| m.counter);
             "coordinator metrics",
         );
@@ -264,13 +363,275 @@ impl Coordinator {
     }
 }
 
-/// Set of signals we're listening for.
-const SIGNAL_SET: SignalSet = SignalSet::all();
+/// Watchdog checking that the worker threads are making progress.
+///
+/// See [`Setup::watchdog`].
+///
+/// [`Setup::watchdog`]: crate::Setup::watchdog
+#[derive(Debug)]
+struct Watchdog {
+    /// Time after which a worker thread is considered stuck.
+    timeout: Duration,
+    /// Whether or not to abort the process once a stuck worker is found.
+    abort: bool,
+    /// Receiving end of the socket used to log the backtraces of the stuck
+    /// worker threads before aborting, `None` if disabled. See
+    /// [`install_backtrace_handler`].
+    backtraces: Option<UnixDatagram>,
+    /// State per worker thread, indexed by worker id minus one.
+    workers: Vec<WatchdogState>,
+}
+
+/// State of a single worker thread, part of [`Watchdog`].
+#[derive(Copy, Clone, Debug)]
+struct WatchdogState {
+    /// Last heartbeat seen.
+    heartbeat: u64,
+    /// Time at which `heartbeat` was last changed.
+    changed: Instant,
+    /// Whether or not the stuck worker thread has been reported.
+    reported: bool,
+}
+
+impl Watchdog {
+    /// Create a new `Watchdog`.
+    ///
+    /// If `backtraces` is `true` (and `abort` is `true`) this installs the
+    /// signal handler for [`BACKTRACE_SIGNAL`].
+    fn new(timeout: Duration, abort: bool, backtraces: bool) -> io::Result<Watchdog> {
+        let backtraces = if backtraces && abort {
+            Some(install_backtrace_handler()?)
+        } else {
+            None
+        };
+        Ok(Watchdog {
+            timeout,
+            abort,
+            backtraces,
+            workers: Vec::new(),
+        })
+    }
+
+    /// Returns the interval at which [`Watchdog::check`] should be called.
+    fn interval(&self) -> Duration {
+        self.timeout / 2
+    }
+
+    /// Check that all `workers` made progress since the last check.
+    fn check(&mut self, internals: &shared::RuntimeInternals, workers: &[worker::Handle]) {
+        let now = Instant::now();
+        if self.workers.is_empty() {
+            let state = WatchdogState {
+                heartbeat: 0,
+                changed: now,
+                reported: false,
+            };
+            self.workers = vec![state; internals.worker_stats().len()];
+        }
 
-/// Setup a new `Signals` instance, registering it with `registry`.
-fn setup_signals(registry: &Registry) -> io::Result<Signals> {
-    trace!(signals = as_debug!(SIGNAL_SET); "setting up signal handling");
-    Signals::new(SIGNAL_SET).and_then(|mut signals| {
+        let mut stuck = Vec::new();
+        for worker in workers {
+            let (stats, state) = match (
+                internals.worker_stats_for(worker.id()),
+                self.workers.get_mut(worker.id().wrapping_sub(1)),
+            ) {
+                (Some(stats), Some(state)) => (stats, state),
+                _ => continue,
+            };
+
+            let (heartbeat, polling) = stats.heartbeat();
+            if heartbeat != state.heartbeat || polling {
+                // Made progress or waiting for events.
+                state.heartbeat = heartbeat;
+                state.changed = now;
+                state.reported = false;
+                continue;
+            }
+
+            let elapsed = now.duration_since(state.changed);
+            if elapsed < self.timeout || state.reported {
+                continue;
+            }
+            state.reported = true;
+            stats.record_stuck();
+            stuck.push(worker);
+            match stats.running() {
+                Some((pid, name)) => error!(
+                    worker_id = worker.id(), pid = pid.0, name = name, elapsed = as_debug!(elapsed);
+                    "worker thread made no progress for {:?}, stuck running process '{}'", elapsed, name
+                ),
+                None => error!(
+                    worker_id = worker.id(), elapsed = as_debug!(elapsed);
+                    "worker thread made no progress for {:?}", elapsed
+                ),
+            }
+        }
+
+        if !stuck.is_empty() && self.abort {
+            if let Some(receiver) = &self.backtraces {
+                log_backtraces(receiver, &stuck);
+            }
+            error!("aborting process because of stuck worker thread(s)");
+            process::abort();
+        }
+    }
+}
+
+/// Signal send to stuck worker threads to capture their backtrace, see
+/// [`Setup::watchdog_backtraces`]. Not used by the runtime otherwise and
+/// ignored by default.
+///
+/// [`Setup::watchdog_backtraces`]: crate::Setup::watchdog_backtraces
+const BACKTRACE_SIGNAL: libc::c_int = libc::SIGURG;
+
+/// Maximum time to wait for the worker threads to capture their backtraces.
+const BACKTRACE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Writing end of the socket used by [`capture_backtrace`] to send the
+/// captured backtraces to the coordinator, -1 if not installed.
+static BACKTRACE_SENDER: AtomicI32 = AtomicI32::new(-1);
+
+/// Message send by [`capture_backtrace`]: the pthread id of the thread and a
+/// pointer to the boxed [`Backtrace`].
+type BacktraceMsg = [usize; 2];
+
+/// Install [`capture_backtrace`] as signal handler for [`BACKTRACE_SIGNAL`].
+///
+/// Returns the receiving end of the socket to which the captured backtraces
+/// are send, see [`receive_backtraces`].
+fn install_backtrace_handler() -> io::Result<UnixDatagram> {
+    let (sender, receiver) = UnixDatagram::pair()?;
+    // Never block in the signal handler.
+    sender.set_nonblocking(true)?;
+    let old_sender = BACKTRACE_SENDER.swap(sender.into_raw_fd(), Ordering::AcqRel);
+    if old_sender != -1 {
+        // SAFETY: we owned the file descriptor.
+        drop(unsafe { UnixDatagram::from_raw_fd(old_sender) });
+    }
+
+    // SAFETY: all zeroes is a valid `sigaction`, i.e. an empty signal mask and
+    // no flags.
+    let mut action: libc::sigaction = unsafe { mem::zeroed() };
+    action.sa_sigaction = capture_backtrace as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // Restart system calls the worker thread might be stuck in.
+    action.sa_flags = libc::SA_RESTART;
+    // SAFETY: `action` is valid and `capture_backtrace` has the correct
+    // signature.
+    if unsafe { libc::sigaction(BACKTRACE_SIGNAL, &action, ptr::null_mut()) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(receiver)
+    }
+}
+
+/// Signal handler for [`BACKTRACE_SIGNAL`], captures the backtrace of the
+/// current thread and sends it to the coordinator.
+///
+/// The backtrace is only captured here, resolving the symbols and logging it
+/// is done by the coordinator in [`receive_backtraces`], outside of the signal
+/// context.
+///
+/// # Notes
+///
+/// Capturing the backtrace is not async-signal-safe, it allocates and unwinds
+/// the stack. This is why it's only used for debugging right before aborting
+/// the process, in which case a (possible) deadlock is acceptable as the
+/// coordinator doesn't wait for it and aborts anyway.
+extern "C" fn capture_backtrace(_: libc::c_int) {
+    let sender = BACKTRACE_SENDER.load(Ordering::Acquire);
+    if sender == -1 {
+        return;
+    }
+    let backtrace = Box::into_raw(Box::new(Backtrace::force_capture()));
+    // SAFETY: `pthread_self` is always safe to call.
+    let msg: BacktraceMsg = [unsafe { libc::pthread_self() } as usize, backtrace as usize];
+    // SAFETY: `send` is async-signal-safe and `msg` is valid for its size.
+    let n = unsafe {
+        libc::send(
+            sender,
+            msg.as_ptr().cast(),
+            mem::size_of::<BacktraceMsg>(),
+            0,
+        )
+    };
+    if n == -1 {
+        // SAFETY: the backtrace wasn't send, so we still own it.
+        drop(unsafe { Box::from_raw(backtrace) });
+    }
+}
+
+/// Receive up to `n` backtraces from `receiver` send by [`capture_backtrace`],
+/// waiting up to `timeout`.
+///
+/// Returns the pthread id of the thread and its backtrace.
+fn receive_backtraces(
+    receiver: &UnixDatagram,
+    n: usize,
+    timeout: Duration,
+) -> Vec<(libc::pthread_t, Box<Backtrace>)> {
+    let deadline = Instant::now() + timeout;
+    let mut backtraces = Vec::with_capacity(n);
+    while backtraces.len() < n {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() || receiver.set_read_timeout(Some(timeout)).is_err() {
+            break;
+        }
+        let mut msg: BacktraceMsg = [0; 2];
+        // SAFETY: `BacktraceMsg` is a plain array of integers.
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(
+                msg.as_mut_ptr().cast::<u8>(),
+                mem::size_of::<BacktraceMsg>(),
+            )
+        };
+        match receiver.recv(buf) {
+            Ok(n) if n == buf.len() => {
+                // SAFETY: `capture_backtrace` send us ownership of the
+                // backtrace.
+                let backtrace = unsafe { Box::from_raw(msg[1] as *mut Backtrace) };
+                backtraces.push((msg[0] as libc::pthread_t, backtrace));
+            }
+            Ok(_) => continue,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+    backtraces
+}
+
+/// Log the backtraces of the `workers`, waiting up to [`BACKTRACE_TIMEOUT`].
+fn log_backtraces(receiver: &UnixDatagram, workers: &[&worker::Handle]) {
+    let mut requested = 0;
+    for worker in workers {
+        match worker.send_os_signal(BACKTRACE_SIGNAL) {
+            Ok(()) => requested += 1,
+            Err(err) => warn!(
+                worker_id = worker.id();
+                "failed to request backtrace of worker thread: {}", err
+            ),
+        }
+    }
+
+    let backtraces = receive_backtraces(receiver, requested, BACKTRACE_TIMEOUT);
+    for (pthread_id, backtrace) in &backtraces {
+        match workers.iter().find(|w| w.pthread_id() == *pthread_id) {
+            Some(worker) => error!(
+                worker_id = worker.id();
+                "backtrace of worker thread {}:\n{}", worker.id(), backtrace
+            ),
+            None => error!("backtrace of unknown thread:\n{}", backtrace),
+        }
+    }
+    if backtraces.len() < requested {
+        warn!("timed out waiting for backtraces of worker threads");
+    }
+}
+
+/// Setup a new `Signals` instance for `signal_set`, registering it with
+/// `registry`.
+fn setup_signals(registry: &Registry, signal_set: SignalSet) -> io::Result<Signals> {
+    trace!(signals = as_debug!(signal_set); "setting up signal handling");
+    Signals::new(signal_set).and_then(|mut signals| {
         registry
             .register(&mut signals, SIGNAL, Interest::READABLE)
             .map(|()| signals)
@@ -305,13 +666,14 @@ fn check_sync_worker_alive(sync_workers: &mut Vec<SyncWorker>) -> Result<(), rt:
 }
 
 /// Relay all signals received from `signals` to the `workers` and
-/// `signal_refs`.
+/// `signal_refs`, and pass them to `trace_log` to dump the flight recorder.
 /// Returns a bool indicating we received `SIGUSR2`, which is used to get
 /// metrics from the runtime. If this returns `true` call `log_metrics`.
 fn relay_signals(
     signals: &mut Signals,
     workers: &mut [worker::Handle],
-    signal_refs: &mut ActorGroup<Signal>,
+    signal_refs: &mut signal::Receivers,
+    trace_log: Option<&trace::CoordinatorLog>,
 ) -> bool {
     signal_refs.remove_disconnected();
 
@@ -319,10 +681,12 @@ fn relay_signals(
     loop {
         match signals.receive() {
             Ok(Some(signal)) => {
-                let signal = Signal::from_mio(signal);
                 if let Signal::User2 = signal {
                     log_metrics = true;
                 }
+                if let Some(trace_log) = trace_log {
+                    trace_log.handle_signal(signal);
+                }
 
                 debug!(signal = as_debug!(signal); "relaying process signal to worker threads");
                 for worker in workers.iter_mut() {
@@ -343,7 +707,7 @@ fn relay_signals(
                 }
 
                 debug!(signal = as_debug!(signal); "relaying process signal to actors");
-                let _ = signal_refs.try_send(signal, Delivery::ToAll);
+                let _ = signal_refs.try_send(signal);
             }
             Ok(None) => break,
             Err(err) => {
@@ -355,6 +719,45 @@ fn relay_signals(
     log_metrics
 }
 
+/// Shut down the runtime.
+///
+/// This sends all `workers` the shutdown message. Synchronous actors can't be
+/// stopped, but we don't wait for them any more by removing them from
+/// `sync_workers`.
+fn shutdown(workers: &mut [worker::Handle], sync_workers: &mut Vec<SyncWorker>) {
+    debug!("shutting down runtime");
+    for worker in workers.iter_mut() {
+        if let Err(err) = worker.send_shutdown() {
+            // NOTE: see `relay_signals` why we're not returning the error.
+            error!(worker_id = worker.id(); "failed to send shutdown to worker: {}", err);
+        }
+    }
+    if !sync_workers.is_empty() {
+        debug!(
+            sync_workers = sync_workers.len();
+            "not waiting for synchronous actors to shut down"
+        );
+        sync_workers.clear();
+    }
+}
+
+/// Handle to shut down the runtime, created by
+/// [`Coordinator::shutdown_handle`].
+///
+/// The runtime is shut down once this is dropped.
+#[derive(Debug)]
+pub(super) struct Shutdown {
+    waker: Arc<mio::Waker>,
+}
+
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        if let Err(err) = self.waker.wake() {
+            error!("failed to shut down the runtime: {}", err);
+        }
+    }
+}
+
 /// Handle an `event` for a worker.
 fn handle_worker_event(workers: &mut Vec<worker::Handle>, event: &Event) -> Result<(), rt::Error> {
     if let Ok(i) = workers.binary_search_by_key(&event.token().0, worker::Handle::id) {
@@ -402,6 +805,8 @@ pub(super) enum Error {
     SendingStartSignal(io::Error),
     /// Error sending function to worker.
     SendingFunc(io::Error),
+    /// Error registering the [`Shutdown`] handle.
+    RegisteringShutdown(io::Error),
 }
 
 impl fmt::Display for Error {
@@ -415,6 +820,7 @@ impl fmt::Display for Error {
             Polling(err) => write!(f, "error polling for OS events: {}", err),
             SendingStartSignal(err) => write!(f, "error sending start signal to worker: {}", err),
             SendingFunc(err) => write!(f, "error sending function to worker: {}", err),
+            RegisteringShutdown(err) => write!(f, "error registering shutdown handle: {}", err),
         }
     }
 }
@@ -427,7 +833,8 @@ impl std::error::Error for Error {
             | RegisteringSyncActors(ref err)
             | Polling(ref err)
             | SendingStartSignal(ref err)
-            | SendingFunc(ref err) => Some(err),
+            | SendingFunc(ref err)
+            | RegisteringShutdown(ref err) => Some(err),
         }
     }
 }
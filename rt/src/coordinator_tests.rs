@@ -0,0 +1,23 @@
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use super::{install_backtrace_handler, receive_backtraces, BACKTRACE_SIGNAL};
+
+#[test]
+fn backtrace_handler() {
+    let receiver = install_backtrace_handler().unwrap();
+    // `raise` only returns after the signal handler returned.
+    assert_eq!(unsafe { libc::raise(BACKTRACE_SIGNAL) }, 0);
+    let backtraces = receive_backtraces(&receiver, 1, Duration::from_secs(1));
+    assert_eq!(backtraces.len(), 1);
+    assert_eq!(backtraces[0].0, unsafe { libc::pthread_self() });
+    let backtrace = backtraces[0].1.to_string();
+    assert!(backtrace.contains("backtrace_handler"), "{}", backtrace);
+}
+
+#[test]
+fn receive_backtraces_timeout() {
+    let (_sender, receiver) = UnixDatagram::pair().unwrap();
+    let backtraces = receive_backtraces(&receiver, 1, Duration::from_millis(10));
+    assert!(backtraces.is_empty());
+}
@@ -35,6 +35,11 @@ enum ErrorInner {
     StartSyncActor(io::Error),
     /// Panic in a synchronous actor thread.
     SyncActorPanic(StringError),
+
+    /// Future passed to [`Runtime::block_on`] didn't complete.
+    ///
+    /// [`Runtime::block_on`]: crate::Runtime::block_on
+    BlockOnIncomplete,
 }
 
 impl Error {
@@ -103,6 +108,12 @@ impl Error {
             inner: ErrorInner::SyncActorPanic(msg),
         }
     }
+
+    pub(super) const fn block_on_incomplete() -> Error {
+        Error {
+            inner: ErrorInner::BlockOnIncomplete,
+        }
+    }
 }
 
 /// Maps a boxed panic messages to a [`StringError`]
@@ -168,6 +179,11 @@ impl fmt::Display for Error {
                 Self::DESC,
                 msg
             ),
+            BlockOnIncomplete => write!(
+                f,
+                "{}: future passed to `Runtime::block_on` didn't complete (did it panic?)",
+                Self::DESC
+            ),
         }
     }
 }
@@ -185,6 +201,7 @@ impl std::error::Error for Error {
             Worker(ref err) => Some(err),
             // All `StringError`.
             Setup(ref err) | WorkerPanic(ref err) | SyncActorPanic(ref err) => Some(err),
+            BlockOnIncomplete => None,
         }
     }
 }
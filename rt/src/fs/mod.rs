@@ -0,0 +1,544 @@
+//! Filesystem manipulation operations.
+//!
+//! Operating systems don't provide (portable) non-blocking filesystem
+//! operations, so all operations in this module are run on a small pool of
+//! threads managed by the runtime. This prevents an actor doing file I/O from
+//! blocking all other actors running on the same worker thread.
+//!
+//! The main type is [`File`], which supports positional reads and writes using
+//! the [`Bytes`] and [`BytesVectored`] traits. Furthermore [`metadata`] and
+//! [`read_dir`] can be used to inspect the filesystem, and [`Watcher`] to
+//! watch the filesystem for changes (Linux only).
+//!
+//! [`Bytes`]: crate::bytes::Bytes
+//! [`BytesVectored`]: crate::bytes::BytesVectored
+//!
+//! # Notes
+//!
+//! Bytes are copied between the buffers passed to the read and write functions
+//! and buffers owned by the thread pool, as the buffers can't be borrowed by
+//! another thread.
+//!
+//! Operations are started when the returned future is first polled. Dropping
+//! the future after that does **not** cancel the operation, it still runs to
+//! completion on the thread pool, only its result is discarded. For example a
+//! dropped [`WriteAt`] future may still write (some of) the bytes to the file.
+//! The exception is an operation still waiting for room in the queue of the
+//! thread pool (see [`Setup::blocking_queue_size`]), those are not run.
+//!
+//! [`Setup::blocking_queue_size`]: crate::Setup::blocking_queue_size
+//!
+//! # Examples
+//!
+//! ```
+//! #![feature(never_type)]
+//!
+//! use std::io;
+//!
+//! use heph::actor;
+//! use heph_rt::fs::File;
+//! use heph_rt::ThreadLocal;
+//!
+//! async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+//!     let file = File::open(&mut ctx, "Cargo.toml").await?;
+//!     let metadata = file.metadata().await?;
+//!
+//!     let mut buf = Vec::with_capacity(metadata.len() as usize);
+//!     let n = file.read_at(&mut buf, 0).await?;
+//!     assert_eq!(n, metadata.len() as usize);
+//!     Ok(())
+//! }
+//! #
+//! # let actor_ref = heph_rt::test::try_spawn_local(
+//! #     heph_rt::test::PanicSupervisor,
+//! #     actor as fn(_) -> _,
+//! #     (),
+//! #     heph_rt::spawn::ActorOptions::default(),
+//! # ).unwrap();
+//! # heph_rt::test::join(&actor_ref, std::time::Duration::from_secs(1)).unwrap();
+//! ```
+
+use std::fs::{self, DirEntry, Metadata, OpenOptions};
+use std::future::Future;
+use std::io::{self, IoSlice};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll};
+use std::{fmt, ptr};
+
+use heph::actor;
+
+use crate::bytes::{Bytes, BytesVectored};
+use crate::{self as rt, blocking, shared};
+
+#[cfg(target_os = "linux")]
+mod watcher;
+
+#[cfg(target_os = "linux")]
+pub use watcher::{Event, Events, NextEvent, Watcher};
+
+/// Run `op` on the blocking pool of `rt`.
+///
+/// The operation is only started once the returned future is first polled.
+fn spawn<F, T>(rt: &Arc<shared::RuntimeInternals>, op: F) -> Blocking<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    Blocking {
+        op: Some((rt.clone(), Box::new(op))),
+        handle: None,
+    }
+}
+
+/// Future for an operation running on the blocking pool.
+struct Blocking<T> {
+    /// Runtime and operation to run, taken when the operation is started on
+    /// the first poll.
+    #[allow(clippy::type_complexity)]
+    op: Option<(
+        Arc<shared::RuntimeInternals>,
+        Box<dyn FnOnce() -> io::Result<T> + Send + Sync>,
+    )>,
+    /// Handle to the started operation.
+    handle: Option<blocking::Handle<io::Result<T>>>,
+}
+
+impl<T: Send + 'static> Future for Blocking<T> {
+    type Output = io::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Blocking { op, handle } = &mut *self;
+        let handle = handle.get_or_insert_with(|| {
+            let (rt, op) = op.take().expect("polled `fs` future after completion");
+            rt.blocking().spawn(op)
+        });
+        match Pin::new(handle).poll(ctx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(panic)) => Poll::Ready(Err(blocking::panic_to_io_error(panic))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Blocking<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blocking")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+/// Macro to create a future that wraps a [`Blocking`] future.
+macro_rules! blocking_future {
+    (
+        $(#[$meta: meta])*
+        $name: ident -> $output: ty
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        #[must_use = "futures do nothing unless you `.await` or poll them"]
+        pub struct $name {
+            inner: Blocking<$output>,
+        }
+
+        impl Future for $name {
+            type Output = io::Result<$output>;
+
+            fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+                Pin::new(&mut self.inner).poll(ctx)
+            }
+        }
+    };
+}
+
+/// A reference to an open file.
+///
+/// Created by [`File::open`], [`File::create`] or using [`OpenOptions`] with
+/// [`File::open_with`].
+///
+/// All operations are run on a pool of threads managed by the runtime, see the
+/// [module documentation] for more information.
+///
+/// [module documentation]: crate::fs
+///
+/// # Notes
+///
+/// The file is not [bound] to an actor, it can be used by any actor in the
+/// runtime that created it.
+///
+/// [bound]: crate::Bound
+pub struct File {
+    inner: Arc<fs::File>,
+    rt: Arc<shared::RuntimeInternals>,
+}
+
+impl File {
+    /// Open the file at `path` in read-only mode.
+    ///
+    /// See [`std::fs::File::open`].
+    pub fn open<M, RT, P>(ctx: &mut actor::Context<M, RT>, path: P) -> Open
+    where
+        RT: rt::Access,
+        P: AsRef<Path>,
+    {
+        let mut options = OpenOptions::new();
+        let _ = options.read(true);
+        File::open_with(ctx, path, options)
+    }
+
+    /// Open the file at `path` in write-only mode, creating the file if it
+    /// doesn't exist and truncating it if it does.
+    ///
+    /// See [`std::fs::File::create`].
+    pub fn create<M, RT, P>(ctx: &mut actor::Context<M, RT>, path: P) -> Open
+    where
+        RT: rt::Access,
+        P: AsRef<Path>,
+    {
+        let mut options = OpenOptions::new();
+        let _ = options.write(true).create(true).truncate(true);
+        File::open_with(ctx, path, options)
+    }
+
+    /// Open the file at `path` using `options`.
+    pub fn open_with<M, RT, P>(
+        ctx: &mut actor::Context<M, RT>,
+        path: P,
+        options: OpenOptions,
+    ) -> Open
+    where
+        RT: rt::Access,
+        P: AsRef<Path>,
+    {
+        let rt = ctx.runtime_ref().shared_internals().clone();
+        let path = path.as_ref().to_owned();
+        Open {
+            inner: spawn(&rt, move || options.open(path)),
+            rt: Some(rt),
+        }
+    }
+
+    /// Convert a [`std::fs::File`] into a `File`.
+    pub fn from_std<M, RT>(ctx: &mut actor::Context<M, RT>, file: fs::File) -> File
+    where
+        RT: rt::Access,
+    {
+        File {
+            inner: Arc::new(file),
+            rt: ctx.runtime_ref().shared_internals().clone(),
+        }
+    }
+
+    /// Read bytes from the file, starting at `offset`, into `buf`.
+    ///
+    /// Returns the number of bytes read, which is at most the spare capacity of
+    /// `buf`. If this returns `Ok(0)` the end of the file is reached (or `buf`
+    /// has no spare capacity).
+    ///
+    /// See [`FileExt::read_at`].
+    pub fn read_at<B>(&self, buf: B, offset: u64) -> ReadAt<B>
+    where
+        B: Bytes,
+    {
+        let len = buf.spare_capacity();
+        let file = self.inner.clone();
+        ReadAt {
+            inner: spawn(&self.rt, move || {
+                let mut data = vec![0; len];
+                let n = file.read_at(&mut data, offset)?;
+                data.truncate(n);
+                Ok(data)
+            }),
+            buf,
+        }
+    }
+
+    /// Read bytes from the file, starting at `offset`, into `bufs`.
+    ///
+    /// Same as [`File::read_at`], but reads into multiple buffers.
+    pub fn read_vectored_at<B>(&self, bufs: B, offset: u64) -> ReadVectoredAt<B>
+    where
+        B: BytesVectored,
+    {
+        let len = bufs.spare_capacity();
+        let file = self.inner.clone();
+        ReadVectoredAt {
+            inner: spawn(&self.rt, move || {
+                let mut data = vec![0; len];
+                let n = file.read_at(&mut data, offset)?;
+                data.truncate(n);
+                Ok(data)
+            }),
+            bufs,
+        }
+    }
+
+    /// Write the bytes in `buf` to the file, starting at `offset`.
+    ///
+    /// Returns the number of bytes written, which may be fewer then the length
+    /// of `buf`.
+    ///
+    /// See [`FileExt::write_at`].
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> WriteAt {
+        let data = buf.to_vec();
+        let file = self.inner.clone();
+        WriteAt {
+            inner: spawn(&self.rt, move || file.write_at(&data, offset)),
+        }
+    }
+
+    /// Write all bytes in `buf` to the file, starting at `offset`.
+    ///
+    /// See [`FileExt::write_all_at`].
+    pub fn write_all_at(&self, buf: &[u8], offset: u64) -> WriteAllAt {
+        let data = buf.to_vec();
+        let file = self.inner.clone();
+        WriteAllAt {
+            inner: spawn(&self.rt, move || file.write_all_at(&data, offset)),
+        }
+    }
+
+    /// Write the bytes in `bufs` to the file, starting at `offset`.
+    ///
+    /// Same as [`File::write_at`], but writes multiple buffers.
+    pub fn write_vectored_at(&self, bufs: &[IoSlice<'_>], offset: u64) -> WriteAt {
+        let data = bufs.iter().fold(Vec::new(), |mut data, buf| {
+            data.extend_from_slice(buf);
+            data
+        });
+        let file = self.inner.clone();
+        WriteAt {
+            inner: spawn(&self.rt, move || file.write_at(&data, offset)),
+        }
+    }
+
+    /// Queries metadata about the file.
+    pub fn metadata(&self) -> GetMetadata {
+        let file = self.inner.clone();
+        GetMetadata {
+            inner: spawn(&self.rt, move || file.metadata()),
+        }
+    }
+
+    /// Truncates or extends the file to `size` bytes.
+    pub fn set_len(&self, size: u64) -> SetLen {
+        let file = self.inner.clone();
+        SetLen {
+            inner: spawn(&self.rt, move || file.set_len(size)),
+        }
+    }
+
+    /// Flush all data and metadata of the file to disk, using `fsync(2)`.
+    pub fn sync_all(&self) -> SyncAll {
+        let file = self.inner.clone();
+        SyncAll {
+            inner: spawn(&self.rt, move || file.sync_all()),
+        }
+    }
+
+    /// Flush the data of the file to disk, but not (all) of its metadata,
+    /// using `fdatasync(2)`.
+    pub fn sync_data(&self) -> SyncData {
+        let file = self.inner.clone();
+        SyncData {
+            inner: spawn(&self.rt, move || file.sync_data()),
+        }
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File").field("inner", &self.inner).finish()
+    }
+}
+
+/// The [`Future`] behind [`File::open`], [`File::create`] and
+/// [`File::open_with`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Open {
+    inner: Blocking<fs::File>,
+    rt: Option<Arc<shared::RuntimeInternals>>,
+}
+
+impl Future for Open {
+    type Output = io::Result<File>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Open { inner, rt } = Pin::into_inner(self);
+        match Pin::new(inner).poll(ctx) {
+            Poll::Ready(Ok(file)) => Poll::Ready(Ok(File {
+                inner: Arc::new(file),
+                rt: rt.take().expect("polled `fs::Open` after completion"),
+            })),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The [`Future`] behind [`File::read_at`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadAt<B> {
+    inner: Blocking<Vec<u8>>,
+    buf: B,
+}
+
+impl<B> Future for ReadAt<B>
+where
+    B: Bytes + Unpin,
+{
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let ReadAt { inner, buf } = Pin::into_inner(self);
+        match Pin::new(inner).poll(ctx) {
+            Poll::Ready(Ok(data)) => {
+                let dst = buf.as_bytes();
+                debug_assert!(data.len() <= dst.len());
+                // Safety: the blocking operation reads at most the spare
+                // capacity of `buf`, so `dst` is large enough.
+                unsafe {
+                    ptr::copy_nonoverlapping(data.as_ptr(), dst.as_mut_ptr().cast(), data.len());
+                    buf.update_length(data.len());
+                }
+                Poll::Ready(Ok(data.len()))
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The [`Future`] behind [`File::read_vectored_at`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadVectoredAt<B> {
+    inner: Blocking<Vec<u8>>,
+    bufs: B,
+}
+
+impl<B> Future for ReadVectoredAt<B>
+where
+    B: BytesVectored + Unpin,
+{
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let ReadVectoredAt { inner, bufs } = Pin::into_inner(self);
+        match Pin::new(inner).poll(ctx) {
+            Poll::Ready(Ok(data)) => {
+                let mut src = &*data;
+                for dst in bufs.as_bufs().as_mut().iter_mut() {
+                    if src.is_empty() {
+                        break;
+                    }
+                    let n = src.len().min(dst.len());
+                    // Safety: `n` is at most the length of both `src` and `dst`.
+                    unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr().cast(), n) };
+                    src = &src[n..];
+                }
+                // Safety: just initialised the bytes above.
+                unsafe { bufs.update_lengths(data.len()) };
+                Poll::Ready(Ok(data.len()))
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+blocking_future!(
+    /// The [`Future`] behind [`File::write_at`] and [`File::write_vectored_at`].
+    ///
+    /// # Notes
+    ///
+    /// Dropping the future after it's first polled doesn't cancel the
+    /// operation, see the [module documentation].
+    ///
+    /// [module documentation]: crate::fs
+    WriteAt -> usize
+);
+
+blocking_future!(
+    /// The [`Future`] behind [`File::write_all_at`].
+    ///
+    /// # Notes
+    ///
+    /// Dropping the future after it's first polled doesn't cancel the
+    /// operation, see the [module documentation].
+    ///
+    /// [module documentation]: crate::fs
+    WriteAllAt -> ()
+);
+
+blocking_future!(
+    /// The [`Future`] behind [`File::metadata`] and [`metadata`].
+    GetMetadata -> Metadata
+);
+
+blocking_future!(
+    /// The [`Future`] behind [`File::set_len`].
+    ///
+    /// # Notes
+    ///
+    /// Dropping the future after it's first polled doesn't cancel the
+    /// operation, see the [module documentation].
+    ///
+    /// [module documentation]: crate::fs
+    SetLen -> ()
+);
+
+blocking_future!(
+    /// The [`Future`] behind [`File::sync_all`].
+    SyncAll -> ()
+);
+
+blocking_future!(
+    /// The [`Future`] behind [`File::sync_data`].
+    SyncData -> ()
+);
+
+blocking_future!(
+    /// The [`Future`] behind [`read_dir`].
+    ReadDir -> Vec<DirEntry>
+);
+
+/// Queries the metadata of the file or directory at `path`, following
+/// symbolic links.
+///
+/// See [`std::fs::metadata`].
+pub fn metadata<M, RT, P>(ctx: &mut actor::Context<M, RT>, path: P) -> GetMetadata
+where
+    RT: rt::Access,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref().to_owned();
+    GetMetadata {
+        inner: spawn(ctx.runtime_ref().shared_internals(), move || {
+            fs::metadata(path)
+        }),
+    }
+}
+
+/// Returns the entries in the directory at `path`.
+///
+/// The order of the entries is platform and filesystem dependent.
+///
+/// See [`std::fs::read_dir`].
+pub fn read_dir<M, RT, P>(ctx: &mut actor::Context<M, RT>, path: P) -> ReadDir
+where
+    RT: rt::Access,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref().to_owned();
+    ReadDir {
+        inner: spawn(ctx.runtime_ref().shared_internals(), move || {
+            fs::read_dir(path)?.collect()
+        }),
+    }
+}
@@ -0,0 +1,478 @@
+//! Module with [`Watcher`].
+
+use std::async_iter::AsyncIterator;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CString, OsStr};
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, Read};
+use std::mem::size_of;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::ptr;
+use std::task::{self, Poll};
+
+use heph::actor;
+use mio::unix::SourceFd;
+use mio::Interest;
+
+use crate::{self as rt, Bound};
+
+/// Maximum length of a file name, `NAME_MAX` in `limits.h`.
+const NAME_MAX: usize = 255;
+
+/// Size of the buffer used to read events, enough for at least 16 events with
+/// the maximum file name length.
+const BUF_SIZE: usize = 16 * (size_of::<libc::inotify_event>() + NAME_MAX + 1);
+
+/// Events we're interested in.
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_MODIFY
+    | libc::IN_DELETE
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_MOVE_SELF
+    | libc::IN_EXCL_UNLINK;
+
+/// Filesystem watcher.
+///
+/// Watches files and directories for changes using [`inotify(7)`]. Paths to
+/// watch are added using [`Watcher::watch`] or, to include all
+/// subdirectories, [`Watcher::watch_recursive`]. Changes are reported as
+/// [`Event`]s, which can be retrieved using [`Watcher::next_event`] or
+/// [`Watcher::events`].
+///
+/// [`inotify(7)`]: https://man7.org/linux/man-pages/man7/inotify.7.html
+///
+/// # Notes
+///
+/// Unlike the other types in the [`fs`] module adding watches is done on the
+/// calling thread, for recursive watches this means walking the entire
+/// directory tree, which can block the worker thread for large trees.
+///
+/// The kernel limits the number of watches per user, see
+/// `/proc/sys/fs/inotify/max_user_watches`.
+///
+/// [`fs`]: crate::fs
+///
+/// # Examples
+///
+/// Reloading a configuration file when it changes.
+///
+/// ```
+/// #![feature(never_type)]
+///
+/// use std::io;
+///
+/// use heph::actor;
+/// use heph_rt::fs::{Event, Watcher};
+/// use heph_rt::ThreadLocal;
+///
+/// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+///     let mut watcher = Watcher::new(&mut ctx)?;
+///     watcher.watch("/etc/my_app/config.toml")?;
+///
+///     loop {
+///         match watcher.next_event().await? {
+///             Event::Modified(path) => println!("reloading {}", path.display()),
+///             Event::Deleted(_) => return Ok(()),
+///             _ => {}
+///         }
+///     }
+/// }
+/// # drop(actor); // Silence dead code warnings.
+/// ```
+#[derive(Debug)]
+pub struct Watcher {
+    fd: File,
+    /// Watch descriptor -> watch.
+    watches: HashMap<libc::c_int, Watch>,
+    /// Events read, but not yet returned.
+    events: VecDeque<Event>,
+    /// Read buffer.
+    buf: Vec<u8>,
+}
+
+/// A single inotify watch.
+#[derive(Debug)]
+struct Watch {
+    path: PathBuf,
+    /// Watch subdirectories.
+    recursive: bool,
+    /// Whether or not the watch was added by the user, `false` for
+    /// subdirectories of recursive watches.
+    root: bool,
+}
+
+/// Event returned by [`Watcher`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// A file or directory was created in a watched directory.
+    Created(PathBuf),
+    /// A file was modified.
+    ///
+    /// This is reported for every write to the file.
+    Modified(PathBuf),
+    /// A file or directory was deleted.
+    Deleted(PathBuf),
+    /// A file or directory was moved (renamed).
+    ///
+    /// If the file was moved out of the watched directories `to` is `None`,
+    /// if it was moved into the watched directories `from` is `None`.
+    Moved {
+        /// Old path.
+        from: Option<PathBuf>,
+        /// New path.
+        to: Option<PathBuf>,
+    },
+    /// The kernel's event queue overflowed, events were lost.
+    Overflow,
+}
+
+impl Watcher {
+    /// Create a new `Watcher`, without any watches.
+    pub fn new<M, RT>(ctx: &mut actor::Context<M, RT>) -> io::Result<Watcher>
+    where
+        RT: rt::Access,
+    {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `inotify_init1(2)` ensures `fd` is valid.
+        let fd = unsafe { File::from_raw_fd(fd) };
+        ctx.runtime()
+            .register(&mut SourceFd(&fd.as_raw_fd()), Interest::READABLE)?;
+        Ok(Watcher {
+            fd,
+            watches: HashMap::new(),
+            events: VecDeque::new(),
+            buf: vec![0; BUF_SIZE],
+        })
+    }
+
+    /// Watch the file or directory at `path`.
+    ///
+    /// If `path` is a directory changes to the directory's entries are
+    /// reported, but not changes in subdirectories. Use
+    /// [`Watcher::watch_recursive`] for that.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.add_watch(path.as_ref().to_owned(), false, true)
+    }
+
+    /// Watch the directory at `path` and all its subdirectories.
+    ///
+    /// Subdirectories created after this call are watched automatically.
+    pub fn watch_recursive<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.add_watch(path.as_ref().to_owned(), true, true)
+    }
+
+    /// Stop watching `path`, including its subdirectories if it was watched
+    /// recursively.
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let wds: Vec<libc::c_int> = self
+            .watches
+            .iter()
+            .filter(|(_, watch)| {
+                watch.path == path || (!watch.root && watch.path.starts_with(path))
+            })
+            .map(|(wd, _)| *wd)
+            .collect();
+        if wds.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "path is not watched",
+            ));
+        }
+        for wd in wds {
+            self.remove_watch(wd)?;
+        }
+        Ok(())
+    }
+
+    /// Attempt to retrieve the next event.
+    ///
+    /// If no events are available this will return an error with the
+    /// [kind] set to [`ErrorKind::WouldBlock`]. Most users should prefer to
+    /// use [`Watcher::next_event`] or [`Watcher::events`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    pub fn try_next_event(&mut self) -> io::Result<Event> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            self.read_events()?;
+        }
+    }
+
+    /// Returns the next event.
+    pub fn next_event(&mut self) -> NextEvent<'_> {
+        NextEvent { watcher: self }
+    }
+
+    /// Returns an [`AsyncIterator`] of events.
+    pub fn events(&mut self) -> Events<'_> {
+        Events { watcher: self }
+    }
+
+    /// Add a watch for `path`, and its subdirectories if `recursive` is true.
+    fn add_watch(&mut self, path: PathBuf, recursive: bool, root: bool) -> io::Result<()> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), MASK) };
+        if wd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if recursive {
+            for entry in fs::read_dir(&path)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    match self.add_watch(entry.path(), true, false) {
+                        Ok(()) => {}
+                        // Directory was removed in the meantime.
+                        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+        }
+
+        let watch = Watch {
+            path,
+            recursive,
+            root,
+        };
+        // NOTE: `inotify_add_watch(2)` returns the same watch descriptor for
+        // the same inode, so this overwrites any previous watch.
+        drop(self.watches.insert(wd, watch));
+        Ok(())
+    }
+
+    /// Remove the watch with descriptor `wd`.
+    fn remove_watch(&mut self, wd: libc::c_int) -> io::Result<()> {
+        drop(self.watches.remove(&wd));
+        if unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd) } == -1 {
+            let err = io::Error::last_os_error();
+            // `EINVAL` means the watch was already removed by the kernel,
+            // e.g. because the file was deleted.
+            if err.raw_os_error() != Some(libc::EINVAL) {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read events from the inotify file descriptor, adding them to `events`.
+    ///
+    /// If an error occurs while handling an event the remaining events are
+    /// still added to `events` before the (first) error is returned.
+    fn read_events(&mut self) -> io::Result<()> {
+        let n = self.fd.read(&mut self.buf)?;
+
+        let mut result = Ok(());
+
+        // `IN_MOVED_FROM` event waiting for the `IN_MOVED_TO` event with the
+        // same cookie.
+        let mut moved_from: Option<(u32, PathBuf, bool)> = None;
+        let mut offset = 0;
+        while offset + size_of::<libc::inotify_event>() <= n {
+            // SAFETY: the kernel writes complete events, `read_unaligned`
+            // handles the alignment of the buffer.
+            let event: libc::inotify_event =
+                unsafe { ptr::read_unaligned(self.buf[offset..].as_ptr().cast()) };
+            let name_start = offset + size_of::<libc::inotify_event>();
+            let name_end = name_start + event.len as usize;
+            offset = name_end;
+
+            if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                self.events.push_back(Event::Overflow);
+                continue;
+            }
+
+            let (path, recursive, root) = match self.watches.get(&event.wd) {
+                Some(watch) => {
+                    // The name is padded with null bytes.
+                    let name = &self.buf[name_start..name_end];
+                    let name = match name.iter().position(|b| *b == 0) {
+                        Some(end) => &name[..end],
+                        None => name,
+                    };
+                    let path = if name.is_empty() {
+                        watch.path.clone()
+                    } else {
+                        watch.path.join(OsStr::from_bytes(name))
+                    };
+                    (path, watch.recursive, watch.root)
+                }
+                // Event for a watch that was already removed.
+                None => continue,
+            };
+            let is_dir = event.mask & libc::IN_ISDIR != 0;
+
+            if event.mask & libc::IN_MOVED_TO != 0 {
+                let from = match moved_from.take() {
+                    Some((cookie, from, _)) if cookie == event.cookie => Some(from),
+                    Some((_, from, from_is_dir)) => {
+                        keep_first_err(&mut result, self.moved_out(from, from_is_dir));
+                        None
+                    }
+                    None => None,
+                };
+                if is_dir && recursive {
+                    match &from {
+                        Some(from) => self.rename_watches(from, &path),
+                        None => keep_first_err(&mut result, self.add_new_dir(path.clone())),
+                    }
+                }
+                self.events.push_back(Event::Moved {
+                    from,
+                    to: Some(path),
+                });
+                continue;
+            }
+
+            if let Some((_, from, from_is_dir)) = moved_from.take() {
+                keep_first_err(&mut result, self.moved_out(from, from_is_dir));
+            }
+
+            if event.mask & libc::IN_MOVED_FROM != 0 {
+                moved_from = Some((event.cookie, path, is_dir));
+            } else if event.mask & libc::IN_CREATE != 0 {
+                if is_dir && recursive {
+                    keep_first_err(&mut result, self.add_new_dir(path.clone()));
+                }
+                self.events.push_back(Event::Created(path));
+            } else if event.mask & libc::IN_MODIFY != 0 {
+                self.events.push_back(Event::Modified(path));
+            } else if event.mask & libc::IN_DELETE != 0 {
+                self.events.push_back(Event::Deleted(path));
+            } else if event.mask & libc::IN_DELETE_SELF != 0 {
+                // Deleting subdirectories is reported by their parents.
+                if root {
+                    self.events.push_back(Event::Deleted(path));
+                }
+            } else if event.mask & libc::IN_MOVE_SELF != 0 {
+                // Moving subdirectories is reported by their parents.
+                if root {
+                    self.events.push_back(Event::Moved {
+                        from: Some(path),
+                        to: None,
+                    });
+                }
+            } else if event.mask & libc::IN_IGNORED != 0 {
+                // Watch was removed, e.g. because the file was deleted.
+                drop(self.watches.remove(&event.wd));
+            }
+        }
+
+        // NOTE: it's possible that the matching `IN_MOVED_TO` event is returned
+        // in the next read, in which case we report two events.
+        if let Some((_, from, from_is_dir)) = moved_from {
+            keep_first_err(&mut result, self.moved_out(from, from_is_dir));
+        }
+        result
+    }
+
+    /// Add watches for directory `path`, created (or moved) in a recursively
+    /// watched directory.
+    fn add_new_dir(&mut self, path: PathBuf) -> io::Result<()> {
+        match self.add_watch(path, true, false) {
+            Ok(()) => Ok(()),
+            // Directory was removed (or moved) in the meantime.
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Handle `path` being moved out of the watched directories.
+    ///
+    /// The event is always added to `events`, even if an error is returned.
+    fn moved_out(&mut self, path: PathBuf, is_dir: bool) -> io::Result<()> {
+        let mut result = Ok(());
+        if is_dir {
+            // Stop watching subdirectories (of recursive watches) that we
+            // can't report events for any more.
+            let wds: Vec<libc::c_int> = self
+                .watches
+                .iter()
+                .filter(|(_, watch)| !watch.root && watch.path.starts_with(&path))
+                .map(|(wd, _)| *wd)
+                .collect();
+            for wd in wds {
+                keep_first_err(&mut result, self.remove_watch(wd));
+            }
+        }
+        self.events.push_back(Event::Moved {
+            from: Some(path),
+            to: None,
+        });
+        result
+    }
+
+    /// Update the paths of the watches after a directory was moved `from` to
+    /// `to`.
+    fn rename_watches(&mut self, from: &Path, to: &Path) {
+        for watch in self.watches.values_mut() {
+            if let Ok(rest) = watch.path.strip_prefix(from) {
+                watch.path = to.join(rest);
+            }
+        }
+    }
+}
+
+/// Sets `result` to `res` if it's an error and `result` isn't one already.
+fn keep_first_err(result: &mut io::Result<()>, res: io::Result<()>) {
+    if let Err(err) = res {
+        if result.is_ok() {
+            *result = Err(err);
+        }
+    }
+}
+
+impl<RT: rt::Access> Bound<RT> for Watcher {
+    type Error = io::Error;
+
+    fn bind_to<M>(&mut self, ctx: &mut actor::Context<M, RT>) -> io::Result<()> {
+        ctx.runtime()
+            .reregister(&mut SourceFd(&self.fd.as_raw_fd()), Interest::READABLE)
+    }
+}
+
+/// The [`Future`] behind [`Watcher::next_event`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct NextEvent<'a> {
+    watcher: &'a mut Watcher,
+}
+
+impl<'a> Future for NextEvent<'a> {
+    type Output = io::Result<Event>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let NextEvent { watcher } = Pin::into_inner(self);
+        try_io!(watcher.try_next_event())
+    }
+}
+
+/// The [`AsyncIterator`] behind [`Watcher::events`].
+#[derive(Debug)]
+#[must_use = "AsyncIterators do nothing unless polled"]
+pub struct Events<'a> {
+    watcher: &'a mut Watcher,
+}
+
+impl<'a> AsyncIterator for Events<'a> {
+    type Item = io::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        let Events { watcher } = Pin::into_inner(self);
+        try_io!(watcher.try_next_event()).map(Some)
+    }
+}
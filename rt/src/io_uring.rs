@@ -0,0 +1,1021 @@
+//! Module with the io_uring I/O backend.
+//!
+//! The backend is enabled using [`Setup::use_io_uring`]. Each worker thread
+//! then creates its own [`Ring`], which is registered with the worker's `Poll`
+//! so that the worker is woken up once operations complete.
+//!
+//! The I/O futures, e.g. [`TcpStream::recv`], use an [`Op`] to submit their
+//! operation to the ring of the worker thread they're polled on. If the thread
+//! doesn't have a ring, because io_uring isn't enabled or isn't supported, the
+//! futures fall back to readiness based I/O using `epoll(7)`.
+//!
+//! The kernel accesses the buffers of an operation until it's completed, which
+//! can be after the future is dropped. Because of this the buffers are owned by
+//! the ring, not borrowed from the caller. Sending copies the bytes into such a
+//! buffer and receiving copies the bytes out of it. Dropping an `Op` while its
+//! operation is in progress cancels the operation, the buffers are freed once
+//! the kernel is done with them.
+//!
+//! [`Setup::use_io_uring`]: crate::Setup::use_io_uring
+//! [`TcpStream::recv`]: crate::net::TcpStream::recv
+
+use std::cell::RefCell;
+use std::mem::{self, size_of, MaybeUninit};
+use std::num::NonZeroUsize;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{self, Poll};
+use std::{fmt, io, ptr};
+
+use log::warn;
+use socket2::SockAddr;
+
+use crate::bytes::Bytes;
+
+#[cfg(test)]
+#[path = "io_uring_tests.rs"]
+mod io_uring_tests;
+
+/// Number of submission queue entries of the ring of a worker thread.
+pub(crate) const ENTRIES: u32 = 256;
+
+/// Maximum number of bytes read from a file at a time in
+/// [`Op::poll_send_file`].
+const SEND_FILE_BUF_SIZE: usize = 64 * 1024;
+
+/// `user_data` used by cancellation operations, we don't need their results.
+const CANCEL_USER_DATA: u64 = u64::MAX;
+
+// Definitions from `linux/io_uring.h`.
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x800_0000;
+const IORING_OFF_SQES: libc::off_t = 0x1000_0000;
+const IORING_SQ_CQ_OVERFLOW: u32 = 1 << 1;
+const IORING_ENTER_GETEVENTS: libc::c_uint = 1 << 0;
+const IORING_FEAT_NODROP: u32 = 1 << 1;
+const IORING_FEAT_FAST_POLL: u32 = 1 << 5;
+const IORING_REGISTER_PROBE: libc::c_uint = 8;
+const IO_URING_OP_SUPPORTED: u16 = 1 << 0;
+const IORING_OP_ACCEPT: u8 = 13;
+const IORING_OP_ASYNC_CANCEL: u8 = 14;
+const IORING_OP_CONNECT: u8 = 16;
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_SEND: u8 = 26;
+const IORING_OP_RECV: u8 = 27;
+
+/// Operations used by the backend, all must be supported by the kernel.
+const OPERATIONS: [u8; 6] = [
+    IORING_OP_ACCEPT,
+    IORING_OP_ASYNC_CANCEL,
+    IORING_OP_CONNECT,
+    IORING_OP_READ,
+    IORING_OP_SEND,
+    IORING_OP_RECV,
+];
+
+/// `struct io_uring_params`.
+#[repr(C)]
+#[derive(Default)]
+struct Parameters {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SubmissionQueueOffsets,
+    cq_off: CompletionQueueOffsets,
+}
+
+/// `struct io_sqring_offsets`.
+#[repr(C)]
+#[derive(Default)]
+struct SubmissionQueueOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+/// `struct io_cqring_offsets`.
+#[repr(C)]
+#[derive(Default)]
+struct CompletionQueueOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+/// `struct io_uring_sqe`.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct Submission {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    /// Offset or second address.
+    off: u64,
+    addr: u64,
+    len: u32,
+    /// Operation specific flags, e.g. `msg_flags` or `accept_flags`.
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+/// `struct io_uring_cqe`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Completion {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+/// `struct io_uring_probe`, with room for all operations.
+#[repr(C)]
+struct Probe {
+    last_op: u8,
+    ops_len: u8,
+    resv: u16,
+    resv2: [u32; 3],
+    ops: [ProbeOperation; 256],
+}
+
+/// `struct io_uring_probe_op`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ProbeOperation {
+    op: u8,
+    resv: u8,
+    flags: u16,
+    resv2: u32,
+}
+
+const _: () = assert!(size_of::<Parameters>() == 120);
+const _: () = assert!(size_of::<Submission>() == 64);
+const _: () = assert!(size_of::<Completion>() == 16);
+
+/// Checks if the kernel supports everything the io_uring backend needs.
+///
+/// This can fail if the kernel is too old (`ENOSYS`), but also if io_uring is
+/// disabled by the administrator (`kernel.io_uring_disabled` sysctl) or by a
+/// seccomp filter, which is common in containers (`EPERM`).
+pub(crate) fn probe() -> io::Result<()> {
+    Ring::new(1).map(|_| ())
+}
+
+thread_local! {
+    /// Ring of the worker thread, see [`set_current`].
+    static CURRENT: RefCell<Option<Arc<Ring>>> = RefCell::new(None);
+}
+
+/// Use `ring` for the operations started on this thread.
+pub(crate) fn set_current(ring: Option<Arc<Ring>>) {
+    CURRENT.with(|current| *current.borrow_mut() = ring);
+}
+
+/// Returns `true` if the current thread uses io_uring.
+pub(crate) fn in_use() -> bool {
+    CURRENT
+        .try_with(|current| current.borrow().is_some())
+        .unwrap_or(false)
+}
+
+/// Returns the ring of the current thread, if any.
+fn current() -> Option<Arc<Ring>> {
+    CURRENT
+        .try_with(|current| current.borrow().clone())
+        .ok()
+        .flatten()
+}
+
+/// io_uring instance of a worker thread.
+pub(crate) struct Ring {
+    /// File descriptor of the ring, becomes readable once operations complete.
+    fd: OwnedFd,
+    inner: Mutex<Inner>,
+}
+
+/// Mutable part of [`Ring`].
+struct Inner {
+    sq: SubmissionQueue,
+    cq: CompletionQueue,
+    /// Number of submissions added to `sq`, but not yet submitted to the
+    /// kernel.
+    unsubmitted: u32,
+    /// State of the operations, indexed by their `user_data`.
+    operations: Vec<Option<Operation>>,
+    /// Indices of the unused entries in `operations`.
+    free: Vec<usize>,
+    /// Number of dropped operations the kernel hasn't completed yet.
+    dropped: usize,
+    /// Memory shared with the kernel, used by `sq` and `cq`.
+    _mmaps: [Mmap; 3],
+}
+
+// SAFETY: the pointers in `Inner` point to memory owned by `Inner`.
+unsafe impl Send for Inner {}
+
+impl Ring {
+    /// Create a new ring with (at least) `entries` submission queue entries.
+    pub(crate) fn new(entries: u32) -> io::Result<Ring> {
+        let mut parameters = Parameters::default();
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries,
+                ptr::addr_of_mut!(parameters),
+            )
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // NOTE: the fd always fits in an `c_int`.
+        #[allow(clippy::cast_possible_truncation)]
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+        check_support(fd.as_raw_fd(), &parameters)?;
+
+        let Parameters {
+            sq_entries,
+            cq_entries,
+            sq_off,
+            cq_off,
+            ..
+        } = parameters;
+        let sq_len = sq_off.array as usize + sq_entries as usize * size_of::<u32>();
+        let sq_mmap = Mmap::new(fd.as_raw_fd(), sq_len, IORING_OFF_SQ_RING)?;
+        let cq_len = cq_off.cqes as usize + cq_entries as usize * size_of::<Completion>();
+        let cq_mmap = Mmap::new(fd.as_raw_fd(), cq_len, IORING_OFF_CQ_RING)?;
+        let sqes_len = sq_entries as usize * size_of::<Submission>();
+        let sqes_mmap = Mmap::new(fd.as_raw_fd(), sqes_len, IORING_OFF_SQES)?;
+
+        // SAFETY: the offsets are provided by the kernel and are within the
+        // mapped memory.
+        let (sq, cq) = unsafe {
+            // We always use the submission queue entry at the same index as
+            // the tail, so we can fill the indirection array once.
+            let array = sq_mmap.at::<u32>(sq_off.array);
+            for i in 0..sq_entries {
+                array.add(i as usize).write(i);
+            }
+            let tail = sq_mmap.at::<AtomicU32>(sq_off.tail);
+            let sq = SubmissionQueue {
+                head: sq_mmap.at(sq_off.head),
+                tail,
+                flags: sq_mmap.at(sq_off.flags),
+                mask: *sq_mmap.at::<u32>(sq_off.ring_mask),
+                entries: *sq_mmap.at::<u32>(sq_off.ring_entries),
+                local_tail: (*tail).load(Ordering::Relaxed),
+                entries_ptr: sqes_mmap.at(0),
+            };
+            let cq = CompletionQueue {
+                head: cq_mmap.at(cq_off.head),
+                tail: cq_mmap.at(cq_off.tail),
+                mask: *cq_mmap.at::<u32>(cq_off.ring_mask),
+                entries_ptr: cq_mmap.at(cq_off.cqes),
+            };
+            (sq, cq)
+        };
+
+        Ok(Ring {
+            fd,
+            inner: Mutex::new(Inner {
+                sq,
+                cq,
+                unsubmitted: 0,
+                operations: Vec::new(),
+                free: Vec::new(),
+                dropped: 0,
+                _mmaps: [sq_mmap, cq_mmap, sqes_mmap],
+            }),
+        })
+    }
+
+    /// Submit all queued operations to the kernel.
+    pub(crate) fn submit(&self) -> io::Result<()> {
+        self.inner.lock().unwrap().submit(self.fd.as_raw_fd())
+    }
+
+    /// Process all completed operations, waking the futures waiting on them.
+    pub(crate) fn complete(&self) {
+        let mut wakers = Vec::new();
+        self.inner
+            .lock()
+            .unwrap()
+            .complete(self.fd.as_raw_fd(), &mut wakers);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Start a new operation, returning its index.
+    fn start(
+        &self,
+        mut submission: Submission,
+        resources: Resources,
+        waker: &task::Waker,
+    ) -> io::Result<usize> {
+        let mut inner = self.inner.lock().unwrap();
+        let operation = Operation {
+            state: State::InProgress(Some(waker.clone())),
+            resources,
+        };
+        let free = inner.free.pop();
+        let index = match free {
+            Some(index) => {
+                inner.operations[index] = Some(operation);
+                index
+            }
+            None => {
+                inner.operations.push(Some(operation));
+                inner.operations.len() - 1
+            }
+        };
+        submission.user_data = index as u64;
+        if let Err(err) = inner.push(self.fd.as_raw_fd(), submission) {
+            drop(inner.remove(index));
+            return Err(err);
+        }
+        Ok(index)
+    }
+
+    /// Poll the operation at `index`, returning the result and resources once
+    /// it's completed.
+    fn poll(&self, index: usize, ctx: &mut task::Context<'_>) -> Poll<(i32, Resources)> {
+        let mut inner = self.inner.lock().unwrap();
+        let operation = inner.operations[index].as_mut().unwrap();
+        match &mut operation.state {
+            State::InProgress(waker) => {
+                match waker {
+                    Some(waker) if waker.will_wake(ctx.waker()) => {}
+                    waker => *waker = Some(ctx.waker().clone()),
+                }
+                Poll::Pending
+            }
+            State::Completed(result) => {
+                let result = *result;
+                Poll::Ready((result, inner.remove(index).resources))
+            }
+            State::Dropped => unreachable!("polled dropped io_uring operation"),
+        }
+    }
+
+    /// Drop the operation at `index`, cancelling it if it's still in progress.
+    fn drop_operation(&self, index: usize) {
+        let fd = self.fd.as_raw_fd();
+        let mut inner = self.inner.lock().unwrap();
+        let operation = inner.operations[index].as_mut().unwrap();
+        match operation.state {
+            State::InProgress(..) => {
+                operation.state = State::Dropped;
+                inner.dropped += 1;
+                let cancel = Submission {
+                    opcode: IORING_OP_ASYNC_CANCEL,
+                    fd: -1,
+                    addr: index as u64,
+                    user_data: CANCEL_USER_DATA,
+                    ..Submission::default()
+                };
+                if let Err(err) = inner.push(fd, cancel) {
+                    warn!("failed to cancel io_uring operation: {}", err);
+                }
+                // The operation must be submitted before the file descriptor
+                // it uses is closed, which could happen right after this
+                // returns.
+                if let Err(err) = inner.submit(fd) {
+                    warn!("failed to submit io_uring operations: {}", err);
+                }
+            }
+            State::Completed(result) => inner.remove(index).resources.release(result),
+            State::Dropped => unreachable!("dropped io_uring operation twice"),
+        }
+    }
+}
+
+impl AsRawFd for Ring {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for Ring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ring").field("fd", &self.fd).finish()
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        let fd = self.fd.as_raw_fd();
+        let inner = self.inner.get_mut().unwrap();
+        // The kernel can still access the resources of dropped operations, so
+        // we have to wait until they're completed before freeing them.
+        if let Err(err) = inner.submit(fd) {
+            warn!("failed to submit io_uring operations: {}", err);
+        }
+        while inner.dropped != 0 {
+            match enter(fd, 0, 1, IORING_ENTER_GETEVENTS) {
+                Ok(_) => inner.complete(fd, &mut Vec::new()),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    warn!(
+                        "failed to wait on cancelled io_uring operations, leaking their resources: {}",
+                        err
+                    );
+                    mem::forget(mem::take(&mut inner.operations));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Inner {
+    /// Add `submission` to the submission queue, submitting the queued
+    /// operations if it's full.
+    fn push(&mut self, fd: RawFd, submission: Submission) -> io::Result<()> {
+        if !self.sq.push(submission) {
+            self.submit(fd)?;
+            if !self.sq.push(submission) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "io_uring submission queue is full",
+                ));
+            }
+        }
+        self.unsubmitted += 1;
+        Ok(())
+    }
+
+    /// Submit all queued operations to the kernel.
+    fn submit(&mut self, fd: RawFd) -> io::Result<()> {
+        while self.unsubmitted != 0 {
+            match enter(fd, self.unsubmitted, 0, 0) {
+                Ok(0) => break,
+                Ok(n) => self.unsubmitted -= n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // The kernel doesn't have the resources to process the
+                // submissions at the moment, we'll try again later.
+                Err(ref err) if matches!(err.raw_os_error(), Some(libc::EAGAIN | libc::EBUSY)) => {
+                    break
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Process all completed operations, adding the wakers of the futures to
+    /// wake to `wakers`.
+    fn complete(&mut self, fd: RawFd, wakers: &mut Vec<task::Waker>) {
+        loop {
+            while let Some(completion) = self.cq.pop() {
+                if completion.user_data == CANCEL_USER_DATA {
+                    continue;
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                let index = completion.user_data as usize;
+                let operation = self.operations[index].as_mut().unwrap();
+                match mem::replace(&mut operation.state, State::Completed(completion.res)) {
+                    State::InProgress(Some(waker)) => wakers.push(waker),
+                    State::InProgress(None) => {}
+                    State::Dropped => {
+                        self.dropped -= 1;
+                        self.remove(index).resources.release(completion.res);
+                    }
+                    State::Completed(..) => unreachable!("io_uring operation completed twice"),
+                }
+            }
+
+            // If the completion queue was full the kernel holds on to the
+            // completions, we need to enter the kernel to get them.
+            if self.sq.flags() & IORING_SQ_CQ_OVERFLOW == 0 {
+                break;
+            }
+            if let Err(err) = enter(fd, 0, 0, IORING_ENTER_GETEVENTS) {
+                warn!("failed to flush io_uring completions: {}", err);
+                break;
+            }
+        }
+    }
+
+    /// Remove the operation at `index`.
+    fn remove(&mut self, index: usize) -> Operation {
+        self.free.push(index);
+        self.operations[index].take().unwrap()
+    }
+}
+
+/// Calls `io_uring_enter(2)`.
+fn enter(fd: RawFd, to_submit: u32, min_complete: u32, flags: libc::c_uint) -> io::Result<u32> {
+    let n = unsafe {
+        libc::syscall(
+            libc::SYS_io_uring_enter,
+            fd,
+            to_submit,
+            min_complete,
+            flags,
+            ptr::null::<libc::sigset_t>(),
+            0_usize,
+        )
+    };
+    if n == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        // NOTE: `n` is at most `to_submit`.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(n as u32)
+    }
+}
+
+/// Check if the ring with `fd` and `parameters` supports everything we need.
+fn check_support(fd: RawFd, parameters: &Parameters) -> io::Result<()> {
+    let features = IORING_FEAT_NODROP | IORING_FEAT_FAST_POLL;
+    if parameters.features & features != features {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "kernel is missing required io_uring features",
+        ));
+    }
+
+    // SAFETY: all zeroes is a valid `Probe`.
+    let mut probe: Box<Probe> = unsafe { Box::new(MaybeUninit::zeroed().assume_init()) };
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_io_uring_register,
+            fd,
+            IORING_REGISTER_PROBE,
+            ptr::addr_of_mut!(*probe),
+            probe.ops.len(),
+        )
+    };
+    if res == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    for op in OPERATIONS {
+        if op > probe.last_op || probe.ops[op as usize].flags & IO_URING_OP_SUPPORTED == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("kernel doesn't support io_uring operation {}", op),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Submission queue shared with the kernel.
+struct SubmissionQueue {
+    head: *const AtomicU32,
+    tail: *const AtomicU32,
+    flags: *const AtomicU32,
+    mask: u32,
+    entries: u32,
+    /// Our copy of `tail`, the kernel doesn't change it.
+    local_tail: u32,
+    entries_ptr: *mut Submission,
+}
+
+impl SubmissionQueue {
+    /// Add `submission` to the queue, returns `false` if the queue is full.
+    fn push(&mut self, submission: Submission) -> bool {
+        let head = unsafe { (*self.head).load(Ordering::Acquire) };
+        if self.local_tail.wrapping_sub(head) >= self.entries {
+            return false;
+        }
+        let index = (self.local_tail & self.mask) as usize;
+        unsafe { self.entries_ptr.add(index).write(submission) };
+        self.local_tail = self.local_tail.wrapping_add(1);
+        unsafe { (*self.tail).store(self.local_tail, Ordering::Release) };
+        true
+    }
+
+    /// Returns the flags set by the kernel.
+    fn flags(&self) -> u32 {
+        unsafe { (*self.flags).load(Ordering::Acquire) }
+    }
+}
+
+/// Completion queue shared with the kernel.
+struct CompletionQueue {
+    head: *const AtomicU32,
+    tail: *const AtomicU32,
+    mask: u32,
+    entries_ptr: *const Completion,
+}
+
+impl CompletionQueue {
+    /// Remove the next completion from the queue, if any.
+    fn pop(&mut self) -> Option<Completion> {
+        let head = unsafe { (*self.head).load(Ordering::Relaxed) };
+        let tail = unsafe { (*self.tail).load(Ordering::Acquire) };
+        if head == tail {
+            return None;
+        }
+        let index = (head & self.mask) as usize;
+        let completion = unsafe { self.entries_ptr.add(index).read() };
+        unsafe { (*self.head).store(head.wrapping_add(1), Ordering::Release) };
+        Some(completion)
+    }
+}
+
+/// Memory mapping shared with the kernel.
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    /// Map `len` bytes of the ring with `fd` at `offset`.
+    fn new(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Mmap> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Mmap { ptr, len })
+        }
+    }
+
+    /// Returns a pointer `offset` bytes into the mapping.
+    ///
+    /// # Safety
+    ///
+    /// `offset` must be within the mapping.
+    unsafe fn at<T>(&self, offset: u32) -> *mut T {
+        self.ptr.cast::<u8>().add(offset as usize).cast()
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if unsafe { libc::munmap(self.ptr, self.len) } == -1 {
+            let err = io::Error::last_os_error();
+            warn!("error unmapping io_uring memory: {}", err);
+        }
+    }
+}
+
+/// State of an operation submitted to a [`Ring`].
+struct Operation {
+    state: State,
+    /// Resources used by the kernel while the operation is in progress.
+    resources: Resources,
+}
+
+#[allow(variant_size_differences)] // Can't make the waker smaller.
+enum State {
+    /// The operation is in progress, the waker is woken once it's completed.
+    InProgress(Option<task::Waker>),
+    /// The operation is completed with the result.
+    Completed(i32),
+    /// The [`Op`] was dropped and the operation cancelled, but the kernel
+    /// hasn't completed the operation yet.
+    Dropped,
+}
+
+/// Resources owned by an operation.
+enum Resources {
+    /// Buffer to receive bytes into.
+    Recv(Vec<u8>),
+    /// Bytes to send.
+    Send(Vec<u8>),
+    /// Buffer to read bytes from a file into, used in
+    /// [`Op::poll_send_file`].
+    Read(Vec<u8>),
+    /// Address of the peer of an accepted connection.
+    Accept(Box<Address>),
+    /// Address to connect to.
+    Connect(Box<Address>),
+}
+
+impl Resources {
+    /// Release the resources of a dropped operation that completed with
+    /// `result`.
+    fn release(self, result: i32) {
+        if let Resources::Accept(..) = self {
+            if result >= 0 {
+                // Close the accepted connection nobody is going to use.
+                drop(unsafe { OwnedFd::from_raw_fd(result) });
+            }
+        }
+    }
+}
+
+/// Socket address storage.
+struct Address {
+    storage: libc::sockaddr_storage,
+    len: libc::socklen_t,
+}
+
+/// Operation submitted to the [`Ring`] of a worker thread, used by the I/O
+/// futures.
+///
+/// If the `Op` is dropped while the operation is in progress the operation is
+/// cancelled.
+pub(crate) struct Op {
+    /// Ring the operation was submitted to and the index of the operation.
+    submitted: Option<(Arc<Ring>, usize)>,
+}
+
+impl Op {
+    /// Create a new `Op`, no operation is started until it's polled.
+    pub(crate) const fn new() -> Op {
+        Op { submitted: None }
+    }
+
+    /// Receive bytes from socket `fd` into `buf`.
+    ///
+    /// Returns `None` if the current thread doesn't have a ring, in which case
+    /// the caller must fall back to readiness based I/O. The same is true for
+    /// all other `poll_*` methods.
+    pub(crate) fn poll_recv<B>(
+        &mut self,
+        ctx: &mut task::Context<'_>,
+        fd: RawFd,
+        buf: &mut B,
+    ) -> Option<Poll<io::Result<usize>>>
+    where
+        B: Bytes,
+    {
+        let len = buf.as_bytes().len();
+        let poll = self.poll(
+            ctx,
+            || Resources::Recv(Vec::with_capacity(len)),
+            |resources| prepare(resources, fd, 0),
+        )?;
+        Some(poll.map_ok(|(n, resources)| {
+            let n = n as usize;
+            if let Resources::Recv(received) = resources {
+                let dst = buf.as_bytes();
+                debug_assert!(n <= dst.len() && n <= received.capacity());
+                // SAFETY: the kernel initialised `n` bytes of `received` and
+                // `dst` is at least `n` bytes long as it's as long as the
+                // capacity of `received`.
+                unsafe {
+                    ptr::copy_nonoverlapping(received.as_ptr(), dst.as_mut_ptr().cast(), n);
+                    buf.update_length(n);
+                }
+            }
+            n
+        }))
+    }
+
+    /// Send the bytes in `buf` to socket `fd`.
+    pub(crate) fn poll_send(
+        &mut self,
+        ctx: &mut task::Context<'_>,
+        fd: RawFd,
+        buf: &[u8],
+    ) -> Option<Poll<io::Result<usize>>> {
+        let poll = self.poll(
+            ctx,
+            || Resources::Send(buf.to_vec()),
+            |resources| prepare(resources, fd, 0),
+        )?;
+        Some(poll.map_ok(|(n, _)| n as usize))
+    }
+
+    /// Send at most `length` bytes of `file`, starting at `offset`, to socket
+    /// `fd`.
+    ///
+    /// io_uring doesn't support `sendfile(2)`, so the bytes are read into a
+    /// buffer, at most 64 KB at a time, and then send using a `send`
+    /// operation.
+    pub(crate) fn poll_send_file(
+        &mut self,
+        ctx: &mut task::Context<'_>,
+        fd: RawFd,
+        file: RawFd,
+        offset: usize,
+        length: Option<NonZeroUsize>,
+    ) -> Option<Poll<io::Result<usize>>> {
+        let len = length.map_or(SEND_FILE_BUF_SIZE, |length| {
+            length.get().min(SEND_FILE_BUF_SIZE)
+        });
+        let prepare = |resources: &mut Resources| match resources {
+            Resources::Read(..) => prepare(resources, file, offset as u64),
+            _ => prepare(resources, fd, 0),
+        };
+        let mut read = None;
+        loop {
+            let poll = match read.take() {
+                None => self.poll(ctx, || Resources::Read(Vec::with_capacity(len)), prepare),
+                Some(buf) => self.poll(ctx, || Resources::Send(buf), prepare),
+            }?;
+            match poll {
+                // Read bytes from the file, now send them.
+                Poll::Ready(Ok((n, Resources::Read(mut buf)))) if n != 0 => {
+                    // SAFETY: the kernel initialised `n` bytes.
+                    unsafe { buf.set_len(n as usize) };
+                    read = Some(buf);
+                }
+                // Send the bytes, or read zero bytes from the file.
+                Poll::Ready(Ok((n, _))) => return Some(Poll::Ready(Ok(n as usize))),
+                Poll::Ready(Err(err)) => return Some(Poll::Ready(Err(err))),
+                Poll::Pending => return Some(Poll::Pending),
+            }
+        }
+    }
+
+    /// Accept a connection on listening socket `fd`.
+    pub(crate) fn poll_accept(
+        &mut self,
+        ctx: &mut task::Context<'_>,
+        fd: RawFd,
+    ) -> Option<Poll<io::Result<(OwnedFd, SockAddr)>>> {
+        let resources = || {
+            Resources::Accept(Box::new(Address {
+                // SAFETY: all zeroes is a valid `sockaddr_storage`.
+                storage: unsafe { mem::zeroed() },
+                #[allow(clippy::cast_possible_truncation)]
+                len: size_of::<libc::sockaddr_storage>() as libc::socklen_t,
+            }))
+        };
+        let poll = self.poll(ctx, resources, |resources| prepare(resources, fd, 0))?;
+        Some(poll.map_ok(|(stream_fd, resources)| {
+            // SAFETY: the kernel returned a new file descriptor.
+            let stream_fd = unsafe { OwnedFd::from_raw_fd(stream_fd as RawFd) };
+            let address = match resources {
+                // SAFETY: the kernel initialised the address.
+                Resources::Accept(address) => unsafe {
+                    SockAddr::new(address.storage, address.len)
+                },
+                _ => unreachable!(),
+            };
+            (stream_fd, address)
+        }))
+    }
+
+    /// Connect socket `fd` to `address`.
+    pub(crate) fn poll_connect(
+        &mut self,
+        ctx: &mut task::Context<'_>,
+        fd: RawFd,
+        address: &SockAddr,
+    ) -> Option<Poll<io::Result<()>>> {
+        let resources = || {
+            // SAFETY: all zeroes is a valid `sockaddr_storage`.
+            let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+            // SAFETY: `SockAddr` is at most `sockaddr_storage` bytes.
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    address.as_ptr().cast::<u8>(),
+                    ptr::addr_of_mut!(storage).cast::<u8>(),
+                    address.len() as usize,
+                );
+            }
+            Resources::Connect(Box::new(Address {
+                storage,
+                len: address.len(),
+            }))
+        };
+        let poll = self.poll(ctx, resources, |resources| prepare(resources, fd, 0))?;
+        Some(poll.map_ok(|_| ()))
+    }
+
+    /// Poll the operation, starting it if it's not in progress.
+    ///
+    /// The operation is started using the `resources` and the submission
+    /// returned by `prepare`. Returns `None` if the current thread doesn't have
+    /// a ring.
+    fn poll<R, P>(
+        &mut self,
+        ctx: &mut task::Context<'_>,
+        resources: R,
+        prepare: P,
+    ) -> Option<Poll<io::Result<(u32, Resources)>>>
+    where
+        R: FnOnce() -> Resources,
+        P: FnOnce(&mut Resources) -> Submission,
+    {
+        let (ring, mut resources) = match self.submitted.take() {
+            Some((ring, index)) => match ring.poll(index, ctx) {
+                // The kernel should wait for the socket to become ready and
+                // not return `EAGAIN`, but if it does we start the operation
+                // again.
+                Poll::Ready((res, resources)) if res == -libc::EAGAIN => (current()?, resources),
+                Poll::Ready((res, _)) if res < 0 => {
+                    return Some(Poll::Ready(Err(io::Error::from_raw_os_error(-res))))
+                }
+                #[allow(clippy::cast_sign_loss)]
+                Poll::Ready((res, resources)) => {
+                    return Some(Poll::Ready(Ok((res as u32, resources))))
+                }
+                Poll::Pending => {
+                    self.submitted = Some((ring, index));
+                    return Some(Poll::Pending);
+                }
+            },
+            None => (current()?, resources()),
+        };
+
+        let submission = prepare(&mut resources);
+        Some(match ring.start(submission, resources, ctx.waker()) {
+            Ok(index) => {
+                self.submitted = Some((ring, index));
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        })
+    }
+}
+
+impl Drop for Op {
+    fn drop(&mut self) {
+        if let Some((ring, index)) = self.submitted.take() {
+            ring.drop_operation(index);
+        }
+    }
+}
+
+impl fmt::Debug for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Op")
+            .field("in_progress", &self.submitted.is_some())
+            .finish()
+    }
+}
+
+/// Prepare the submission for the operation on `fd` using `resources`.
+///
+/// `offset` is only used when reading from a file.
+#[allow(clippy::cast_sign_loss)]
+fn prepare(resources: &mut Resources, fd: RawFd, offset: u64) -> Submission {
+    let (opcode, addr, len, off, op_flags) = match resources {
+        Resources::Recv(buf) => (
+            IORING_OP_RECV,
+            buf.as_mut_ptr() as u64,
+            buf.capacity(),
+            0,
+            0,
+        ),
+        Resources::Send(buf) => (
+            IORING_OP_SEND,
+            buf.as_ptr() as u64,
+            buf.len(),
+            0,
+            libc::MSG_NOSIGNAL as u32,
+        ),
+        Resources::Read(buf) => (
+            IORING_OP_READ,
+            buf.as_mut_ptr() as u64,
+            buf.capacity(),
+            offset,
+            0,
+        ),
+        Resources::Accept(address) => (
+            IORING_OP_ACCEPT,
+            ptr::addr_of_mut!(address.storage) as u64,
+            0,
+            ptr::addr_of_mut!(address.len) as u64,
+            (libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC) as u32,
+        ),
+        Resources::Connect(address) => (
+            IORING_OP_CONNECT,
+            ptr::addr_of_mut!(address.storage) as u64,
+            0,
+            u64::from(address.len),
+            0,
+        ),
+    };
+    Submission {
+        opcode,
+        fd,
+        off,
+        addr,
+        // NOTE: the kernel can't process more than `u32::MAX` bytes at a time
+        // anyway.
+        #[allow(clippy::cast_possible_truncation)]
+        len: len.min(u32::MAX as usize) as u32,
+        op_flags,
+        ..Submission::default()
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{self, Poll, Wake};
+
+use super::{probe, set_current, Op, Ring};
+
+const DATA: &[u8] = b"Hello world";
+
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Returns `None` if io_uring is not supported, in which case the test is
+/// skipped.
+fn setup() -> Option<(Arc<Ring>, UnixStream, UnixStream)> {
+    if let Err(err) = probe() {
+        eprintln!("skipping test: io_uring not supported: {}", err);
+        return None;
+    }
+    let ring = Arc::new(Ring::new(8).unwrap());
+    set_current(Some(ring.clone()));
+    let (s1, s2) = UnixStream::pair().unwrap();
+    s1.set_nonblocking(true).unwrap();
+    Some((ring, s1, s2))
+}
+
+/// Complete operations until `woken` is set.
+fn complete_until(ring: &Ring, woken: &Woken) {
+    while !woken.0.load(Ordering::Acquire) {
+        ring.submit().unwrap();
+        ring.complete();
+    }
+}
+
+#[test]
+fn recv() {
+    let (ring, s1, mut s2) = match setup() {
+        Some(setup) => setup,
+        None => return,
+    };
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = task::Waker::from(woken.clone());
+    let mut ctx = task::Context::from_waker(&waker);
+
+    let mut op = Op::new();
+    let mut buf = Vec::with_capacity(64);
+    let poll = op.poll_recv(&mut ctx, s1.as_raw_fd(), &mut buf).unwrap();
+    assert!(poll.is_pending());
+    ring.submit().unwrap();
+
+    s2.write_all(DATA).unwrap();
+    complete_until(&ring, &woken);
+    match op.poll_recv(&mut ctx, s1.as_raw_fd(), &mut buf).unwrap() {
+        Poll::Ready(Ok(n)) => assert_eq!(n, DATA.len()),
+        poll => panic!("unexpected result: {:?}", poll),
+    }
+    assert_eq!(buf, DATA);
+    set_current(None);
+}
+
+#[test]
+fn send() {
+    let (ring, s1, mut s2) = match setup() {
+        Some(setup) => setup,
+        None => return,
+    };
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = task::Waker::from(woken.clone());
+    let mut ctx = task::Context::from_waker(&waker);
+
+    let mut op = Op::new();
+    let poll = op.poll_send(&mut ctx, s1.as_raw_fd(), DATA).unwrap();
+    if poll.is_pending() {
+        complete_until(&ring, &woken);
+        match op.poll_send(&mut ctx, s1.as_raw_fd(), DATA).unwrap() {
+            Poll::Ready(Ok(n)) => assert_eq!(n, DATA.len()),
+            poll => panic!("unexpected result: {:?}", poll),
+        }
+    }
+    let mut buf = [0; DATA.len()];
+    s2.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, DATA);
+    set_current(None);
+}
+
+#[test]
+fn dropped_operation_is_cancelled() {
+    let (ring, mut s1, mut s2) = match setup() {
+        Some(setup) => setup,
+        None => return,
+    };
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = task::Waker::from(woken.clone());
+    let mut ctx = task::Context::from_waker(&waker);
+
+    let mut op = Op::new();
+    let mut buf = Vec::with_capacity(64);
+    let poll = op.poll_recv(&mut ctx, s1.as_raw_fd(), &mut buf).unwrap();
+    assert!(poll.is_pending());
+    ring.submit().unwrap();
+    drop(op);
+
+    // Once the cancellation is completed the kernel no longer uses the buffer.
+    while ring.inner.lock().unwrap().dropped != 0 {
+        ring.submit().unwrap();
+        ring.complete();
+    }
+    assert!(!woken.0.load(Ordering::Acquire));
+
+    // The data should not be consumed by the cancelled operation.
+    s2.write_all(DATA).unwrap();
+    s1.set_nonblocking(false).unwrap();
+    let mut buf = [0; DATA.len()];
+    s1.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, DATA);
+    set_current(None);
+}
+
+#[test]
+fn no_ring() {
+    set_current(None);
+    let (s1, _s2) = UnixStream::pair().unwrap();
+    let waker = task::Waker::from(Arc::new(Woken(AtomicBool::new(false))));
+    let mut ctx = task::Context::from_waker(&waker);
+    let mut op = Op::new();
+    assert!(op.poll_send(&mut ctx, s1.as_raw_fd(), DATA).is_none());
+}
@@ -0,0 +1,77 @@
+//! Stand-in for the io_uring I/O backend on platforms that don't support it.
+//!
+//! All I/O futures use readiness based I/O, see the Linux version of this
+//! module for more information.
+
+use std::io;
+use std::num::NonZeroUsize;
+use std::os::unix::io::{OwnedFd, RawFd};
+use std::task::{self, Poll};
+
+use socket2::SockAddr;
+
+use crate::bytes::Bytes;
+
+/// Always returns `false`.
+pub(crate) const fn in_use() -> bool {
+    false
+}
+
+/// Operation that is never started, all `poll_*` methods return `None`.
+#[derive(Debug)]
+pub(crate) struct Op;
+
+impl Op {
+    pub(crate) const fn new() -> Op {
+        Op
+    }
+
+    pub(crate) fn poll_recv<B>(
+        &mut self,
+        _: &mut task::Context<'_>,
+        _: RawFd,
+        _: &mut B,
+    ) -> Option<Poll<io::Result<usize>>>
+    where
+        B: Bytes,
+    {
+        None
+    }
+
+    pub(crate) fn poll_send(
+        &mut self,
+        _: &mut task::Context<'_>,
+        _: RawFd,
+        _: &[u8],
+    ) -> Option<Poll<io::Result<usize>>> {
+        None
+    }
+
+    pub(crate) fn poll_send_file(
+        &mut self,
+        _: &mut task::Context<'_>,
+        _: RawFd,
+        _: RawFd,
+        _: usize,
+        _: Option<NonZeroUsize>,
+    ) -> Option<Poll<io::Result<usize>>> {
+        None
+    }
+
+    pub(crate) fn poll_accept(
+        &mut self,
+        _: &mut task::Context<'_>,
+        _: RawFd,
+    ) -> Option<Poll<io::Result<(OwnedFd, SockAddr)>>> {
+        None
+    }
+
+    pub(crate) fn poll_connect(
+        &mut self,
+        _: &mut task::Context<'_>,
+        _: RawFd,
+        _: &SockAddr,
+    ) -> Option<Poll<io::Result<()>>> {
+        None
+    }
+}
@@ -22,7 +22,8 @@
 //! such option is the number of threads the runtime uses, this can configured
 //! with the [`num_threads`] and [`use_all_cores`] methods. When using
 //! `use_all_cores` the CPU affinity can automatically be set using
-//! [`auto_cpu_affinity`].
+//! [`auto_cpu_affinity`], or the CPUs to use can be picked using
+//! [`worker_cpus`].
 //!
 //! Once the runtime is fully configured it can be [`build`], which returns the
 //! [`Runtime`] type.
@@ -49,6 +50,7 @@
 //! [`num_threads`]: Setup::num_threads
 //! [`use_all_cores`]: Setup::use_all_cores
 //! [`auto_cpu_affinity`]: Setup::auto_cpu_affinity
+//! [`worker_cpus`]: Setup::worker_cpus
 //! [`build`]: Setup::build
 //! [`try_spawn`]: Runtime::try_spawn
 //! [`spawn_sync_actor`]: Runtime::spawn_sync_actor
@@ -127,6 +129,7 @@
 
 #![feature(
     async_iterator,
+    backtrace,
     const_option,
     doc_auto_cfg,
     doc_cfg_hide,
@@ -190,26 +193,39 @@ macro_rules! try_io {
 
 use std::convert::TryInto;
 use std::future::Future;
+use std::pin::Pin;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::task::Poll;
 use std::time::Duration;
 use std::time::Instant;
 use std::{io, task};
 
 use ::log::{as_debug, debug, warn};
 use heph::actor::{self, NewActor, SyncActor};
-use heph::actor_ref::{ActorGroup, ActorRef};
+use heph::actor_ref::ActorRef;
 use heph::supervisor::{Supervisor, SyncSupervisor};
 use heph_inbox as inbox;
-use mio::{event, Interest, Token};
+use mio::{event, Interest, Registry, Token};
 
 pub mod access;
+pub(crate) mod blocking;
 pub mod bytes;
 pub(crate) mod channel;
+#[cfg(target_os = "linux")]
+pub mod child;
+pub mod codec;
 mod coordinator;
 mod error;
+pub mod fs;
+#[cfg(target_os = "linux")]
+pub(crate) mod io_uring;
+#[cfg(not(target_os = "linux"))]
+#[path = "io_uring_unsupported.rs"]
+pub(crate) mod io_uring;
 pub(crate) mod local;
 pub mod log;
+pub mod metrics;
 pub mod net;
 pub mod pipe;
 mod process;
@@ -235,12 +251,15 @@ pub(crate) use process::ProcessId;
 #[doc(no_inline)]
 pub use access::{Access, Sync, ThreadLocal, ThreadSafe};
 pub use error::Error;
-pub use setup::Setup;
-pub use signal::Signal;
+pub use setup::{CpuSet, Setup};
+pub use signal::{Signal, SignalSet};
 
 use coordinator::Coordinator;
 use local::waker::MAX_THREADS;
-use spawn::{ActorOptions, AddActorError, FutureOptions, PrivateSpawn, Spawn, SyncActorOptions};
+use spawn::{
+    ActorOptions, AddActorError, FutureOptions, JoinHandle, PrivateSpawn, Spawn, SpawnBlocking,
+    SyncActorOptions,
+};
 use sync_worker::SyncWorker;
 
 pub(crate) const SYNC_WORKER_ID_START: usize = 10000;
@@ -286,7 +305,7 @@ pub struct Runtime {
     /// Synchronous actor threads.
     sync_actors: Vec<SyncWorker>,
     /// List of actor references that want to receive process signals.
-    signals: ActorGroup<Signal>,
+    signals: signal::Receivers,
     /// Trace log.
     trace_log: Option<trace::CoordinatorLog>,
 }
@@ -294,7 +313,9 @@ pub struct Runtime {
 impl Runtime {
     /// Setup a new `Runtime`.
     ///
-    /// See [`Setup`] for the available configuration options.
+    /// See [`Setup`] for the available configuration options. To configure the
+    /// runtime using environment variables or a configuration file see
+    /// [`Setup::from_env`] and [`Setup::from_config_file`].
     pub const fn setup() -> Setup {
         Setup::new()
     }
@@ -377,13 +398,8 @@ impl Runtime {
             debug!(sync_worker_id = id; "spawning synchronous actor");
         }
 
-        #[allow(clippy::cast_possible_truncation)]
-        // Safety: MAX_THREADS always fits in u32.
-        let trace_log = self
-            .trace_log
-            .as_ref()
-            .map(|trace_log| trace_log.new_stream(id as u32));
         let shared = self.coordinator.shared_internals().clone();
+        let trace_log = self.trace_log.as_ref();
         SyncWorker::start(id, supervisor, actor, arg, options, shared, trace_log)
             .map(|(worker, actor_ref)| {
                 self.sync_actors.push(worker);
@@ -395,15 +411,36 @@ impl Runtime {
     /// Spawn a thread-safe [`Future`].
     ///
     /// See [`RuntimeRef::spawn_future`] for more documentation.
-    pub fn spawn_future<Fut>(&mut self, future: Fut, options: FutureOptions)
+    pub fn spawn_future<Fut>(
+        &mut self,
+        future: Fut,
+        options: FutureOptions,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + Send + std::marker::Sync + 'static,
+        Fut: Future + Send + std::marker::Sync + 'static,
+        Fut::Output: Send,
     {
         self.coordinator
             .shared_internals()
             .spawn_future(future, options)
     }
 
+    /// Returns the registry for user defined metrics.
+    ///
+    /// See the [`metrics`] module for more information.
+    pub fn metrics_registry(&self) -> &metrics::Registry {
+        self.coordinator.shared_internals().metrics_registry()
+    }
+
+    /// Returns a snapshot of the metrics of the runtime.
+    ///
+    /// See [`RuntimeMetrics`] for the metrics collected.
+    ///
+    /// [`RuntimeMetrics`]: metrics::RuntimeMetrics
+    pub fn metrics(&self) -> metrics::RuntimeMetrics {
+        metrics::RuntimeMetrics::collect(self.coordinator.shared_internals())
+    }
+
     /// Run the function `f` on all worker threads.
     ///
     /// This can be used to spawn thread-local actors, e.g. [`TcpServer`], or to
@@ -431,11 +468,22 @@ impl Runtime {
     /// Receive [process signals] as messages.
     ///
     /// This adds the `actor_ref` to the list of actor references that will
-    /// receive a process signal.
+    /// receive a process signal. Only the signals handled by default are
+    /// relayed, see [`Signal`] for which.
     ///
     /// [process signals]: Signal
     pub fn receive_signals(&mut self, actor_ref: ActorRef<Signal>) {
-        self.signals.add(actor_ref);
+        self.signals.add(actor_ref, SignalSet::DEFAULT);
+    }
+
+    /// Receive the [process signals] in `signals` as messages.
+    ///
+    /// This works like [`Runtime::receive_signals`], but only relays the
+    /// signals in `signals` to `actor_ref`.
+    ///
+    /// [process signals]: Signal
+    pub fn receive_signals_filtered(&mut self, actor_ref: ActorRef<Signal>, signals: SignalSet) {
+        self.signals.add(actor_ref, signals);
     }
 
     /// Run the runtime.
@@ -453,6 +501,80 @@ impl Runtime {
         self.coordinator
             .run(self.workers, self.sync_actors, self.signals, self.trace_log)
     }
+
+    /// Run the runtime until `future` is completed, returning its output.
+    ///
+    /// This starts the runtime, like [`Runtime::start`], and runs `future` as a
+    /// thread-safe future (see [`Runtime::spawn_future`]). Once `future` is
+    /// completed the runtime is shut down: all actors and futures that are
+    /// still running are stopped (dropped). Synchronous actors can't be
+    /// stopped, so those are not waited on.
+    ///
+    /// If the runtime stops before `future` is completed, e.g. because it
+    /// panicked or the process received a signal that no actor handled, this
+    /// returns an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heph_rt::Runtime;
+    ///
+    /// let runtime = Runtime::setup().num_threads(2).build()?;
+    /// let output = runtime.block_on(async { 1 + 1 })?;
+    /// assert_eq!(output, 2);
+    /// # Ok::<(), heph_rt::Error>(())
+    /// ```
+    pub fn block_on<Fut>(mut self, future: Fut) -> Result<Fut::Output, Error>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let shutdown = self.coordinator.shutdown_handle()?;
+        let (sender, mut receiver) = inbox::oneshot::new_oneshot();
+        let _ = self.spawn_future(
+            BlockOn {
+                future: Box::pin(future),
+                sender: Some(sender),
+                shutdown: Some(shutdown),
+            },
+            FutureOptions::default(),
+        );
+        self.start()?;
+        receiver
+            .try_recv()
+            .map_err(|_| Error::block_on_incomplete())
+    }
+}
+
+/// The [`Future`] spawned by [`Runtime::block_on`].
+struct BlockOn<Fut: Future> {
+    future: Pin<Box<Fut>>,
+    sender: Option<inbox::oneshot::Sender<Fut::Output>>,
+    /// Shuts down the runtime once dropped, i.e. once `future` is completed
+    /// (or if it panics).
+    shutdown: Option<coordinator::Shutdown>,
+}
+
+// Safety: `future` is only accessed mutably (in `poll`), never through a
+// shared reference, so it doesn't need to be `Sync`.
+unsafe impl<Fut: Future + Send> std::marker::Sync for BlockOn<Fut> where Fut::Output: Send {}
+
+impl<Fut: Future> Future for BlockOn<Fut> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = Pin::into_inner(self);
+        match this.future.as_mut().poll(ctx) {
+            Poll::Ready(output) => {
+                if let Some(sender) = this.sender.take() {
+                    let _ = sender.try_send(output);
+                }
+                drop(this.shutdown.take());
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
 }
 
 impl<S, NA> Spawn<S, NA, ThreadSafe> for Runtime
@@ -578,10 +700,40 @@ impl RuntimeRef {
     /// Similar to thread-local actors this will only run on a single thread.
     /// See the discussion of thread-local vs. thread-safe actors in the
     /// [`actor`] module for additional information.
+    ///
+    /// Returns a [`JoinHandle`] that can be used to retrieve the output of the
+    /// future or to abort it. Dropping the handle does *not* stop the future.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(never_type)]
+    ///
+    /// use heph::actor;
+    /// use heph_rt::spawn::{FutureOptions, JoinError};
+    /// use heph_rt::ThreadLocal;
+    ///
+    /// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) {
+    ///     let options = FutureOptions::default();
+    ///     let handle = ctx.runtime().spawn_local_future(async { 1 + 1 }, options.clone());
+    ///     assert_eq!(handle.await, Ok(2));
+    ///
+    ///     // Aborting a future.
+    ///     let future = std::future::pending::<()>();
+    ///     let handle = ctx.runtime().spawn_local_future(future, options);
+    ///     handle.abort();
+    ///     assert_eq!(handle.await, Err(JoinError::Cancelled));
+    /// }
+    /// # drop(actor); // Silence dead code warnings.
+    /// ```
     #[allow(clippy::needless_pass_by_value)]
-    pub fn spawn_local_future<Fut>(&mut self, future: Fut, options: FutureOptions)
+    pub fn spawn_local_future<Fut>(
+        &mut self,
+        future: Fut,
+        options: FutureOptions,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + 'static,
+        Fut: Future + 'static,
     {
         self.internals
             .scheduler
@@ -594,24 +746,128 @@ impl RuntimeRef {
     /// Similar to thread-safe actors this can run on any of the workers
     /// threads. See the discussion of thread-local vs. thread-safe actors in
     /// the [`actor`] module for additional information.
-    pub fn spawn_future<Fut>(&mut self, future: Fut, options: FutureOptions)
+    ///
+    /// Returns a [`JoinHandle`], see [`RuntimeRef::spawn_local_future`].
+    pub fn spawn_future<Fut>(
+        &mut self,
+        future: Fut,
+        options: FutureOptions,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + Send + std::marker::Sync + 'static,
+        Fut: Future + Send + std::marker::Sync + 'static,
+        Fut::Output: Send,
     {
         self.internals.shared.spawn_future(future, options)
     }
 
+    /// Run the blocking function `f` on the pool of blocking threads.
+    ///
+    /// Actors shouldn't block the worker thread they're running on, as that
+    /// prevents all other actors on the same thread from running. Functions
+    /// that do block, e.g. compression or calls to a blocking C library, can
+    /// instead be run on the pool of blocking threads managed by the runtime.
+    /// The number of threads in the pool can be set using
+    /// [`Setup::blocking_threads`].
+    ///
+    /// The returned [`Future`] returns the value returned by `f`. If `f`
+    /// panics the panic is resumed in the actor awaiting the future.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(never_type)]
+    ///
+    /// use heph::actor;
+    /// use heph_rt::ThreadLocal;
+    ///
+    /// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) {
+    ///     let sum = ctx.runtime().spawn_blocking(|| (1..=100u64).sum::<u64>()).await;
+    ///     assert_eq!(sum, 5050);
+    /// }
+    /// # drop(actor); // Silence dead code warnings.
+    /// ```
+    pub fn spawn_blocking<F, T>(&mut self, f: F) -> SpawnBlocking<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        SpawnBlocking::new(self.internals.shared.blocking(), f)
+    }
+
     /// Receive [process signals] as messages.
     ///
     /// This adds the `actor_ref` to the list of actor references that will
-    /// receive a process signal.
+    /// receive a process signal. Only the signals handled by default are
+    /// relayed, see [`Signal`] for which.
     ///
     /// [process signals]: Signal
     pub fn receive_signals(&mut self, actor_ref: ActorRef<Signal>) {
+        self.receive_signals_filtered(actor_ref, SignalSet::DEFAULT)
+    }
+
+    /// Receive the [process signals] in `signals` as messages.
+    ///
+    /// This works like [`RuntimeRef::receive_signals`], but only relays the
+    /// signals in `signals` to `actor_ref`. Calling this multiple times with
+    /// the same actor reference adds the signals to the signals it already
+    /// receives.
+    ///
+    /// [process signals]: Signal
+    ///
+    /// # Examples
+    ///
+    /// Reloading configuration on `SIGHUP`. Note that this requires the runtime
+    /// to handle `SIGHUP`, see [`Setup::handle_signals`].
+    ///
+    /// ```
+    /// #![feature(never_type)]
+    ///
+    /// use heph::actor;
+    /// use heph_rt::{Signal, SignalSet, ThreadLocal};
+    ///
+    /// async fn actor(mut ctx: actor::Context<Signal, ThreadLocal>) {
+    ///     let actor_ref = ctx.actor_ref();
+    ///     ctx.runtime().receive_signals_filtered(actor_ref, SignalSet::from(Signal::Hangup));
+    ///
+    ///     while let Ok(signal) = ctx.receive_next().await {
+    ///         assert_eq!(signal, Signal::Hangup);
+    ///         // Reload configuration.
+    ///     }
+    /// }
+    /// # drop(actor); // Silence dead code warnings.
+    /// ```
+    pub fn receive_signals_filtered(&mut self, actor_ref: ActorRef<Signal>, signals: SignalSet) {
         self.internals
             .signal_receivers
             .borrow_mut()
-            .add_unique(actor_ref)
+            .add(actor_ref, signals)
+    }
+
+    /// Returns the registry for user defined metrics.
+    ///
+    /// See the [`metrics`] module for more information.
+    pub fn metrics_registry(&self) -> &metrics::Registry {
+        self.internals.shared.metrics_registry()
+    }
+
+    /// Returns a snapshot of the metrics of the runtime.
+    ///
+    /// See [`RuntimeMetrics`] for the metrics collected.
+    ///
+    /// [`RuntimeMetrics`]: metrics::RuntimeMetrics
+    pub fn metrics(&self) -> metrics::RuntimeMetrics {
+        metrics::RuntimeMetrics::collect(&self.internals.shared)
+    }
+
+    /// Dump the trace events kept in memory by the flight recorder to a file.
+    ///
+    /// This does nothing if the flight recorder isn't enabled, see
+    /// [`Setup::enable_flight_recorder`].
+    pub fn dump_trace(&self) -> io::Result<()> {
+        match &*self.internals.trace_log.borrow() {
+            Some(trace_log) => trace_log.dump(),
+            None => Ok(()),
+        }
     }
 
     /// Register an `event::Source`, see [`mio::Registry::register`].
@@ -631,6 +887,11 @@ impl RuntimeRef {
             .register(source, token, interest)
     }
 
+    /// Returns a clone of the registry, see [`mio::Registry::try_clone`].
+    pub(crate) fn try_clone_registry(&self) -> io::Result<Registry> {
+        self.internals.poll.borrow().registry().try_clone()
+    }
+
     /// Reregister an `event::Source`, see [`mio::Registry::reregister`].
     pub(crate) fn reregister<S>(
         &mut self,
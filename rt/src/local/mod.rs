@@ -2,12 +2,12 @@
 
 use std::cell::RefCell;
 use std::num::NonZeroUsize;
+use std::rc::Rc;
 use std::sync::Arc;
 
-use heph::actor_ref::ActorGroup;
 use mio::Poll;
 
-use crate::{shared, trace, Signal};
+use crate::{shared, signal, trace};
 
 mod scheduler;
 mod timers;
@@ -33,11 +33,14 @@ pub(super) struct RuntimeInternals {
     /// Timers, deadlines and timeouts.
     pub(crate) timers: RefCell<Timers>,
     /// Actor references to relay received `Signal`s to.
-    pub(super) signal_receivers: RefCell<ActorGroup<Signal>>,
+    pub(super) signal_receivers: RefCell<signal::Receivers>,
     /// CPU affinity of the worker thread, or `None` if not set.
     pub(super) cpu: Option<usize>,
     /// Log used for tracing, `None` is tracing is disabled.
-    pub(super) trace_log: RefCell<Option<trace::Log>>,
+    ///
+    /// Shared with the hooks that record the message flows, see
+    /// [`trace::set_flow_log`].
+    pub(super) trace_log: trace::ThreadLog,
 }
 
 impl RuntimeInternals {
@@ -57,9 +60,9 @@ impl RuntimeInternals {
             scheduler: RefCell::new(Scheduler::new()),
             poll: RefCell::new(poll),
             timers: RefCell::new(Timers::new()),
-            signal_receivers: RefCell::new(ActorGroup::empty()),
+            signal_receivers: RefCell::new(signal::Receivers::new()),
             cpu,
-            trace_log: RefCell::new(trace_log),
+            trace_log: Rc::new(RefCell::new(trace_log)),
         }
     }
 }
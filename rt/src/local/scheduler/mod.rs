@@ -10,11 +10,13 @@ use std::mem::MaybeUninit;
 use std::pin::Pin;
 
 use heph::actor::NewActor;
+use heph::actor_ref::Envelope;
 use heph::supervisor::Supervisor;
 use heph_inbox::Manager;
 use log::{debug, trace};
 
 use crate::process::{self, ActorProcess, FutureProcess, ProcessId};
+use crate::spawn::join::{self, JoinHandle};
 use crate::spawn::options::Priority;
 use crate::{ptr_as_usize, ThreadLocal};
 
@@ -73,16 +75,22 @@ impl Scheduler {
         }
     }
 
-    pub(crate) fn add_future<Fut>(&mut self, future: Fut, priority: Priority)
+    pub(crate) fn add_future<Fut>(
+        &mut self,
+        future: Fut,
+        priority: Priority,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + 'static,
+        Fut: Future + 'static,
     {
+        let (handle, completer) = join::new();
         let process = Box::pin(ProcessData::new(
             priority,
-            Box::pin(FutureProcess::<Fut, ThreadLocal>::new(future)),
+            Box::pin(FutureProcess::<Fut, ThreadLocal>::new(future, completer)),
         ));
         debug!(pid = process.as_ref().id().0; "spawning thread-local future");
-        self.ready.push(process)
+        self.ready.push(process);
+        handle
     }
 
     /// Mark the process, with `pid`, as ready to run.
@@ -133,7 +141,7 @@ impl<'s> AddActor<'s> {
         supervisor: S,
         new_actor: NA,
         actor: NA::Actor,
-        inbox: Manager<NA::Message>,
+        inbox: Manager<Envelope<NA::Message>>,
         is_ready: bool,
     ) where
         S: Supervisor<NA> + 'static,
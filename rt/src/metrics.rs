@@ -0,0 +1,1284 @@
+//! Runtime metrics.
+//!
+//! The runtime collects various metrics about itself, such as the number of
+//! processes in the schedulers, the number of timers, CPU time used by the
+//! worker threads and the number of events written to the trace log. On top of
+//! that applications can register their own metrics using the metrics
+//! [`Registry`], available via [`RuntimeRef::metrics_registry`] and
+//! [`Runtime::metrics_registry`]. The following kinds of metrics can be
+//! registered:
+//!  * [`Counter`]: a monotonically increasing counter, e.g. the number of
+//!    requests handled.
+//!  * [`Gauge`]: a value that can go up and down, e.g. the number of open
+//!    connections.
+//!  * [`Histogram`]: observations counted in configurable buckets, e.g. the
+//!    latency of requests.
+//!
+//! A snapshot of the runtime metrics, without the user defined metrics, can be
+//! retrieved using [`Runtime::metrics`] or [`RuntimeRef::metrics`], see
+//! [`RuntimeMetrics`]. This is useful for health check endpoints or to assert
+//! on in tests.
+//!
+//! All metrics can be exposed in the [Prometheus text exposition format] using
+//! the metrics [`server`], which is a [`TcpServer`] that responds to HTTP
+//! requests for `/metrics`.
+//!
+//! [`RuntimeRef::metrics_registry`]: crate::RuntimeRef::metrics_registry
+//! [`Runtime::metrics_registry`]: crate::Runtime::metrics_registry
+//! [`Runtime::metrics`]: crate::Runtime::metrics
+//! [`RuntimeRef::metrics`]: crate::RuntimeRef::metrics
+//! [Prometheus text exposition format]: https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format
+//! [`TcpServer`]: crate::net::TcpServer
+//!
+//! # Examples
+//!
+//! Registering a counter and starting the metrics server on all worker
+//! threads.
+//!
+//! ```
+//! #![feature(never_type)]
+//!
+//! use std::io;
+//!
+//! use heph::actor::{self, NewActor};
+//! use heph::supervisor::{NoSupervisor, Supervisor, SupervisorStrategy};
+//! use heph_rt::net::tcp;
+//! use heph_rt::spawn::ActorOptions;
+//! use heph_rt::{self as rt, metrics, Runtime, RuntimeRef, ThreadLocal};
+//! use log::error;
+//!
+//! fn main() -> Result<(), rt::Error> {
+//!     let address = "127.0.0.1:9100".parse().unwrap();
+//!     let server = metrics::server(address).map_err(rt::Error::setup)?;
+//!
+//!     let mut runtime = Runtime::setup().build()?;
+//!     runtime.run_on_workers(move |mut runtime_ref: RuntimeRef| -> io::Result<()> {
+//!         let server_ref =
+//!             runtime_ref.try_spawn_local(ServerSupervisor, server, (), ActorOptions::default())?;
+//!         // Stop the server when the process is signaled to stop.
+//!         runtime_ref.receive_signals(server_ref.try_map());
+//!
+//!         // Register our own metric, using the same counter on all worker
+//!         // threads.
+//!         let greetings = runtime_ref
+//!             .metrics_registry()
+//!             .counter("greetings_total", "Number of greetings.");
+//!         let actor = actor as fn(_, _) -> _;
+//!         let _ = runtime_ref.spawn_local(NoSupervisor, actor, greetings, ActorOptions::default());
+//!         Ok(())
+//!     })?;
+//!     // In a real application this would run until the process is signaled
+//!     // to stop.
+//!     # if false {
+//!     runtime.start()?;
+//!     # }
+//!     Ok(())
+//! }
+//!
+//! async fn actor(_: actor::Context<!, ThreadLocal>, greetings: metrics::Counter) {
+//!     println!("Hello world");
+//!     greetings.inc();
+//! }
+//!
+//! #[derive(Copy, Clone, Debug)]
+//! struct ServerSupervisor;
+//!
+//! impl<NA> Supervisor<NA> for ServerSupervisor
+//! where
+//!     NA: NewActor<Argument = (), Error = io::Error>,
+//!     NA::Actor: actor::Actor<Error = tcp::server::Error<!>>,
+//! {
+//!     fn decide(&mut self, err: tcp::server::Error<!>) -> SupervisorStrategy<()> {
+//!         error!("error in metrics server: {}", err);
+//!         SupervisorStrategy::Restart(())
+//!     }
+//!
+//!     fn decide_on_restart_error(&mut self, err: io::Error) -> SupervisorStrategy<()> {
+//!         error!("error restarting metrics server: {}", err);
+//!         SupervisorStrategy::Stop
+//!     }
+//!
+//!     fn second_restart_error(&mut self, err: io::Error) {
+//!         error!("error restarting metrics server a second time: {}", err);
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::env::consts::ARCH;
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{
+    fence, AtomicBool, AtomicI64, AtomicPtr, AtomicU32, AtomicU64, AtomicUsize, Ordering,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{hint, io, process, ptr, slice, str};
+
+use heph::actor::{self, NewActor};
+use heph::supervisor::{Supervisor, SupervisorStrategy};
+use log::warn;
+
+use crate::net::{tcp, TcpServer, TcpStream};
+use crate::spawn::ActorOptions;
+use crate::timer::Deadline;
+use crate::{cpu_usage, shared, PrivateAccess, ProcessId, ThreadLocal};
+
+/// Default buckets used by [`Registry::histogram`], in seconds.
+///
+/// These are the same defaults as used by the Prometheus client libraries and
+/// are meant to measure latencies between 5 milliseconds and 10 seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Registry of user defined metrics.
+///
+/// See the [module documentation] for more information.
+///
+/// [module documentation]: crate::metrics
+#[derive(Clone, Debug)]
+pub struct Registry {
+    metrics: Arc<Mutex<Vec<Metric>>>,
+}
+
+/// A registered metric.
+#[derive(Debug)]
+struct Metric {
+    name: String,
+    help: String,
+    kind: MetricKind,
+}
+
+/// Kind of a [`Metric`].
+#[derive(Debug)]
+enum MetricKind {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+impl MetricKind {
+    /// Returns the name used for the metric type in the text format.
+    const fn type_name(&self) -> &'static str {
+        match self {
+            MetricKind::Counter(..) => "counter",
+            MetricKind::Gauge(..) => "gauge",
+            MetricKind::Histogram(..) => "histogram",
+        }
+    }
+}
+
+impl Registry {
+    /// Create a new empty registry.
+    pub(crate) fn new() -> Registry {
+        Registry {
+            metrics: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Register a new [`Counter`] with `name` and `help` text.
+    ///
+    /// If a counter with the same name is already registered this returns the
+    /// existing counter. This makes it possible to register the same metric
+    /// in a function that runs on all worker threads.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `name` is not a valid Prometheus metric name or if a
+    /// metric of a different kind is already registered with `name`.
+    pub fn counter(&self, name: &str, help: &str) -> Counter {
+        let kind = self.register(name, help, || {
+            MetricKind::Counter(Counter {
+                value: Arc::new(AtomicU64::new(0)),
+            })
+        });
+        match kind {
+            MetricKind::Counter(counter) => counter,
+            kind => panic!(
+                "metric '{}' already registered as {}",
+                name,
+                kind.type_name()
+            ),
+        }
+    }
+
+    /// Register a new [`Gauge`] with `name` and `help` text.
+    ///
+    /// See [`Registry::counter`] for more information, including panics.
+    pub fn gauge(&self, name: &str, help: &str) -> Gauge {
+        let kind = self.register(name, help, || {
+            MetricKind::Gauge(Gauge {
+                value: Arc::new(AtomicI64::new(0)),
+            })
+        });
+        match kind {
+            MetricKind::Gauge(gauge) => gauge,
+            kind => panic!(
+                "metric '{}' already registered as {}",
+                name,
+                kind.type_name()
+            ),
+        }
+    }
+
+    /// Register a new [`Histogram`] with `name` and `help` text.
+    ///
+    /// `buckets` are the upper bounds of the buckets, the `+Inf` bucket is
+    /// added automatically. [`DEFAULT_BUCKETS`] can be used to measure
+    /// latencies. If a histogram with the same name is already registered this
+    /// returns the existing histogram, ignoring `buckets`.
+    ///
+    /// # Panics
+    ///
+    /// Same as [`Registry::counter`], and if `buckets` are not sorted.
+    pub fn histogram(&self, name: &str, help: &str, buckets: &[f64]) -> Histogram {
+        assert!(
+            buckets.windows(2).all(|w| w[0] < w[1]),
+            "histogram buckets must be sorted"
+        );
+        let kind = self.register(name, help, || {
+            MetricKind::Histogram(Histogram {
+                inner: Arc::new(HistogramInner {
+                    buckets: buckets.to_vec().into_boxed_slice(),
+                    counts: buckets.iter().map(|_| AtomicU64::new(0)).collect(),
+                    count: AtomicU64::new(0),
+                    sum: AtomicU64::new(0.0f64.to_bits()),
+                }),
+            })
+        });
+        match kind {
+            MetricKind::Histogram(histogram) => histogram,
+            kind => panic!(
+                "metric '{}' already registered as {}",
+                name,
+                kind.type_name()
+            ),
+        }
+    }
+
+    /// Register a metric, if it's not already registered, returning a copy of
+    /// the metric's kind.
+    fn register<F>(&self, name: &str, help: &str, new_kind: F) -> MetricKind
+    where
+        F: FnOnce() -> MetricKind,
+    {
+        assert!(is_valid_name(name), "invalid metric name: '{}'", name);
+        let mut metrics = self.metrics.lock().unwrap();
+        let idx = match metrics.iter().position(|metric| metric.name == name) {
+            Some(idx) => idx,
+            None => {
+                metrics.push(Metric {
+                    name: name.to_owned(),
+                    help: help.to_owned(),
+                    kind: new_kind(),
+                });
+                metrics.len() - 1
+            }
+        };
+        match &metrics[idx].kind {
+            MetricKind::Counter(counter) => MetricKind::Counter(counter.clone()),
+            MetricKind::Gauge(gauge) => MetricKind::Gauge(gauge.clone()),
+            MetricKind::Histogram(histogram) => MetricKind::Histogram(histogram.clone()),
+        }
+    }
+
+    /// Write all metrics in the Prometheus text format to `output`.
+    fn write_text(&self, output: &mut String) {
+        let metrics = self.metrics.lock().unwrap();
+        for metric in metrics.iter() {
+            write_header(output, &metric.name, &metric.help, metric.kind.type_name());
+            match &metric.kind {
+                MetricKind::Counter(counter) => {
+                    write_sample(output, &metric.name, "", counter.get());
+                }
+                MetricKind::Gauge(gauge) => {
+                    write_sample(output, &metric.name, "", gauge.get());
+                }
+                MetricKind::Histogram(histogram) => {
+                    histogram.write_text(output, &metric.name);
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` if `name` is a valid Prometheus metric name, i.e. matches
+/// `[a-zA-Z_:][a-zA-Z0-9_:]*`.
+fn is_valid_name(name: &str) -> bool {
+    let mut bytes = name.bytes();
+    matches!(bytes.next(), Some(b) if b.is_ascii_alphabetic() || b == b'_' || b == b':')
+        && bytes.all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b':')
+}
+
+/// Counter metric, see [`Registry::counter`].
+///
+/// Cloning the counter returns a handle to the same counter.
+#[derive(Clone, Debug)]
+pub struct Counter {
+    value: Arc<AtomicU64>,
+}
+
+impl Counter {
+    /// Increase the counter by one.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Increase the counter by `n`.
+    pub fn add(&self, n: u64) {
+        let _ = self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the current value of the counter.
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// Gauge metric, see [`Registry::gauge`].
+///
+/// Cloning the gauge returns a handle to the same gauge.
+#[derive(Clone, Debug)]
+pub struct Gauge {
+    value: Arc<AtomicI64>,
+}
+
+impl Gauge {
+    /// Set the gauge to `value`.
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    /// Increase the gauge by one.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Decrease the gauge by one.
+    pub fn dec(&self) {
+        self.add(-1);
+    }
+
+    /// Add `n` to the gauge, `n` may be negative.
+    pub fn add(&self, n: i64) {
+        let _ = self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the current value of the gauge.
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// Histogram metric, see [`Registry::histogram`].
+///
+/// Cloning the histogram returns a handle to the same histogram.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    inner: Arc<HistogramInner>,
+}
+
+#[derive(Debug)]
+struct HistogramInner {
+    /// Upper bounds of the buckets, sorted.
+    buckets: Box<[f64]>,
+    /// Number of observations per bucket, **not** cumulative.
+    counts: Box<[AtomicU64]>,
+    /// Total number of observations.
+    count: AtomicU64,
+    /// Sum of all observations, bits of a `f64`.
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    /// Observe a single `value`.
+    pub fn observe(&self, value: f64) {
+        let inner = &*self.inner;
+        if let Some(idx) = inner.buckets.iter().position(|bound| value <= *bound) {
+            let _ = inner.counts[idx].fetch_add(1, Ordering::Relaxed);
+        }
+        let _ = inner.count.fetch_add(1, Ordering::Relaxed);
+        let _ = inner
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + value).to_bits())
+            });
+    }
+
+    /// Observe `duration` in seconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    /// Returns the total number of observations.
+    pub fn count(&self) -> u64 {
+        self.inner.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sum of all observations.
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.inner.sum.load(Ordering::Relaxed))
+    }
+
+    /// Write the histogram samples in the Prometheus text format to `output`.
+    fn write_text(&self, output: &mut String, name: &str) {
+        let inner = &*self.inner;
+        let bucket_name = format!("{}_bucket", name);
+        let mut cumulative = 0;
+        for (bound, count) in inner.buckets.iter().zip(inner.counts.iter()) {
+            cumulative += count.load(Ordering::Relaxed);
+            let labels = format!("le=\"{}\"", bound);
+            write_sample(output, &bucket_name, &labels, cumulative);
+        }
+        let count = self.count();
+        write_sample(output, &bucket_name, "le=\"+Inf\"", count);
+        write_sample(output, &format!("{}_sum", name), "", self.sum());
+        write_sample(output, &format!("{}_count", name), "", count);
+    }
+}
+
+/// Snapshot of the runtime's metrics.
+///
+/// Returned by [`Runtime::metrics`] and [`RuntimeRef::metrics`].
+///
+/// [`Runtime::metrics`]: crate::Runtime::metrics
+/// [`RuntimeRef::metrics`]: crate::RuntimeRef::metrics
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RuntimeMetrics {
+    /// Version of Heph's runtime.
+    pub heph_version: &'static str,
+    /// Name of the application.
+    pub app_name: String,
+    /// OS name and version.
+    pub host_os: String,
+    /// Architecture of the host.
+    pub host_arch: &'static str,
+    /// Name of the host.
+    pub host_name: String,
+    /// Id of the host.
+    pub host_id: String,
+    /// Id of the process.
+    pub process_id: u32,
+    /// Time since the runtime was created.
+    pub uptime: Duration,
+    /// CPU time used by the process.
+    pub cpu_time: Duration,
+    /// Number of thread-safe processes ready to run.
+    pub shared_scheduler_ready: usize,
+    /// Number of inactive thread-safe processes.
+    pub shared_scheduler_inactive: usize,
+    /// Number of timers for thread-safe processes.
+    pub shared_timers_total: usize,
+    /// Time until the next timer for thread-safe processes expires, if any.
+    pub shared_timers_next: Option<Duration>,
+    /// Number of events written to the trace log of the stream with id 0,
+    /// `None` if tracing is disabled.
+    pub trace_counter: Option<u32>,
+    /// Metrics of all worker threads.
+    pub workers: Vec<WorkerMetrics>,
+    /// Metrics of all sync worker threads, i.e. the threads running
+    /// synchronous actors.
+    pub sync_workers: Vec<SyncWorkerMetrics>,
+    /// Metrics per actor, sorted by name. Only includes actors for which
+    /// something was recorded.
+    pub actors: Vec<ActorMetrics>,
+}
+
+/// Snapshot of the metrics of a worker thread, part of [`RuntimeMetrics`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct WorkerMetrics {
+    /// Id of the worker thread.
+    pub id: usize,
+    /// CPU affinity of the worker thread, if set.
+    pub cpu_affinity: Option<usize>,
+    /// Number of thread-local processes ready to run.
+    pub scheduler_ready: usize,
+    /// Number of inactive thread-local processes.
+    pub scheduler_inactive: usize,
+    /// Number of timers for thread-local processes.
+    pub timers_total: usize,
+    /// Number of actors receiving process signals.
+    pub process_signal_receivers: usize,
+    /// Number of events written to the trace log of the worker thread.
+    pub trace_counter: u32,
+    /// CPU time used by the worker thread, `None` if not supported by the OS
+    /// or if the thread isn't running.
+    pub cpu_time: Option<Duration>,
+    /// Number of times the worker thread polled for OS events.
+    pub polls: u64,
+    /// Total number of OS events received.
+    pub os_events: u64,
+    /// Total time spent polling for OS events, including the time waiting for
+    /// events.
+    pub poll_time: Duration,
+    /// Number of times the watchdog reported the worker thread as stuck, see
+    /// [`Setup::watchdog`].
+    ///
+    /// [`Setup::watchdog`]: crate::Setup::watchdog
+    pub stuck: u64,
+}
+
+/// Snapshot of the metrics of a sync worker thread, part of
+/// [`RuntimeMetrics`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SyncWorkerMetrics {
+    /// Id of the sync worker thread.
+    pub id: usize,
+    /// Name of the synchronous actor running on the thread.
+    pub actor_name: &'static str,
+    /// CPU time used by the sync worker thread, `None` if not supported by
+    /// the OS or if the thread isn't running.
+    pub cpu_time: Option<Duration>,
+}
+
+/// Snapshot of the metrics of an actor, part of [`RuntimeMetrics`].
+///
+/// All actors (or futures) with the same name, as returned by
+/// [`NewActor::name`], share the same metrics.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ActorMetrics {
+    /// Name of the actor.
+    pub name: &'static str,
+    /// Number of polls that took longer than the slow poll threshold, see
+    /// [`Setup::slow_poll_threshold`].
+    ///
+    /// [`Setup::slow_poll_threshold`]: crate::Setup::slow_poll_threshold
+    pub slow_polls: u64,
+}
+
+impl RuntimeMetrics {
+    /// Collect the metrics from `internals`.
+    pub(crate) fn collect(internals: &shared::RuntimeInternals) -> RuntimeMetrics {
+        let host = internals.host_info();
+        let shared = internals.metrics();
+        RuntimeMetrics {
+            heph_version: concat!("v", env!("CARGO_PKG_VERSION")),
+            app_name: host.app_name.to_string(),
+            host_os: host.os.to_string(),
+            host_arch: ARCH,
+            host_name: host.name.to_string(),
+            host_id: host.id.to_string(),
+            process_id: process::id(),
+            uptime: internals.uptime(),
+            cpu_time: cpu_usage(libc::CLOCK_PROCESS_CPUTIME_ID),
+            shared_scheduler_ready: shared.scheduler_ready,
+            shared_scheduler_inactive: shared.scheduler_inactive,
+            shared_timers_total: shared.timers_total,
+            shared_timers_next: shared.timers_next,
+            trace_counter: internals.trace_counter(),
+            workers: internals
+                .worker_stats()
+                .iter()
+                .enumerate()
+                .map(|(i, stats)| stats.snapshot(i + 1))
+                .collect(),
+            sync_workers: internals.sync_worker_stats(),
+            actors: internals.actor_stats().snapshot(),
+        }
+    }
+
+    /// Write the metrics in the Prometheus text format to `output`.
+    fn write_text(&self, output: &mut String) {
+        let o = output;
+        write_metric(
+            o,
+            "heph_uptime_seconds",
+            "Time since the runtime was created.",
+            "gauge",
+            self.uptime.as_secs_f64(),
+        );
+        write_metric(
+            o,
+            "heph_cpu_seconds_total",
+            "CPU time used by the process.",
+            "counter",
+            self.cpu_time.as_secs_f64(),
+        );
+        write_metric(
+            o,
+            "heph_shared_scheduler_ready",
+            "Number of thread-safe processes ready to run.",
+            "gauge",
+            self.shared_scheduler_ready,
+        );
+        write_metric(
+            o,
+            "heph_shared_scheduler_inactive",
+            "Number of inactive thread-safe processes.",
+            "gauge",
+            self.shared_scheduler_inactive,
+        );
+        write_metric(
+            o,
+            "heph_shared_timers_total",
+            "Number of timers for thread-safe processes.",
+            "gauge",
+            self.shared_timers_total,
+        );
+        if let Some(counter) = self.trace_counter {
+            write_metric(
+                o,
+                "heph_trace_counter",
+                "Number of events written to the shared trace log.",
+                "gauge",
+                counter,
+            );
+        }
+
+        let workers = &self.workers;
+        write_metric(
+            o,
+            "heph_worker_threads",
+            "Number of worker threads.",
+            "gauge",
+            workers.len(),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_scheduler_ready",
+            "Number of thread-local processes ready to run.",
+            "gauge",
+            |w| Some((w.id, w.scheduler_ready)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_scheduler_inactive",
+            "Number of inactive thread-local processes.",
+            "gauge",
+            |w| Some((w.id, w.scheduler_inactive)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_timers_total",
+            "Number of timers for thread-local processes.",
+            "gauge",
+            |w| Some((w.id, w.timers_total)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_process_signal_receivers",
+            "Number of actors receiving process signals.",
+            "gauge",
+            |w| Some((w.id, w.process_signal_receivers)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_trace_counter",
+            "Number of events written to the trace log of the worker thread.",
+            "gauge",
+            |w| Some((w.id, w.trace_counter)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_cpu_seconds_total",
+            "CPU time used by the worker thread.",
+            "counter",
+            |w| w.cpu_time.map(|t| (w.id, t.as_secs_f64())),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_polls_total",
+            "Number of times the worker thread polled for OS events.",
+            "counter",
+            |w| Some((w.id, w.polls)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_os_events_total",
+            "Number of OS events received by the worker thread.",
+            "counter",
+            |w| Some((w.id, w.os_events)),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_poll_seconds_total",
+            "Time the worker thread spent polling for OS events.",
+            "counter",
+            |w| Some((w.id, w.poll_time.as_secs_f64())),
+        );
+        write_labeled(
+            o,
+            workers,
+            "heph_worker_stuck_total",
+            "Number of times the watchdog reported the worker thread as stuck.",
+            "counter",
+            |w| Some((w.id, w.stuck)),
+        );
+
+        let sync_workers = &self.sync_workers;
+        write_metric(
+            o,
+            "heph_sync_worker_threads",
+            "Number of sync worker threads.",
+            "gauge",
+            sync_workers.len(),
+        );
+        write_labeled(
+            o,
+            sync_workers,
+            "heph_sync_worker_cpu_seconds_total",
+            "CPU time used by the sync worker thread.",
+            "counter",
+            |w| w.cpu_time.map(|t| (w.id, t.as_secs_f64())),
+        );
+
+        let name = "heph_actor_slow_polls_total";
+        write_header(
+            o,
+            name,
+            "Number of polls of the actor that took longer than the slow poll threshold.",
+            "counter",
+        );
+        for actor in &self.actors {
+            let labels = format!("actor=\"{}\"", escape_label(actor.name));
+            write_sample(o, name, &labels, actor.slow_polls);
+        }
+    }
+}
+
+/// Metrics published by a worker thread.
+///
+/// The worker thread's internals can only be accessed on the worker thread
+/// itself, so the worker thread updates these on each iteration of its event
+/// loop.
+#[derive(Debug)]
+pub(crate) struct WorkerStats {
+    scheduler_ready: AtomicUsize,
+    scheduler_inactive: AtomicUsize,
+    timers_total: AtomicUsize,
+    process_signal_receivers: AtomicUsize,
+    trace_counter: AtomicU32,
+    polls: AtomicU64,
+    os_events: AtomicU64,
+    /// In nanoseconds.
+    poll_time: AtomicU64,
+    /// Incremented on each iteration of the event loop and each time a
+    /// process is run, used by the coordinator's watchdog.
+    heartbeat: AtomicU64,
+    /// Whether or not the worker thread is polling for OS events, i.e. waiting
+    /// for something to do.
+    polling: AtomicBool,
+    /// Process currently running on the worker thread, only set if the
+    /// watchdog is enabled. See [`WorkerStats::running`].
+    running: RunningProcess,
+    /// Number of times the watchdog reported the worker thread as stuck.
+    stuck: AtomicU64,
+    /// Set when the worker thread is started.
+    thread: Mutex<ThreadInfo>,
+}
+
+/// Information about a running (sync) worker thread.
+#[derive(Debug, Default)]
+struct ThreadInfo {
+    /// Clock used to determine the CPU time of the thread.
+    cpu_clock: Option<libc::clockid_t>,
+    cpu_affinity: Option<usize>,
+}
+
+impl WorkerStats {
+    /// Create new worker stats.
+    pub(crate) fn new() -> WorkerStats {
+        WorkerStats {
+            scheduler_ready: AtomicUsize::new(0),
+            scheduler_inactive: AtomicUsize::new(0),
+            timers_total: AtomicUsize::new(0),
+            process_signal_receivers: AtomicUsize::new(0),
+            trace_counter: AtomicU32::new(0),
+            polls: AtomicU64::new(0),
+            os_events: AtomicU64::new(0),
+            poll_time: AtomicU64::new(0),
+            heartbeat: AtomicU64::new(0),
+            polling: AtomicBool::new(false),
+            running: RunningProcess::new(),
+            stuck: AtomicU64::new(0),
+            thread: Mutex::new(ThreadInfo::default()),
+        }
+    }
+
+    /// Mark the worker thread as started.
+    ///
+    /// Must be called on the worker thread.
+    pub(crate) fn started(&self, cpu_affinity: Option<usize>) {
+        *self.thread.lock().unwrap() = ThreadInfo {
+            cpu_clock: current_thread_cpu_clock(),
+            cpu_affinity,
+        };
+    }
+
+    /// Update the metrics.
+    pub(crate) fn update(
+        &self,
+        scheduler_ready: usize,
+        scheduler_inactive: usize,
+        timers_total: usize,
+        process_signal_receivers: usize,
+        trace_counter: u32,
+    ) {
+        self.scheduler_ready
+            .store(scheduler_ready, Ordering::Relaxed);
+        self.scheduler_inactive
+            .store(scheduler_inactive, Ordering::Relaxed);
+        self.timers_total.store(timers_total, Ordering::Relaxed);
+        self.process_signal_receivers
+            .store(process_signal_receivers, Ordering::Relaxed);
+        self.trace_counter.store(trace_counter, Ordering::Relaxed);
+        let _ = self.heartbeat.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Mark the worker thread as (not) polling for OS events.
+    pub(crate) fn set_polling(&self, polling: bool) {
+        self.polling.store(polling, Ordering::Relaxed);
+    }
+
+    /// Mark the process with `pid` and `name` as running on the worker thread,
+    /// until [`WorkerStats::exit_process`] is called.
+    pub(crate) fn enter_process(&self, pid: ProcessId, name: &'static str) {
+        self.running.set(pid, name);
+        let _ = self.heartbeat.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Mark the process started in [`WorkerStats::enter_process`] as stopped.
+    pub(crate) fn exit_process(&self) {
+        self.running.clear();
+        let _ = self.heartbeat.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the heartbeat of the worker thread and whether or not it's
+    /// polling for OS events.
+    pub(crate) fn heartbeat(&self) -> (u64, bool) {
+        (
+            self.heartbeat.load(Ordering::Relaxed),
+            self.polling.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the process currently running on the worker thread, if any.
+    pub(crate) fn running(&self) -> Option<(ProcessId, &'static str)> {
+        self.running.get()
+    }
+
+    /// Record that the watchdog reported the worker thread as stuck.
+    pub(crate) fn record_stuck(&self) {
+        let _ = self.stuck.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a poll for OS events that returned `events` events and took
+    /// `elapsed` time.
+    pub(crate) fn record_poll(&self, events: usize, elapsed: Duration) {
+        let _ = self.polls.fetch_add(1, Ordering::Relaxed);
+        let _ = self.os_events.fetch_add(events as u64, Ordering::Relaxed);
+        #[allow(clippy::cast_possible_truncation)] // Only overflows after 584 years.
+        let _ = self
+            .poll_time
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns a snapshot of the metrics of the worker with `id`.
+    fn snapshot(&self, id: usize) -> WorkerMetrics {
+        let thread = self.thread.lock().unwrap();
+        WorkerMetrics {
+            id,
+            cpu_affinity: thread.cpu_affinity,
+            scheduler_ready: self.scheduler_ready.load(Ordering::Relaxed),
+            scheduler_inactive: self.scheduler_inactive.load(Ordering::Relaxed),
+            timers_total: self.timers_total.load(Ordering::Relaxed),
+            process_signal_receivers: self.process_signal_receivers.load(Ordering::Relaxed),
+            trace_counter: self.trace_counter.load(Ordering::Relaxed),
+            cpu_time: thread.cpu_clock.and_then(thread_cpu_time),
+            polls: self.polls.load(Ordering::Relaxed),
+            os_events: self.os_events.load(Ordering::Relaxed),
+            poll_time: Duration::from_nanos(self.poll_time.load(Ordering::Relaxed)),
+            stuck: self.stuck.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Process running on a worker thread, part of [`WorkerStats`].
+///
+/// Written by the worker thread on every process run and read by the
+/// coordinator, so this can't use a lock. Instead it's a sequence lock: `seq`
+/// is odd while the worker thread is writing and the reader retries if `seq`
+/// changed while reading.
+#[derive(Debug)]
+struct RunningProcess {
+    seq: AtomicUsize,
+    pid: AtomicUsize,
+    /// Pointer and length of the `&'static str` name, null if no process is
+    /// running.
+    name_ptr: AtomicPtr<u8>,
+    name_len: AtomicUsize,
+}
+
+impl RunningProcess {
+    const fn new() -> RunningProcess {
+        RunningProcess {
+            seq: AtomicUsize::new(0),
+            pid: AtomicUsize::new(0),
+            name_ptr: AtomicPtr::new(ptr::null_mut()),
+            name_len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Set the running process.
+    ///
+    /// Must only be called by the worker thread.
+    fn set(&self, pid: ProcessId, name: &'static str) {
+        self.write(pid.0, name.as_ptr() as *mut u8, name.len());
+    }
+
+    /// Clear the running process.
+    ///
+    /// Must only be called by the worker thread.
+    fn clear(&self) {
+        self.write(0, ptr::null_mut(), 0);
+    }
+
+    fn write(&self, pid: usize, name_ptr: *mut u8, name_len: usize) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.pid.store(pid, Ordering::Relaxed);
+        self.name_ptr.store(name_ptr, Ordering::Relaxed);
+        self.name_len.store(name_len, Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Returns the running process, if any.
+    fn get(&self) -> Option<(ProcessId, &'static str)> {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                // Worker thread is writing.
+                hint::spin_loop();
+                continue;
+            }
+            let pid = self.pid.load(Ordering::Relaxed);
+            let name_ptr = self.name_ptr.load(Ordering::Relaxed);
+            let name_len = self.name_len.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) != seq {
+                continue;
+            }
+            if name_ptr.is_null() {
+                return None;
+            }
+            // SAFETY: `seq` didn't change while reading, so `name_ptr` and
+            // `name_len` are from the same `&'static str` passed to `set`.
+            let name =
+                unsafe { str::from_utf8_unchecked(slice::from_raw_parts(name_ptr, name_len)) };
+            return Some((ProcessId(pid), name));
+        }
+    }
+}
+
+/// Metrics of a sync worker thread.
+#[derive(Debug)]
+pub(crate) struct SyncWorkerStats {
+    id: usize,
+    actor_name: &'static str,
+    /// Set when the sync worker thread is started.
+    thread: Mutex<ThreadInfo>,
+}
+
+impl SyncWorkerStats {
+    /// Create new sync worker stats.
+    pub(crate) fn new(id: usize, actor_name: &'static str) -> SyncWorkerStats {
+        SyncWorkerStats {
+            id,
+            actor_name,
+            thread: Mutex::new(ThreadInfo::default()),
+        }
+    }
+
+    /// Mark the sync worker thread as started.
+    ///
+    /// Must be called on the sync worker thread.
+    pub(crate) fn started(&self) {
+        self.thread.lock().unwrap().cpu_clock = current_thread_cpu_clock();
+    }
+
+    /// Returns a snapshot of the metrics.
+    pub(crate) fn snapshot(&self) -> SyncWorkerMetrics {
+        SyncWorkerMetrics {
+            id: self.id,
+            actor_name: self.actor_name,
+            cpu_time: self
+                .thread
+                .lock()
+                .unwrap()
+                .cpu_clock
+                .and_then(thread_cpu_time),
+        }
+    }
+}
+
+/// Metrics per actor.
+#[derive(Debug)]
+pub(crate) struct ActorStats {
+    /// Number of slow polls per actor name.
+    slow_polls: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl ActorStats {
+    /// Create new actor stats.
+    pub(crate) fn new() -> ActorStats {
+        ActorStats {
+            slow_polls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a slow poll of the actor with `name`.
+    pub(crate) fn record_slow_poll(&self, name: &'static str) {
+        *self.slow_polls.lock().unwrap().entry(name).or_insert(0) += 1;
+    }
+
+    /// Returns a snapshot of the metrics, sorted by actor name.
+    fn snapshot(&self) -> Vec<ActorMetrics> {
+        let mut actors: Vec<ActorMetrics> = self
+            .slow_polls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&name, &slow_polls)| ActorMetrics { name, slow_polls })
+            .collect();
+        actors.sort_unstable_by_key(|actor| actor.name);
+        actors
+    }
+}
+
+/// Returns the CPU time clock of the calling thread, if supported.
+fn current_thread_cpu_clock() -> Option<libc::clockid_t> {
+    #[cfg(any(target_os = "freebsd", target_os = "linux"))]
+    {
+        let mut clock_id = 0;
+        let res = unsafe { libc::pthread_getcpuclockid(libc::pthread_self(), &mut clock_id) };
+        if res == 0 {
+            Some(clock_id)
+        } else {
+            let err = io::Error::from_raw_os_error(res);
+            warn!("failed to get CPU time clock of thread: {}", err);
+            None
+        }
+    }
+    #[cfg(not(any(target_os = "freebsd", target_os = "linux")))]
+    {
+        None
+    }
+}
+
+/// Returns the CPU time of the thread with `clock_id`. Returns `None` if the
+/// thread is no longer running.
+fn thread_cpu_time(clock_id: libc::clockid_t) -> Option<Duration> {
+    let mut duration = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(clock_id, &mut duration) } == -1 {
+        None
+    } else {
+        Some(Duration::new(
+            duration.tv_sec.try_into().unwrap_or(0),
+            duration.tv_nsec.try_into().unwrap_or(u32::MAX),
+        ))
+    }
+}
+
+/// Gather all runtime and user defined metrics in the Prometheus text format.
+pub(crate) fn gather(internals: &shared::RuntimeInternals) -> String {
+    let mut output = String::with_capacity(4096);
+    RuntimeMetrics::collect(internals).write_text(&mut output);
+    internals.metrics_registry().write_text(&mut output);
+    output
+}
+
+/// Write a single, unlabeled, metric.
+fn write_metric<V: fmt::Display>(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    type_name: &str,
+    value: V,
+) {
+    write_header(output, name, help, type_name);
+    write_sample(output, name, "", value);
+}
+
+/// Write a metric of all (sync) worker threads, labeled with the worker id
+/// returned by `get`. Workers for which `get` returns `None` are skipped.
+fn write_labeled<T, F, V>(
+    output: &mut String,
+    workers: &[T],
+    name: &str,
+    help: &str,
+    type_name: &str,
+    get: F,
+) where
+    F: Fn(&T) -> Option<(usize, V)>,
+    V: fmt::Display,
+{
+    write_header(output, name, help, type_name);
+    for worker in workers {
+        if let Some((id, value)) = get(worker) {
+            write_sample(output, name, &format!("worker=\"{}\"", id), value);
+        }
+    }
+}
+
+/// Write the `HELP` and `TYPE` lines for a metric.
+fn write_header(output: &mut String, name: &str, help: &str, type_name: &str) {
+    let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, type_name);
+}
+
+/// Escape `value` for use as label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Write a single sample line for a metric, `labels` are written without
+/// escaping.
+fn write_sample<V: fmt::Display>(output: &mut String, name: &str, labels: &str, value: V) {
+    if labels.is_empty() {
+        let _ = writeln!(output, "{} {}", name, value);
+    } else {
+        let _ = writeln!(output, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+/// [`TcpServer`] that serves the metrics, see [`server`].
+pub type Server = tcp::server::Setup<ConnectionSupervisor, NewConnection>;
+
+/// Create a new [`TcpServer`] that serves the runtime's metrics in the
+/// Prometheus text exposition format over HTTP on `address`.
+///
+/// The server responds to `GET /metrics` requests, all other requests get a
+/// `404 Not Found` or `405 Method Not Allowed` response. Each connection
+/// handles a single request.
+///
+/// Like any other `TcpServer` the returned server must be spawned as
+/// thread-local actor, see the [module documentation] for an example.
+///
+/// [module documentation]: crate::metrics
+pub fn server(address: SocketAddr) -> io::Result<Server> {
+    TcpServer::setup(
+        address,
+        ConnectionSupervisor,
+        NewConnection,
+        ActorOptions::default(),
+    )
+}
+
+/// Supervisor for the connection actors of the metrics [`server`].
+///
+/// It logs the error and stops the actor.
+#[derive(Copy, Clone, Debug)]
+pub struct ConnectionSupervisor;
+
+impl Supervisor<NewConnection> for ConnectionSupervisor {
+    fn decide(&mut self, err: io::Error) -> SupervisorStrategy<(TcpStream, SocketAddr)> {
+        warn!("error handling metrics request: {}", err);
+        SupervisorStrategy::Stop
+    }
+
+    fn decide_on_restart_error(&mut self, err: !) -> SupervisorStrategy<(TcpStream, SocketAddr)> {
+        err
+    }
+
+    fn second_restart_error(&mut self, err: !) {
+        err
+    }
+}
+
+/// [`NewActor`] implementation for the connection actors of the metrics
+/// [`server`].
+#[derive(Copy, Clone, Debug)]
+pub struct NewConnection;
+
+impl NewActor for NewConnection {
+    type Message = !;
+    type Argument = (TcpStream, SocketAddr);
+    type Actor = Pin<Box<dyn Future<Output = io::Result<()>>>>;
+    type Error = !;
+    type RuntimeAccess = ThreadLocal;
+
+    fn new(
+        &mut self,
+        ctx: actor::Context<Self::Message, Self::RuntimeAccess>,
+        (stream, _): Self::Argument,
+    ) -> Result<Self::Actor, Self::Error> {
+        Ok(Box::pin(connection(ctx, stream)))
+    }
+}
+
+/// Maximum size of a request we accept.
+const MAX_REQUEST_SIZE: usize = 4096;
+
+/// Timeout used for reading the request and writing the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Actor that handles a single request on `stream`.
+async fn connection(
+    mut ctx: actor::Context<!, ThreadLocal>,
+    mut stream: TcpStream,
+) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(MAX_REQUEST_SIZE);
+    // Read until the end of the request head, we don't read request bodies.
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buf.len() >= MAX_REQUEST_SIZE {
+            let response = response("431 Request Header Fields Too Large", "");
+            return Deadline::after(&mut ctx, TIMEOUT, stream.send_all(response.as_bytes())).await;
+        }
+        let n = Deadline::after(&mut ctx, TIMEOUT, stream.recv(&mut buf)).await?;
+        if n == 0 {
+            // Connection closed before the entire request was send.
+            return Ok(());
+        }
+    }
+
+    let mut request_line = buf
+        .split(|b| *b == b'\r')
+        .next()
+        .unwrap_or(&[])
+        .split(|b| *b == b' ');
+    let method = request_line.next().unwrap_or(&[]);
+    let path = request_line.next().unwrap_or(&[]);
+    let response = match (method, path) {
+        (b"GET", b"/metrics") => {
+            let metrics = gather(ctx.runtime().shared_internals());
+            response("200 OK", &metrics)
+        }
+        (b"GET", _) => response("404 Not Found", ""),
+        (_, _) => response("405 Method Not Allowed", ""),
+    };
+    Deadline::after(&mut ctx, TIMEOUT, stream.send_all(response.as_bytes())).await
+}
+
+/// Create a HTTP response with `status` and `body`.
+fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    )
+}
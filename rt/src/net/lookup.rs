@@ -0,0 +1,172 @@
+//! Module with hostname resolution, see [`lookup_host`].
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{self, Poll};
+use std::time::{Duration, Instant};
+
+use heph::actor;
+
+use crate::{self as rt, blocking};
+
+/// Time a successful lookup is cached.
+///
+/// `getaddrinfo(3)` doesn't expose the time-to-live of the DNS records, so a
+/// fixed duration is used instead.
+pub const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Maximum number of hostnames kept in the cache.
+const MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Resolve `host` into one or more socket addresses.
+///
+/// `host` must be in the form `host:port`, e.g. `localhost:8080` or
+/// `example.com:443`. If `host` is already a socket address, e.g.
+/// `127.0.0.1:8080`, no lookup is done.
+///
+/// The lookup is done using `getaddrinfo(3)`, which blocks, so it's run on a
+/// separate pool of threads to not block the actor (or any other actors running
+/// on the same thread). Successful lookups are cached for [`CACHE_TTL`], shared
+/// between all actors in the runtime.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(never_type)]
+///
+/// use std::io;
+///
+/// use heph::actor;
+/// use heph_rt::net::lookup_host;
+/// use heph_rt::ThreadLocal;
+///
+/// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+///     let addresses = lookup_host(&mut ctx, "localhost:8080").await?;
+///     for address in addresses {
+///         println!("localhost resolves to: {}", address);
+///     }
+///     Ok(())
+/// }
+/// #
+/// # drop(actor); // Silent dead code warnings.
+/// ```
+pub fn lookup_host<M, RT>(ctx: &mut actor::Context<M, RT>, host: &str) -> LookupHost
+where
+    RT: rt::Access,
+{
+    if let Ok(address) = host.parse() {
+        return LookupHost {
+            state: LookupState::Done(Some(Ok(vec![address]))),
+        };
+    }
+
+    let shared = ctx.runtime_ref().shared_internals();
+    if let Some(addresses) = shared.lookup_cache().get(host) {
+        return LookupHost {
+            state: LookupState::Done(Some(Ok(addresses))),
+        };
+    }
+
+    let host: Box<str> = host.into();
+    // NOTE: only the cache is moved into the job, not the runtime internals,
+    // as the internals own the blocking pool (and thus the job).
+    let cache = shared.lookup_cache().clone();
+    let handle = shared.blocking().spawn(move || {
+        let addresses: Vec<SocketAddr> = (&*host).to_socket_addrs()?.collect();
+        if addresses.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "hostname resolved to no addresses",
+            ));
+        }
+        cache.insert(host, addresses.clone());
+        Ok(addresses)
+    });
+    LookupHost {
+        state: LookupState::Resolving(handle),
+    }
+}
+
+/// The [`Future`] behind [`lookup_host`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LookupHost {
+    state: LookupState,
+}
+
+#[derive(Debug)]
+#[allow(variant_size_differences)] // Doesn't allocate for cached results.
+enum LookupState {
+    /// Result is already known, e.g. from the cache.
+    Done(Option<io::Result<Vec<SocketAddr>>>),
+    /// Lookup running on the blocking pool.
+    Resolving(blocking::Handle<io::Result<Vec<SocketAddr>>>),
+}
+
+impl Future for LookupHost {
+    type Output = io::Result<Vec<SocketAddr>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let LookupHost { state } = Pin::into_inner(self);
+        match state {
+            LookupState::Done(result) => {
+                Poll::Ready(result.take().expect("polled `LookupHost` after completion"))
+            }
+            LookupState::Resolving(handle) => match Pin::new(handle).poll(ctx) {
+                Poll::Ready(Ok(result)) => Poll::Ready(result),
+                Poll::Ready(Err(panic)) => Poll::Ready(Err(blocking::panic_to_io_error(panic))),
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+}
+
+/// Cache of successful lookups, see [`lookup_host`].
+#[derive(Debug)]
+pub(crate) struct Cache {
+    entries: Mutex<HashMap<Box<str>, CacheEntry>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    addresses: Vec<SocketAddr>,
+    expires: Instant,
+}
+
+impl Cache {
+    /// Create a new empty cache.
+    pub(crate) fn new() -> Cache {
+        Cache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached addresses for `host`, if any and not expired.
+    fn get(&self, host: &str) -> Option<Vec<SocketAddr>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(host) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.addresses.clone()),
+            _ => None,
+        }
+    }
+
+    /// Add `addresses` for `host` to the cache.
+    fn insert(&self, host: Box<str>, addresses: Vec<SocketAddr>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_CACHE_ENTRIES {
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= MAX_CACHE_ENTRIES {
+                // Don't let the cache grow without bounds, simply don't cache
+                // this lookup.
+                return;
+            }
+        }
+        let expires = now + CACHE_TTL;
+        drop(entries.insert(host, CacheEntry { addresses, expires }));
+    }
+}
@@ -9,6 +9,9 @@
 //! * [User Datagram Protocol] (UDP) only provides a single socket type:
 //!   * [`UdpSocket`].
 //!
+//! Furthermore hostnames can be resolved using [`lookup_host`], or resolved
+//! and connected to in one go using [`TcpStream::connect_host`].
+//!
 //! [Transmission Control Protocol]: crate::net::tcp
 //! [TCP stream]: crate::net::TcpStream
 //! [TCP listening socket]: crate::net::TcpListener
@@ -49,9 +52,12 @@ use std::net::SocketAddr;
 
 use socket2::SockAddr;
 
+pub mod lookup;
 pub mod tcp;
 pub mod udp;
 
+#[doc(no_inline)]
+pub use lookup::lookup_host;
 #[doc(no_inline)]
 pub use tcp::{TcpListener, TcpServer, TcpStream};
 #[doc(no_inline)]
@@ -4,13 +4,15 @@ use std::async_iter::AsyncIterator;
 use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
 use std::pin::Pin;
 use std::task::{self, Poll};
 
 use heph::actor;
 use mio::{net, Interest};
 
-use crate::net::TcpStream;
+use crate::io_uring::Op;
+use crate::net::{convert_address, TcpStream};
 use crate::{self as rt, Bound};
 
 /// A TCP socket listener.
@@ -239,6 +241,7 @@ impl TcpListener {
     pub fn accept(&mut self) -> Accept<'_> {
         Accept {
             listener: Some(self),
+            op: Op::new(),
         }
     }
 
@@ -247,7 +250,35 @@ impl TcpListener {
     ///
     /// See the [`TcpListener`] documentation for an example.
     pub fn incoming(&mut self) -> Incoming<'_> {
-        Incoming { listener: self }
+        Incoming {
+            listener: self,
+            op: Op::new(),
+        }
+    }
+
+    /// Accept a connection using io_uring (see [`Op`]), falling back to
+    /// [`TcpListener::try_accept`] if the current thread doesn't use io_uring.
+    ///
+    /// Returns an error with [`io::ErrorKind::WouldBlock`] while the operation
+    /// is in progress.
+    fn accept_op(
+        &mut self,
+        op: &mut Op,
+        ctx: &mut task::Context<'_>,
+    ) -> io::Result<(UnboundTcpStream, SocketAddr)> {
+        match op.poll_accept(ctx, self.socket.as_raw_fd()) {
+            Some(Poll::Ready(Ok((fd, address)))) => {
+                // SAFETY: the file descriptor is a new TCP stream.
+                let socket = unsafe { net::TcpStream::from_raw_fd(fd.into_raw_fd()) };
+                let stream = UnboundTcpStream {
+                    stream: TcpStream { socket },
+                };
+                convert_address(address).map(|address| (stream, address))
+            }
+            Some(Poll::Ready(Err(err))) => Err(err),
+            Some(Poll::Pending) => Err(io::ErrorKind::WouldBlock.into()),
+            None => self.try_accept(),
+        }
     }
 
     /// Get the value of the `SO_ERROR` option on this socket.
@@ -291,14 +322,16 @@ impl UnboundTcpStream {
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Accept<'a> {
     listener: Option<&'a mut TcpListener>,
+    op: Op,
 }
 
 impl<'a> Future for Accept<'a> {
     type Output = io::Result<(UnboundTcpStream, SocketAddr)>;
 
-    fn poll(mut self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
-        match self.listener {
-            Some(ref mut listener) => try_io!(listener.try_accept()).map(|res| {
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Accept { listener, op } = &mut *self;
+        match listener {
+            Some(listener) => try_io!(listener.accept_op(op, ctx)).map(|res| {
                 // Only remove the listener if we return a stream.
                 self.listener = None;
                 res
@@ -313,13 +346,18 @@ impl<'a> Future for Accept<'a> {
 #[must_use = "AsyncIterators do nothing unless polled"]
 pub struct Incoming<'a> {
     listener: &'a mut TcpListener,
+    op: Op,
 }
 
 impl<'a> AsyncIterator for Incoming<'a> {
     type Item = io::Result<(UnboundTcpStream, SocketAddr)>;
 
-    fn poll_next(mut self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
-        try_io!(self.listener.try_accept()).map(Some)
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        ctx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let Incoming { listener, op } = &mut *self;
+        try_io!(listener.accept_op(op, ctx)).map(Some)
     }
 }
 
@@ -5,23 +5,39 @@
 // that once Mio uses Socket2 and supports all the methods we need, Mio's
 // tracking issue: https://github.com/tokio-rs/mio/issues/1381.
 
+use std::collections::VecDeque;
+use std::error::Error;
 use std::future::Future;
 use std::io::{self, IoSlice};
 use std::net::{Shutdown, SocketAddr};
 use std::num::NonZeroUsize;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{self, Poll};
+use std::time::Duration;
+use std::{fmt, net as std_net};
 
 #[cfg(target_os = "linux")]
 use log::warn;
-use mio::{net, Interest};
+use mio::unix::SourceFd;
+use mio::{net, Interest, Registry};
 
 use heph::actor;
-use socket2::SockRef;
+use socket2::{Domain, Protocol, SockAddr, SockRef, Socket, Type};
 
 use crate::bytes::{Bytes, BytesVectored, MaybeUninitSlice};
+use crate::io_uring::{self, Op};
+use crate::net::lookup::{lookup_host, LookupHost};
+#[cfg(target_os = "linux")]
+use crate::pipe;
+use crate::timer::Timer;
 use crate::{self as rt, Bound};
 
+/// Time to wait for a connection attempt to succeed before starting the next
+/// one in [`TcpStream::connect_host`], as recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
 /// A non-blocking TCP stream between a local socket and a remote socket.
 ///
 /// # Examples
@@ -52,6 +68,12 @@ pub struct TcpStream {
 }
 
 impl TcpStream {
+    /// Returns the raw file descriptor of the socket.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+
     /// Create a new TCP stream and issues a non-blocking connect to the
     /// specified `address`.
     ///
@@ -69,16 +91,82 @@ impl TcpStream {
     where
         RT: rt::Access,
     {
-        let mut socket = net::TcpStream::connect(address)?;
+        let (mut socket, address) = if io_uring::in_use() {
+            // The `Connect` future will connect the socket.
+            let domain = Domain::for_address(address);
+            let ty = Type::STREAM.nonblocking().cloexec();
+            let socket = Socket::new(domain, ty, Some(Protocol::TCP))?;
+            let socket = net::TcpStream::from_std(socket.into());
+            (socket, Some(SockAddr::from(address)))
+        } else {
+            (net::TcpStream::connect(address)?, None)
+        };
         ctx.runtime()
             .register(&mut socket, Interest::READABLE | Interest::WRITABLE)?;
         Ok(Connect {
             socket: Some(socket),
+            address,
+            op: Op::new(),
             #[cfg(target_os = "linux")]
             cpu_affinity: ctx.runtime_ref().cpu(),
         })
     }
 
+    /// Resolve `host` and connect to one of the resolved addresses.
+    ///
+    /// `host` must be in the form `host:port`, see [`lookup_host`] for how the
+    /// hostname is resolved.
+    ///
+    /// This uses the "Happy Eyeballs" algorithm ([RFC 8305]): the resolved IPv6
+    /// and IPv4 addresses are interleaved and a connection attempt is started to
+    /// the first address. If it doesn't succeed within 250 milliseconds, or it
+    /// fails, a connection attempt to the next address is started, while
+    /// keeping the earlier attempts running. The first connection to succeed is
+    /// returned, all other attempts are cancelled. If all attempts fail the
+    /// error of the last attempt is returned.
+    ///
+    /// [RFC 8305]: https://tools.ietf.org/html/rfc8305
+    ///
+    /// # Notes
+    ///
+    /// The stream is also [bound] to the actor that owns the `actor::Context`,
+    /// which means the actor will be run every time the stream is ready to read
+    /// or write.
+    ///
+    /// [bound]: crate::Bound
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(never_type)]
+    ///
+    /// use std::io;
+    ///
+    /// use heph::actor;
+    /// use heph_rt::net::TcpStream;
+    /// use heph_rt::ThreadLocal;
+    ///
+    /// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+    ///     let mut stream = TcpStream::connect_host(&mut ctx, "localhost:12345").await?;
+    ///     stream.send_all(b"Hello world!").await
+    /// }
+    /// #
+    /// # drop(actor); // Silent dead code warnings.
+    /// ```
+    pub fn connect_host<'a, M, RT>(
+        ctx: &'a mut actor::Context<M, RT>,
+        host: &str,
+    ) -> ConnectHost<'a, M, RT>
+    where
+        RT: rt::Access + Clone,
+    {
+        let lookup = lookup_host(ctx, host);
+        ConnectHost {
+            ctx,
+            state: ConnectHostState::Resolving(lookup),
+        }
+    }
+
     /// Returns the socket address of the remote peer of this TCP connection.
     pub fn peer_addr(&mut self) -> io::Result<SocketAddr> {
         self.socket.peer_addr()
@@ -138,7 +226,7 @@ impl TcpStream {
     /// [kind]: io::Error::kind
     /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
     pub fn try_send(&mut self, buf: &[u8]) -> io::Result<usize> {
-        SockRef::from(&self.socket).send(buf)
+        self.try_send_ref(buf)
     }
 
     /// Send the bytes in `buf` to the peer.
@@ -146,7 +234,11 @@ impl TcpStream {
     /// Return the number of bytes written. This may we fewer then the length of
     /// `buf`. To ensure that all bytes are written use [`TcpStream::send_all`].
     pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> Send<'a, 'b> {
-        Send { stream: self, buf }
+        Send {
+            stream: self,
+            buf,
+            op: Op::new(),
+        }
     }
 
     /// Send the all bytes in `buf` to the peer.
@@ -154,7 +246,11 @@ impl TcpStream {
     /// If this fails to send all bytes (this happens if a write returns
     /// `Ok(0)`) this will return [`io::ErrorKind::WriteZero`].
     pub fn send_all<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SendAll<'a, 'b> {
-        SendAll { stream: self, buf }
+        SendAll {
+            stream: self,
+            buf,
+            op: Op::new(),
+        }
     }
 
     /// Attempt to send bytes in `bufs` to the peer.
@@ -166,7 +262,7 @@ impl TcpStream {
     /// [kind]: io::Error::kind
     /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
     pub fn try_send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        SockRef::from(&self.socket).send_vectored(bufs)
+        self.try_send_vectored_ref(bufs)
     }
 
     /// Send the bytes in `bufs` to the peer.
@@ -233,21 +329,11 @@ impl TcpStream {
     /// #
     /// # drop(actor); // Silent dead code warnings.
     /// ```
-    pub fn try_recv<B>(&mut self, mut buf: B) -> io::Result<usize>
+    pub fn try_recv<B>(&mut self, buf: B) -> io::Result<usize>
     where
         B: Bytes,
     {
-        debug_assert!(
-            buf.has_spare_capacity(),
-            "called `TcpStream::try_recv with an empty buffer"
-        );
-        SockRef::from(&self.socket)
-            .recv(buf.as_bytes())
-            .map(|read| {
-                // Safety: just read the bytes.
-                unsafe { buf.update_length(read) }
-                read
-            })
+        self.try_recv_ref(buf)
     }
 
     /// Receive messages from the stream, writing them into `buf`.
@@ -280,7 +366,11 @@ impl TcpStream {
     where
         B: Bytes,
     {
-        Recv { stream: self, buf }
+        Recv {
+            stream: self,
+            buf,
+            op: Op::new(),
+        }
     }
 
     /// Receive at least `n` bytes from the stream, writing them into `buf`.
@@ -328,6 +418,7 @@ impl TcpStream {
             stream: self,
             buf,
             left: n,
+            op: Op::new(),
         }
     }
 
@@ -339,24 +430,11 @@ impl TcpStream {
     ///
     /// [kind]: io::Error::kind
     /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
-    pub fn try_recv_vectored<B>(&mut self, mut bufs: B) -> io::Result<usize>
+    pub fn try_recv_vectored<B>(&mut self, bufs: B) -> io::Result<usize>
     where
         B: BytesVectored,
     {
-        debug_assert!(
-            bufs.has_spare_capacity(),
-            "called `TcpStream::try_recv_vectored` with empty buffers"
-        );
-        let res = SockRef::from(&self.socket)
-            .recv_vectored(MaybeUninitSlice::as_socket2(bufs.as_bufs().as_mut()));
-        match res {
-            Ok((read, _)) => {
-                // Safety: just read the bytes.
-                unsafe { bufs.update_lengths(read) }
-                Ok(read)
-            }
-            Err(err) => Err(err),
-        }
+        self.try_recv_vectored_ref(bufs)
     }
 
     /// Receive messages from the stream, writing them into `bufs`.
@@ -390,21 +468,11 @@ impl TcpStream {
     /// Attempt to receive messages from the stream, writing them into `buf`,
     /// without removing that data from the queue. On success, returns the
     /// number of bytes peeked.
-    pub fn try_peek<B>(&mut self, mut buf: B) -> io::Result<usize>
+    pub fn try_peek<B>(&mut self, buf: B) -> io::Result<usize>
     where
         B: Bytes,
     {
-        debug_assert!(
-            buf.has_spare_capacity(),
-            "called `TcpStream::try_peek with an empty buffer"
-        );
-        SockRef::from(&self.socket)
-            .peek(buf.as_bytes())
-            .map(|read| {
-                // Safety: just read the bytes.
-                unsafe { buf.update_length(read) }
-                read
-            })
+        self.try_peek_ref(buf)
     }
 
     /// Receive messages from the stream, writing them into `buf`, without
@@ -420,26 +488,11 @@ impl TcpStream {
     /// Attempt to receive messages from the stream using vectored I/O, writing
     /// them into `bufs`, without removing that data from the queue. On success,
     /// returns the number of bytes peeked.
-    pub fn try_peek_vectored<B>(&mut self, mut bufs: B) -> io::Result<usize>
+    pub fn try_peek_vectored<B>(&mut self, bufs: B) -> io::Result<usize>
     where
         B: BytesVectored,
     {
-        debug_assert!(
-            bufs.has_spare_capacity(),
-            "called `TcpStream::try_peek_vectored` with empty buffers"
-        );
-        let res = SockRef::from(&self.socket).recv_vectored_with_flags(
-            MaybeUninitSlice::as_socket2(bufs.as_bufs().as_mut()),
-            libc::MSG_PEEK,
-        );
-        match res {
-            Ok((read, _)) => {
-                // Safety: just read the bytes.
-                unsafe { bufs.update_lengths(read) }
-                Ok(read)
-            }
-            Err(err) => Err(err),
-        }
+        self.try_peek_vectored_ref(bufs)
     }
 
     /// Receive messages from the stream using vectored I/O, writing them into
@@ -492,6 +545,7 @@ impl TcpStream {
             file,
             offset,
             length,
+            op: Op::new(),
         }
     }
 
@@ -513,6 +567,7 @@ impl TcpStream {
             file,
             start: offset,
             end: length.and_then(|length| NonZeroUsize::new(offset + length.get())),
+            op: Op::new(),
         }
     }
 
@@ -526,6 +581,50 @@ impl TcpStream {
         self.send_file_all(file, 0, None)
     }
 
+    /// Attempt to move at most `length` bytes from the stream into `pipe`
+    /// using a `splice(2)` system call.
+    ///
+    /// If no bytes can currently be moved this will return an error with the
+    /// [kind] set to [`ErrorKind::WouldBlock`]. Most users should prefer to use
+    /// [`TcpStream::splice_to`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    #[cfg(target_os = "linux")]
+    pub fn try_splice_to(&mut self, pipe: &mut pipe::Sender, length: usize) -> io::Result<usize> {
+        pipe::splice(self.raw_fd(), pipe.raw_fd(), length)
+    }
+
+    /// Move at most `length` bytes from the stream into `pipe`, without
+    /// copying the bytes into user space.
+    ///
+    /// Returns the number of bytes moved, which may be fewer then `length`. If
+    /// this returns `Ok(0)` the peer has closed the writing side of the
+    /// connection (and `length` wasn't zero).
+    ///
+    /// The bytes can be moved from the pipe into another stream using
+    /// [`pipe::Receiver::splice_to`]. To forward bytes between two streams in
+    /// both directions, e.g. in a proxy, see [`copy_bidirectional`].
+    ///
+    /// # Notes
+    ///
+    /// Both the stream and `pipe` must be [bound] to the same actor, this
+    /// future is only woken once either of them becomes ready again.
+    ///
+    /// [bound]: crate::Bound
+    #[cfg(target_os = "linux")]
+    pub fn splice_to<'a, 'b>(
+        &'a mut self,
+        pipe: &'b mut pipe::Sender,
+        length: usize,
+    ) -> SpliceTo<'a, 'b> {
+        SpliceTo {
+            stream: self,
+            pipe,
+            length,
+        }
+    }
+
     /// Shuts down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O on the specified
@@ -543,77 +642,502 @@ impl TcpStream {
     pub fn take_error(&mut self) -> io::Result<Option<io::Error>> {
         self.socket.take_error()
     }
-}
 
-/// The [`Future`] behind [`TcpStream::connect`].
-#[derive(Debug)]
-#[must_use = "futures do nothing unless you `.await` or poll them"]
-pub struct Connect {
-    socket: Option<net::TcpStream>,
-    #[cfg(target_os = "linux")]
-    cpu_affinity: Option<usize>,
+    /// Split the stream into an owned read and write half.
+    ///
+    /// Both halves are [bound] to the actor that owns `ctx`, but can be bound
+    /// to different actors, e.g. one that only reads and another that only
+    /// writes, possibly running on different workers. The halves can be
+    /// rejoined using [`ReadHalf::reunite`].
+    ///
+    /// Dropping the [`WriteHalf`] will shutdown the writing side of the
+    /// connection, i.e. the peer will receive an end of file. The connection
+    /// is closed once both halves are dropped.
+    ///
+    /// [bound]: crate::Bound
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(never_type)]
+    ///
+    /// use std::io;
+    ///
+    /// use heph::actor;
+    /// use heph_rt::net::TcpStream;
+    /// use heph_rt::ThreadLocal;
+    ///
+    /// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+    ///     let address = "127.0.0.1:12345".parse().unwrap();
+    ///     let stream = TcpStream::connect(&mut ctx, address)?.await?;
+    ///     let (mut read_half, mut write_half) = stream.into_split(&mut ctx)?;
+    ///
+    ///     // The write half could be send to another actor here.
+    ///     write_half.send_all(b"Hello world!").await?;
+    ///
+    ///     let mut buf = Vec::with_capacity(4 * 1024); // 4 KB.
+    ///     let n = read_half.recv(&mut buf).await?;
+    ///     println!("read {} bytes: {:?}", n, buf);
+    ///
+    ///     Ok(())
+    /// }
+    /// #
+    /// # drop(actor); // Silent dead code warnings.
+    /// ```
+    pub fn into_split<M, RT>(
+        mut self,
+        ctx: &mut actor::Context<M, RT>,
+    ) -> io::Result<(ReadHalf, WriteHalf)>
+    where
+        RT: rt::Access,
+    {
+        // Both halves use their own file descriptor, both referring to the
+        // same socket, this allows them to be registered with different
+        // actors (and different `mio::Poll` instances).
+        let socket = SockRef::from(&self.socket).try_clone()?;
+        let mut write = TcpStream {
+            socket: net::TcpStream::from_std(std_net::TcpStream::from(socket)),
+        };
+        let read_registration = Registration::new(ctx, &self)?;
+        let write_registration = Registration::new(ctx, &write)?;
+        ctx.runtime()
+            .register(&mut write.socket, Interest::WRITABLE)?;
+        ctx.runtime()
+            .reregister(&mut self.socket, Interest::READABLE)?;
+        let id = Arc::new(());
+        let read = ReadHalf {
+            registration: read_registration,
+            stream: self,
+            id: id.clone(),
+        };
+        let write = WriteHalf {
+            registration: write_registration,
+            stream: write,
+            id,
+            shutdown_on_drop: true,
+        };
+        Ok((read, write))
+    }
+
+    /// Split the stream into a borrowed read and write half.
+    ///
+    /// Unlike [`TcpStream::into_split`] the halves can't be moved to another
+    /// actor, but they allow reading and writing at the same time from within
+    /// the same actor, e.g. by polling a receive and send future concurrently.
+    ///
+    /// Dropping the [`WriteHalfRef`] does *not* shutdown the writing side of
+    /// the connection, use [`WriteHalfRef::shutdown`] for that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(never_type)]
+    ///
+    /// use std::io;
+    ///
+    /// use heph::actor;
+    /// use heph_rt::net::TcpStream;
+    /// use heph_rt::ThreadLocal;
+    ///
+    /// async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+    ///     let address = "127.0.0.1:12345".parse().unwrap();
+    ///     let mut stream = TcpStream::connect(&mut ctx, address)?.await?;
+    ///     let (mut read_half, mut write_half) = stream.split();
+    ///
+    ///     let mut buf = Vec::with_capacity(4 * 1024); // 4 KB.
+    ///     let recv = read_half.recv(&mut buf);
+    ///     let send = write_half.send_all(b"Hello world!");
+    ///     // Both futures can be polled at the same time here.
+    ///     # drop((recv, send));
+    ///
+    ///     Ok(())
+    /// }
+    /// #
+    /// # drop(actor); // Silent dead code warnings.
+    /// ```
+    pub fn split(&mut self) -> (ReadHalfRef<'_>, WriteHalfRef<'_>) {
+        (ReadHalfRef { stream: self }, WriteHalfRef { stream: self })
+    }
 }
 
-impl Future for Connect {
-    type Output = io::Result<TcpStream>;
+/// Implementations of the `try_*` methods that only require a shared reference
+/// to the stream, used by the futures and the borrowed halves returned by
+/// [`TcpStream::split`].
+impl TcpStream {
+    fn try_send_ref(&self, buf: &[u8]) -> io::Result<usize> {
+        SockRef::from(&self.socket).send(buf)
+    }
 
-    #[track_caller]
-    fn poll(mut self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
-        // This relates directly Mio and `kqueue(2)` and `epoll(2)`. To do a
-        // non-blocking TCP connect properly we need to a couple of things.
-        //
-        // 1. Setup a socket and call `connect(2)`. Mio does this for us.
-        //    However it doesn't mean the socket is connected, as we can't
-        //    determine that without blocking.
-        // 2. To determine if a socket is connected we need to wait for a
-        //    `kqueue(2)`/`epoll(2)` event (we get scheduled once we do). But
-        //    that doesn't tell us whether or not the socket is connected. To
-        //    determine if the socket is connected we need to use `getpeername`
-        //    (`TcpStream::peer_addr`). But before checking if we're connected
-        //    we need to check for a connection error, by checking `SO_ERROR`
-        //    (`TcpStream::take_error`) to not lose that information.
-        //    However if we get an event (and thus get scheduled) and
-        //    `getpeername` fails with `ENOTCONN` it doesn't actually mean the
-        //    socket will never connect properly. So we loop (by returned
-        //    `Poll::Pending`) until either `SO_ERROR` is set or the socket is
-        //    connected.
-        //
-        // Sources:
-        // * https://cr.yp.to/docs/connect.html
-        // * https://stackoverflow.com/questions/17769964/linux-sockets-non-blocking-connect
-        match self.socket.take() {
-            Some(socket) => {
-                // If we hit an error while connecting return that error.
-                if let Ok(Some(err)) | Err(err) = socket.take_error() {
-                    return Poll::Ready(Err(err));
-                }
+    fn try_send_vectored_ref(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        SockRef::from(&self.socket).send_vectored(bufs)
+    }
 
-                // If we can get a peer address it means the stream is
-                // connected.
-                match socket.peer_addr() {
-                    Ok(..) => {
-                        #[allow(unused_mut)]
-                        let mut stream = TcpStream { socket };
-                        #[cfg(target_os = "linux")]
-                        if let Some(cpu) = self.cpu_affinity {
-                            if let Err(err) = stream.set_cpu_affinity(cpu) {
-                                warn!("failed to set CPU affinity on TcpStream: {}", err);
-                            }
-                        }
-                        Poll::Ready(Ok(stream))
-                    }
-                    // `NotConnected` (`ENOTCONN`) means the socket not yet
-                    // connected, but still working on it. `ECONNREFUSED` will
-                    // be reported if it fails.
-                    Err(err)
-                        if err.kind() == io::ErrorKind::NotConnected
-                            || err.raw_os_error() == Some(libc::EINPROGRESS) =>
-                    {
-                        // Socket is not (yet) connected but haven't hit an
-                        // error either. So we return `Pending` and wait for
-                        // another event.
-                        self.socket = Some(socket);
-                        Poll::Pending
+    fn try_recv_ref<B>(&self, mut buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        debug_assert!(
+            buf.has_spare_capacity(),
+            "called `TcpStream::try_recv with an empty buffer"
+        );
+        SockRef::from(&self.socket)
+            .recv(buf.as_bytes())
+            .map(|read| {
+                // Safety: just read the bytes.
+                unsafe { buf.update_length(read) }
+                read
+            })
+    }
+
+    fn try_recv_vectored_ref<B>(&self, mut bufs: B) -> io::Result<usize>
+    where
+        B: BytesVectored,
+    {
+        debug_assert!(
+            bufs.has_spare_capacity(),
+            "called `TcpStream::try_recv_vectored` with empty buffers"
+        );
+        let res = SockRef::from(&self.socket)
+            .recv_vectored(MaybeUninitSlice::as_socket2(bufs.as_bufs().as_mut()));
+        match res {
+            Ok((read, _)) => {
+                // Safety: just read the bytes.
+                unsafe { bufs.update_lengths(read) }
+                Ok(read)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn try_peek_ref<B>(&self, mut buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        debug_assert!(
+            buf.has_spare_capacity(),
+            "called `TcpStream::try_peek with an empty buffer"
+        );
+        SockRef::from(&self.socket)
+            .peek(buf.as_bytes())
+            .map(|read| {
+                // Safety: just read the bytes.
+                unsafe { buf.update_length(read) }
+                read
+            })
+    }
+
+    fn try_peek_vectored_ref<B>(&self, mut bufs: B) -> io::Result<usize>
+    where
+        B: BytesVectored,
+    {
+        debug_assert!(
+            bufs.has_spare_capacity(),
+            "called `TcpStream::try_peek_vectored` with empty buffers"
+        );
+        let res = SockRef::from(&self.socket).recv_vectored_with_flags(
+            MaybeUninitSlice::as_socket2(bufs.as_bufs().as_mut()),
+            libc::MSG_PEEK,
+        );
+        match res {
+            Ok((read, _)) => {
+                // Safety: just read the bytes.
+                unsafe { bufs.update_lengths(read) }
+                Ok(read)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Implementations of the I/O operations used by the futures. These use
+/// io_uring (see [`Op`]) and fall back to the `try_*` methods if the current
+/// thread doesn't use io_uring.
+///
+/// Return an error with [`io::ErrorKind::WouldBlock`] while the operation is
+/// in progress.
+impl TcpStream {
+    fn send_op(&self, op: &mut Op, ctx: &mut task::Context<'_>, buf: &[u8]) -> io::Result<usize> {
+        match op.poll_send(ctx, self.socket.as_raw_fd(), buf) {
+            Some(Poll::Ready(res)) => res,
+            Some(Poll::Pending) => Err(io::ErrorKind::WouldBlock.into()),
+            None => self.try_send_ref(buf),
+        }
+    }
+
+    fn recv_op<B>(&self, op: &mut Op, ctx: &mut task::Context<'_>, buf: &mut B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        match op.poll_recv(ctx, self.socket.as_raw_fd(), buf) {
+            Some(Poll::Ready(res)) => res,
+            Some(Poll::Pending) => Err(io::ErrorKind::WouldBlock.into()),
+            None => self.try_recv_ref(buf),
+        }
+    }
+
+    fn send_file_op<F>(
+        &mut self,
+        op: &mut Op,
+        ctx: &mut task::Context<'_>,
+        file: &F,
+        offset: usize,
+        length: Option<NonZeroUsize>,
+    ) -> io::Result<usize>
+    where
+        F: FileSend,
+    {
+        let fd = self.socket.as_raw_fd();
+        match op.poll_send_file(ctx, fd, file.as_raw_fd(), offset, length) {
+            Some(Poll::Ready(res)) => res,
+            Some(Poll::Pending) => Err(io::ErrorKind::WouldBlock.into()),
+            None => self.try_send_file(file, offset, length),
+        }
+    }
+}
+
+/// The [`Future`] behind [`TcpStream::connect_host`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ConnectHost<'a, M, RT: rt::Access> {
+    ctx: &'a mut actor::Context<M, RT>,
+    state: ConnectHostState<RT>,
+}
+
+enum ConnectHostState<RT: rt::Access> {
+    /// Resolving the hostname.
+    Resolving(LookupHost),
+    /// Connecting to the resolved addresses.
+    Connecting {
+        /// Addresses we haven't tried yet, in order.
+        addresses: VecDeque<SocketAddr>,
+        /// Running connection attempts.
+        attempts: Vec<Connect>,
+        /// Timer for starting the next connection attempt.
+        next_attempt: Option<Timer<RT>>,
+        /// Error of the last failed connection attempt.
+        last_error: Option<io::Error>,
+    },
+}
+
+impl<'a, M, RT> Future for ConnectHost<'a, M, RT>
+where
+    RT: rt::Access + Clone,
+{
+    type Output = io::Result<TcpStream>;
+
+    fn poll(self: Pin<&mut Self>, task_ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let ConnectHost { ctx, state } = Pin::into_inner(self);
+        if let ConnectHostState::Resolving(lookup) = state {
+            match Pin::new(lookup).poll(task_ctx) {
+                Poll::Ready(Ok(addresses)) => {
+                    *state = ConnectHostState::Connecting {
+                        addresses: interleave_addresses(addresses),
+                        attempts: Vec::new(),
+                        next_attempt: None,
+                        last_error: None,
+                    };
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let (addresses, attempts, next_attempt, last_error) = match state {
+            ConnectHostState::Connecting {
+                addresses,
+                attempts,
+                next_attempt,
+                last_error,
+            } => (addresses, attempts, next_attempt, last_error),
+            ConnectHostState::Resolving(..) => unreachable!(),
+        };
+
+        // Whether or not a connection attempt failed, in which case we start
+        // the next attempt right away.
+        let mut failed = false;
+        loop {
+            // Check the running connection attempts, the first one to succeed
+            // wins.
+            let mut i = 0;
+            while i < attempts.len() {
+                match Pin::new(&mut attempts[i]).poll(task_ctx) {
+                    Poll::Ready(Ok(stream)) => return Poll::Ready(Ok(stream)),
+                    Poll::Ready(Err(err)) => {
+                        drop(attempts.swap_remove(i));
+                        *last_error = Some(err);
+                        failed = true;
+                    }
+                    Poll::Pending => i += 1,
+                }
+            }
+
+            // Start a new connection attempt if the previous attempt failed or
+            // is taking too long.
+            let start_next = failed
+                || attempts.is_empty()
+                || next_attempt.as_ref().map_or(true, Timer::has_passed);
+            if !start_next {
+                return Poll::Pending;
+            }
+
+            match addresses.pop_front() {
+                Some(address) => match TcpStream::connect(*ctx, address) {
+                    Ok(connect) => {
+                        attempts.push(connect);
+                        failed = false;
+                        *next_attempt = Some(Timer::after(*ctx, CONNECTION_ATTEMPT_DELAY));
+                        // Loop around to poll the new attempt.
+                    }
+                    Err(err) => {
+                        *last_error = Some(err);
+                        failed = true;
+                    }
+                },
+                None if attempts.is_empty() => {
+                    let err = last_error.take().unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+                    });
+                    return Poll::Ready(Err(err));
+                }
+                None => {
+                    // No more addresses to try, wait for the running attempts.
+                    *next_attempt = None;
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, M, RT: rt::Access> fmt::Debug for ConnectHost<'a, M, RT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match &self.state {
+            ConnectHostState::Resolving(..) => "resolving",
+            ConnectHostState::Connecting { .. } => "connecting",
+        };
+        f.debug_struct("ConnectHost")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+/// Interleave the IPv6 and IPv4 `addresses`, starting with the family of the
+/// first address, as described in RFC 8305 section 4.
+fn interleave_addresses(addresses: Vec<SocketAddr>) -> VecDeque<SocketAddr> {
+    let first_is_ipv6 = addresses.first().map_or(true, SocketAddr::is_ipv6);
+    let (mut first, mut second): (VecDeque<_>, VecDeque<_>) = addresses
+        .into_iter()
+        .partition(|address| address.is_ipv6() == first_is_ipv6);
+    let mut interleaved = VecDeque::with_capacity(first.len() + second.len());
+    loop {
+        match (first.pop_front(), second.pop_front()) {
+            (None, None) => return interleaved,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// The [`Future`] behind [`TcpStream::connect`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Connect {
+    socket: Option<net::TcpStream>,
+    /// Address to connect to using `op`, if the socket isn't connected yet.
+    address: Option<SockAddr>,
+    op: Op,
+    #[cfg(target_os = "linux")]
+    cpu_affinity: Option<usize>,
+}
+
+impl Future for Connect {
+    type Output = io::Result<TcpStream>;
+
+    #[track_caller]
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        // When using io_uring the connect operation is only started here.
+        if let Some(address) = self.address.take() {
+            let this = &mut *self;
+            let fd = match this.socket {
+                Some(ref socket) => socket.as_raw_fd(),
+                None => panic!("polled `tcp::stream::Connect` after completion"),
+            };
+            match this.op.poll_connect(ctx, fd, &address) {
+                Some(Poll::Ready(Ok(()))) => {}
+                // Connection is in progress, wait for the socket to become
+                // ready like we do below.
+                Some(Poll::Ready(Err(ref err)))
+                    if matches!(err.raw_os_error(), Some(libc::EINPROGRESS | libc::EALREADY)) => {}
+                Some(Poll::Ready(Err(err))) => return Poll::Ready(Err(err)),
+                Some(Poll::Pending) => {
+                    this.address = Some(address);
+                    return Poll::Pending;
+                }
+                // The current thread doesn't use io_uring, use a non-blocking
+                // connect instead.
+                None => match SockRef::from(this.socket.as_ref().unwrap()).connect(&address) {
+                    Ok(()) => {}
+                    Err(ref err) if err.raw_os_error() == Some(libc::EINPROGRESS) => {
+                        return Poll::Pending
+                    }
+                    Err(err) => return Poll::Ready(Err(err)),
+                },
+            }
+        }
+
+        // This relates directly Mio and `kqueue(2)` and `epoll(2)`. To do a
+        // non-blocking TCP connect properly we need to a couple of things.
+        //
+        // 1. Setup a socket and call `connect(2)`. Mio does this for us.
+        //    However it doesn't mean the socket is connected, as we can't
+        //    determine that without blocking.
+        // 2. To determine if a socket is connected we need to wait for a
+        //    `kqueue(2)`/`epoll(2)` event (we get scheduled once we do). But
+        //    that doesn't tell us whether or not the socket is connected. To
+        //    determine if the socket is connected we need to use `getpeername`
+        //    (`TcpStream::peer_addr`). But before checking if we're connected
+        //    we need to check for a connection error, by checking `SO_ERROR`
+        //    (`TcpStream::take_error`) to not lose that information.
+        //    However if we get an event (and thus get scheduled) and
+        //    `getpeername` fails with `ENOTCONN` it doesn't actually mean the
+        //    socket will never connect properly. So we loop (by returned
+        //    `Poll::Pending`) until either `SO_ERROR` is set or the socket is
+        //    connected.
+        //
+        // Sources:
+        // * https://cr.yp.to/docs/connect.html
+        // * https://stackoverflow.com/questions/17769964/linux-sockets-non-blocking-connect
+        match self.socket.take() {
+            Some(socket) => {
+                // If we hit an error while connecting return that error.
+                if let Ok(Some(err)) | Err(err) = socket.take_error() {
+                    return Poll::Ready(Err(err));
+                }
+
+                // If we can get a peer address it means the stream is
+                // connected.
+                match socket.peer_addr() {
+                    Ok(..) => {
+                        #[allow(unused_mut)]
+                        let mut stream = TcpStream { socket };
+                        #[cfg(target_os = "linux")]
+                        if let Some(cpu) = self.cpu_affinity {
+                            if let Err(err) = stream.set_cpu_affinity(cpu) {
+                                warn!("failed to set CPU affinity on TcpStream: {}", err);
+                            }
+                        }
+                        Poll::Ready(Ok(stream))
+                    }
+                    // `NotConnected` (`ENOTCONN`) means the socket not yet
+                    // connected, but still working on it. `ECONNREFUSED` will
+                    // be reported if it fails.
+                    Err(err)
+                        if err.kind() == io::ErrorKind::NotConnected
+                            || err.raw_os_error() == Some(libc::EINPROGRESS) =>
+                    {
+                        // Socket is not (yet) connected but haven't hit an
+                        // error either. So we return `Pending` and wait for
+                        // another event.
+                        self.socket = Some(socket);
+                        Poll::Pending
                     }
                     Err(err) => Poll::Ready(Err(err)),
                 }
@@ -627,16 +1151,17 @@ impl Future for Connect {
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Send<'a, 'b> {
-    stream: &'a mut TcpStream,
+    stream: &'a TcpStream,
     buf: &'b [u8],
+    op: Op,
 }
 
 impl<'a, 'b> Future for Send<'a, 'b> {
     type Output = io::Result<usize>;
 
-    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
-        let Send { stream, buf } = Pin::into_inner(self);
-        try_io!(stream.try_send(*buf))
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Send { stream, buf, op } = Pin::into_inner(self);
+        try_io!(stream.send_op(op, ctx, *buf))
     }
 }
 
@@ -644,17 +1169,18 @@ impl<'a, 'b> Future for Send<'a, 'b> {
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct SendAll<'a, 'b> {
-    stream: &'a mut TcpStream,
+    stream: &'a TcpStream,
     buf: &'b [u8],
+    op: Op,
 }
 
 impl<'a, 'b> Future for SendAll<'a, 'b> {
     type Output = io::Result<()>;
 
-    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
-        let SendAll { stream, buf } = Pin::into_inner(self);
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let SendAll { stream, buf, op } = Pin::into_inner(self);
         loop {
-            match stream.try_send(*buf) {
+            match stream.send_op(op, ctx, *buf) {
                 Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
                 Ok(n) if buf.len() <= n => return Poll::Ready(Ok(())),
                 Ok(n) => {
@@ -674,7 +1200,7 @@ impl<'a, 'b> Future for SendAll<'a, 'b> {
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct SendVectored<'a, 'b> {
-    stream: &'a mut TcpStream,
+    stream: &'a TcpStream,
     bufs: &'b mut [IoSlice<'b>],
 }
 
@@ -683,7 +1209,7 @@ impl<'a, 'b> Future for SendVectored<'a, 'b> {
 
     fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         let SendVectored { stream, bufs } = Pin::into_inner(self);
-        try_io!(stream.try_send_vectored(*bufs))
+        try_io!(stream.try_send_vectored_ref(*bufs))
     }
 }
 
@@ -691,7 +1217,7 @@ impl<'a, 'b> Future for SendVectored<'a, 'b> {
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct SendVectoredAll<'a, 'b> {
-    stream: &'a mut TcpStream,
+    stream: &'a TcpStream,
     bufs: &'b mut [IoSlice<'b>],
 }
 
@@ -701,7 +1227,7 @@ impl<'a, 'b> Future for SendVectoredAll<'a, 'b> {
     fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         let SendVectoredAll { stream, bufs } = Pin::into_inner(self);
         while !bufs.is_empty() {
-            match stream.try_send_vectored(*bufs) {
+            match stream.try_send_vectored_ref(*bufs) {
                 Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
                 Ok(n) => IoSlice::advance_slices(bufs, n),
                 Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
@@ -717,8 +1243,9 @@ impl<'a, 'b> Future for SendVectoredAll<'a, 'b> {
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Recv<'b, B> {
-    stream: &'b mut TcpStream,
+    stream: &'b TcpStream,
     buf: B,
+    op: Op,
 }
 
 impl<'b, B> Future for Recv<'b, B>
@@ -727,9 +1254,9 @@ where
 {
     type Output = io::Result<usize>;
 
-    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
-        let Recv { stream, buf } = Pin::into_inner(self);
-        try_io!(stream.try_recv(&mut *buf))
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let Recv { stream, buf, op } = Pin::into_inner(self);
+        try_io!(stream.recv_op(op, ctx, &mut *buf))
     }
 }
 
@@ -737,7 +1264,7 @@ where
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Peek<'b, B> {
-    stream: &'b mut TcpStream,
+    stream: &'b TcpStream,
     buf: B,
 }
 
@@ -749,7 +1276,7 @@ where
 
     fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         let Peek { stream, buf } = Pin::into_inner(self);
-        try_io!(stream.try_peek(&mut *buf))
+        try_io!(stream.try_peek_ref(&mut *buf))
     }
 }
 
@@ -757,9 +1284,10 @@ where
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct RecvN<'b, B> {
-    stream: &'b mut TcpStream,
+    stream: &'b TcpStream,
     buf: B,
     left: usize,
+    op: Op,
 }
 
 impl<'b, B> Future for RecvN<'b, B>
@@ -768,10 +1296,15 @@ where
 {
     type Output = io::Result<()>;
 
-    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
-        let RecvN { stream, buf, left } = Pin::into_inner(self);
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let RecvN {
+            stream,
+            buf,
+            left,
+            op,
+        } = Pin::into_inner(self);
         loop {
-            match stream.try_recv(&mut *buf) {
+            match stream.recv_op(op, ctx, &mut *buf) {
                 Ok(0) => return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into())),
                 Ok(n) if n >= *left => return Poll::Ready(Ok(())),
                 Ok(n) => {
@@ -791,7 +1324,7 @@ where
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct RecvVectored<'b, B> {
-    stream: &'b mut TcpStream,
+    stream: &'b TcpStream,
     bufs: B,
 }
 
@@ -803,7 +1336,7 @@ where
 
     fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         let RecvVectored { stream, bufs } = Pin::into_inner(self);
-        try_io!(stream.try_recv_vectored(&mut *bufs))
+        try_io!(stream.try_recv_vectored_ref(&mut *bufs))
     }
 }
 
@@ -811,7 +1344,7 @@ where
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct RecvNVectored<'b, B> {
-    stream: &'b mut TcpStream,
+    stream: &'b TcpStream,
     bufs: B,
     left: usize,
 }
@@ -825,7 +1358,7 @@ where
     fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         let RecvNVectored { stream, bufs, left } = Pin::into_inner(self);
         loop {
-            match stream.try_recv_vectored(&mut *bufs) {
+            match stream.try_recv_vectored_ref(&mut *bufs) {
                 Ok(0) => return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into())),
                 Ok(n) if n >= *left => return Poll::Ready(Ok(())),
                 Ok(n) => {
@@ -845,7 +1378,7 @@ where
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct PeekVectored<'b, B> {
-    stream: &'b mut TcpStream,
+    stream: &'b TcpStream,
     bufs: B,
 }
 
@@ -857,7 +1390,7 @@ where
 
     fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         let PeekVectored { stream, bufs } = Pin::into_inner(self);
-        try_io!(stream.try_peek_vectored(&mut *bufs))
+        try_io!(stream.try_peek_vectored_ref(&mut *bufs))
     }
 }
 
@@ -869,6 +1402,7 @@ pub struct SendFile<'a, 'f, F> {
     file: &'f F,
     offset: usize,
     length: Option<NonZeroUsize>,
+    op: Op,
 }
 
 impl<'a, 'f, F> Future for SendFile<'a, 'f, F>
@@ -877,10 +1411,10 @@ where
 {
     type Output = io::Result<usize>;
 
-    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
         #[rustfmt::skip]
-        let SendFile { stream, file, offset, length } = Pin::into_inner(self);
-        try_io!(stream.try_send_file(*file, *offset, *length))
+        let SendFile { stream, file, offset, length, op } = Pin::into_inner(self);
+        try_io!(stream.send_file_op(op, ctx, *file, *offset, *length))
     }
 }
 
@@ -894,6 +1428,7 @@ pub struct SendFileAll<'a, 'f, F> {
     /// If `start >= end` all bytes are send.
     start: usize,
     end: Option<NonZeroUsize>,
+    op: Op,
 }
 
 impl<'a, 'f, F> Future for SendFileAll<'a, 'f, F>
@@ -902,12 +1437,12 @@ where
 {
     type Output = io::Result<()>;
 
-    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
         #[rustfmt::skip]
-        let SendFileAll { stream, file, start, end } = Pin::into_inner(self);
+        let SendFileAll { stream, file, start, end, op } = Pin::into_inner(self);
         loop {
             let length = end.and_then(|end| NonZeroUsize::new(end.get() - *start));
-            match stream.try_send_file(*file, *start, length) {
+            match stream.send_file_op(op, ctx, *file, *start, length) {
                 // If zero bytes are send it means the entire file was send.
                 Ok(0) => break Poll::Ready(Ok(())),
                 Ok(n) => {
@@ -931,6 +1466,740 @@ where
     }
 }
 
+/// The [`Future`] behind [`TcpStream::splice_to`].
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SpliceTo<'a, 'b> {
+    stream: &'a mut TcpStream,
+    pipe: &'b mut pipe::Sender,
+    length: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for SpliceTo<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        #[rustfmt::skip]
+        let SpliceTo { stream, pipe, length } = Pin::into_inner(self);
+        try_io!(stream.try_splice_to(pipe, *length))
+    }
+}
+
+/// Maximum number of bytes moved in a single `splice(2)` call in
+/// [`copy_bidirectional`], the default capacity of a pipe on Linux.
+#[cfg(target_os = "linux")]
+const SPLICE_LENGTH: usize = 64 * 1024;
+
+/// Copy bytes between streams `a` and `b` in both directions, using
+/// `splice(2)` to avoid copying the bytes into user space.
+///
+/// This creates two pipes, one for each direction, which are bound to the
+/// actor that owns `ctx`. Once one side of the connection is closed for
+/// writing (i.e. a read returns zero bytes) all remaining bytes are forwarded
+/// and the writing side of the other stream is shutdown.
+///
+/// The future returns once both directions are closed, returning the number of
+/// bytes copied from `a` to `b` and from `b` to `a`.
+///
+/// # Notes
+///
+/// Both streams must be [bound] to the actor that owns `ctx`.
+///
+/// [bound]: crate::Bound
+///
+/// # Examples
+///
+/// A simple TCP proxy.
+///
+/// ```
+/// #![feature(never_type)]
+///
+/// use std::io;
+/// use std::net::SocketAddr;
+///
+/// use heph::actor;
+/// use heph_rt::net::tcp::stream::copy_bidirectional;
+/// use heph_rt::net::TcpStream;
+/// use heph_rt::ThreadLocal;
+///
+/// async fn proxy(
+///     mut ctx: actor::Context<!, ThreadLocal>,
+///     mut client: TcpStream,
+///     upstream: SocketAddr,
+/// ) -> io::Result<()> {
+///     let mut upstream = TcpStream::connect(&mut ctx, upstream)?.await?;
+///     let (sent, received) = copy_bidirectional(&mut ctx, &mut client, &mut upstream)?.await?;
+///     println!("proxied {} bytes upstream and {} bytes downstream", sent, received);
+///     Ok(())
+/// }
+/// #
+/// # drop(proxy); // Silent dead code warnings.
+/// ```
+#[cfg(target_os = "linux")]
+pub fn copy_bidirectional<'a, M, RT>(
+    ctx: &mut actor::Context<M, RT>,
+    a: &'a mut TcpStream,
+    b: &'a mut TcpStream,
+) -> io::Result<CopyBidirectional<'a>>
+where
+    RT: rt::Access,
+{
+    let a_to_b = pipe::new(ctx)?;
+    let b_to_a = pipe::new(ctx)?;
+    Ok(CopyBidirectional {
+        a,
+        b,
+        a_to_b: SpliceDirection::new(a_to_b),
+        b_to_a: SpliceDirection::new(b_to_a),
+    })
+}
+
+/// The [`Future`] behind [`copy_bidirectional`].
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CopyBidirectional<'a> {
+    a: &'a mut TcpStream,
+    b: &'a mut TcpStream,
+    a_to_b: SpliceDirection,
+    b_to_a: SpliceDirection,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for CopyBidirectional<'a> {
+    type Output = io::Result<(u64, u64)>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        #[rustfmt::skip]
+        let CopyBidirectional { a, b, a_to_b, b_to_a } = Pin::into_inner(self);
+        if let Poll::Ready(Err(err)) = a_to_b.poll(a, b) {
+            return Poll::Ready(Err(err));
+        }
+        if let Poll::Ready(Err(err)) = b_to_a.poll(b, a) {
+            return Poll::Ready(Err(err));
+        }
+        if a_to_b.done && b_to_a.done {
+            Poll::Ready(Ok((a_to_b.total, b_to_a.total)))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A single direction of [`CopyBidirectional`].
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct SpliceDirection {
+    sender: pipe::Sender,
+    receiver: pipe::Receiver,
+    /// Number of bytes in the pipe.
+    in_pipe: usize,
+    /// Whether or not the reading side returned zero bytes.
+    read_closed: bool,
+    /// Whether or not all bytes are copied and the writing side is shutdown.
+    done: bool,
+    /// Total number of bytes copied.
+    total: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl SpliceDirection {
+    fn new((sender, receiver): (pipe::Sender, pipe::Receiver)) -> SpliceDirection {
+        SpliceDirection {
+            sender,
+            receiver,
+            in_pipe: 0,
+            read_closed: false,
+            done: false,
+            total: 0,
+        }
+    }
+
+    /// Move bytes from `from` to `to` until either would block, or all bytes
+    /// are copied.
+    fn poll(&mut self, from: &mut TcpStream, to: &mut TcpStream) -> Poll<io::Result<()>> {
+        if self.done {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            let mut progress = false;
+
+            if !self.read_closed {
+                match from.try_splice_to(&mut self.sender, SPLICE_LENGTH) {
+                    Ok(0) => {
+                        self.read_closed = true;
+                        progress = true;
+                    }
+                    Ok(n) => {
+                        self.in_pipe += n;
+                        progress = true;
+                    }
+                    Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Poll::Ready(Err(err)),
+                }
+            }
+
+            if self.in_pipe != 0 {
+                match self.receiver.try_splice_to(to, self.in_pipe) {
+                    Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                    Ok(n) => {
+                        self.in_pipe -= n;
+                        self.total += n as u64;
+                        progress = true;
+                    }
+                    Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Poll::Ready(Err(err)),
+                }
+            }
+
+            if self.read_closed && self.in_pipe == 0 {
+                self.done = true;
+                return match to.shutdown(Shutdown::Write) {
+                    // Peer already closed the connection.
+                    Err(ref err) if err.kind() == io::ErrorKind::NotConnected => {
+                        Poll::Ready(Ok(()))
+                    }
+                    res => Poll::Ready(res),
+                };
+            }
+
+            if !progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Owned read half of a [`TcpStream`], created by [`TcpStream::into_split`].
+///
+/// The read half only supports receiving (and peeking) of bytes, see the
+/// methods on [`TcpStream`] for documentation.
+#[derive(Debug)]
+pub struct ReadHalf {
+    /// Must be dropped before `stream`.
+    registration: Registration,
+    stream: TcpStream,
+    /// Shared between the read and write half to check if they belong to the
+    /// same stream in [`ReadHalf::reunite`].
+    id: Arc<()>,
+}
+
+impl ReadHalf {
+    /// Returns the socket address of the remote peer of this TCP connection.
+    pub fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Returns the socket address of the local half of this TCP connection.
+    pub fn local_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    /// See [`TcpStream::try_recv`].
+    pub fn try_recv<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        self.stream.try_recv(buf)
+    }
+
+    /// See [`TcpStream::recv`].
+    pub fn recv<'a, B>(&'a mut self, buf: B) -> Recv<'a, B>
+    where
+        B: Bytes,
+    {
+        self.stream.recv(buf)
+    }
+
+    /// See [`TcpStream::recv_n`].
+    pub fn recv_n<'a, B>(&'a mut self, buf: B, n: usize) -> RecvN<'a, B>
+    where
+        B: Bytes,
+    {
+        self.stream.recv_n(buf, n)
+    }
+
+    /// See [`TcpStream::try_recv_vectored`].
+    pub fn try_recv_vectored<B>(&mut self, bufs: B) -> io::Result<usize>
+    where
+        B: BytesVectored,
+    {
+        self.stream.try_recv_vectored(bufs)
+    }
+
+    /// See [`TcpStream::recv_vectored`].
+    pub fn recv_vectored<B>(&mut self, bufs: B) -> RecvVectored<'_, B>
+    where
+        B: BytesVectored,
+    {
+        self.stream.recv_vectored(bufs)
+    }
+
+    /// See [`TcpStream::recv_n_vectored`].
+    pub fn recv_n_vectored<B>(&mut self, bufs: B, n: usize) -> RecvNVectored<'_, B>
+    where
+        B: BytesVectored,
+    {
+        self.stream.recv_n_vectored(bufs, n)
+    }
+
+    /// See [`TcpStream::try_peek`].
+    pub fn try_peek<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        self.stream.try_peek(buf)
+    }
+
+    /// See [`TcpStream::peek`].
+    pub fn peek<'a, B>(&'a mut self, buf: B) -> Peek<'a, B>
+    where
+        B: Bytes,
+    {
+        self.stream.peek(buf)
+    }
+
+    /// Rejoin the read half with the `write` half, returning the original
+    /// [`TcpStream`].
+    ///
+    /// The returned stream is [bound] to the actor that owns `ctx`. If `write`
+    /// doesn't belong to the same stream as this read half, or the stream
+    /// can't be bound to the actor, both halves are returned in the error.
+    ///
+    /// [bound]: crate::Bound
+    pub fn reunite<M, RT>(
+        mut self,
+        mut write: WriteHalf,
+        ctx: &mut actor::Context<M, RT>,
+    ) -> Result<TcpStream, ReuniteError>
+    where
+        RT: rt::Access,
+    {
+        if !Arc::ptr_eq(&self.id, &write.id) {
+            return Err(ReuniteError::Mismatch(self, write));
+        }
+
+        if let Err(err) = self.stream.bind_to(ctx) {
+            return Err(ReuniteError::Bind(err, self, write));
+        }
+        // Don't shutdown the connection, we're still using it.
+        write.shutdown_on_drop = false;
+        drop(write);
+        let ReadHalf {
+            registration,
+            stream,
+            ..
+        } = self;
+        // The stream remains registered.
+        registration.keep();
+        Ok(stream)
+    }
+}
+
+impl<RT: rt::Access> Bound<RT> for ReadHalf {
+    type Error = io::Error;
+
+    fn bind_to<M>(&mut self, ctx: &mut actor::Context<M, RT>) -> io::Result<()> {
+        ctx.runtime()
+            .reregister(&mut self.stream.socket, Interest::READABLE)
+    }
+}
+
+/// Owned write half of a [`TcpStream`], created by [`TcpStream::into_split`].
+///
+/// The write half only supports sending of bytes, see the methods on
+/// [`TcpStream`] for documentation.
+///
+/// # Notes
+///
+/// Dropping the write half will shutdown the writing side of the connection,
+/// unless it's rejoined with its read half using [`ReadHalf::reunite`].
+#[derive(Debug)]
+pub struct WriteHalf {
+    /// Must be dropped before `stream`.
+    #[allow(dead_code)] // Only used when dropped.
+    registration: Registration,
+    stream: TcpStream,
+    /// See [`ReadHalf::id`].
+    id: Arc<()>,
+    /// Whether or not to shutdown the writing side of the connection when
+    /// dropped.
+    shutdown_on_drop: bool,
+}
+
+impl WriteHalf {
+    /// Returns the socket address of the remote peer of this TCP connection.
+    pub fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Returns the socket address of the local half of this TCP connection.
+    pub fn local_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    /// See [`TcpStream::try_send`].
+    pub fn try_send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.try_send(buf)
+    }
+
+    /// See [`TcpStream::send`].
+    pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> Send<'a, 'b> {
+        self.stream.send(buf)
+    }
+
+    /// See [`TcpStream::send_all`].
+    pub fn send_all<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SendAll<'a, 'b> {
+        self.stream.send_all(buf)
+    }
+
+    /// See [`TcpStream::try_send_vectored`].
+    pub fn try_send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.stream.try_send_vectored(bufs)
+    }
+
+    /// See [`TcpStream::send_vectored`].
+    pub fn send_vectored<'a, 'b>(
+        &'a mut self,
+        bufs: &'b mut [IoSlice<'b>],
+    ) -> SendVectored<'a, 'b> {
+        self.stream.send_vectored(bufs)
+    }
+
+    /// See [`TcpStream::send_vectored_all`].
+    pub fn send_vectored_all<'a, 'b>(
+        &'a mut self,
+        bufs: &'b mut [IoSlice<'b>],
+    ) -> SendVectoredAll<'a, 'b> {
+        self.stream.send_vectored_all(bufs)
+    }
+
+    /// See [`TcpStream::send_file`].
+    pub fn send_file<'a, 'f, F>(
+        &'a mut self,
+        file: &'f F,
+        offset: usize,
+        length: Option<NonZeroUsize>,
+    ) -> SendFile<'a, 'f, F>
+    where
+        F: FileSend,
+    {
+        self.stream.send_file(file, offset, length)
+    }
+
+    /// See [`TcpStream::send_file_all`].
+    pub fn send_file_all<'a, 'f, F>(
+        &'a mut self,
+        file: &'f F,
+        offset: usize,
+        length: Option<NonZeroUsize>,
+    ) -> SendFileAll<'a, 'f, F>
+    where
+        F: FileSend,
+    {
+        self.stream.send_file_all(file, offset, length)
+    }
+
+    /// See [`TcpStream::send_entire_file`].
+    pub fn send_entire_file<'a, 'f, F>(&'a mut self, file: &'f F) -> SendFileAll<'a, 'f, F>
+    where
+        F: FileSend,
+    {
+        self.stream.send_entire_file(file)
+    }
+
+    /// Shuts down the writing side of the connection.
+    ///
+    /// This is also done when the write half is dropped.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Write)
+    }
+}
+
+impl<RT: rt::Access> Bound<RT> for WriteHalf {
+    type Error = io::Error;
+
+    fn bind_to<M>(&mut self, ctx: &mut actor::Context<M, RT>) -> io::Result<()> {
+        ctx.runtime()
+            .reregister(&mut self.stream.socket, Interest::WRITABLE)
+    }
+}
+
+impl Drop for WriteHalf {
+    fn drop(&mut self) {
+        if self.shutdown_on_drop {
+            // The socket is only closed once the read half is dropped as well,
+            // so we need to shutdown the writing side to let the peer know
+            // we're done. If the peer already closed the connection this will
+            // fail, which we can safely ignore.
+            let _ = self.stream.shutdown(Shutdown::Write);
+        }
+    }
+}
+
+/// Registration of a [`ReadHalf`] or [`WriteHalf`], deregisters the file
+/// descriptor when dropped.
+///
+/// Both halves use their own file descriptor for the same socket. However
+/// epoll only removes a registration once all file descriptors referring to
+/// the socket are closed, so each half must deregister its file descriptor
+/// when it's dropped. Otherwise the actor that owned the dropped half would
+/// keep getting woken up (or another process reusing its pid).
+#[derive(Debug)]
+struct Registration {
+    /// `None` if the file descriptor should remain registered.
+    registry: Option<Registry>,
+    fd: RawFd,
+}
+
+impl Registration {
+    /// Create a new registration for `stream`, which must be registered with
+    /// the runtime of `ctx`.
+    fn new<M, RT>(ctx: &mut actor::Context<M, RT>, stream: &TcpStream) -> io::Result<Registration>
+    where
+        RT: rt::Access,
+    {
+        Ok(Registration {
+            registry: Some(ctx.runtime_ref().try_clone_registry()?),
+            fd: stream.socket.as_raw_fd(),
+        })
+    }
+
+    /// Keep the file descriptor registered.
+    fn keep(mut self) {
+        drop(self.registry.take());
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.as_ref() {
+            // If this fails the file descriptor was likely never registered,
+            // nothing we can do about that.
+            let _ = registry.deregister(&mut SourceFd(&self.fd));
+        }
+    }
+}
+
+/// Borrowed read half of a [`TcpStream`], created by [`TcpStream::split`].
+///
+/// The read half only supports receiving (and peeking) of bytes, see the
+/// methods on [`TcpStream`] for documentation.
+#[derive(Debug)]
+pub struct ReadHalfRef<'a> {
+    stream: &'a TcpStream,
+}
+
+impl<'a> ReadHalfRef<'a> {
+    /// Returns the socket address of the remote peer of this TCP connection.
+    pub fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.socket.peer_addr()
+    }
+
+    /// Returns the socket address of the local half of this TCP connection.
+    pub fn local_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.socket.local_addr()
+    }
+
+    /// See [`TcpStream::try_recv`].
+    pub fn try_recv<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        self.stream.try_recv_ref(buf)
+    }
+
+    /// See [`TcpStream::recv`].
+    pub fn recv<B>(&mut self, buf: B) -> Recv<'a, B>
+    where
+        B: Bytes,
+    {
+        Recv {
+            stream: self.stream,
+            buf,
+            op: Op::new(),
+        }
+    }
+
+    /// See [`TcpStream::recv_n`].
+    pub fn recv_n<B>(&mut self, buf: B, n: usize) -> RecvN<'a, B>
+    where
+        B: Bytes,
+    {
+        debug_assert!(
+            buf.spare_capacity() >= n,
+            "called `ReadHalfRef::recv_n` with a buffer smaller then `n`"
+        );
+        RecvN {
+            stream: self.stream,
+            buf,
+            left: n,
+            op: Op::new(),
+        }
+    }
+
+    /// See [`TcpStream::try_recv_vectored`].
+    pub fn try_recv_vectored<B>(&mut self, bufs: B) -> io::Result<usize>
+    where
+        B: BytesVectored,
+    {
+        self.stream.try_recv_vectored_ref(bufs)
+    }
+
+    /// See [`TcpStream::recv_vectored`].
+    pub fn recv_vectored<B>(&mut self, bufs: B) -> RecvVectored<'a, B>
+    where
+        B: BytesVectored,
+    {
+        RecvVectored {
+            stream: self.stream,
+            bufs,
+        }
+    }
+
+    /// See [`TcpStream::recv_n_vectored`].
+    pub fn recv_n_vectored<B>(&mut self, bufs: B, n: usize) -> RecvNVectored<'a, B>
+    where
+        B: BytesVectored,
+    {
+        debug_assert!(
+            bufs.spare_capacity() >= n,
+            "called `ReadHalfRef::recv_n_vectored` with a buffer smaller then `n`"
+        );
+        RecvNVectored {
+            stream: self.stream,
+            bufs,
+            left: n,
+        }
+    }
+
+    /// See [`TcpStream::try_peek`].
+    pub fn try_peek<B>(&mut self, buf: B) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        self.stream.try_peek_ref(buf)
+    }
+
+    /// See [`TcpStream::peek`].
+    pub fn peek<B>(&mut self, buf: B) -> Peek<'a, B>
+    where
+        B: Bytes,
+    {
+        Peek {
+            stream: self.stream,
+            buf,
+        }
+    }
+}
+
+/// Borrowed write half of a [`TcpStream`], created by [`TcpStream::split`].
+///
+/// The write half only supports sending of bytes, see the methods on
+/// [`TcpStream`] for documentation.
+#[derive(Debug)]
+pub struct WriteHalfRef<'a> {
+    stream: &'a TcpStream,
+}
+
+impl<'a> WriteHalfRef<'a> {
+    /// Returns the socket address of the remote peer of this TCP connection.
+    pub fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.socket.peer_addr()
+    }
+
+    /// Returns the socket address of the local half of this TCP connection.
+    pub fn local_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.socket.local_addr()
+    }
+
+    /// See [`TcpStream::try_send`].
+    pub fn try_send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.try_send_ref(buf)
+    }
+
+    /// See [`TcpStream::send`].
+    pub fn send<'b>(&mut self, buf: &'b [u8]) -> Send<'a, 'b> {
+        Send {
+            stream: self.stream,
+            buf,
+            op: Op::new(),
+        }
+    }
+
+    /// See [`TcpStream::send_all`].
+    pub fn send_all<'b>(&mut self, buf: &'b [u8]) -> SendAll<'a, 'b> {
+        SendAll {
+            stream: self.stream,
+            buf,
+            op: Op::new(),
+        }
+    }
+
+    /// See [`TcpStream::try_send_vectored`].
+    pub fn try_send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.stream.try_send_vectored_ref(bufs)
+    }
+
+    /// See [`TcpStream::send_vectored`].
+    pub fn send_vectored<'b>(&mut self, bufs: &'b mut [IoSlice<'b>]) -> SendVectored<'a, 'b> {
+        SendVectored {
+            stream: self.stream,
+            bufs,
+        }
+    }
+
+    /// See [`TcpStream::send_vectored_all`].
+    pub fn send_vectored_all<'b>(
+        &mut self,
+        bufs: &'b mut [IoSlice<'b>],
+    ) -> SendVectoredAll<'a, 'b> {
+        SendVectoredAll {
+            stream: self.stream,
+            bufs,
+        }
+    }
+
+    /// Shuts down the writing side of the connection.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.stream.socket.shutdown(Shutdown::Write)
+    }
+}
+
+/// Error returned by [`ReadHalf::reunite`].
+#[derive(Debug)]
+pub enum ReuniteError {
+    /// The read and write halves don't belong to the same [`TcpStream`].
+    Mismatch(ReadHalf, WriteHalf),
+    /// Error binding the stream to the actor, returns both halves.
+    Bind(io::Error, ReadHalf, WriteHalf),
+}
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReuniteError::Mismatch(..) => {
+                f.write_str("tried to reunite halves that are not from the same TCP stream")
+            }
+            ReuniteError::Bind(err, ..) => write!(f, "failed to bind TCP stream: {}", err),
+        }
+    }
+}
+
+impl Error for ReuniteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReuniteError::Mismatch(..) => None,
+            ReuniteError::Bind(err, ..) => Some(err),
+        }
+    }
+}
+
 /// Trait that determines which types are safe to use in
 /// [`TcpStream::try_send_file`], [`TcpStream::send_file`] and
 /// [`TcpStream::send_file_all`].
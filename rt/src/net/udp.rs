@@ -11,7 +11,11 @@ use std::fmt;
 use std::future::Future;
 use std::io::{self, IoSlice};
 use std::marker::PhantomData;
-use std::net::SocketAddr;
+#[cfg(target_os = "linux")]
+use std::mem::{self, size_of, MaybeUninit};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
 use std::pin::Pin;
 use std::task::{self, Poll};
 
@@ -54,6 +58,11 @@ pub enum Connected {}
 /// Both unconnected and connected sockets have three main operations send,
 /// receive and peek, all these methods return a [`Future`].
 ///
+/// Sockets can also join (and leave) multicast groups, see
+/// [`UdpSocket::join_multicast_v4`]. On Linux unconnected sockets can send and
+/// receive multiple datagrams using a single system call, see
+/// [`UdpSocket::send_mmsg`] and [`UdpSocket::recv_mmsg`].
+///
 /// [connected]: UdpSocket::connect
 ///
 /// # Examples
@@ -198,8 +207,159 @@ impl<M> UdpSocket<M> {
     pub fn take_error(&mut self) -> io::Result<Option<io::Error>> {
         self.socket.take_error()
     }
+
+    /// Sets the value of the `SO_BROADCAST` option for this socket.
+    ///
+    /// When enabled, this socket is allowed to send packets to a broadcast
+    /// address.
+    pub fn set_broadcast(&mut self, broadcast: bool) -> io::Result<()> {
+        self.socket.set_broadcast(broadcast)
+    }
+
+    /// Gets the value of the `SO_BROADCAST` option for this socket.
+    ///
+    /// See [`UdpSocket::set_broadcast`].
+    pub fn broadcast(&mut self) -> io::Result<bool> {
+        self.socket.broadcast()
+    }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet sent
+    /// from this socket.
+    pub fn set_ttl(&mut self, ttl: u32) -> io::Result<()> {
+        self.socket.set_ttl(ttl)
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    ///
+    /// See [`UdpSocket::set_ttl`].
+    pub fn ttl(&mut self) -> io::Result<u32> {
+        self.socket.ttl()
+    }
+
+    /// Sets the time-to-live value of outgoing multicast packets for this
+    /// socket.
+    ///
+    /// This sets the `IP_MULTICAST_TTL` option for IPv4 sockets and the
+    /// `IPV6_MULTICAST_HOPS` option for IPv6 sockets, based on the local
+    /// address of the socket. Use this for code that works with both IPv4 and
+    /// IPv6 sockets, [`UdpSocket::set_multicast_ttl_v4`] only works for IPv4
+    /// sockets.
+    pub fn set_multicast_ttl(&mut self, ttl: u32) -> io::Result<()> {
+        if self.socket.local_addr()?.is_ipv4() {
+            self.socket.set_multicast_ttl_v4(ttl)
+        } else {
+            SockRef::from(&self.socket).set_multicast_hops_v6(ttl)
+        }
+    }
+
+    /// Gets the time-to-live value of outgoing multicast packets for this
+    /// socket, for both IPv4 and IPv6 sockets.
+    ///
+    /// See [`UdpSocket::set_multicast_ttl`].
+    pub fn multicast_ttl(&mut self) -> io::Result<u32> {
+        if self.socket.local_addr()?.is_ipv4() {
+            self.socket.multicast_ttl_v4()
+        } else {
+            SockRef::from(&self.socket).multicast_hops_v6()
+        }
+    }
+
+    /// Sets the value of the `IP_MULTICAST_TTL` option for this socket.
+    ///
+    /// Indicates the time-to-live value of outgoing multicast packets for this
+    /// socket. The default value is 1 which means that multicast packets don't
+    /// leave the local network unless explicitly requested.
+    pub fn set_multicast_ttl_v4(&mut self, ttl: u32) -> io::Result<()> {
+        self.socket.set_multicast_ttl_v4(ttl)
+    }
+
+    /// Gets the value of the `IP_MULTICAST_TTL` option for this socket.
+    ///
+    /// See [`UdpSocket::set_multicast_ttl_v4`].
+    pub fn multicast_ttl_v4(&mut self) -> io::Result<u32> {
+        self.socket.multicast_ttl_v4()
+    }
+
+    /// Sets the value of the `IP_MULTICAST_LOOP` option for this socket.
+    ///
+    /// If enabled, multicast packets will be looped back to the local socket.
+    pub fn set_multicast_loop_v4(&mut self, multicast_loop: bool) -> io::Result<()> {
+        self.socket.set_multicast_loop_v4(multicast_loop)
+    }
+
+    /// Gets the value of the `IP_MULTICAST_LOOP` option for this socket.
+    ///
+    /// See [`UdpSocket::set_multicast_loop_v4`].
+    pub fn multicast_loop_v4(&mut self) -> io::Result<bool> {
+        self.socket.multicast_loop_v4()
+    }
+
+    /// Sets the value of the `IPV6_MULTICAST_LOOP` option for this socket.
+    ///
+    /// Controls whether this socket sees the multicast packets it sends itself.
+    pub fn set_multicast_loop_v6(&mut self, multicast_loop: bool) -> io::Result<()> {
+        self.socket.set_multicast_loop_v6(multicast_loop)
+    }
+
+    /// Gets the value of the `IPV6_MULTICAST_LOOP` option for this socket.
+    ///
+    /// See [`UdpSocket::set_multicast_loop_v6`].
+    pub fn multicast_loop_v6(&mut self) -> io::Result<bool> {
+        self.socket.multicast_loop_v6()
+    }
+
+    /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
+    ///
+    /// This function specifies a new multicast group for this socket to join.
+    /// The address must be a valid multicast address, and `interface` is the
+    /// address of the local interface with which the system should join the
+    /// multicast group. If it's equal to `INADDR_ANY` then an appropriate
+    /// interface is chosen by the system.
+    pub fn join_multicast_v4(
+        &mut self,
+        multiaddr: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        self.socket.join_multicast_v4(multiaddr, interface)
+    }
+
+    /// Executes an operation of the `IPV6_ADD_MEMBERSHIP` type.
+    ///
+    /// This function specifies a new multicast group for this socket to join.
+    /// The address must be a valid multicast address, and `interface` is the
+    /// index of the interface to join/leave (or 0 to indicate any interface).
+    pub fn join_multicast_v6(&mut self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.socket.join_multicast_v6(multiaddr, interface)
+    }
+
+    /// Executes an operation of the `IP_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`UdpSocket::join_multicast_v4`].
+    pub fn leave_multicast_v4(
+        &mut self,
+        multiaddr: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        self.socket.leave_multicast_v4(multiaddr, interface)
+    }
+
+    /// Executes an operation of the `IPV6_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`UdpSocket::join_multicast_v6`].
+    pub fn leave_multicast_v6(&mut self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.socket.leave_multicast_v6(multiaddr, interface)
+    }
 }
 
+/// Maximum number of datagrams send or received in a single call to
+/// [`UdpSocket::try_send_mmsg`] or [`UdpSocket::try_recv_mmsg`].
+#[cfg(target_os = "linux")]
+pub const MAX_MMSG: usize = 64;
+
 impl UdpSocket<Unconnected> {
     /// Attempt to send data to the given `target` address.
     ///
@@ -408,6 +568,173 @@ impl UdpSocket<Unconnected> {
     {
         PeekFromVectored { socket: self, bufs }
     }
+
+    /// Attempt to send multiple datagrams using a single system call.
+    ///
+    /// Each element in `msgs` is send as a single datagram to the paired
+    /// address. Returns the number of datagrams send, which may be fewer then
+    /// the number of messages in `msgs`. At most [`MAX_MMSG`] datagrams are
+    /// send per call.
+    ///
+    /// If no datagrams can currently be send this will return an error with
+    /// the [kind] set to [`ErrorKind::WouldBlock`]. Most users should prefer
+    /// to use [`UdpSocket::send_mmsg`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    #[cfg(target_os = "linux")]
+    pub fn try_send_mmsg(&mut self, msgs: &[(&[u8], SocketAddr)]) -> io::Result<usize> {
+        let n = msgs.len().min(MAX_MMSG);
+        let mut addresses: [MaybeUninit<SockAddr>; MAX_MMSG] = MaybeUninit::uninit_array();
+        let mut iovecs: [MaybeUninit<libc::iovec>; MAX_MMSG] = MaybeUninit::uninit_array();
+        // Safety: all zeroes is valid for `mmsghdr`.
+        let mut headers: [libc::mmsghdr; MAX_MMSG] = unsafe { mem::zeroed() };
+        for (i, (buf, target)) in msgs.iter().take(n).enumerate() {
+            let address = addresses[i].write(SockAddr::from(*target));
+            headers[i].msg_hdr.msg_name = address.as_ptr() as *mut libc::c_void;
+            headers[i].msg_hdr.msg_namelen = address.len();
+            let iovec = iovecs[i].write(libc::iovec {
+                iov_base: buf.as_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            });
+            headers[i].msg_hdr.msg_iov = iovec;
+            headers[i].msg_hdr.msg_iovlen = 1;
+        }
+
+        #[allow(clippy::cast_possible_truncation)] // `n <= MAX_MMSG`.
+        let res = unsafe {
+            libc::sendmmsg(
+                self.socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                n as libc::c_uint,
+                0,
+            )
+        };
+        if res == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            #[allow(clippy::cast_sign_loss)] // Checked above.
+            Ok(res as usize)
+        }
+    }
+
+    /// Send multiple datagrams using a single system call. Returns a
+    /// [`Future`] that on success returns the number of datagrams send
+    /// (`io::Result<usize>`).
+    ///
+    /// See [`UdpSocket::try_send_mmsg`] for more information.
+    #[cfg(target_os = "linux")]
+    pub fn send_mmsg<'a, 'b>(&'a mut self, msgs: &'b [(&'b [u8], SocketAddr)]) -> SendMmsg<'a, 'b> {
+        SendMmsg { socket: self, msgs }
+    }
+
+    /// Attempt to receive multiple datagrams using a single system call.
+    ///
+    /// Each buffer in `bufs` will receive at most a single datagram, for which
+    /// the source address is pushed to `addresses`, i.e. the address of the
+    /// datagram in `bufs[0]` is in `addresses[addresses.len() - n]`, where `n`
+    /// is the returned number of datagrams received. The addresses already in
+    /// `addresses` remain untouched. At most [`MAX_MMSG`] datagrams are
+    /// received per call.
+    ///
+    /// If the source address of a datagram can't be converted into a
+    /// [`SocketAddr`] the number of datagrams before it is returned and it,
+    /// and the datagrams after it, are dropped. If it's the first datagram an
+    /// error is returned instead.
+    ///
+    /// If no datagrams can currently be received this will return an error
+    /// with the [kind] set to [`ErrorKind::WouldBlock`]. Most users should
+    /// prefer to use [`UdpSocket::recv_mmsg`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    #[cfg(target_os = "linux")]
+    pub fn try_recv_mmsg<B>(
+        &mut self,
+        bufs: &mut [B],
+        addresses: &mut Vec<SocketAddr>,
+    ) -> io::Result<usize>
+    where
+        B: Bytes,
+    {
+        debug_assert!(
+            bufs.iter().all(Bytes::has_spare_capacity),
+            "called `UdpSocket::try_recv_mmsg` with an empty buffer"
+        );
+        let n = bufs.len().min(MAX_MMSG);
+        // Safety: all zeroes is valid for `sockaddr_storage` and `mmsghdr`.
+        let mut storage: [libc::sockaddr_storage; MAX_MMSG] = unsafe { mem::zeroed() };
+        let mut headers: [libc::mmsghdr; MAX_MMSG] = unsafe { mem::zeroed() };
+        let mut iovecs: [MaybeUninit<libc::iovec>; MAX_MMSG] = MaybeUninit::uninit_array();
+        for (i, buf) in bufs.iter_mut().take(n).enumerate() {
+            let bytes = buf.as_bytes();
+            let iovec = iovecs[i].write(libc::iovec {
+                iov_base: bytes.as_mut_ptr().cast(),
+                iov_len: bytes.len(),
+            });
+            headers[i].msg_hdr.msg_iov = iovec;
+            headers[i].msg_hdr.msg_iovlen = 1;
+            headers[i].msg_hdr.msg_name = (&mut storage[i] as *mut libc::sockaddr_storage).cast();
+            #[allow(clippy::cast_possible_truncation)] // Fits in `socklen_t`.
+            let len = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            headers[i].msg_hdr.msg_namelen = len;
+        }
+
+        #[allow(clippy::cast_possible_truncation)] // `n <= MAX_MMSG`.
+        let res = unsafe {
+            libc::recvmmsg(
+                self.socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                n as libc::c_uint,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        #[allow(clippy::cast_sign_loss)] // Checked above.
+        let received = res as usize;
+        addresses.reserve(received);
+        for (i, buf) in bufs.iter_mut().take(received).enumerate() {
+            // Safety: the kernel initialised the address.
+            let address = unsafe { SockAddr::new(storage[i], headers[i].msg_hdr.msg_namelen) };
+            // Convert the address before updating the buffer, so that the
+            // caller never sees a datagram without an address.
+            let address = match convert_address(address) {
+                Ok(address) => address,
+                // Return the datagrams already processed.
+                Err(_) if i != 0 => return Ok(i),
+                Err(err) => return Err(err),
+            };
+            // Safety: just read the bytes.
+            unsafe { buf.update_length(headers[i].msg_len as usize) }
+            addresses.push(address);
+        }
+        Ok(received)
+    }
+
+    /// Receive multiple datagrams using a single system call. Returns a
+    /// [`Future`] that on success returns the number of datagrams received
+    /// (`io::Result<usize>`).
+    ///
+    /// See [`UdpSocket::try_recv_mmsg`] for more information.
+    #[cfg(target_os = "linux")]
+    pub fn recv_mmsg<'a, 'b, B>(
+        &'a mut self,
+        bufs: &'b mut [B],
+        addresses: &'b mut Vec<SocketAddr>,
+    ) -> RecvMmsg<'a, 'b, B>
+    where
+        B: Bytes,
+    {
+        RecvMmsg {
+            socket: self,
+            bufs,
+            addresses,
+        }
+    }
 }
 
 /// The [`Future`] behind [`UdpSocket::send_to`].
@@ -528,6 +855,49 @@ where
     }
 }
 
+/// The [`Future`] behind [`UdpSocket::send_mmsg`].
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendMmsg<'a, 'b> {
+    socket: &'a mut UdpSocket<Unconnected>,
+    msgs: &'b [(&'b [u8], SocketAddr)],
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for SendMmsg<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let SendMmsg { socket, msgs } = Pin::into_inner(self);
+        try_io!(socket.try_send_mmsg(msgs))
+    }
+}
+
+/// The [`Future`] behind [`UdpSocket::recv_mmsg`].
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RecvMmsg<'a, 'b, B> {
+    socket: &'a mut UdpSocket<Unconnected>,
+    bufs: &'b mut [B],
+    addresses: &'b mut Vec<SocketAddr>,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b, B> Future for RecvMmsg<'a, 'b, B>
+where
+    B: Bytes,
+{
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        #[rustfmt::skip]
+        let RecvMmsg { socket, bufs, addresses } = Pin::into_inner(self);
+        try_io!(socket.try_recv_mmsg(bufs, addresses))
+    }
+}
+
 impl UdpSocket<Connected> {
     /// Attempt to send data to the peer.
     ///
@@ -108,8 +108,12 @@
 use std::future::Future;
 use std::io::{self, IoSlice};
 use std::mem::MaybeUninit;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::pin::Pin;
 use std::process::{ChildStderr, ChildStdin, ChildStdout};
+#[cfg(target_os = "linux")]
+use std::ptr;
 use std::task::{self, Poll};
 
 use heph::actor;
@@ -117,6 +121,8 @@ use mio::unix::pipe;
 use mio::Interest;
 
 use crate::bytes::{Bytes, BytesVectored, MaybeUninitSlice};
+#[cfg(target_os = "linux")]
+use crate::net::TcpStream;
 use crate::{self as rt, Bound};
 
 /// Create a new Unix pipe.
@@ -151,6 +157,12 @@ pub struct Sender {
 }
 
 impl Sender {
+    /// Returns the raw file descriptor of the pipe.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
     /// Convert a [`ChildStdin`] to a `Sender`.
     pub fn from_child_stdin<M, RT>(
         ctx: &mut actor::Context<M, RT>,
@@ -338,6 +350,12 @@ pub struct Receiver {
 }
 
 impl Receiver {
+    /// Returns the raw file descriptor of the pipe.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
     /// Convert a [`ChildStdout`] to a `Receiver`.
     pub fn from_child_stdout<M, RT>(
         ctx: &mut actor::Context<M, RT>,
@@ -483,6 +501,68 @@ impl Receiver {
             left: n,
         }
     }
+
+    /// Attempt to move at most `length` bytes from the pipe into `stream`
+    /// using a `splice(2)` system call.
+    ///
+    /// If no bytes can currently be moved this will return an error with the
+    /// [kind] set to [`ErrorKind::WouldBlock`]. Most users should prefer to use
+    /// [`Receiver::splice_to`].
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::WouldBlock`]: io::ErrorKind::WouldBlock
+    #[cfg(target_os = "linux")]
+    pub fn try_splice_to(&mut self, stream: &mut TcpStream, length: usize) -> io::Result<usize> {
+        splice(self.raw_fd(), stream.raw_fd(), length)
+    }
+
+    /// Move at most `length` bytes from the pipe into `stream`, without
+    /// copying the bytes into user space.
+    ///
+    /// Returns the number of bytes moved, which may be fewer then `length`.
+    ///
+    /// # Notes
+    ///
+    /// Both the pipe and `stream` must be [bound] to the same actor, this
+    /// future is only woken once either of them becomes ready again.
+    ///
+    /// [bound]: crate::Bound
+    #[cfg(target_os = "linux")]
+    pub fn splice_to<'a, 'b>(
+        &'a mut self,
+        stream: &'b mut TcpStream,
+        length: usize,
+    ) -> SpliceTo<'a, 'b> {
+        SpliceTo {
+            receiver: self,
+            stream,
+            length,
+        }
+    }
+}
+
+/// Make a non-blocking `splice(2)` system call, moving at most `length` bytes
+/// from `fd_in` to `fd_out`. At least one of the file descriptors must be a
+/// pipe.
+#[cfg(target_os = "linux")]
+pub(crate) fn splice(fd_in: RawFd, fd_out: RawFd, length: usize) -> io::Result<usize> {
+    let flags = libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK;
+    let n = unsafe {
+        libc::splice(
+            fd_in,
+            ptr::null_mut(),
+            fd_out,
+            ptr::null_mut(),
+            length,
+            flags,
+        )
+    };
+    if n == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        #[allow(clippy::cast_sign_loss)] // Checked above.
+        Ok(n as usize)
+    }
 }
 
 /// The [`Future`] behind [`Receiver::read`].
@@ -601,6 +681,27 @@ where
     }
 }
 
+/// The [`Future`] behind [`Receiver::splice_to`].
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SpliceTo<'a, 'b> {
+    receiver: &'a mut Receiver,
+    stream: &'b mut TcpStream,
+    length: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for SpliceTo<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        #[rustfmt::skip]
+        let SpliceTo { receiver, stream, length } = Pin::into_inner(self);
+        try_io!(receiver.try_splice_to(stream, *length))
+    }
+}
+
 impl<RT: rt::Access> Bound<RT> for Receiver {
     type Error = io::Error;
 
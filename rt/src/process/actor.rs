@@ -7,9 +7,10 @@ use std::pin::Pin;
 use std::task::{self, Poll};
 
 use heph::actor::{self, Actor, NewActor};
+use heph::actor_ref::Envelope;
 use heph::supervisor::{Supervisor, SupervisorStrategy};
 use heph_inbox::{Manager, Receiver};
-use log::error;
+use log::{error, warn};
 
 use crate::access::PrivateAccess;
 use crate::process::{panic_message, Process, ProcessId, ProcessResult};
@@ -24,7 +25,7 @@ pub(crate) struct ActorProcess<S, NA: NewActor> {
     new_actor: NA,
     /// The inbox of the actor, used in creating a new [`actor::Context`]
     /// if the actor is restarted.
-    inbox: Manager<NA::Message>,
+    inbox: Manager<Envelope<NA::Message>>,
     /// The running actor.
     actor: NA::Actor,
 }
@@ -40,7 +41,7 @@ where
         supervisor: S,
         new_actor: NA,
         actor: NA::Actor,
-        inbox: Manager<NA::Message>,
+        inbox: Manager<Envelope<NA::Message>>,
     ) -> ActorProcess<S, NA> {
         ActorProcess {
             supervisor,
@@ -176,6 +177,9 @@ where
             Err(panic) => {
                 let msg = panic_message(&*panic);
                 error!("actor '{}' panicked at '{}'", NA::name(), msg);
+                if let Err(err) = runtime_ref.dump_trace() {
+                    warn!("error dumping trace after actor panic: {}", err);
+                }
                 this.handle_actor_panic(runtime_ref, pid, panic)
             }
         }
@@ -188,7 +192,7 @@ pub(crate) trait RuntimeSupport {
     /// Creates a new context.
     fn new_context<M>(
         pid: ProcessId,
-        inbox: Receiver<M>,
+        inbox: Receiver<Envelope<M>>,
         runtime_ref: &mut RuntimeRef,
     ) -> actor::Context<M, Self>
     where
@@ -201,7 +205,7 @@ pub(crate) trait RuntimeSupport {
 impl RuntimeSupport for ThreadLocal {
     fn new_context<M>(
         pid: ProcessId,
-        inbox: Receiver<M>,
+        inbox: Receiver<Envelope<M>>,
         runtime_ref: &mut RuntimeRef,
     ) -> actor::Context<M, ThreadLocal> {
         actor::Context::new(inbox, ThreadLocal::new(pid, runtime_ref.clone()))
@@ -215,7 +219,7 @@ impl RuntimeSupport for ThreadLocal {
 impl RuntimeSupport for ThreadSafe {
     fn new_context<M>(
         pid: ProcessId,
-        inbox: Receiver<M>,
+        inbox: Receiver<Envelope<M>>,
         runtime_ref: &mut RuntimeRef,
     ) -> actor::Context<M, ThreadSafe> {
         actor::Context::new(inbox, ThreadSafe::new(pid, runtime_ref.clone_shared()))
@@ -7,23 +7,36 @@ use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::pin::Pin;
 use std::task::{self, Poll};
 
-use log::error;
+use log::{debug, error};
 
 use crate::process::{panic_message, Process, ProcessId, ProcessResult};
+use crate::spawn::join::Completer;
+use crate::spawn::JoinError;
 use crate::{self as rt, RuntimeRef};
 
 /// A process that represent a [`Future`].
-pub(crate) struct FutureProcess<Fut, RT> {
+pub(crate) struct FutureProcess<Fut: Future, RT> {
     future: Fut,
+    /// Used to return the output of the future to the [`JoinHandle`].
+    ///
+    /// [`JoinHandle`]: crate::spawn::JoinHandle
+    completer: Completer<Fut::Output>,
+    /// Whether or not the process' waker is set in `completer`.
+    waker_set: bool,
     /// We need to know whether we need to create thread-local or thread-safe
     /// waker.
     _phantom: PhantomData<RT>,
 }
 
-impl<Fut, RT> FutureProcess<Fut, RT> {
-    pub(crate) const fn new(future: Fut) -> FutureProcess<Fut, RT> {
+impl<Fut: Future, RT> FutureProcess<Fut, RT> {
+    pub(crate) const fn new(
+        future: Fut,
+        completer: Completer<Fut::Output>,
+    ) -> FutureProcess<Fut, RT> {
         FutureProcess {
             future,
+            completer,
+            waker_set: false,
             _phantom: PhantomData,
         }
     }
@@ -31,7 +44,7 @@ impl<Fut, RT> FutureProcess<Fut, RT> {
 
 impl<Fut, RT> Process for FutureProcess<Fut, RT>
 where
-    Fut: Future<Output = ()>,
+    Fut: Future,
     RT: rt::Access,
 {
     fn name(&self) -> &'static str {
@@ -40,17 +53,33 @@ where
 
     fn run(self: Pin<&mut Self>, runtime_ref: &mut RuntimeRef, pid: ProcessId) -> ProcessResult {
         // This is safe because we're not moving the future.
-        let future = unsafe { Pin::map_unchecked_mut(self, |this| &mut this.future) };
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
 
         let waker = RT::new_task_waker(runtime_ref, pid);
-        let mut task_ctx = task::Context::from_waker(&waker);
+        if !this.waker_set {
+            // NOTE: the waker for a process doesn't change, so we only have to
+            // set it once.
+            this.completer.set_waker(&waker);
+            this.waker_set = true;
+        }
+        if this.completer.is_aborted() {
+            debug!("future '{}' aborted", name::<Fut>());
+            this.completer.complete(Err(JoinError::Cancelled));
+            return ProcessResult::Complete;
+        }
 
+        let mut task_ctx = task::Context::from_waker(&waker);
         match catch_unwind(AssertUnwindSafe(|| Future::poll(future, &mut task_ctx))) {
-            Ok(Poll::Ready(())) => ProcessResult::Complete,
+            Ok(Poll::Ready(output)) => {
+                this.completer.complete(Ok(output));
+                ProcessResult::Complete
+            }
             Ok(Poll::Pending) => ProcessResult::Pending,
             Err(panic) => {
                 let msg = panic_message(&*panic);
                 error!("future '{}' panicked at '{}'", name::<Fut>(), msg);
+                this.completer.complete(Err(JoinError::Panicked));
                 ProcessResult::Complete
             }
         }
@@ -6,11 +6,11 @@ use std::fmt;
 use std::pin::Pin;
 use std::time::{Duration, Instant};
 
-use log::{as_debug, trace};
+use log::{as_debug, trace, warn};
 use mio::Token;
 
 use crate::spawn::options::Priority;
-use crate::RuntimeRef;
+use crate::{metrics, trace, RuntimeRef};
 
 mod actor;
 mod future;
@@ -99,7 +99,7 @@ pub(crate) enum ProcessResult {
 
 /// Attempts to extract a message from a panic, defaulting to `<unknown>`.
 /// Note: be sure to derefence the `Box`!
-fn panic_message<'a>(panic: &'a (dyn Any + Send + 'static)) -> &'a str {
+pub(crate) fn panic_message<'a>(panic: &'a (dyn Any + Send + 'static)) -> &'a str {
     match panic.downcast_ref::<&'static str>() {
         Some(s) => *s,
         None => match panic.downcast_ref::<String>() {
@@ -164,9 +164,21 @@ impl<P: Process + ?Sized> ProcessData<P> {
         let name = self.process.name();
         trace!(pid = pid.0, name = name; "running process");
 
+        let slow_poll_threshold = runtime_ref.internals.shared.slow_poll_threshold();
+        let timing = match slow_poll_threshold {
+            Some(_) => trace::start(&*runtime_ref.internals.trace_log.borrow()),
+            None => None,
+        };
+        // Let the coordinator's watchdog know what process we're running.
+        if let Some(stats) = watchdog_stats(runtime_ref) {
+            stats.enter_process(pid, name);
+        }
         let start = Instant::now();
         let result = self.process.as_mut().run(runtime_ref, pid);
         let elapsed = start.elapsed();
+        if let Some(stats) = watchdog_stats(runtime_ref) {
+            stats.exit_process();
+        }
         let fair_elapsed = elapsed * self.priority;
         self.fair_runtime += fair_elapsed;
 
@@ -174,10 +186,49 @@ impl<P: Process + ?Sized> ProcessData<P> {
             pid = pid.0, name = name, elapsed = as_debug!(elapsed), result = as_debug!(result);
             "finished running process",
         );
+        if let Some(threshold) = slow_poll_threshold {
+            if elapsed > threshold {
+                report_slow_poll(runtime_ref, timing, pid, name, elapsed);
+            }
+        }
         result
     }
 }
 
+/// Returns the stats of the current worker thread, if the coordinator's
+/// watchdog is enabled.
+fn watchdog_stats(runtime_ref: &RuntimeRef) -> Option<&metrics::WorkerStats> {
+    let internals = &*runtime_ref.internals;
+    if internals.shared.watchdog_enabled() {
+        internals.shared.worker_stats_for(internals.id.get())
+    } else {
+        None
+    }
+}
+
+/// Report that polling the process with `pid` and `name` took `elapsed` time,
+/// which is longer than the slow poll threshold.
+fn report_slow_poll(
+    runtime_ref: &RuntimeRef,
+    timing: Option<trace::EventTiming>,
+    pid: ProcessId,
+    name: &'static str,
+    elapsed: Duration,
+) {
+    let internals = &*runtime_ref.internals;
+    warn!(
+        worker_id = internals.id.get(), pid = pid.0, name = name, elapsed = as_debug!(elapsed);
+        "polling process '{}' took {:?}, it's likely blocking the worker thread", name, elapsed
+    );
+    trace::finish_rt(
+        internals.trace_log.borrow_mut().as_mut(),
+        timing,
+        "Slow process poll",
+        &[("id", &pid.0), ("name", &name)],
+    );
+    internals.shared.actor_stats().record_slow_poll(name);
+}
+
 impl<P: ?Sized> Eq for ProcessData<P> {}
 
 impl<P: ?Sized> PartialEq for ProcessData<P> {
@@ -6,6 +6,7 @@ use std::mem::size_of;
 use std::pin::Pin;
 use std::sync::atomic::{self, AtomicBool};
 use std::sync::Arc;
+use std::task::Poll;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -14,6 +15,7 @@ use heph::supervisor::{NoSupervisor, Supervisor, SupervisorStrategy};
 use mio::Token;
 
 use crate::process::{ActorProcess, FutureProcess, Process, ProcessData, ProcessId, ProcessResult};
+use crate::spawn::join::{self, JoinError};
 use crate::spawn::options::Priority;
 use crate::test::{self, init_local_actor_with_inbox, AssertUnmoved, TEST_PID};
 use crate::{RuntimeRef, ThreadLocal, ThreadSafe};
@@ -293,7 +295,8 @@ fn actor_process_assert_actor_unmoved() {
 
 #[test]
 fn future_process_thread_local_assert_future_unmoved() {
-    let process = FutureProcess::<_, ThreadLocal>::new(AssertUnmoved::new(pending()));
+    let (_, completer) = join::new::<()>();
+    let process = FutureProcess::<_, ThreadLocal>::new(AssertUnmoved::new(pending()), completer);
     let mut process: Pin<Box<dyn Process>> = Box::pin(process);
 
     // All we do is run it a couple of times, it should panic if the actor is
@@ -309,7 +312,8 @@ fn future_process_thread_local_assert_future_unmoved() {
 
 #[test]
 fn future_process_thread_safe_assert_future_unmoved() {
-    let process = FutureProcess::<_, ThreadSafe>::new(AssertUnmoved::new(pending()));
+    let (_, completer) = join::new::<()>();
+    let process = FutureProcess::<_, ThreadSafe>::new(AssertUnmoved::new(pending()), completer);
     let mut process: Pin<Box<dyn Process>> = Box::pin(process);
 
     // All we do is run it a couple of times, it should panic if the actor is
@@ -322,3 +326,65 @@ fn future_process_thread_safe_assert_future_unmoved() {
     let res = process.as_mut().run(&mut runtime_ref, ProcessId(0));
     assert_eq!(res, ProcessResult::Pending);
 }
+
+#[test]
+fn future_process_output() {
+    let (mut handle, completer) = join::new();
+    let process = FutureProcess::<_, ThreadLocal>::new(async { 123 }, completer);
+    let mut process: Pin<Box<dyn Process>> = Box::pin(process);
+
+    assert_eq!(test::poll_future(Pin::new(&mut handle)), Poll::Pending);
+    let mut runtime_ref = test::runtime();
+    let res = process.as_mut().run(&mut runtime_ref, ProcessId(0));
+    assert_eq!(res, ProcessResult::Complete);
+    assert!(handle.is_finished());
+    assert_eq!(
+        test::poll_future(Pin::new(&mut handle)),
+        Poll::Ready(Ok(123))
+    );
+}
+
+#[test]
+fn future_process_panic() {
+    let (mut handle, completer) = join::new::<()>();
+    let process = FutureProcess::<_, ThreadSafe>::new(async { panic!("oops") }, completer);
+    let mut process: Pin<Box<dyn Process>> = Box::pin(process);
+
+    let mut runtime_ref = test::runtime();
+    let res = process.as_mut().run(&mut runtime_ref, ProcessId(0));
+    assert_eq!(res, ProcessResult::Complete);
+    assert_eq!(
+        test::poll_future(Pin::new(&mut handle)),
+        Poll::Ready(Err(JoinError::Panicked))
+    );
+}
+
+#[test]
+fn future_process_abort() {
+    let (mut handle, completer) = join::new::<()>();
+    let process = FutureProcess::<_, ThreadLocal>::new(pending(), completer);
+    let mut process: Pin<Box<dyn Process>> = Box::pin(process);
+
+    let mut runtime_ref = test::runtime();
+    let res = process.as_mut().run(&mut runtime_ref, ProcessId(0));
+    assert_eq!(res, ProcessResult::Pending);
+
+    handle.abort();
+    let res = process.as_mut().run(&mut runtime_ref, ProcessId(0));
+    assert_eq!(res, ProcessResult::Complete);
+    assert_eq!(
+        test::poll_future(Pin::new(&mut handle)),
+        Poll::Ready(Err(JoinError::Cancelled))
+    );
+}
+
+#[test]
+fn future_process_dropped() {
+    let (mut handle, completer) = join::new::<()>();
+    let process = FutureProcess::<_, ThreadLocal>::new(pending(), completer);
+    drop(process);
+    assert_eq!(
+        test::poll_future(Pin::new(&mut handle)),
+        Poll::Ready(Err(JoinError::Cancelled))
+    );
+}
@@ -1,17 +1,24 @@
 //! Module with [`Setup`].
 
 use std::ffi::CStr;
+use std::iter::FromIterator;
 use std::mem::MaybeUninit;
 use std::num::NonZeroUsize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{env, fmt, io, thread};
 
-use heph::actor_ref::ActorGroup;
 use log::{debug, warn};
 
 use crate::coordinator::Coordinator;
-use crate::trace;
-use crate::{worker, Error, Runtime, MAX_THREADS};
+#[cfg(target_os = "linux")]
+use crate::io_uring;
+use crate::{blocking, shared, signal, trace};
+use crate::{worker, Error, Runtime, Signal, SignalSet, MAX_THREADS};
+
+#[cfg(test)]
+#[path = "setup_tests.rs"]
+mod setup_tests;
 
 /// Setup a [`Runtime`].
 ///
@@ -28,8 +35,32 @@ pub struct Setup {
     threads: usize,
     /// Whether or not to automatically set CPU affinity.
     auto_cpu_affinity: bool,
+    /// CPUs to run the worker threads on.
+    worker_cpus: Option<CpuSet>,
+    /// CPUs to run the coordinator on.
+    coordinator_cpus: Option<CpuSet>,
+    /// Maximum number of threads in the blocking pool.
+    blocking_threads: usize,
+    /// Maximum number of jobs waiting in the queue of the blocking pool.
+    blocking_queue_size: usize,
+    /// Whether or not to use io_uring, if supported.
+    use_io_uring: bool,
     /// Optional trace log.
     trace_log: Option<trace::CoordinatorLog>,
+    /// Path of the trace log set using `HEPH_TRACE_PATH`, only opened once the
+    /// runtime is build so that `enable_tracing` or `enable_flight_recorder`
+    /// can still overwrite it without creating the file.
+    trace_path: Option<PathBuf>,
+    /// Filter for the trace log.
+    trace_filter: Option<trace::TraceFilter>,
+    /// Threshold for polls of a process to be considered slow.
+    slow_poll_threshold: Option<Duration>,
+    /// Watchdog timeout and whether or not to abort the process.
+    watchdog: Option<(Duration, bool)>,
+    /// Log the backtraces of stuck worker threads before aborting.
+    watchdog_backtraces: bool,
+    /// Process signals to handle.
+    signals: SignalSet,
 }
 
 impl Setup {
@@ -39,7 +70,18 @@ impl Setup {
             name: None,
             threads: 1,
             auto_cpu_affinity: false,
+            worker_cpus: None,
+            coordinator_cpus: None,
+            blocking_threads: blocking::DEFAULT_MAX_THREADS,
+            blocking_queue_size: blocking::DEFAULT_QUEUE_SIZE,
+            use_io_uring: false,
             trace_log: None,
+            trace_path: None,
+            trace_filter: None,
+            slow_poll_threshold: None,
+            watchdog: None,
+            watchdog_backtraces: false,
+            signals: SignalSet::DEFAULT,
         }
     }
 
@@ -127,6 +169,133 @@ impl Setup {
         self
     }
 
+    /// Run the worker threads on the CPUs in `cpus`.
+    ///
+    /// Each worker thread is pinned to a single CPU in `cpus`, if there are
+    /// more worker threads than CPUs multiple worker threads will share a CPU.
+    /// CPUs not in `cpus` are left free for other threads and processes, e.g.
+    /// synchronous actors (see [`SyncActorOptions::cpu_affinity`]) or the
+    /// coordinator (see [`Setup::coordinator_cpus`]).
+    ///
+    /// The CPUs are assigned to the worker threads grouped by NUMA node, i.e.
+    /// all CPUs in `cpus` of the first NUMA node are used before using the CPUs
+    /// of the second node. Like [`Setup::auto_cpu_affinity`] sockets created
+    /// in worker threads will use [`SO_INCOMING_CPU`] to set their CPU affinity
+    /// to the CPU of the worker thread.
+    ///
+    /// This overwrites [`Setup::auto_cpu_affinity`].
+    ///
+    /// [`SyncActorOptions::cpu_affinity`]: crate::spawn::SyncActorOptions::cpu_affinity
+    /// [`SO_INCOMING_CPU`]: https://man7.org/linux/man-pages/man7/socket.7.html
+    ///
+    /// # Notes
+    ///
+    /// This is currently only implementated on Linux.
+    pub fn worker_cpus(mut self, cpus: CpuSet) -> Self {
+        assert!(!cpus.is_empty(), "Can't run worker threads on zero CPUs");
+        self.worker_cpus = Some(cpus);
+        self
+    }
+
+    /// Run the coordinator on the CPUs in `cpus`.
+    ///
+    /// The coordinator runs on the thread that calls [`Runtime::start`], the
+    /// CPU affinity is set when the runtime is started.
+    ///
+    /// # Notes
+    ///
+    /// This is currently only implementated on Linux.
+    pub fn coordinator_cpus(mut self, cpus: CpuSet) -> Self {
+        assert!(!cpus.is_empty(), "Can't run the coordinator on zero CPUs");
+        self.coordinator_cpus = Some(cpus);
+        self
+    }
+
+    /// Set the maximum number of threads used to run blocking operations,
+    /// defaults to four.
+    ///
+    /// These threads are used by [`RuntimeRef::spawn_blocking`] and by the
+    /// [`fs`] module, among others. Threads are started when needed, up to
+    /// this maximum.
+    ///
+    /// [`RuntimeRef::spawn_blocking`]: crate::RuntimeRef::spawn_blocking
+    /// [`fs`]: crate::fs
+    pub fn blocking_threads(mut self, n: usize) -> Self {
+        assert!(
+            n != 0,
+            "Can't create zero blocking threads, one is the minimum"
+        );
+        self.blocking_threads = n;
+        self
+    }
+
+    /// Set the maximum number of blocking operations waiting for a thread,
+    /// defaults to 1024.
+    ///
+    /// Once all blocking threads (see [`Setup::blocking_threads`]) are busy new
+    /// operations are queued. If the queue is full the futures of new
+    /// operations, e.g. the future returned by [`RuntimeRef::spawn_blocking`],
+    /// hold on to their operation and return [`Poll::Pending`] until there is
+    /// room in the queue. This applies backpressure to the actors starting the
+    /// operations, rather than allowing the queue to grow without limit.
+    ///
+    /// [`RuntimeRef::spawn_blocking`]: crate::RuntimeRef::spawn_blocking
+    /// [`Poll::Pending`]: std::task::Poll::Pending
+    pub fn blocking_queue_size(mut self, n: usize) -> Self {
+        assert!(n != 0, "Can't create a blocking queue of size zero");
+        self.blocking_queue_size = n;
+        self
+    }
+
+    /// Use [`io_uring(7)`] as I/O backend for the worker threads, if supported.
+    ///
+    /// Each worker thread creates its own ring, to which the I/O futures submit
+    /// their operations. Currently this is used in receiving, sending, sending
+    /// files and connecting [`TcpStream`]s and in accepting connections on a
+    /// [`TcpListener`], all other I/O still uses [`epoll(7)`]. The public I/O
+    /// types and futures are the same for both backends.
+    ///
+    /// When the runtime is build it checks whether or not the kernel supports
+    /// io_uring. If it doesn't, for example because the kernel is too old or
+    /// io_uring is blocked by a seccomp filter, a warning is logged and the
+    /// runtime falls back to using `epoll(7)`.
+    ///
+    /// [`io_uring(7)`]: https://man7.org/linux/man-pages/man7/io_uring.7.html
+    /// [`TcpStream`]: crate::net::TcpStream
+    /// [`TcpListener`]: crate::net::TcpListener
+    /// [`epoll(7)`]: https://man7.org/linux/man-pages/man7/epoll.7.html
+    ///
+    /// # Notes
+    ///
+    /// The kernel accesses the buffers of an operation until it's completed,
+    /// which can be after the future is dropped. Because of this the bytes to
+    /// send are copied into a buffer owned by the operation, and received bytes
+    /// are copied out of such a buffer. Dropping a future cancels the
+    /// operation, bytes it received but not yet returned are lost.
+    ///
+    /// Files are send by reading them into a buffer and sending that, as
+    /// io_uring doesn't support `sendfile(2)`.
+    ///
+    /// This is only supported on Linux, on other platforms it does nothing.
+    pub const fn use_io_uring(mut self) -> Self {
+        self.use_io_uring = true;
+        self
+    }
+
+    /// Returns the maximum number of threads used to run blocking operations.
+    ///
+    /// See [`Setup::blocking_threads`].
+    pub const fn get_blocking_threads(&self) -> usize {
+        self.blocking_threads
+    }
+
+    /// Returns the maximum number of blocking operations waiting for a thread.
+    ///
+    /// See [`Setup::blocking_queue_size`].
+    pub const fn get_blocking_queue_size(&self) -> usize {
+        self.blocking_queue_size
+    }
+
     /// Generate a trace of the runtime, writing it to the file specified by
     /// `path`.
     ///
@@ -138,21 +307,363 @@ impl Setup {
         match trace::CoordinatorLog::open(path.as_ref()) {
             Ok(trace_log) => {
                 self.trace_log = Some(trace_log);
+                self.trace_path = None;
                 Ok(())
             }
             Err(err) => Err(Error::setup_trace(err)),
         }
     }
 
+    /// Record a trace of the runtime in memory, only writing it to the file
+    /// specified by `path` when requested.
+    ///
+    /// Each worker thread keeps the most recent events, as limited by `limit`.
+    /// The events are written to a file when the process receives
+    /// `dump_signal` (if any), when an actor panics or when
+    /// [`RuntimeRef::dump_trace`] is called. See the flight recorder section
+    /// in the [`mod@trace`] module for more information.
+    ///
+    /// This overwrites a previous call to [`Setup::enable_tracing`]. If set,
+    /// `dump_signal` is handled by the runtime, see [`Setup::handle_signals`].
+    ///
+    /// [`RuntimeRef::dump_trace`]: crate::RuntimeRef::dump_trace
+    pub fn enable_flight_recorder<P: Into<PathBuf>>(
+        mut self,
+        path: P,
+        limit: trace::RecorderLimit,
+        dump_signal: Option<Signal>,
+    ) -> Self {
+        if let Some(signal) = dump_signal {
+            self.signals = self.signals | signal;
+        }
+        self.trace_log = Some(trace::CoordinatorLog::recorder(
+            path.into(),
+            limit,
+            dump_signal,
+        ));
+        self.trace_path = None;
+        self
+    }
+
+    /// Set the `filter` used for tracing.
+    ///
+    /// This only has an effect if tracing is enabled using
+    /// [`Setup::enable_tracing`] or [`Setup::enable_flight_recorder`]. The
+    /// filter is recorded in the metadata of the trace.
+    pub fn trace_filter(mut self, filter: trace::TraceFilter) -> Self {
+        self.trace_filter = Some(filter);
+        self
+    }
+
+    /// Report polls of processes that take longer than `threshold`.
+    ///
+    /// An actor (or future) should never block, as it stalls all other actors
+    /// running on the same worker thread. If polling a process takes longer
+    /// than `threshold` a warning is logged, including the name and pid of the
+    /// actor, the duration of the poll and the id of the worker thread. If
+    /// tracing is enabled a "Slow process poll" event is added to the trace.
+    /// The number of slow polls per actor is available in the runtime's
+    /// [`metrics`].
+    ///
+    /// Disabled by default.
+    ///
+    /// [`metrics`]: crate::metrics::RuntimeMetrics::actors
+    pub const fn slow_poll_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_poll_threshold = Some(threshold);
+        self
+    }
+
+    /// Returns the slow poll threshold, if set.
+    ///
+    /// See [`Setup::slow_poll_threshold`].
+    pub const fn get_slow_poll_threshold(&self) -> Option<Duration> {
+        self.slow_poll_threshold
+    }
+
+    /// Enable the watchdog for worker threads.
+    ///
+    /// The coordinator periodically checks that all worker threads are making
+    /// progress. A worker thread that is waiting for events is considered
+    /// idle, not stuck. But if a worker thread doesn't complete an iteration of
+    /// its event loop within `timeout`, e.g. because an actor is stuck in an
+    /// endless loop, an error is logged including the name and pid of the
+    /// process running on the worker thread.
+    ///
+    /// If `abort` is `true` the process is aborted (see [`process::abort`])
+    /// after logging the error, so that a service manager (such as systemd)
+    /// can restart it. The backtraces of all threads can be retrieved from the
+    /// resulting core dump, if enabled. Alternatively the backtraces of the
+    /// stuck worker threads can be logged, see [`Setup::watchdog_backtraces`].
+    ///
+    /// Disabled by default.
+    ///
+    /// [`process::abort`]: std::process::abort
+    ///
+    /// # Notes
+    ///
+    /// Workers are checked about twice per `timeout`, so a stuck worker thread
+    /// is reported between `timeout` and one and a half times `timeout` after
+    /// it stopped making progress.
+    pub fn watchdog(mut self, timeout: Duration, abort: bool) -> Self {
+        assert!(!timeout.is_zero(), "Can't use a watchdog timeout of zero");
+        self.watchdog = Some((timeout, abort));
+        self
+    }
+
+    /// Log the backtraces of the stuck worker threads before aborting the
+    /// process. Only meant for debugging, disabled by default.
+    ///
+    /// Only used if the watchdog is enabled with `abort` set to `true`, see
+    /// [`Setup::watchdog`]. Before aborting the coordinator sends each stuck
+    /// worker thread a `SIGURG` signal, on which the worker thread captures
+    /// its backtrace and sends it back to the coordinator. The coordinator
+    /// waits up to a second for the backtraces and logs them (at the error
+    /// level).
+    ///
+    /// This is useful if core dumps are not available, e.g. when running in a
+    /// container.
+    ///
+    /// # Notes
+    ///
+    /// Capturing a backtrace in a signal handler is not async-signal-safe, e.g.
+    /// it allocates memory. If the worker thread is stuck holding a lock
+    /// required to capture the backtrace the backtrace is not logged, but the
+    /// process is still aborted. This is why backtraces are only captured
+    /// right before aborting and why this should not be enabled in production
+    /// if core dumps are available.
+    pub const fn watchdog_backtraces(mut self) -> Self {
+        self.watchdog_backtraces = true;
+        self
+    }
+
+    /// Handle the process `signals`, in addition to the signals handled by
+    /// default.
+    ///
+    /// By default the runtime only handles [`Signal::Interrupt`],
+    /// [`Signal::Terminate`], [`Signal::Quit`], [`Signal::User1`] and
+    /// [`Signal::User2`]. Other signals, such as [`Signal::Hangup`], keep their
+    /// default disposition (which for most signals terminates the process),
+    /// unless they're added here. Actors can receive these signals using
+    /// [`RuntimeRef::receive_signals_filtered`].
+    ///
+    /// [`RuntimeRef::receive_signals_filtered`]: crate::RuntimeRef::receive_signals_filtered
+    ///
+    /// # Notes
+    ///
+    /// Once added the runtime consumes the signals, even if no actor receives
+    /// them. Signals are only supported on Linux, see [`Signal`].
+    pub fn handle_signals(mut self, signals: SignalSet) -> Self {
+        self.signals = self.signals | signals;
+        self
+    }
+
+    /// Create a new `Setup` configured using environment variables.
+    ///
+    /// The following environment variables are supported:
+    ///
+    /// * `HEPH_APP_NAME`: name of the application, see [`Setup::with_name`].
+    /// * `HEPH_THREADS`: number of worker threads, see [`Setup::num_threads`].
+    ///   Use `all` to use all CPU cores, see [`Setup::use_all_cores`].
+    /// * `HEPH_BLOCKING_THREADS`: maximum number of threads used to run
+    ///   blocking operations, see [`Setup::blocking_threads`].
+    /// * `HEPH_BLOCKING_QUEUE_SIZE`: maximum number of blocking operations
+    ///   waiting for a thread, see [`Setup::blocking_queue_size`].
+    /// * `HEPH_CPU_AFFINITY`: `auto` to set the CPU affinity automatically,
+    ///   see [`Setup::auto_cpu_affinity`]. Or a list of CPUs to run the worker
+    ///   threads on, e.g. `0-3,6`, see [`Setup::worker_cpus`].
+    /// * `HEPH_TRACE_PATH`: path to write a trace to, see
+    ///   [`Setup::enable_tracing`]. The file is only created once the runtime
+    ///   is build, see [`Setup::build`].
+    ///
+    /// Variables that are not set use the default value. Builder methods called
+    /// on the returned `Setup` overwrite the values read from the environment.
+    ///
+    /// Returns an error if a variable has an invalid value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # std::env::set_var("HEPH_THREADS", "2");
+    /// use heph_rt::Setup;
+    ///
+    /// // With `HEPH_THREADS=2` set.
+    /// let setup = Setup::from_env()?;
+    /// assert_eq!(setup.get_threads(), 2);
+    ///
+    /// // Explicitly set options take precedence.
+    /// let setup = setup.num_threads(4);
+    /// assert_eq!(setup.get_threads(), 4);
+    /// # Ok::<(), heph_rt::Error>(())
+    /// ```
+    pub fn from_env() -> Result<Setup, Error> {
+        let mut vars = Vec::new();
+        for key in CONFIG_KEYS {
+            match env::var(key) {
+                Ok(value) => vars.push((key, value)),
+                Err(env::VarError::NotPresent) => {}
+                Err(err) => return Err(Error::setup(format!("invalid {}: {}", key, err))),
+            }
+        }
+        Setup::from_vars(vars)
+    }
+
+    /// Create a new `Setup` configured using the key-value pairs in `vars`,
+    /// e.g. the environment variables. Unknown keys are ignored.
+    fn from_vars<'a, I, V>(vars: I) -> Result<Setup, Error>
+    where
+        I: IntoIterator<Item = (&'a str, V)>,
+        V: AsRef<str>,
+    {
+        let mut setup = Setup::new();
+        for (key, value) in vars {
+            if CONFIG_KEYS.contains(&key) {
+                setup = setup.apply_config(key, value.as_ref())?;
+            }
+        }
+        Ok(setup)
+    }
+
+    /// Create a new `Setup` configured using the configuration file at `path`.
+    ///
+    /// The file contains one option per line in the form `KEY=value`, using the
+    /// same keys as the environment variables in [`Setup::from_env`]. Empty
+    /// lines and lines starting with `#` are ignored. Environment variables
+    /// are not read.
+    ///
+    /// Returns an error if the file can't be read, contains an unknown key or
+    /// an invalid value.
+    ///
+    /// # Examples
+    ///
+    /// An example configuration file.
+    ///
+    /// ```text
+    /// # Configuration of the Heph runtime.
+    /// HEPH_APP_NAME=my_app
+    /// HEPH_THREADS=all
+    /// HEPH_CPU_AFFINITY=auto
+    /// ```
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Setup, Error> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path).map_err(|err| {
+            Error::setup(format!(
+                "failed to read configuration file '{}': {}",
+                path.display(),
+                err
+            ))
+        })?;
+
+        let mut setup = Setup::new();
+        for (n, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) if CONFIG_KEYS.contains(&key.trim()) => (key.trim(), value),
+                Some((key, _)) => {
+                    return Err(Error::setup(format!(
+                        "unknown configuration option '{}' in '{}' on line {}",
+                        key.trim(),
+                        path.display(),
+                        n + 1
+                    )))
+                }
+                None => {
+                    return Err(Error::setup(format!(
+                        "invalid line in configuration file '{}' on line {}: expected `KEY=value`",
+                        path.display(),
+                        n + 1
+                    )))
+                }
+            };
+            setup = setup.apply_config(key, value.trim())?;
+        }
+        Ok(setup)
+    }
+
+    /// Apply the configuration option `key` with `value`.
+    fn apply_config(mut self, key: &str, value: &str) -> Result<Setup, Error> {
+        let invalid = |err: &dyn fmt::Display| {
+            Error::setup(format!("invalid value for {}: '{}': {}", key, value, err))
+        };
+        match key {
+            ENV_APP_NAME if value.is_empty() => return Err(invalid(&"empty name")),
+            ENV_APP_NAME => self.name = Some(value.to_owned()),
+            ENV_THREADS if value == "all" => self = self.use_all_cores(),
+            ENV_THREADS => match value.parse() {
+                Ok(0) => return Err(invalid(&"one thread is the minimum")),
+                Ok(n) if n >= MAX_THREADS => return Err(invalid(&"too many threads")),
+                Ok(n) => self.threads = n,
+                Err(err) => return Err(invalid(&err)),
+            },
+            ENV_BLOCKING_THREADS => match value.parse() {
+                Ok(0) => return Err(invalid(&"one thread is the minimum")),
+                Ok(n) => self.blocking_threads = n,
+                Err(err) => return Err(invalid(&err)),
+            },
+            ENV_BLOCKING_QUEUE_SIZE => match value.parse() {
+                Ok(0) => return Err(invalid(&"size must be at least one")),
+                Ok(n) => self.blocking_queue_size = n,
+                Err(err) => return Err(invalid(&err)),
+            },
+            ENV_CPU_AFFINITY if value == "auto" => self.auto_cpu_affinity = true,
+            ENV_CPU_AFFINITY => match parse_cpu_list(value) {
+                Ok(cpus) if cpus.is_empty() => return Err(invalid(&"no CPUs")),
+                Ok(cpus) => self.worker_cpus = Some(cpus),
+                Err(err) => return Err(invalid(&err)),
+            },
+            ENV_TRACE_PATH if value.is_empty() => return Err(invalid(&"empty path")),
+            ENV_TRACE_PATH => {
+                self.trace_log = None;
+                self.trace_path = Some(PathBuf::from(value));
+            }
+            _ => unreachable!("unknown configuration key"),
+        }
+        Ok(self)
+    }
+
     /// Build the runtime.
     ///
     /// This will spawn a number of worker threads (see [`Setup::num_threads`])
     /// to run all the actors.
+    ///
+    /// If `HEPH_TRACE_PATH` was set (see [`Setup::from_env`]) this creates the
+    /// trace file, returning an error if it already exists or can't be
+    /// created.
     pub fn build(self) -> Result<Runtime, Error> {
         #[rustfmt::skip]
-        let Setup { name, threads, auto_cpu_affinity, mut trace_log } = self;
+        let Setup { name, threads, auto_cpu_affinity, worker_cpus, coordinator_cpus, blocking_threads, blocking_queue_size, use_io_uring, mut trace_log, trace_path, trace_filter, slow_poll_threshold, watchdog, watchdog_backtraces, signals } = self;
         let name = name.unwrap_or_else(default_app_name).into_boxed_str();
-        debug!(name = name, workers = threads; "building Heph runtime");
+        #[cfg(target_os = "linux")]
+        let use_io_uring = use_io_uring
+            && match io_uring::probe() {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!("io_uring is not supported: {}, falling back to epoll", err);
+                    false
+                }
+            };
+        #[cfg(not(target_os = "linux"))]
+        let use_io_uring = {
+            let _ = use_io_uring; // Silence unused variables warnings.
+            false
+        };
+        debug!(name = name, workers = threads, io_uring = use_io_uring; "building Heph runtime");
+
+        let host_info = shared::HostInfo::new(name).map_err(Error::init_coordinator)?;
+        if let Some(path) = trace_path {
+            let log = trace::CoordinatorLog::open(&path).map_err(Error::setup_trace)?;
+            trace_log = Some(log);
+        }
+        if let Some(trace_log) = trace_log.as_mut() {
+            if let Some(filter) = trace_filter {
+                trace_log.set_filter(filter).map_err(Error::setup_trace)?;
+            }
+            trace_log
+                .set_process_info(&host_info)
+                .map_err(Error::setup_trace)?;
+        }
 
         // Setup the worker threads.
         let timing = trace::start(&trace_log);
@@ -169,20 +680,43 @@ impl Setup {
         // Create the coordinator to oversee all workers.
         let thread_wakers = thread_wakers.into_boxed_slice();
         let shared_trace_log = trace_log.as_ref().map(trace::CoordinatorLog::clone_shared);
-        let coordinator = Coordinator::init(name, thread_wakers, shared_trace_log)
-            .map_err(Error::init_coordinator)?;
+        let coordinator = Coordinator::init(
+            host_info,
+            thread_wakers,
+            coordinator_cpus,
+            blocking::Pool::new(blocking_threads, blocking_queue_size),
+            slow_poll_threshold,
+            watchdog,
+            watchdog_backtraces,
+            signals,
+            shared_trace_log,
+        )
+        .map_err(Error::init_coordinator)?;
+
+        // Determine the CPU for each worker thread.
+        let worker_cpus = match worker_cpus {
+            Some(cpus) => Some(sort_by_numa_node(cpus)),
+            None if auto_cpu_affinity => Some((0..threads).collect()),
+            None => None,
+        };
 
         // Spawn the worker threads.
         let workers = worker_setups
             .into_iter()
             .map(|worker_setup| {
                 #[allow(clippy::cast_possible_truncation)]
-                let trace_log = trace_log
+                let trace_log = trace_log.as_ref().map(|trace_log| {
+                    let name = format!("Worker {}", worker_setup.id());
+                    trace_log.new_stream(worker_setup.id() as u32, &name)
+                });
+                // Worker ids start at 1.
+                let cpu = worker_cpus
                     .as_ref()
-                    .map(|trace_log| trace_log.new_stream(worker_setup.id() as u32));
+                    .map(|cpus: &Vec<usize>| cpus[(worker_setup.id() - 1) % cpus.len()]);
                 worker_setup.start(
                     coordinator.shared_internals().clone(),
-                    auto_cpu_affinity,
+                    cpu,
+                    use_io_uring,
                     trace_log,
                 )
             })
@@ -200,12 +734,186 @@ impl Setup {
             coordinator,
             workers,
             sync_actors: Vec::new(),
-            signals: ActorGroup::empty(),
+            signals: signal::Receivers::new(),
             trace_log,
         })
     }
 }
 
+/// Name of the environment variable used to set the application name.
+const ENV_APP_NAME: &str = "HEPH_APP_NAME";
+/// Name of the environment variable used to set the number of worker threads.
+const ENV_THREADS: &str = "HEPH_THREADS";
+/// Name of the environment variable used to set the number of blocking
+/// threads.
+const ENV_BLOCKING_THREADS: &str = "HEPH_BLOCKING_THREADS";
+/// Name of the environment variable used to set the size of the blocking
+/// queue.
+const ENV_BLOCKING_QUEUE_SIZE: &str = "HEPH_BLOCKING_QUEUE_SIZE";
+/// Name of the environment variable used to set the CPU affinity.
+const ENV_CPU_AFFINITY: &str = "HEPH_CPU_AFFINITY";
+/// Name of the environment variable used to enable tracing.
+const ENV_TRACE_PATH: &str = "HEPH_TRACE_PATH";
+
+/// All configuration keys, used by [`Setup::from_env`] and
+/// [`Setup::from_config_file`].
+const CONFIG_KEYS: [&str; 6] = [
+    ENV_APP_NAME,
+    ENV_THREADS,
+    ENV_BLOCKING_THREADS,
+    ENV_BLOCKING_QUEUE_SIZE,
+    ENV_CPU_AFFINITY,
+    ENV_TRACE_PATH,
+];
+
+/// Parse a list of CPUs, e.g. `0-3,6`, as used by Linux in `/sys` and
+/// `taskset(1)`.
+fn parse_cpu_list(list: &str) -> Result<CpuSet, String> {
+    let mut cpus = CpuSet::empty();
+    for part in list.split(',').map(str::trim) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start: usize = start
+            .parse()
+            .map_err(|err| format!("'{}': {}", part, err))?;
+        let end: usize = end.parse().map_err(|err| format!("'{}': {}", part, err))?;
+        if start > end {
+            return Err(format!("'{}': invalid range", part));
+        } else if end >= MAX_CPUS {
+            return Err(format!("'{}': CPU too large", part));
+        }
+        for cpu in start..=end {
+            cpus.insert(cpu);
+        }
+    }
+    Ok(cpus)
+}
+
+/// Maximum number of CPUs in a [`CpuSet`].
+const MAX_CPUS: usize = 1024;
+
+/// Set of CPUs.
+///
+/// Used in [`Setup::worker_cpus`], [`Setup::coordinator_cpus`] and
+/// [`SyncActorOptions::cpu_affinity`] to set the CPU affinity of threads.
+///
+/// CPUs are identified by their index, starting at zero. A set can contain at
+/// most 1024 CPUs.
+///
+/// [`SyncActorOptions::cpu_affinity`]: crate::spawn::SyncActorOptions::cpu_affinity
+///
+/// # Examples
+///
+/// Running the worker threads on CPUs 2 to 8, leaving CPUs 0 and 1 for the
+/// coordinator and other processes.
+///
+/// ```
+/// use heph_rt::{CpuSet, Runtime};
+///
+/// let worker_cpus: CpuSet = (2..8).collect();
+/// assert_eq!(worker_cpus.len(), 6);
+/// assert!(!worker_cpus.contains(1));
+///
+/// let mut coordinator_cpus = CpuSet::empty();
+/// coordinator_cpus.insert(0);
+/// coordinator_cpus.insert(1);
+///
+/// let setup = Runtime::setup()
+///     .num_threads(6)
+///     .worker_cpus(worker_cpus)
+///     .coordinator_cpus(coordinator_cpus);
+/// # drop(setup); // Silence unused variable warning.
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct CpuSet {
+    bits: [u64; MAX_CPUS / 64],
+}
+
+impl CpuSet {
+    /// Create an empty set.
+    pub const fn empty() -> CpuSet {
+        CpuSet {
+            bits: [0; MAX_CPUS / 64],
+        }
+    }
+
+    /// Add `cpu` to the set.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `cpu` is 1024 or larger.
+    pub fn insert(&mut self, cpu: usize) {
+        assert!(
+            cpu < MAX_CPUS,
+            "CPU {} is too large for `CpuSet`, {} is the maximum",
+            cpu,
+            MAX_CPUS - 1
+        );
+        self.bits[cpu / 64] |= 1 << (cpu % 64);
+    }
+
+    /// Remove `cpu` from the set.
+    pub fn remove(&mut self, cpu: usize) {
+        if cpu < MAX_CPUS {
+            self.bits[cpu / 64] &= !(1 << (cpu % 64));
+        }
+    }
+
+    /// Returns `true` if the set contains `cpu`.
+    pub const fn contains(&self, cpu: usize) -> bool {
+        cpu < MAX_CPUS && self.bits[cpu / 64] & (1 << (cpu % 64)) != 0
+    }
+
+    /// Returns the number of CPUs in the set.
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|bits| bits.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|bits| *bits == 0)
+    }
+
+    /// Returns an iterator over the CPUs in the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..MAX_CPUS).filter(move |cpu| self.contains(*cpu))
+    }
+
+    /// Convert the set into a `cpu_set_t`.
+    #[cfg(target_os = "linux")]
+    fn to_libc(self) -> libc::cpu_set_t {
+        let mut cpu_set = unsafe { std::mem::zeroed() };
+        unsafe { libc::CPU_ZERO(&mut cpu_set) };
+        for cpu in self.iter() {
+            if cpu < libc::CPU_SETSIZE as usize {
+                unsafe { libc::CPU_SET(cpu, &mut cpu_set) };
+            }
+        }
+        cpu_set
+    }
+}
+
+impl FromIterator<usize> for CpuSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> CpuSet {
+        let mut set = CpuSet::empty();
+        for cpu in iter {
+            set.insert(cpu);
+        }
+        set
+    }
+}
+
+impl fmt::Debug for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
 /// Returns the name of the binary called (i.e. `arg[0]`) as name.
 fn default_app_name() -> String {
     match env::args().next() {
@@ -383,51 +1091,96 @@ pub(crate) fn host_id() -> io::Result<Uuid> {
     }
 }
 
-// Setup functions used by `worker`.
+// Setup functions used by `worker`, `sync_worker` and `coordinator`.
 
-/// Set thread's CPU affinity.
-pub(crate) fn set_cpu_affinity(worker_id: NonZeroUsize) -> Option<usize> {
+/// Set the worker thread's CPU affinity to `cpu`.
+///
+/// Returns the `cpu` if the affinity was set.
+pub(crate) fn set_cpu_affinity(worker_id: NonZeroUsize, cpu: usize) -> Option<usize> {
     #[cfg(not(target_os = "linux"))]
     {
-        let _ = worker_id; // Silence unused variables warnings.
+        let _ = (worker_id, cpu); // Silence unused variables warnings.
         None
     }
 
     #[cfg(target_os = "linux")]
     {
-        let cpu = worker_id.get() - 1; // Worker ids start at 1, cpus at 0.
-        let cpu_set = cpu_set(cpu);
-        match set_affinity(&cpu_set) {
+        let mut cpus = CpuSet::empty();
+        cpus.insert(cpu);
+        match set_thread_cpu_affinity(&cpus) {
             Ok(()) => {
-                debug!(worker_id = {}; "worker thread CPU affinity set to {}", cpu);
+                debug!(
+                    worker_id = worker_id.get(), numa_node = numa_node(cpu);
+                    "worker thread CPU affinity set to {}", cpu
+                );
                 Some(cpu)
             }
             Err(err) => {
-                warn!(worker_id = {}; "failed to set CPU affinity on thread: {}", err);
+                warn!(worker_id = worker_id.get(); "failed to set CPU affinity on thread: {}", err);
                 None
             }
         }
     }
 }
 
-/// Create a cpu set that may only run on `cpu`.
-#[cfg(target_os = "linux")]
-fn cpu_set(cpu: usize) -> libc::cpu_set_t {
-    let mut cpu_set = unsafe { std::mem::zeroed() };
-    unsafe { libc::CPU_ZERO(&mut cpu_set) };
-    unsafe { libc::CPU_SET(cpu % libc::CPU_SETSIZE as usize, &mut cpu_set) };
-    cpu_set
+/// Set the CPU affinity of the calling thread to `cpus`.
+pub(crate) fn set_thread_cpu_affinity(cpus: &CpuSet) -> io::Result<()> {
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = cpus; // Silence unused variables warnings.
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "setting the CPU affinity of a thread is not supported on this platform",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let cpu_set = cpus.to_libc();
+        let thread = unsafe { libc::pthread_self() };
+        let res = unsafe {
+            libc::pthread_setaffinity_np(thread, std::mem::size_of_val(&cpu_set), &cpu_set)
+        };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::from_raw_os_error(res))
+        }
+    }
 }
 
-/// Set the affinity of this thread to the `cpu_set`.
-#[cfg(target_os = "linux")]
-fn set_affinity(cpu_set: &libc::cpu_set_t) -> io::Result<()> {
-    let thread = unsafe { libc::pthread_self() };
-    let res =
-        unsafe { libc::pthread_setaffinity_np(thread, std::mem::size_of_val(cpu_set), cpu_set) };
-    if res == 0 {
-        Ok(())
-    } else {
-        Err(io::Error::last_os_error())
+/// Returns the CPUs in `cpus` sorted by NUMA node, and by CPU within a node.
+fn sort_by_numa_node(cpus: CpuSet) -> Vec<usize> {
+    sort_by_node(cpus, numa_node)
+}
+
+/// Returns the CPUs in `cpus` sorted by the node returned by `node_of`, and by
+/// CPU within a node. CPUs with an unknown node are considered part of node 0.
+fn sort_by_node<F>(cpus: CpuSet, node_of: F) -> Vec<usize>
+where
+    F: Fn(usize) -> Option<usize>,
+{
+    let mut cpus: Vec<usize> = cpus.iter().collect();
+    cpus.sort_by_key(|cpu| (node_of(*cpu).unwrap_or(0), *cpu));
+    cpus
+}
+
+/// Returns the NUMA node of `cpu`, if known.
+///
+/// On Linux this uses the `nodeN` link in `/sys/devices/system/cpu/cpuM`.
+fn numa_node(cpu: usize) -> Option<usize> {
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = cpu; // Silence unused variables warnings.
+        None
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let path = format!("/sys/devices/system/cpu/cpu{}", cpu);
+        std::fs::read_dir(path).ok()?.find_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_prefix("node")?.parse().ok()
+        })
     }
 }
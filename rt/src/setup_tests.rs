@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::Setup;
+
+use super::{sort_by_node, CpuSet, MAX_CPUS};
+
+#[test]
+fn cpu_set_empty() {
+    let cpus = CpuSet::empty();
+    assert!(cpus.is_empty());
+    assert_eq!(cpus.len(), 0);
+    assert!(!cpus.contains(0));
+    assert_eq!(cpus.iter().next(), None);
+}
+
+#[test]
+fn cpu_set_insert_remove() {
+    let mut cpus = CpuSet::empty();
+    cpus.insert(0);
+    cpus.insert(63);
+    cpus.insert(64);
+    cpus.insert(MAX_CPUS - 1);
+    // Inserting twice shouldn't change anything.
+    cpus.insert(64);
+    assert!(!cpus.is_empty());
+    assert_eq!(cpus.len(), 4);
+    assert!(cpus.contains(0));
+    assert!(cpus.contains(63));
+    assert!(cpus.contains(64));
+    assert!(cpus.contains(MAX_CPUS - 1));
+    assert!(!cpus.contains(1));
+    assert!(!cpus.contains(MAX_CPUS));
+    assert_eq!(cpus.iter().collect::<Vec<_>>(), [0, 63, 64, MAX_CPUS - 1]);
+
+    cpus.remove(63);
+    // Removing CPUs not in the set, or out of range, is a no-op.
+    cpus.remove(1);
+    cpus.remove(MAX_CPUS);
+    assert_eq!(cpus.len(), 3);
+    assert!(!cpus.contains(63));
+    assert_eq!(cpus.iter().collect::<Vec<_>>(), [0, 64, MAX_CPUS - 1]);
+}
+
+#[test]
+#[should_panic = "CPU 1024 is too large for `CpuSet`, 1023 is the maximum"]
+fn cpu_set_insert_too_large() {
+    CpuSet::empty().insert(MAX_CPUS);
+}
+
+#[test]
+fn cpu_set_from_iter() {
+    let cpus: CpuSet = [5, 2, 2, 130].into_iter().collect();
+    assert_eq!(cpus.len(), 3);
+    assert_eq!(cpus.iter().collect::<Vec<_>>(), [2, 5, 130]);
+    assert_eq!(cpus, [130, 5, 2].into_iter().collect());
+}
+
+#[test]
+fn cpu_set_fmt_debug() {
+    let cpus: CpuSet = (1..4).collect();
+    assert_eq!(format!("{:?}", cpus), "{1, 2, 3}");
+    assert_eq!(format!("{:?}", CpuSet::empty()), "{}");
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn cpu_set_to_libc() {
+    let cpus: CpuSet = [0, 3, 100].into_iter().collect();
+    let cpu_set = cpus.to_libc();
+    for cpu in 0..libc::CPU_SETSIZE as usize {
+        let expected = cpus.contains(cpu);
+        assert_eq!(
+            unsafe { libc::CPU_ISSET(cpu, &cpu_set) },
+            expected,
+            "CPU {}",
+            cpu
+        );
+    }
+}
+
+#[test]
+fn sort_by_node_groups_numa_nodes() {
+    // Even CPUs on node 0, odd CPUs on node 1.
+    let cpus: CpuSet = (0..8).collect();
+    let got = sort_by_node(cpus, |cpu| Some(cpu % 2));
+    assert_eq!(got, [0, 2, 4, 6, 1, 3, 5, 7]);
+}
+
+#[test]
+fn sort_by_node_unknown_node() {
+    // CPUs with an unknown node are considered part of node 0.
+    let cpus: CpuSet = (0..6).collect();
+    let got = sort_by_node(cpus, |cpu| if cpu < 3 { Some(1) } else { None });
+    assert_eq!(got, [3, 4, 5, 0, 1, 2]);
+}
+
+#[test]
+fn sort_by_node_single_node() {
+    let cpus: CpuSet = [7, 1, 4].into_iter().collect();
+    assert_eq!(sort_by_node(cpus, |_| Some(0)), [1, 4, 7]);
+    assert_eq!(sort_by_node(cpus, |_| None), [1, 4, 7]);
+    assert_eq!(
+        sort_by_node(CpuSet::empty(), |_| Some(0)),
+        Vec::<usize>::new()
+    );
+}
+
+#[test]
+fn setup_from_vars() {
+    let vars = HashMap::from([
+        ("HEPH_APP_NAME", "from_vars"),
+        ("HEPH_THREADS", "3"),
+        ("HEPH_BLOCKING_THREADS", "5"),
+        ("HEPH_BLOCKING_QUEUE_SIZE", "64"),
+        ("HEPH_CPU_AFFINITY", "0-1,4"),
+        ("PATH", "/bin"),
+    ]);
+    let setup = Setup::from_vars(vars).unwrap();
+    assert_eq!(setup.name(), Some("from_vars"));
+    assert_eq!(setup.get_threads(), 3);
+    assert_eq!(setup.get_blocking_threads(), 5);
+    assert_eq!(setup.get_blocking_queue_size(), 64);
+    assert!(!setup.auto_cpu_affinity);
+    assert_eq!(setup.worker_cpus, Some([0, 1, 4].into_iter().collect()));
+
+    // Explicitly set options take precedence.
+    let setup = setup.num_threads(2);
+    assert_eq!(setup.get_threads(), 2);
+}
+
+#[test]
+fn setup_from_vars_empty() {
+    let setup = Setup::from_vars(HashMap::<&str, &str>::new()).unwrap();
+    let default = Setup::new();
+    assert_eq!(setup.name(), default.name());
+    assert_eq!(setup.get_threads(), default.get_threads());
+    assert_eq!(setup.get_blocking_threads(), default.get_blocking_threads());
+    assert_eq!(
+        setup.get_blocking_queue_size(),
+        default.get_blocking_queue_size()
+    );
+    assert!(setup.worker_cpus.is_none());
+}
+
+#[test]
+fn setup_from_vars_all_threads_auto_affinity() {
+    let vars = HashMap::from([("HEPH_THREADS", "all"), ("HEPH_CPU_AFFINITY", "auto")]);
+    let setup = Setup::from_vars(vars).unwrap();
+    assert_eq!(
+        setup.get_threads(),
+        Setup::new().use_all_cores().get_threads()
+    );
+    assert!(setup.auto_cpu_affinity);
+    assert!(setup.worker_cpus.is_none());
+}
+
+#[test]
+fn setup_from_vars_trace_path_not_created() {
+    let mut path = std::env::temp_dir();
+    path.push("heph_setup_from_vars_trace_path.bin.log");
+    let _ = std::fs::remove_file(&path);
+
+    let vars = HashMap::from([("HEPH_TRACE_PATH", path.to_str().unwrap())]);
+    let setup = Setup::from_vars(vars).unwrap();
+    // The file is only created when the runtime is build.
+    assert_eq!(setup.trace_path.as_deref(), Some(&*path));
+    assert!(setup.trace_log.is_none());
+    assert!(!path.exists());
+
+    // Enabling the flight recorder overwrites the path from the environment.
+    let recorder_path = path.with_extension("recorder");
+    let setup =
+        setup.enable_flight_recorder(recorder_path, crate::trace::RecorderLimit::Events(10), None);
+    assert!(setup.trace_path.is_none());
+    assert!(setup.trace_log.is_some());
+    assert!(!path.exists());
+}
+
+#[test]
+fn setup_from_vars_invalid() {
+    let tests = [
+        ("HEPH_APP_NAME", ""),
+        ("HEPH_THREADS", "zero"),
+        ("HEPH_THREADS", "0"),
+        ("HEPH_THREADS", "1000000"),
+        ("HEPH_BLOCKING_THREADS", "0"),
+        ("HEPH_BLOCKING_THREADS", "-1"),
+        ("HEPH_BLOCKING_QUEUE_SIZE", "0"),
+        ("HEPH_TRACE_PATH", ""),
+        ("HEPH_CPU_AFFINITY", "3-1"),
+        ("HEPH_CPU_AFFINITY", "1024"),
+        ("HEPH_CPU_AFFINITY", "a"),
+    ];
+    for (key, value) in tests {
+        let err = Setup::from_vars(HashMap::from([(key, value)])).unwrap_err();
+        assert!(err.to_string().contains(key), "{}={}: {}", key, value, err);
+    }
+}
@@ -17,9 +17,11 @@ use log::{debug, error, trace};
 use mio::unix::SourceFd;
 use mio::{event, Events, Interest, Poll, Registry, Token};
 
-use crate::spawn::{ActorOptions, AddActorError, FutureOptions};
+use crate::net::lookup;
+use crate::setup::{host_id, host_info, Uuid};
+use crate::spawn::{ActorOptions, AddActorError, FutureOptions, JoinHandle};
 use crate::thread_waker::ThreadWaker;
-use crate::{trace, ProcessId, ThreadSafe};
+use crate::{blocking, metrics, trace, ProcessId, ThreadSafe};
 
 mod scheduler;
 mod timers;
@@ -50,10 +52,18 @@ impl RuntimeSetup {
         self,
         shared_id: WakerId,
         worker_wakers: Box<[&'static ThreadWaker]>,
+        blocking: blocking::Pool,
+        slow_poll_threshold: Option<Duration>,
+        watchdog: bool,
         trace_log: Option<Arc<trace::SharedLog>>,
+        host_info: HostInfo,
     ) -> RuntimeInternals {
         // Needed by `RuntimeInternals::wake_workers`.
         debug_assert!(worker_wakers.len() >= 1);
+        let worker_stats = worker_wakers
+            .iter()
+            .map(|_| metrics::WorkerStats::new())
+            .collect();
         RuntimeInternals {
             shared_id,
             worker_wakers,
@@ -62,14 +72,25 @@ impl RuntimeSetup {
             registry: self.registry,
             scheduler: Scheduler::new(),
             timers: Timers::new(),
+            blocking: Arc::new(blocking),
+            lookup_cache: Arc::new(lookup::Cache::new()),
             trace_log,
+            start: Instant::now(),
+            host_info,
+            worker_stats,
+            sync_worker_stats: Mutex::new(Vec::new()),
+            slow_poll_threshold,
+            watchdog,
+            actor_stats: metrics::ActorStats::new(),
+            metrics_registry: metrics::Registry::new(),
         }
     }
 }
 
 /// Shared internals of the runtime.
+// NOTE: public because it used in the `PrivateAccess` trait.
 #[derive(Debug)]
-pub(crate) struct RuntimeInternals {
+pub struct RuntimeInternals {
     /// Waker id used to create [`task::Waker`]s for thread-safe actors.
     shared_id: WakerId,
     /// Thread wakers for all the workers.
@@ -86,6 +107,10 @@ pub(crate) struct RuntimeInternals {
     scheduler: Scheduler,
     /// Timers for thread-safe actors.
     timers: Timers,
+    /// Pool of threads to run blocking operations on.
+    blocking: Arc<blocking::Pool>,
+    /// Cache for hostname lookups.
+    lookup_cache: Arc<lookup::Cache>,
     /// Shared trace log.
     ///
     /// # Notes
@@ -93,6 +118,56 @@ pub(crate) struct RuntimeInternals {
     /// Prefer not to use this but use [`trace::Log`] in local internals
     /// instead.
     trace_log: Option<Arc<trace::SharedLog>>,
+    /// Time the runtime was created.
+    start: Instant,
+    /// Information about the host and application.
+    host_info: HostInfo,
+    /// Metrics published by the worker threads, indexed by worker id minus
+    /// one.
+    worker_stats: Box<[metrics::WorkerStats]>,
+    /// Metrics of the sync worker threads.
+    sync_worker_stats: Mutex<Vec<Arc<metrics::SyncWorkerStats>>>,
+    /// Polls of processes taking longer than this are reported, see
+    /// [`Setup::slow_poll_threshold`].
+    ///
+    /// [`Setup::slow_poll_threshold`]: crate::Setup::slow_poll_threshold
+    slow_poll_threshold: Option<Duration>,
+    /// Whether or not the coordinator's watchdog is enabled, see
+    /// [`Setup::watchdog`].
+    ///
+    /// [`Setup::watchdog`]: crate::Setup::watchdog
+    watchdog: bool,
+    /// Metrics per actor.
+    actor_stats: metrics::ActorStats,
+    /// Registry for user defined metrics.
+    metrics_registry: metrics::Registry,
+}
+
+/// Information about the host and application.
+#[derive(Debug)]
+pub(crate) struct HostInfo {
+    /// Name of the application.
+    pub(crate) app_name: Box<str>,
+    /// OS name and version, from `uname(2)`.
+    pub(crate) os: Box<str>,
+    /// Name of the host. `nodename` field from `uname(2)`.
+    pub(crate) name: Box<str>,
+    /// Id of the host.
+    pub(crate) id: Uuid,
+}
+
+impl HostInfo {
+    /// Gather the host information.
+    pub(crate) fn new(app_name: Box<str>) -> io::Result<HostInfo> {
+        let (os, name) = host_info()?;
+        let id = host_id()?;
+        Ok(HostInfo {
+            app_name,
+            os,
+            name,
+            id,
+        })
+    }
 }
 
 /// Metrics for [`RuntimeInternals`].
@@ -122,6 +197,63 @@ impl RuntimeInternals {
         }
     }
 
+    /// Returns the time since the runtime was created.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Returns information about the host and application.
+    pub(crate) const fn host_info(&self) -> &HostInfo {
+        &self.host_info
+    }
+
+    /// Returns the metrics published by all worker threads.
+    pub(crate) fn worker_stats(&self) -> &[metrics::WorkerStats] {
+        &self.worker_stats
+    }
+
+    /// Returns the metrics of the worker thread with `worker_id`, or `None` if
+    /// it's not a worker thread of this runtime (e.g. in the test module).
+    pub(crate) fn worker_stats_for(&self, worker_id: usize) -> Option<&metrics::WorkerStats> {
+        self.worker_stats.get(worker_id.wrapping_sub(1))
+    }
+
+    /// Add the metrics of a new sync worker thread.
+    pub(crate) fn add_sync_worker_stats(&self, stats: Arc<metrics::SyncWorkerStats>) {
+        self.sync_worker_stats.lock().unwrap().push(stats);
+    }
+
+    /// Returns a snapshot of the metrics of all sync worker threads.
+    pub(crate) fn sync_worker_stats(&self) -> Vec<metrics::SyncWorkerMetrics> {
+        let stats = self.sync_worker_stats.lock().unwrap();
+        stats.iter().map(|stats| stats.snapshot()).collect()
+    }
+
+    /// Returns the slow poll threshold, if any.
+    pub(crate) const fn slow_poll_threshold(&self) -> Option<Duration> {
+        self.slow_poll_threshold
+    }
+
+    /// Returns `true` if the coordinator's watchdog is enabled.
+    pub(crate) const fn watchdog_enabled(&self) -> bool {
+        self.watchdog
+    }
+
+    /// Returns the metrics per actor.
+    pub(crate) const fn actor_stats(&self) -> &metrics::ActorStats {
+        &self.actor_stats
+    }
+
+    /// Returns the registry for user defined metrics.
+    pub(crate) const fn metrics_registry(&self) -> &metrics::Registry {
+        &self.metrics_registry
+    }
+
+    /// Returns the counter of the shared trace log, if tracing is enabled.
+    pub(crate) fn trace_counter(&self) -> Option<u32> {
+        self.trace_log.as_ref().map(|log| log.metrics().counter)
+    }
+
     /// Returns a new [`task::Waker`] for the thread-safe actor with `pid`.
     pub(crate) fn new_task_waker(&self, pid: ProcessId) -> task::Waker {
         waker::new(self.shared_id, pid)
@@ -159,6 +291,11 @@ impl RuntimeInternals {
         self.registry.register(source, token, interest)
     }
 
+    /// Returns a clone of the registry, see [`mio::Registry::try_clone`].
+    pub(crate) fn try_clone_registry(&self) -> io::Result<Registry> {
+        self.registry.try_clone()
+    }
+
     /// Reregister an `event::Source`, see [`mio::Registry::reregister`].
     pub(crate) fn reregister<S>(
         &self,
@@ -257,9 +394,14 @@ impl RuntimeInternals {
 
     /// Spawn a thread-safe `future`.
     #[allow(clippy::needless_pass_by_value)]
-    pub(crate) fn spawn_future<Fut>(&self, future: Fut, options: FutureOptions)
+    pub(crate) fn spawn_future<Fut>(
+        &self,
+        future: Fut,
+        options: FutureOptions,
+    ) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + Send + Sync + 'static,
+        Fut: Future + Send + Sync + 'static,
+        Fut::Output: Send,
     {
         self.scheduler.add_future(future, options.priority())
     }
@@ -333,6 +475,16 @@ impl RuntimeInternals {
         self.scheduler.complete(process);
     }
 
+    /// Returns the pool of threads to run blocking operations on.
+    pub(crate) const fn blocking(&self) -> &Arc<blocking::Pool> {
+        &self.blocking
+    }
+
+    /// Returns the cache for hostname lookups.
+    pub(crate) const fn lookup_cache(&self) -> &Arc<lookup::Cache> {
+        &self.lookup_cache
+    }
+
     pub(crate) fn start_trace(&self) -> Option<trace::EventTiming> {
         trace::start(&self.trace_log.as_deref())
     }
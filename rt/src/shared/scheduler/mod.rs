@@ -9,11 +9,13 @@ use std::mem::MaybeUninit;
 use std::pin::Pin;
 
 use heph::actor::NewActor;
+use heph::actor_ref::Envelope;
 use heph::supervisor::Supervisor;
 use heph_inbox::Manager;
 use log::{debug, trace};
 
 use crate::process::{self, ActorProcess, FutureProcess, Process, ProcessId};
+use crate::spawn::join::{self, JoinHandle};
 use crate::spawn::options::Priority;
 use crate::{ptr_as_usize, ThreadSafe};
 
@@ -150,16 +152,19 @@ impl Scheduler {
         }
     }
 
-    pub(super) fn add_future<Fut>(&self, future: Fut, priority: Priority)
+    pub(super) fn add_future<Fut>(&self, future: Fut, priority: Priority) -> JoinHandle<Fut::Output>
     where
-        Fut: Future<Output = ()> + Send + Sync + 'static,
+        Fut: Future + Send + Sync + 'static,
+        Fut::Output: Send,
     {
+        let (handle, completer) = join::new();
         let process = Box::pin(ProcessData::new(
             priority,
-            Box::pin(FutureProcess::<Fut, ThreadSafe>::new(future)),
+            Box::pin(FutureProcess::<Fut, ThreadSafe>::new(future, completer)),
         ));
         debug!(pid = process.as_ref().id().0; "spawning thread-safe future");
-        self.ready.add(process)
+        self.ready.add(process);
+        handle
     }
 
     /// Mark the process, with `pid`, as ready to run.
@@ -224,7 +229,7 @@ impl<'s> AddActor<'s> {
         supervisor: S,
         new_actor: NA,
         actor: NA::Actor,
-        inbox: Manager<NA::Message>,
+        inbox: Manager<Envelope<NA::Message>>,
         is_ready: bool,
     ) where
         S: Supervisor<NA> + Send + Sync + 'static,
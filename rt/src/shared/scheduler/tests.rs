@@ -210,8 +210,8 @@ fn assert_future_process_unmoved() {
     let scheduler = Scheduler::new();
     let mut runtime_ref = test::runtime();
 
-    let future = AssertUnmoved::new(pending());
-    scheduler.add_future(future, Priority::NORMAL);
+    let future = AssertUnmoved::new(pending::<()>());
+    let _ = scheduler.add_future(future, Priority::NORMAL);
 
     // Run the process multiple times, ensure it's not moved in the
     // process.
@@ -183,9 +183,9 @@ mod tests {
 
     use crate::process::{Process, ProcessData, ProcessId, ProcessResult};
     use crate::shared::waker::{self, WakerData};
-    use crate::shared::{RuntimeInternals, Scheduler};
+    use crate::shared::{HostInfo, RuntimeInternals, Scheduler};
     use crate::spawn::options::Priority;
-    use crate::{test, RuntimeRef};
+    use crate::{blocking, test, RuntimeRef};
 
     const PID1: ProcessId = ProcessId(1);
     const PID2: ProcessId = ProcessId(2);
@@ -323,7 +323,16 @@ mod tests {
         Arc::new_cyclic(|shared_internals| {
             let waker_id = waker::init(shared_internals.clone());
             let worker_wakers = vec![&*test::NOOP_WAKER].into_boxed_slice();
-            setup.complete(waker_id, worker_wakers, None)
+            let host_info = HostInfo::new("heph_test".into()).unwrap();
+            setup.complete(
+                waker_id,
+                worker_wakers,
+                blocking::Pool::new(blocking::DEFAULT_MAX_THREADS, blocking::DEFAULT_QUEUE_SIZE),
+                None,
+                false,
+                None,
+                host_info,
+            )
         })
     }
 
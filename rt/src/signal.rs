@@ -1,14 +1,34 @@
-use std::fmt;
+use std::ops::BitOr;
+use std::{fmt, io};
+
+use heph::actor_ref::{ActorRef, SendError};
+use mio::{event, Interest, Registry, Token};
 
 /// Process signal.
 ///
 /// All actors can receive process signals by calling
 /// [`Runtime::receive_signals`] or [`RuntimeRef::receive_signals`] with their
-/// actor reference. This causes all process signals to be relayed to the actor
-/// which should handle them accordingly.
+/// actor reference. This causes the [`Interrupt`], [`Terminate`], [`Quit`],
+/// [`User1`] and [`User2`] signals to be relayed to the actor which should
+/// handle them accordingly. To only receive some signals use
+/// [`Runtime::receive_signals_filtered`] or
+/// [`RuntimeRef::receive_signals_filtered`] with a [`SignalSet`].
+///
+/// The other signals, e.g. [`Hangup`], are only handled by the runtime if
+/// they're enabled using [`Setup::handle_signals`], and can only be received
+/// using the filtered variants.
 ///
 /// [`Runtime::receive_signals`]: crate::Runtime::receive_signals
 /// [`RuntimeRef::receive_signals`]: crate::RuntimeRef::receive_signals
+/// [`Interrupt`]: Signal::Interrupt
+/// [`Terminate`]: Signal::Terminate
+/// [`Quit`]: Signal::Quit
+/// [`User1`]: Signal::User1
+/// [`User2`]: Signal::User2
+/// [`Runtime::receive_signals_filtered`]: crate::Runtime::receive_signals_filtered
+/// [`RuntimeRef::receive_signals_filtered`]: crate::RuntimeRef::receive_signals_filtered
+/// [`Hangup`]: Signal::Hangup
+/// [`Setup::handle_signals`]: crate::Setup::handle_signals
 ///
 /// # Notes
 ///
@@ -22,6 +42,12 @@ use std::fmt;
 /// If the message can't be send it's **not** retried. Ensure that the inbox of
 /// the actor has enough room to receive the message.
 ///
+/// Actor references are removed from the list of receivers once they're
+/// disconnected, i.e. once the actor has stopped.
+///
+/// [`Signal::Hangup`], [`Signal::Child`], [`Signal::WindowChange`] and
+/// [`Signal::Pipe`] are currently only supported on Linux.
+///
 /// [`rt::Setup::build`]: crate::Setup::build
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Signal {
@@ -62,25 +88,84 @@ pub enum Signal {
     /// The runtime will output various metrics about itself when it receives
     /// this signal.
     User2,
+    /// Hangup signal.
+    ///
+    /// This signal is received when the controlling terminal is closed, but is
+    /// also commonly used to request a daemon to reload its configuration.
+    ///
+    /// Corresponds to POSIX signal `SIGHUP`.
+    Hangup,
+    /// Child stopped or terminated signal.
+    ///
+    /// Corresponds to POSIX signal `SIGCHLD`.
+    Child,
+    /// Terminal window size changed signal.
+    ///
+    /// Corresponds to POSIX signal `SIGWINCH`.
+    WindowChange,
+    /// Broken pipe signal.
+    ///
+    /// Corresponds to POSIX signal `SIGPIPE`.
+    ///
+    /// # Notes
+    ///
+    /// When writing to a closed pipe the signal is send to the thread that
+    /// wrote to the pipe, not the process, and such signals are not relayed.
+    /// The write call will return an error with [kind] set to
+    /// [`ErrorKind::BrokenPipe`] instead.
+    ///
+    /// [kind]: io::Error::kind
+    /// [`ErrorKind::BrokenPipe`]: io::ErrorKind::BrokenPipe
+    Pipe,
 }
 
 impl Signal {
-    /// Convert a [`mio_signals::Signal`] into our own `Signal`.
-    pub(super) const fn from_mio(signal: mio_signals::Signal) -> Signal {
-        match signal {
-            mio_signals::Signal::Interrupt => Signal::Interrupt,
-            mio_signals::Signal::Terminate => Signal::Terminate,
-            mio_signals::Signal::Quit => Signal::Quit,
-            mio_signals::Signal::User1 => Signal::User1,
-            mio_signals::Signal::User2 => Signal::User2,
-        }
+    /// All signals.
+    const ALL: [Signal; 9] = [
+        Signal::Interrupt,
+        Signal::Terminate,
+        Signal::Quit,
+        Signal::User1,
+        Signal::User2,
+        Signal::Hangup,
+        Signal::Child,
+        Signal::WindowChange,
+        Signal::Pipe,
+    ];
+
+    /// Convert a signal number into a `Signal`, returns `None` for unknown
+    /// signals.
+    #[cfg(target_os = "linux")]
+    const fn from_signo(signo: libc::c_int) -> Option<Signal> {
+        Some(match signo {
+            libc::SIGINT => Signal::Interrupt,
+            libc::SIGTERM => Signal::Terminate,
+            libc::SIGQUIT => Signal::Quit,
+            libc::SIGUSR1 => Signal::User1,
+            libc::SIGUSR2 => Signal::User2,
+            libc::SIGHUP => Signal::Hangup,
+            libc::SIGCHLD => Signal::Child,
+            libc::SIGWINCH => Signal::WindowChange,
+            libc::SIGPIPE => Signal::Pipe,
+            _ => return None,
+        })
+    }
+
+    /// Returns the bit used in [`SignalSet`].
+    const fn bit(self) -> u16 {
+        1 << self as u16
     }
 
     /// Whether or not the `Signal` is considered a "stopping" signal.
     pub(super) const fn should_stop(self) -> bool {
         match self {
             Signal::Interrupt | Signal::Terminate | Signal::Quit => true,
-            Signal::User1 | Signal::User2 => false,
+            Signal::User1
+            | Signal::User2
+            | Signal::Hangup
+            | Signal::Child
+            | Signal::WindowChange
+            | Signal::Pipe => false,
         }
     }
 
@@ -92,6 +177,25 @@ impl Signal {
             Signal::Quit => "quit",
             Signal::User1 => "user-1",
             Signal::User2 => "user-2",
+            Signal::Hangup => "hangup",
+            Signal::Child => "child",
+            Signal::WindowChange => "window-change",
+            Signal::Pipe => "pipe",
+        }
+    }
+
+    /// Returns the signal number.
+    pub(crate) const fn to_signo(self) -> libc::c_int {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Quit => libc::SIGQUIT,
+            Signal::User1 => libc::SIGUSR1,
+            Signal::User2 => libc::SIGUSR2,
+            Signal::Hangup => libc::SIGHUP,
+            Signal::Child => libc::SIGCHLD,
+            Signal::WindowChange => libc::SIGWINCH,
+            Signal::Pipe => libc::SIGPIPE,
         }
     }
 
@@ -103,6 +207,10 @@ impl Signal {
             Signal::Quit => "SIGQUIT",
             Signal::User1 => "SIGUSR1",
             Signal::User2 => "SIGUSR2",
+            Signal::Hangup => "SIGHUP",
+            Signal::Child => "SIGCHLD",
+            Signal::WindowChange => "SIGWINCH",
+            Signal::Pipe => "SIGPIPE",
         }
     }
 }
@@ -118,3 +226,338 @@ impl fmt::Display for Signal {
         Ok(())
     }
 }
+
+impl BitOr for Signal {
+    type Output = SignalSet;
+
+    fn bitor(self, rhs: Signal) -> SignalSet {
+        SignalSet::from(self) | rhs
+    }
+}
+
+/// Set of [`Signal`]s.
+///
+/// Used in [`Runtime::receive_signals_filtered`] and
+/// [`RuntimeRef::receive_signals_filtered`] to only receive some signals.
+///
+/// [`Runtime::receive_signals_filtered`]: crate::Runtime::receive_signals_filtered
+/// [`RuntimeRef::receive_signals_filtered`]: crate::RuntimeRef::receive_signals_filtered
+///
+/// # Examples
+///
+/// ```
+/// use heph_rt::{Signal, SignalSet};
+///
+/// let set = Signal::Hangup | Signal::User1;
+/// assert!(set.contains(Signal::Hangup));
+/// assert!(!set.contains(Signal::Interrupt));
+///
+/// let set = set | Signal::Interrupt;
+/// assert!(set.contains(Signal::Interrupt));
+///
+/// assert!(SignalSet::all().contains(Signal::Pipe));
+/// assert!(SignalSet::empty().is_empty());
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct SignalSet(u16);
+
+impl SignalSet {
+    /// Signals handled by the runtime by default, see [`Signal`].
+    pub(crate) const DEFAULT: SignalSet = SignalSet(
+        Signal::Interrupt.bit()
+            | Signal::Terminate.bit()
+            | Signal::Quit.bit()
+            | Signal::User1.bit()
+            | Signal::User2.bit(),
+    );
+
+    /// Create an empty set.
+    pub const fn empty() -> SignalSet {
+        SignalSet(0)
+    }
+
+    /// Create a set with all signals.
+    pub const fn all() -> SignalSet {
+        let mut set = 0;
+        let mut i = 0;
+        while i < Signal::ALL.len() {
+            set |= Signal::ALL[i].bit();
+            i += 1;
+        }
+        SignalSet(set)
+    }
+
+    /// Returns `true` if the set contains `signal`.
+    pub const fn contains(self, signal: Signal) -> bool {
+        self.0 & signal.bit() != 0
+    }
+
+    /// Returns `true` if the set is empty.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over all signals in the set.
+    fn iter(self) -> impl Iterator<Item = Signal> {
+        Signal::ALL
+            .into_iter()
+            .filter(move |signal| self.contains(*signal))
+    }
+}
+
+impl From<Signal> for SignalSet {
+    fn from(signal: Signal) -> SignalSet {
+        SignalSet(signal.bit())
+    }
+}
+
+impl BitOr for SignalSet {
+    type Output = SignalSet;
+
+    fn bitor(self, rhs: SignalSet) -> SignalSet {
+        SignalSet(self.0 | rhs.0)
+    }
+}
+
+impl BitOr<Signal> for SignalSet {
+    type Output = SignalSet;
+
+    fn bitor(self, rhs: Signal) -> SignalSet {
+        SignalSet(self.0 | rhs.bit())
+    }
+}
+
+impl fmt::Debug for SignalSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Actor references that want to receive process signals, with the signals
+/// they want to receive.
+#[derive(Debug)]
+pub(crate) struct Receivers {
+    receivers: Vec<(ActorRef<Signal>, SignalSet)>,
+}
+
+impl Receivers {
+    /// Create an empty list of receivers.
+    pub(crate) const fn new() -> Receivers {
+        Receivers {
+            receivers: Vec::new(),
+        }
+    }
+
+    /// Add `actor_ref` to receive `signals`.
+    ///
+    /// If `actor_ref` was already added, `signals` are added to the signals it
+    /// already receives.
+    pub(crate) fn add(&mut self, actor_ref: ActorRef<Signal>, signals: SignalSet) {
+        for (receiver, set) in &mut self.receivers {
+            if receiver.sends_to(&actor_ref) {
+                *set = *set | signals;
+                return;
+            }
+        }
+        self.receivers.push((actor_ref, signals));
+    }
+
+    /// Returns the number of receivers.
+    pub(crate) fn len(&self) -> usize {
+        self.receivers.len()
+    }
+
+    /// Remove all actor references that have been disconnected.
+    pub(crate) fn remove_disconnected(&mut self) {
+        self.receivers
+            .retain(|(actor_ref, _)| actor_ref.is_connected());
+    }
+
+    /// Attempts to send `signal` to all receivers that want to receive it.
+    ///
+    /// Returns an error if no receiver wants to receive `signal`.
+    pub(crate) fn try_send(&self, signal: Signal) -> Result<(), SendError> {
+        let mut sent = false;
+        for (actor_ref, set) in &self.receivers {
+            if set.contains(signal) {
+                let _ = actor_ref.try_send(signal);
+                sent = true;
+            }
+        }
+        if sent {
+            Ok(())
+        } else {
+            Err(SendError)
+        }
+    }
+}
+
+/// Process signal handling.
+///
+/// On Linux this uses `signalfd(2)`, which supports all signals in [`Signal`].
+/// On other platforms this uses the `mio-signals` crate, which only supports
+/// a subset of the signals.
+pub(crate) struct Signals {
+    #[cfg(target_os = "linux")]
+    fd: std::fs::File,
+    #[cfg(not(target_os = "linux"))]
+    inner: mio_signals::Signals,
+}
+
+impl Signals {
+    /// Create a new `Signals` instance, receiving `signals`.
+    ///
+    /// # Notes
+    ///
+    /// This blocks the signals for the calling thread, and thus all threads
+    /// spawned by it. Therefore this must be called before starting any other
+    /// threads.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn new(signals: SignalSet) -> io::Result<Signals> {
+        use std::mem::MaybeUninit;
+        use std::os::unix::io::FromRawFd;
+        use std::ptr;
+
+        let mut set: MaybeUninit<libc::sigset_t> = MaybeUninit::uninit();
+        if unsafe { libc::sigemptyset(set.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        for signal in signals.iter() {
+            if unsafe { libc::sigaddset(set.as_mut_ptr(), signal.to_signo()) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        // SAFETY: initialised by `sigemptyset` above.
+        let set = unsafe { set.assume_init() };
+
+        let res = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) };
+        if res != 0 {
+            return Err(io::Error::from_raw_os_error(res));
+        }
+
+        let fd = unsafe { libc::signalfd(-1, &set, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `signalfd(2)` ensures `fd` is valid.
+        let fd = unsafe { std::fs::File::from_raw_fd(fd) };
+        Ok(Signals { fd })
+    }
+
+    /// Create a new `Signals` instance, receiving `signals`.
+    ///
+    /// # Notes
+    ///
+    /// Signals not supported by `mio-signals` are ignored.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn new(signals: SignalSet) -> io::Result<Signals> {
+        let mut set = mio_signals::SignalSet::empty();
+        for signal in signals.iter() {
+            let signal = match signal {
+                Signal::Interrupt => mio_signals::Signal::Interrupt,
+                Signal::Terminate => mio_signals::Signal::Terminate,
+                Signal::Quit => mio_signals::Signal::Quit,
+                Signal::User1 => mio_signals::Signal::User1,
+                Signal::User2 => mio_signals::Signal::User2,
+                Signal::Hangup | Signal::Child | Signal::WindowChange | Signal::Pipe => continue,
+            };
+            set = set | signal;
+        }
+        mio_signals::Signals::new(set).map(|inner| Signals { inner })
+    }
+
+    /// Receive a signal, returns `Ok(None)` if no more signals are pending.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn receive(&mut self) -> io::Result<Option<Signal>> {
+        use std::io::Read;
+        use std::mem::size_of;
+
+        let mut info = [0; size_of::<libc::signalfd_siginfo>()];
+        loop {
+            match self.fd.read(&mut info) {
+                Ok(n) if n == info.len() => {
+                    // `ssi_signo` is the first field.
+                    #[allow(clippy::cast_possible_wrap)]
+                    let signo =
+                        u32::from_ne_bytes([info[0], info[1], info[2], info[3]]) as libc::c_int;
+                    match Signal::from_signo(signo) {
+                        Some(signal) => return Ok(Some(signal)),
+                        // Shouldn't happen, but we'll try the next signal.
+                        None => continue,
+                    }
+                }
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "short read from signalfd",
+                    ))
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Receive a signal, returns `Ok(None)` if no more signals are pending.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn receive(&mut self) -> io::Result<Option<Signal>> {
+        self.inner.receive().map(|signal| {
+            signal.map(|signal| match signal {
+                mio_signals::Signal::Interrupt => Signal::Interrupt,
+                mio_signals::Signal::Terminate => Signal::Terminate,
+                mio_signals::Signal::Quit => Signal::Quit,
+                mio_signals::Signal::User1 => Signal::User1,
+                mio_signals::Signal::User2 => Signal::User2,
+            })
+        })
+    }
+}
+
+impl event::Source for Signals {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            mio::unix::SourceFd(&self.fd.as_raw_fd()).register(registry, token, interests)
+        }
+        #[cfg(not(target_os = "linux"))]
+        self.inner.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            mio::unix::SourceFd(&self.fd.as_raw_fd()).reregister(registry, token, interests)
+        }
+        #[cfg(not(target_os = "linux"))]
+        self.inner.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            mio::unix::SourceFd(&self.fd.as_raw_fd()).deregister(registry)
+        }
+        #[cfg(not(target_os = "linux"))]
+        self.inner.deregister(registry)
+    }
+}
+
+impl fmt::Debug for Signals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signals").finish()
+    }
+}
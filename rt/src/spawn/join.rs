@@ -0,0 +1,183 @@
+//! Module with [`JoinHandle`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{self, Poll};
+use std::{fmt, mem};
+
+/// Create a new [`JoinHandle`] and [`Completer`] pair.
+pub(crate) fn new<T>() -> (JoinHandle<T>, Completer<T>) {
+    let shared = Arc::new(Shared {
+        aborted: AtomicBool::new(false),
+        state: Mutex::new(State {
+            outcome: Outcome::Pending,
+            joiner: None,
+            process: None,
+        }),
+    });
+    let handle = JoinHandle {
+        shared: shared.clone(),
+    };
+    (handle, Completer { shared })
+}
+
+/// State shared between the [`JoinHandle`] and the [`Completer`].
+struct Shared<T> {
+    /// Set by [`JoinHandle::abort`].
+    aborted: AtomicBool,
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    outcome: Outcome<T>,
+    /// Waker of the task waiting on the [`JoinHandle`].
+    joiner: Option<task::Waker>,
+    /// Waker of the process running the future, used to wake it when it's
+    /// aborted.
+    process: Option<task::Waker>,
+}
+
+/// Result of the spawned future.
+enum Outcome<T> {
+    /// Future is still running.
+    Pending,
+    /// Future completed, or was cancelled or panicked.
+    Ready(Result<T, JoinError>),
+    /// Result was returned by the [`JoinHandle`].
+    Taken,
+}
+
+/// Handle to a spawned [`Future`].
+///
+/// This is returned by [`RuntimeRef::spawn_future`],
+/// [`RuntimeRef::spawn_local_future`] and [`ThreadSafe::spawn_future`].
+///
+/// The `JoinHandle` itself is a [`Future`] that returns the output of the
+/// spawned future once it completes. If the future was cancelled, e.g. using
+/// [`JoinHandle::abort`], or panicked it returns a [`JoinError`].
+///
+/// Dropping the `JoinHandle` detaches the future, it keeps running in the
+/// background.
+///
+/// [`RuntimeRef::spawn_future`]: crate::RuntimeRef::spawn_future
+/// [`RuntimeRef::spawn_local_future`]: crate::RuntimeRef::spawn_local_future
+/// [`ThreadSafe::spawn_future`]: crate::ThreadSafe::spawn_future
+pub struct JoinHandle<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Abort the future.
+    ///
+    /// The future is dropped the next time the scheduler gets to it, after
+    /// which this handle returns [`JoinError::Cancelled`]. If the future
+    /// already completed this does nothing.
+    pub fn abort(&self) {
+        self.shared.aborted.store(true, Ordering::Release);
+        let waker = self.shared.state.lock().unwrap().process.take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns `true` if the future is completed, i.e. the handle will return
+    /// the result without waiting.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.shared.state.lock().unwrap().outcome,
+            Outcome::Ready(..)
+        )
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.state.lock().unwrap();
+        match mem::replace(&mut state.outcome, Outcome::Taken) {
+            Outcome::Ready(result) => Poll::Ready(result),
+            Outcome::Pending => {
+                state.outcome = Outcome::Pending;
+                match &mut state.joiner {
+                    Some(waker) if waker.will_wake(ctx.waker()) => {}
+                    waker => *waker = Some(ctx.waker().clone()),
+                }
+                Poll::Pending
+            }
+            Outcome::Taken => panic!("polled `JoinHandle` after completion"),
+        }
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+/// Error returned by [`JoinHandle`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum JoinError {
+    /// The future was cancelled, either using [`JoinHandle::abort`] or because
+    /// the runtime shut down before the future completed.
+    Cancelled,
+    /// The future panicked.
+    Panicked,
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JoinError::Cancelled => "future was cancelled",
+            JoinError::Panicked => "future panicked",
+        })
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+/// Completing side of a [`JoinHandle`], held by the process running the future.
+///
+/// If this is dropped before [`Completer::complete`] is called the
+/// [`JoinHandle`] returns [`JoinError::Cancelled`].
+pub(crate) struct Completer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Completer<T> {
+    /// Set the `waker` to wake the process running the future when it's
+    /// aborted.
+    pub(crate) fn set_waker(&self, waker: &task::Waker) {
+        self.shared.state.lock().unwrap().process = Some(waker.clone());
+    }
+
+    /// Returns `true` if the future was aborted, see [`JoinHandle::abort`].
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.shared.aborted.load(Ordering::Acquire)
+    }
+
+    /// Complete the [`JoinHandle`] with `result`.
+    pub(crate) fn complete(&self, result: Result<T, JoinError>) {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Outcome::Pending = state.outcome {
+            state.outcome = Outcome::Ready(result);
+            state.process = None;
+            let joiner = state.joiner.take();
+            drop(state);
+            if let Some(waker) = joiner {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.complete(Err(JoinError::Cancelled));
+    }
+}
@@ -1,13 +1,23 @@
 //! Module with the [`Spawn`] trait.
 
+use std::future::Future;
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll};
+
 use heph::actor::{self, NewActor};
 use heph::actor_ref::ActorRef;
 use heph::supervisor::Supervisor;
 
+use crate::blocking;
+
+pub(crate) mod join;
 pub mod options;
 
 pub(crate) use private::{AddActorError, PrivateSpawn};
 
+pub use join::{JoinError, JoinHandle};
 #[doc(no_inline)]
 pub use options::{ActorOptions, FutureOptions, SyncActorOptions};
 
@@ -69,6 +79,45 @@ pub trait Spawn<S, NA, RT>: PrivateSpawn<S, NA, RT> {
     }
 }
 
+/// The [`Future`] behind [`RuntimeRef::spawn_blocking`] and
+/// [`ThreadSafe::spawn_blocking`].
+///
+/// Returns the value returned by the closure. If the closure panicked the
+/// panic is resumed when polling this future.
+///
+/// [`RuntimeRef::spawn_blocking`]: crate::RuntimeRef::spawn_blocking
+/// [`ThreadSafe::spawn_blocking`]: crate::ThreadSafe::spawn_blocking
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SpawnBlocking<T> {
+    handle: blocking::Handle<T>,
+}
+
+impl<T> SpawnBlocking<T> {
+    /// Run `f` on the blocking pool `pool`.
+    pub(crate) fn new<F>(pool: &Arc<blocking::Pool>, f: F) -> SpawnBlocking<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        SpawnBlocking {
+            handle: pool.spawn(f),
+        }
+    }
+}
+
+impl<T> Future for SpawnBlocking<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.handle).poll(ctx) {
+            Poll::Ready(Ok(value)) => Poll::Ready(value),
+            Poll::Ready(Err(panic)) => resume_unwind(panic),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 mod private {
     //! Module with private types.
 
@@ -9,6 +9,8 @@ use std::num::NonZeroU8;
 use std::ops::Mul;
 use std::time::Duration;
 
+use crate::CpuSet;
+
 /// Options for [spawning] an [`Actor`].
 ///
 /// [spawning]: crate::spawn::Spawn
@@ -191,10 +193,23 @@ fn priority_duration_multiplication() {
 /// let opts = SyncActorOptions::default().with_name("My sync actor".to_owned());
 /// # drop(opts); // Silence unused variable warning.
 /// ```
+///
+/// Running the synchronous actor on CPU 3.
+///
+/// ```
+/// use heph_rt::spawn::SyncActorOptions;
+/// use heph_rt::CpuSet;
+///
+/// let mut cpus = CpuSet::empty();
+/// cpus.insert(3);
+/// let opts = SyncActorOptions::default().cpu_affinity(cpus);
+/// # drop(opts); // Silence unused variable warning.
+/// ```
 #[derive(Debug, Default)]
 #[must_use]
 pub struct SyncActorOptions {
     thread_name: Option<String>,
+    cpu_affinity: Option<CpuSet>,
 }
 
 impl SyncActorOptions {
@@ -204,8 +219,8 @@ impl SyncActorOptions {
     }
 
     /// Removes the name.
-    pub(crate) fn take_name(self) -> Option<String> {
-        self.thread_name
+    pub(crate) fn take_name(&mut self) -> Option<String> {
+        self.thread_name.take()
     }
 
     /// Set the name of the actor. This is for example used in the naming of the
@@ -216,6 +231,24 @@ impl SyncActorOptions {
         self.thread_name = Some(thread_name);
         self
     }
+
+    /// Returns the CPU affinity of the synchronous actor, if set.
+    pub const fn get_cpu_affinity(&self) -> Option<&CpuSet> {
+        self.cpu_affinity.as_ref()
+    }
+
+    /// Set the CPU affinity of the thread running the synchronous actor.
+    ///
+    /// If the CPU affinity can't be set a warning is logged and the actor is
+    /// run without setting it.
+    ///
+    /// # Notes
+    ///
+    /// This is currently only implementated on Linux.
+    pub fn cpu_affinity(mut self, cpus: CpuSet) -> Self {
+        self.cpu_affinity = Some(cpus);
+        self
+    }
 }
 
 /// Options for spawning a [`Future`].
@@ -10,20 +10,23 @@
 //!
 //! [coordinator]: crate::coordinator
 
+use std::cell::RefCell;
 use std::io::{self, Write};
+use std::rc::Rc;
 use std::sync::Arc;
 use std::thread;
 
 use heph::actor::{SyncActor, SyncContext};
-use heph::actor_ref::ActorRef;
+use heph::actor_ref::{ActorRef, Envelope};
 use heph::supervisor::{SupervisorStrategy, SyncSupervisor};
 use heph_inbox::{self as inbox, ReceiverConnected};
-use log::trace;
+use log::{as_debug, debug, trace, warn};
 use mio::{unix, Interest, Registry, Token};
 
+use crate::setup::set_thread_cpu_affinity;
 use crate::spawn::options::SyncActorOptions;
 use crate::trace;
-use crate::{self as rt, shared};
+use crate::{self as rt, metrics, shared, CpuSet};
 
 /// Handle to a synchronous worker.
 #[derive(Debug)]
@@ -43,9 +46,9 @@ impl SyncWorker {
         supervisor: S,
         actor: A,
         arg: A::Argument,
-        options: SyncActorOptions,
+        mut options: SyncActorOptions,
         rt: Arc<shared::RuntimeInternals>,
-        trace_log: Option<trace::Log>,
+        trace_log: Option<&trace::CoordinatorLog>,
     ) -> io::Result<(SyncWorker, ActorRef<A::Message>)>
     where
         S: SyncSupervisor<A> + Send + 'static,
@@ -59,9 +62,22 @@ impl SyncWorker {
             let thread_name = options
                 .take_name()
                 .unwrap_or_else(|| format!("Sync actor {}", id));
+            #[allow(clippy::cast_possible_truncation)]
+            // Safety: MAX_THREADS always fits in u32.
+            let trace_log =
+                trace_log.map(|trace_log| trace_log.new_stream(id as u32, &thread_name));
+            let cpus = options.get_cpu_affinity().copied();
+            let stats = Arc::new(metrics::SyncWorkerStats::new(id, heph::actor::name::<A>()));
+            rt.add_sync_worker_stats(stats.clone());
             thread::Builder::new()
                 .name(thread_name)
-                .spawn(move || main(id, supervisor, actor, arg, manager, receiver, rt, trace_log))
+                .spawn(move || {
+                    stats.started();
+                    if let Some(cpus) = cpus {
+                        set_cpu_affinity(id, &cpus);
+                    }
+                    main(id, supervisor, actor, arg, manager, receiver, rt, trace_log)
+                })
                 .map(|handle| (SyncWorker { id, handle, sender }, actor_ref))
         })
     }
@@ -100,6 +116,18 @@ impl SyncWorker {
     }
 }
 
+/// Set the CPU affinity of the sync worker thread to `cpus`.
+fn set_cpu_affinity(id: usize, cpus: &CpuSet) {
+    match set_thread_cpu_affinity(cpus) {
+        Ok(()) => {
+            debug!(sync_worker_id = id, cpus = as_debug!(cpus); "sync worker thread CPU affinity set");
+        }
+        Err(err) => {
+            warn!(sync_worker_id = id; "failed to set CPU affinity on sync worker thread: {}", err);
+        }
+    }
+}
+
 /// Run a synchronous actor worker thread.
 #[allow(clippy::too_many_arguments)]
 fn main<S, A>(
@@ -107,10 +135,10 @@ fn main<S, A>(
     mut supervisor: S,
     actor: A,
     mut arg: A::Argument,
-    inbox: inbox::Manager<A::Message>,
+    inbox: inbox::Manager<Envelope<A::Message>>,
     receiver: unix::pipe::Receiver,
     rt: Arc<shared::RuntimeInternals>,
-    mut trace_log: Option<trace::Log>,
+    trace_log: Option<trace::Log>,
 ) where
     S: SyncSupervisor<A> + 'static,
     A: SyncActor<RuntimeAccess = rt::Sync>,
@@ -118,32 +146,43 @@ fn main<S, A>(
     let thread = thread::current();
     let name = thread.name().unwrap();
     trace!(sync_worker_id = id, name = name; "running synchronous actor");
+    // This thread only runs this actor, so we don't have to call
+    // `trace::exit_process`.
+    trace::enter_process(&trace_log, heph::actor::name::<A>());
+    // Shared with the hooks that record the message flows.
+    let trace_log: trace::ThreadLog = Rc::new(RefCell::new(trace_log));
+    trace::set_flow_log(Some(trace_log.clone()));
     loop {
-        let timing = trace::start(&trace_log);
+        let timing = trace::start(&*trace_log.borrow());
         let receiver = inbox.new_receiver().unwrap_or_else(inbox_failure);
-        let rt = rt::Sync::new(rt.clone(), trace_log.clone());
+        let rt = rt::Sync::new(rt.clone(), trace_log.borrow().clone());
         let ctx = SyncContext::new(receiver, rt);
         trace::finish_rt(
-            trace_log.as_mut(),
+            trace_log.borrow_mut().as_mut(),
             timing,
             "setting up synchronous actor",
             &[],
         );
 
-        let timing = trace::start(&trace_log);
+        let timing = trace::start(&*trace_log.borrow());
         let res = actor.run(ctx, arg);
-        trace::finish_rt(trace_log.as_mut(), timing, "running synchronous actor", &[]);
+        trace::finish_rt(
+            trace_log.borrow_mut().as_mut(),
+            timing,
+            "running synchronous actor",
+            &[],
+        );
 
         match res {
             Ok(()) => break,
             Err(err) => {
-                let timing = trace::start(&trace_log);
+                let timing = trace::start(&*trace_log.borrow());
                 match supervisor.decide(err) {
                     SupervisorStrategy::Restart(new_arg) => {
                         trace!(sync_worker_id = id, name = name; "restarting synchronous actor");
                         arg = new_arg;
                         trace::finish_rt(
-                            trace_log.as_mut(),
+                            trace_log.borrow_mut().as_mut(),
                             timing,
                             "restarting synchronous actor",
                             &[],
@@ -151,7 +190,7 @@ fn main<S, A>(
                     }
                     SupervisorStrategy::Stop => {
                         trace::finish_rt(
-                            trace_log.as_mut(),
+                            trace_log.borrow_mut().as_mut(),
                             timing,
                             "stopping synchronous actor",
                             &[],
@@ -170,6 +209,7 @@ fn main<S, A>(
     drop(supervisor);
     drop(inbox);
     drop(rt);
+    trace::set_flow_log(None);
     drop(trace_log);
     // After dropping all values let the coordinator know we're done.
     drop(receiver);
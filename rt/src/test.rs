@@ -58,19 +58,19 @@ use std::time::{Duration, Instant};
 use std::{io, slice, thread};
 
 use heph::actor::{self, Actor, NewActor, SyncActor, SyncWaker};
-use heph::actor_ref::{ActorGroup, ActorRef};
+use heph::actor_ref::{ActorGroup, ActorRef, Envelope};
 use heph::supervisor::{Supervisor, SyncSupervisor};
 use heph_inbox::oneshot::new_oneshot;
 use heph_inbox::Manager;
 
 use crate::shared::waker;
-use crate::spawn::{ActorOptions, FutureOptions, SyncActorOptions};
+use crate::spawn::{ActorOptions, FutureOptions, JoinHandle, SyncActorOptions};
 use crate::sync_worker::SyncWorker;
 use crate::thread_waker::ThreadWaker;
 use crate::worker::{Control, Worker};
 use crate::{
-    self as rt, shared, ProcessId, RuntimeRef, Sync, ThreadLocal, ThreadSafe, SYNC_WORKER_ID_END,
-    SYNC_WORKER_ID_START,
+    self as rt, blocking, shared, ProcessId, RuntimeRef, Sync, ThreadLocal, ThreadSafe,
+    SYNC_WORKER_ID_END, SYNC_WORKER_ID_START,
 };
 
 #[doc(no_inline)]
@@ -92,7 +92,17 @@ static SHARED_INTERNAL: SyncLazy<Arc<shared::RuntimeInternals>> = SyncLazy::new(
     Arc::new_cyclic(|shared_internals| {
         let waker_id = waker::init(shared_internals.clone());
         let worker_wakers = vec![&*NOOP_WAKER].into_boxed_slice();
-        setup.complete(waker_id, worker_wakers, None)
+        let host_info = shared::HostInfo::new("heph_test".into())
+            .expect("failed to get host information for test module");
+        setup.complete(
+            waker_id,
+            worker_wakers,
+            blocking::Pool::new(blocking::DEFAULT_MAX_THREADS, blocking::DEFAULT_QUEUE_SIZE),
+            None,
+            false,
+            None,
+            host_info,
+        )
     })
 });
 
@@ -180,7 +190,7 @@ where
 {
     let (sender, receiver) = new_oneshot();
     let waker = SyncWaker::new();
-    spawn_local_future(
+    let _ = spawn_local_future(
         async move {
             let result = future.await;
             assert!(
@@ -267,14 +277,12 @@ where
 /// runtime.
 ///
 /// [module documentation]: crate::test
-pub fn spawn_local_future<Fut>(future: Fut, options: FutureOptions)
+pub fn spawn_local_future<Fut>(future: Fut, options: FutureOptions) -> JoinHandle<Fut::Output>
 where
-    Fut: Future<Output = ()> + Send + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
 {
-    run_on_test_runtime(move |mut runtime_ref| {
-        runtime_ref.spawn_local_future(future, options);
-        Ok(())
-    });
+    run_on_test_runtime_wait(move |mut runtime_ref| runtime_ref.spawn_local_future(future, options))
 }
 
 /// Spawn a thread-safe [`Future`] on the *test* runtime.
@@ -283,14 +291,12 @@ where
 /// runtime.
 ///
 /// [module documentation]: crate::test
-pub fn spawn_future<Fut>(future: Fut, options: FutureOptions)
+pub fn spawn_future<Fut>(future: Fut, options: FutureOptions) -> JoinHandle<Fut::Output>
 where
-    Fut: Future<Output = ()> + Send + std::marker::Sync + 'static,
+    Fut: Future + Send + std::marker::Sync + 'static,
+    Fut::Output: Send,
 {
-    run_on_test_runtime(move |mut runtime_ref| {
-        runtime_ref.spawn_future(future, options);
-        Ok(())
-    });
+    run_on_test_runtime_wait(move |mut runtime_ref| runtime_ref.spawn_future(future, options))
 }
 
 /// Returned by [`join`] and [`join_many`].
@@ -384,7 +390,14 @@ where
 pub(crate) fn init_local_actor_with_inbox<NA>(
     mut new_actor: NA,
     arg: NA::Argument,
-) -> Result<(NA::Actor, Manager<NA::Message>, ActorRef<NA::Message>), NA::Error>
+) -> Result<
+    (
+        NA::Actor,
+        Manager<Envelope<NA::Message>>,
+        ActorRef<NA::Message>,
+    ),
+    NA::Error,
+>
 where
     NA: NewActor<RuntimeAccess = ThreadLocal>,
 {
@@ -399,7 +412,14 @@ where
 pub(crate) fn init_actor_with_inbox<NA>(
     mut new_actor: NA,
     arg: NA::Argument,
-) -> Result<(NA::Actor, Manager<NA::Message>, ActorRef<NA::Message>), NA::Error>
+) -> Result<
+    (
+        NA::Actor,
+        Manager<Envelope<NA::Message>>,
+        ActorRef<NA::Message>,
+    ),
+    NA::Error,
+>
 where
     NA: NewActor<RuntimeAccess = ThreadSafe>,
 {
@@ -13,6 +13,38 @@
 //!
 //! [`Setup::enable_tracing`]: crate::Setup::enable_tracing
 //!
+//! ## Flight recorder
+//!
+//! Writing every event to a file is too expensive to leave on in production.
+//! For that the runtime can be setup to act as a flight recorder using
+//! [`Setup::enable_flight_recorder`]. In this mode each worker thread keeps
+//! only the most recent events in memory, limited by a [`RecorderLimit`].
+//!
+//! The events in memory are written to a file, using the same format as
+//! [`Setup::enable_tracing`], when:
+//!  * the process receives the signal passed to
+//!    [`Setup::enable_flight_recorder`], if any,
+//!  * an actor panics, or
+//!  * [`RuntimeRef::dump_trace`] is called.
+//!
+//! The first dump is written to the path passed to
+//! [`Setup::enable_flight_recorder`], subsequent dumps insert a number before
+//! the extension(s), e.g. `trace.bin.log`, `trace.1.bin.log`,
+//! `trace.2.bin.log`, etc. Existing files are never overwritten.
+//!
+//! [`Setup::enable_flight_recorder`]: crate::Setup::enable_flight_recorder
+//! [`RuntimeRef::dump_trace`]: crate::RuntimeRef::dump_trace
+//!
+//! ## Filtering
+//!
+//! By default all events are recorded. Using [`Setup::trace_filter`] events
+//! can be filtered by the name of the actor (or future) creating them and by
+//! their description, see [`TraceFilter`]. It also supports sampling events,
+//! only recording a fraction of the events of a stream. The active filter is
+//! recorded in the metadata of the trace.
+//!
+//! [`Setup::trace_filter`]: crate::Setup::trace_filter
+//!
 //! # Creating Trace Events
 //!
 //! The runtime already add its own trace events, e.g. when running actors, but
@@ -42,6 +74,55 @@
 //! [`start_trace`]: Trace::start_trace
 //! [`finish_trace`]: Trace::finish_trace
 //!
+//! ## Message flows
+//!
+//! Events of different actors can be linked together using a [`FlowId`], for
+//! example to follow a request as it's passed between actors (possibly on
+//! different threads). All events with the same flow id, as `flow_id`
+//! attribute, are part of the same flow. The `convert_trace` tool shows flows
+//! as arrows from one event to the next (ordered by start time).
+//!
+//! Messages send using [`ActorRef::send`] and [`ActorRef::try_send`] are
+//! automatically part of a flow, which links the "Sending message" event of the
+//! sending actor to the "Receiving message" event of the receiving actor. For
+//! [RPC] the flow links the "Sending RPC request", "Receiving message",
+//! "Sending RPC response" and "Receiving RPC response" events.
+//!
+//! [`ActorRef::send`]: heph::actor_ref::ActorRef::send
+//! [`ActorRef::try_send`]: heph::actor_ref::ActorRef::try_send
+//! [RPC]: heph::actor_ref::rpc
+//!
+//! To link other events, e.g. the handling of the message, a flow id can be
+//! passed along with the message, as the example below shows.
+//!
+//! ```
+//! use heph::actor;
+//! use heph::actor_ref::ActorRef;
+//! use heph_rt::ThreadLocal;
+//! use heph_rt::trace::{FlowId, Trace};
+//!
+//! struct Request {
+//!     flow_id: FlowId,
+//!     value: usize,
+//! }
+//!
+//! async fn sender(mut ctx: actor::Context<(), ThreadLocal>, actor_ref: ActorRef<Request>) {
+//!     let flow_id = FlowId::new();
+//!     let timing = ctx.start_trace();
+//!     let _ = actor_ref.send(Request { flow_id, value: 123 }).await;
+//!     ctx.finish_trace(timing, "Sending request", &[("flow_id", &flow_id)]);
+//! }
+//!
+//! async fn receiver(mut ctx: actor::Context<Request, ThreadLocal>) {
+//!     while let Ok(request) = ctx.receive_next().await {
+//!         let timing = ctx.start_trace();
+//!         println!("got request: {}", request.value);
+//!         ctx.finish_trace(timing, "Handling request", &[("flow_id", &request.flow_id)]);
+//!     }
+//! }
+//! # drop((sender, receiver));
+//! ```
+//!
 //! ## Notes
 //!
 //! You might notice that the `start_trace` doesn't actually return
@@ -61,22 +142,39 @@
 //! However as it's a binary format it can be hard to read. So tools are
 //! provided to convert into [Chrome's Trace Event Format], which can be viewed
 //! using [Catapult]. [Example 8 "Runtime Tracing"] shows a complete example of
-//! this.
+//! this. The `convert_trace` tool (in the `tools` directory of the repository)
+//! can also convert into Perfetto's protobuf format and the Firefox Profiler's
+//! processed profile format using the `--format` flag. Traces of multiple
+//! processes, e.g. running on different hosts, can be merged into a single
+//! timeline using the `--merge` flag, for which the application name, process
+//! id and host id, as well as the names of the streams (i.e. the threads), are
+//! recorded in the metadata of the trace. For a quick
+//! overview without a trace viewer the `trace_stats` tool prints the count,
+//! duration and latency percentiles per event, as well as the slowest events.
 //!
 //! [Trace Format]: https://github.com/Thomasdezeeuw/heph/blob/master/doc/Trace%20Format.md
 //! [Chrome's Trace Event Format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU/preview
 //! [Catapult]: https://chromium.googlesource.com/catapult/+/refs/heads/master/tracing/README.md
 //! [Example 8 "Runtime Tracing"]: https://github.com/Thomasdezeeuw/heph/blob/master/examples/README.md#8-runtime-tracing
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{self, Write};
-use std::path::Path;
-use std::sync::atomic::{self, AtomicU32};
-use std::sync::Arc;
-use std::time::{Instant, SystemTime};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{self, AtomicU32, AtomicU64};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+use heph::trace::{FlowEvent, Hooks};
+use log::{as_debug, debug, warn};
 
-use log::warn;
+use crate::shared::HostInfo;
+use crate::Signal;
 
 /// Default buffer size, only needs to hold a single trace event.
 const BUF_SIZE: usize = 128;
@@ -170,7 +268,8 @@ pub(crate) struct CoordinatorLog {
 /// Metrics for [`CoordinatorLog`].
 #[derive(Debug)]
 pub(crate) struct CoordinatorMetrics<'l> {
-    pub(crate) file: &'l File,
+    /// `None` when using the flight recorder.
+    pub(crate) file: Option<&'l File>,
     pub(crate) counter: u32,
 }
 
@@ -189,36 +288,157 @@ impl CoordinatorLog {
             .open(path)?;
 
         // Write the metadata for the trace log, currently it only sets the
-        // epoch time.
+        // epoch time and the name of the coordinator's stream.
         let mut buf = Vec::with_capacity(BUF_SIZE);
         write_epoch_metadata(&mut buf, timestamp);
+        write_stream_name_metadata(&mut buf, 0, COORDINATOR_STREAM_NAME);
         write_once(&file, &buf)?;
 
         Ok(CoordinatorLog {
             shared: Arc::new(SharedLog {
-                file,
+                output: Output::File(file),
                 counter: AtomicU32::new(0),
                 epoch,
+                filter: TraceFilter::new(),
+                process: None,
+                sampled: AtomicU64::new(0),
             }),
             buf: Vec::with_capacity(BUF_SIZE),
         })
     }
 
+    /// Create a new trace log that keeps the events in memory, writing them
+    /// to `path` only when dumped.
+    ///
+    /// See the flight recorder section in the [module documentation].
+    ///
+    /// [module documentation]: crate::trace
+    pub(crate) fn recorder(
+        path: PathBuf,
+        limit: RecorderLimit,
+        dump_signal: Option<Signal>,
+    ) -> CoordinatorLog {
+        let timestamp = SystemTime::now();
+        let epoch = Instant::now();
+        let mut stream_names = Vec::new();
+        write_stream_name_metadata(&mut stream_names, 0, COORDINATOR_STREAM_NAME);
+        CoordinatorLog {
+            shared: Arc::new(SharedLog {
+                output: Output::Recorder(Box::new(Recorder {
+                    path,
+                    limit,
+                    dump_signal,
+                    timestamp,
+                    coordinator: Mutex::new(EventBuffer::new()),
+                    streams: Mutex::new(Vec::new()),
+                    stream_names: Mutex::new(stream_names),
+                    dumps: Mutex::new(0),
+                })),
+                counter: AtomicU32::new(0),
+                epoch,
+                filter: TraceFilter::new(),
+                process: None,
+                sampled: AtomicU64::new(0),
+            }),
+            buf: Vec::with_capacity(BUF_SIZE),
+        }
+    }
+
+    /// Set the `filter` used for all events.
+    ///
+    /// # Panics
+    ///
+    /// This must be called before any streams are created.
+    pub(crate) fn set_filter(&mut self, filter: TraceFilter) -> io::Result<()> {
+        let shared = Arc::get_mut(&mut self.shared).expect("trace log already shared");
+        if let Output::File(file) = &shared.output {
+            let mut buf = Vec::with_capacity(BUF_SIZE);
+            write_filter_metadata(&mut buf, &filter);
+            write_once(file, &buf)?;
+        }
+        shared.filter = filter;
+        Ok(())
+    }
+
+    /// Set the information about the process writing the trace, using the
+    /// application name and host id from `host`.
+    ///
+    /// # Panics
+    ///
+    /// This must be called before any streams are created.
+    pub(crate) fn set_process_info(&mut self, host: &HostInfo) -> io::Result<()> {
+        let shared = Arc::get_mut(&mut self.shared).expect("trace log already shared");
+        let process = ProcessInfo {
+            host_id: host.id.to_string().into_boxed_str(),
+            app_name: host.app_name.clone(),
+            process_id: process::id(),
+        };
+        if let Output::File(file) = &shared.output {
+            let mut buf = Vec::with_capacity(BUF_SIZE);
+            write_process_metadata(&mut buf, &process);
+            write_once(file, &buf)?;
+        }
+        shared.process = Some(process);
+        Ok(())
+    }
+
     /// Gather metrics for the coordinator log.
     pub(crate) fn metrics<'l>(&'l self) -> CoordinatorMetrics<'l> {
         CoordinatorMetrics {
-            file: &self.shared.file,
+            file: match &self.shared.output {
+                Output::File(file) => Some(file),
+                Output::Recorder(..) => None,
+            },
             counter: self.shared.counter.load(atomic::Ordering::Relaxed),
         }
     }
 
     /// Create a new stream with `stream_id`, writing to the same file.
-    pub(crate) fn new_stream(&self, stream_id: u32) -> Log {
+    ///
+    /// The `name` of the stream, e.g. the name of the thread, is written in the
+    /// metadata of the trace.
+    pub(crate) fn new_stream(&self, stream_id: u32, name: &str) -> Log {
+        let buffer = match &self.shared.output {
+            Output::File(file) => {
+                let mut buf = Vec::with_capacity(BUF_SIZE);
+                write_stream_name_metadata(&mut buf, stream_id, name);
+                if let Err(err) = write_once(file, &buf) {
+                    warn!("error writing trace metadata: {}", err);
+                }
+                None
+            }
+            Output::Recorder(recorder) => {
+                let mut stream_names = recorder.stream_names.lock().unwrap();
+                write_stream_name_metadata(&mut stream_names, stream_id, name);
+                drop(stream_names);
+                let buffer = Arc::new(Mutex::new(EventBuffer::new()));
+                let mut streams = recorder.streams.lock().unwrap();
+                // Remove the buffers of the streams that are dropped.
+                streams.retain(|buffer| buffer.strong_count() != 0);
+                streams.push(Arc::downgrade(&buffer));
+                Some(buffer)
+            }
+        };
         Log {
             shared: self.shared.clone(),
             stream_id,
             stream_counter: 0,
             buf: Vec::with_capacity(BUF_SIZE),
+            buffer,
+            sample_rate: self.shared.filter.get_sample_rate(stream_id),
+            sampled: 0,
+        }
+    }
+
+    /// Handle a process `signal`, dumping the trace if it's the signal
+    /// configured for the flight recorder.
+    pub(crate) fn handle_signal(&self, signal: Signal) {
+        if let Output::Recorder(recorder) = &self.shared.output {
+            if recorder.dump_signal == Some(signal) {
+                if let Err(err) = self.shared.dump() {
+                    warn!("error dumping trace: {}", err);
+                }
+            }
         }
     }
 
@@ -237,17 +457,246 @@ impl CoordinatorLog {
 /// Data shared between [`CoordinatorLog`] and mulitple [`Log`]s.
 #[derive(Debug)]
 pub(crate) struct SharedLog {
-    /// File to write the trace to.
-    ///
-    /// This file is shared between one or more threads, thus writes to it
-    /// should be atomic, i.e. no partial writes. Most OSs support atomic writes
-    /// up to a page size (usually 4KB).
-    file: File,
+    /// Where to write the events to.
+    output: Output,
     /// Counter for the stream with id 0, which is owned by the coordinator, but
     /// also used by the worker threads for thread-safe actors.
     counter: AtomicU32,
     /// Time which we use as zero, or epoch, time for all events.
     epoch: Instant,
+    /// Filter applied to all events.
+    filter: TraceFilter,
+    /// Information about the process writing the trace, if set.
+    process: Option<ProcessInfo>,
+    /// Number of events considered for sampling in the stream with id 0.
+    sampled: AtomicU64,
+}
+
+/// Information about the process writing the trace, used to label the trace
+/// when merging traces of multiple processes.
+#[derive(Debug)]
+struct ProcessInfo {
+    /// Id of the host, see [`HostInfo::id`].
+    host_id: Box<str>,
+    /// Name of the application.
+    app_name: Box<str>,
+    /// Id of the OS process.
+    process_id: u32,
+}
+
+/// Output of the trace events.
+#[derive(Debug)]
+enum Output {
+    /// File to write the trace to.
+    ///
+    /// This file is shared between one or more threads, thus writes to it
+    /// should be atomic, i.e. no partial writes. Most OSs support atomic writes
+    /// up to a page size (usually 4KB).
+    File(File),
+    /// Keep the events in memory, see [`Recorder`].
+    Recorder(Box<Recorder>),
+}
+
+/// Flight recorder, keeping the most recent events in memory.
+#[derive(Debug)]
+struct Recorder {
+    /// Path to write the first dump to.
+    path: PathBuf,
+    /// Limit on the events kept in memory, per stream.
+    limit: RecorderLimit,
+    /// Process signal that triggers a dump.
+    dump_signal: Option<Signal>,
+    /// Wall-clock time of the `epoch`, written in the metadata of each dump.
+    timestamp: SystemTime,
+    /// Events for the stream with id 0, see [`SharedLog::counter`].
+    coordinator: Mutex<EventBuffer>,
+    /// Events for all other streams, one per [`Log`].
+    ///
+    /// The buffer is owned by the `Log`, once it's dropped (e.g. when a sync
+    /// actor stops) the buffer is removed.
+    streams: Mutex<Vec<Weak<Mutex<EventBuffer>>>>,
+    /// The `stream_name` metadata packets of all streams, written in each
+    /// dump.
+    stream_names: Mutex<Vec<u8>>,
+    /// Number of dumps made, also ensures only a single dump is made at a
+    /// time.
+    dumps: Mutex<usize>,
+}
+
+/// Limit on the number of events kept in memory by the flight recorder.
+///
+/// The limit applies to each worker thread separately, see
+/// [`Setup::enable_flight_recorder`].
+///
+/// [`Setup::enable_flight_recorder`]: crate::Setup::enable_flight_recorder
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RecorderLimit {
+    /// Keep the last `N` events.
+    Events(usize),
+    /// Keep the events that ended in the last `duration`.
+    ///
+    /// # Notes
+    ///
+    /// This doesn't limit the amount of memory used, under heavy load a lot of
+    /// events can be created in a short amount of time.
+    Duration(Duration),
+}
+
+/// Buffer of formatted events, dropping old events based on a
+/// [`RecorderLimit`].
+#[derive(Debug)]
+struct EventBuffer {
+    /// End time and formatted event, oldest first.
+    events: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl EventBuffer {
+    fn new() -> EventBuffer {
+        EventBuffer {
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Add the formatted event in `buf`, which ended at `end`.
+    fn push(&mut self, limit: RecorderLimit, end: Instant, buf: &[u8]) {
+        let mut event = match limit {
+            RecorderLimit::Events(0) => return,
+            RecorderLimit::Events(max) if self.events.len() >= max => {
+                // Reuse the allocation of the oldest event.
+                let mut event = self.events.pop_front().unwrap().1;
+                event.clear();
+                event
+            }
+            RecorderLimit::Events(..) => Vec::with_capacity(buf.len()),
+            RecorderLimit::Duration(duration) => {
+                self.remove_expired(duration, end);
+                Vec::with_capacity(buf.len())
+            }
+        };
+        event.extend_from_slice(buf);
+        self.events.push_back((end, event));
+    }
+
+    /// Remove all events that ended more than `duration` before `now`.
+    fn remove_expired(&mut self, duration: Duration, now: Instant) {
+        while let Some((end, _)) = self.events.front() {
+            if now.saturating_duration_since(*end) <= duration {
+                break;
+            }
+            let _ = self.events.pop_front();
+        }
+    }
+
+    /// Write all events to `output`.
+    fn write_to(&mut self, limit: RecorderLimit, output: &mut Vec<u8>) {
+        if let RecorderLimit::Duration(duration) = limit {
+            self.remove_expired(duration, Instant::now());
+        }
+        for (_, event) in &self.events {
+            output.extend_from_slice(event);
+        }
+    }
+}
+
+impl SharedLog {
+    /// Gather metrics for the shared log.
+    pub(crate) fn metrics(&self) -> Metrics {
+        let counter = self.counter.load(atomic::Ordering::Relaxed);
+        Metrics {
+            counter,
+            stream_counter: counter,
+        }
+    }
+
+    /// Returns `true` if the next event of the stream with id 0 should be
+    /// recorded based on the sample rate.
+    fn sample(&self) -> bool {
+        let rate = self.filter.get_sample_rate(COORDINATOR_STREAM_ID);
+        rate >= 1.0 || sample(rate, self.sampled.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+
+    /// Write the formatted event in `buf`, which ended at `end`.
+    ///
+    /// When using the flight recorder the event is added to `buffer`, or the
+    /// buffer for stream 0 if `buffer` is `None`.
+    fn write(
+        &self,
+        buffer: Option<&Mutex<EventBuffer>>,
+        end: Instant,
+        buf: &[u8],
+    ) -> io::Result<()> {
+        match &self.output {
+            Output::File(file) => write_once(file, buf),
+            Output::Recorder(recorder) => {
+                let buffer = buffer.unwrap_or(&recorder.coordinator);
+                buffer.lock().unwrap().push(recorder.limit, end, buf);
+                Ok(())
+            }
+        }
+    }
+
+    /// Dump all events kept in memory by the flight recorder to a file.
+    ///
+    /// Does nothing if the flight recorder isn't used.
+    pub(crate) fn dump(&self) -> io::Result<()> {
+        let recorder = match &self.output {
+            Output::File(..) => return Ok(()),
+            Output::Recorder(recorder) => recorder,
+        };
+
+        let mut dumps = recorder.dumps.lock().unwrap();
+        let (path, mut file) = loop {
+            let path = dump_path(&recorder.path, *dumps);
+            *dumps += 1;
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                // Don't overwrite dumps of previous runs.
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        };
+
+        let mut buf = Vec::with_capacity(4096);
+        write_epoch_metadata(&mut buf, recorder.timestamp);
+        write_filter_metadata(&mut buf, &self.filter);
+        if let Some(process) = &self.process {
+            write_process_metadata(&mut buf, process);
+        }
+        buf.extend_from_slice(&recorder.stream_names.lock().unwrap());
+        recorder
+            .coordinator
+            .lock()
+            .unwrap()
+            .write_to(recorder.limit, &mut buf);
+        let mut streams = recorder.streams.lock().unwrap();
+        streams.retain(|buffer| match buffer.upgrade() {
+            Some(buffer) => {
+                buffer.lock().unwrap().write_to(recorder.limit, &mut buf);
+                true
+            }
+            None => false,
+        });
+        drop(streams);
+        file.write_all(&buf)?;
+        debug!(path = as_debug!(path); "dumped trace");
+        Ok(())
+    }
+}
+
+/// Returns the path of the `n`th dump, inserting `n` before the extension(s)
+/// of `path`.
+fn dump_path(path: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        return path.to_owned();
+    }
+    let file_name = path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let file_name = match file_name.split_once('.') {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, n, extension),
+        None => format!("{}.{}", file_name, n),
+    };
+    path.with_file_name(file_name)
 }
 
 /// Trace log.
@@ -262,12 +711,21 @@ pub(crate) struct Log {
     stream_counter: u32,
     /// Used to buffer writes for a single event.
     buf: Vec<u8>,
+    /// Events kept in memory, only used by the flight recorder.
+    buffer: Option<Arc<Mutex<EventBuffer>>>,
+    /// Sample rate of this stream, see [`TraceFilter::stream_sample_rate`].
+    sample_rate: f64,
+    /// Number of events considered for sampling.
+    sampled: u64,
 }
 
 /// Metrics for [`Log`].
 #[derive(Debug)]
 pub(crate) struct Metrics {
+    /// Counter of the stream with id 0.
     pub(crate) counter: u32,
+    /// Counter of the stream of the log.
+    pub(crate) stream_counter: u32,
 }
 
 impl Log {
@@ -282,8 +740,15 @@ impl Log {
     pub(crate) fn metrics(&self) -> Metrics {
         Metrics {
             counter: self.shared.counter.load(atomic::Ordering::Relaxed),
+            stream_counter: self.stream_counter,
         }
     }
+
+    /// Dump the events kept in memory by the flight recorder, see
+    /// [`SharedLog::dump`].
+    pub(crate) fn dump(&self) -> io::Result<()> {
+        self.shared.dump()
+    }
 }
 
 /// Write an epoch metadata packet to `buf`.
@@ -312,6 +777,73 @@ fn write_epoch_metadata(buf: &mut Vec<u8>, time: SystemTime) {
     buf.extend_from_slice(&nanos_since_unix.to_be_bytes());
 }
 
+/// Write the metadata packets for `filter` to `buf`.
+///
+/// Writes the `filter` option, with a human readable version of the include
+/// and exclude filters, and the `sample_rate` and `stream_sample_rate` options.
+/// Nothing is written for the default filter.
+fn write_filter_metadata(buf: &mut Vec<u8>, filter: &TraceFilter) {
+    use private::AttributeValue;
+
+    if filter.has_filters() {
+        let mut value = Vec::new();
+        filter.to_string().as_str().write_attribute(&mut value);
+        write_metadata(buf, "filter", &value);
+    }
+    if filter.sample_rate < 1.0 {
+        write_metadata(buf, "sample_rate", &filter.sample_rate.to_be_bytes());
+    }
+    for (stream_id, rate) in &filter.stream_sample_rates {
+        let mut value = Vec::with_capacity(12);
+        value.extend_from_slice(&stream_id.to_be_bytes());
+        value.extend_from_slice(&rate.to_be_bytes());
+        write_metadata(buf, "stream_sample_rate", &value);
+    }
+}
+
+/// Write the `host_id`, `app_name` and `process_id` metadata packets to `buf`.
+fn write_process_metadata(buf: &mut Vec<u8>, process: &ProcessInfo) {
+    use private::AttributeValue;
+
+    let mut value = Vec::new();
+    process.host_id.write_attribute(&mut value);
+    write_metadata(buf, "host_id", &value);
+    value.clear();
+    process.app_name.write_attribute(&mut value);
+    write_metadata(buf, "app_name", &value);
+    write_metadata(buf, "process_id", &process.process_id.to_be_bytes());
+}
+
+/// Name of the stream with id 0, owned by the coordinator.
+const COORDINATOR_STREAM_NAME: &str = "Coordinator";
+
+/// Write the `stream_name` metadata packet for the stream with `stream_id` to
+/// `buf`.
+fn write_stream_name_metadata(buf: &mut Vec<u8>, stream_id: u32, name: &str) {
+    use private::AttributeValue;
+
+    let mut value = Vec::with_capacity(4 + 2 + name.len());
+    value.extend_from_slice(&stream_id.to_be_bytes());
+    name.write_attribute(&mut value);
+    write_metadata(buf, "stream_name", &value);
+}
+
+/// Write a metadata packet setting `option` to `value` to `buf`.
+fn write_metadata(buf: &mut Vec<u8>, option: &str, value: &[u8]) {
+    use private::AttributeValue;
+    #[allow(clippy::unreadable_literal)]
+    const MAGIC: u32 = 0x75D11D4D;
+
+    let start = buf.len();
+    buf.extend_from_slice(&MAGIC.to_be_bytes());
+    buf.extend_from_slice(&0_u32.to_be_bytes()); // Written later.
+    option.write_attribute(buf);
+    buf.extend_from_slice(value);
+    #[allow(clippy::cast_possible_truncation)]
+    let packet_size = (buf.len() - start) as u32;
+    buf[start + 4..start + 8].copy_from_slice(&packet_size.to_be_bytes());
+}
+
 /// Write the entire `buf`fer into the `output` or return an error.
 fn write_once<W>(mut output: W, buf: &[u8]) -> io::Result<()>
 where
@@ -338,25 +870,152 @@ impl Clone for Log {
             stream_id: self.stream_id,
             stream_counter: 0,
             buf: Vec::with_capacity(BUF_SIZE),
+            buffer: self.buffer.clone(),
+            sample_rate: self.sample_rate,
+            sampled: 0,
         }
     }
 }
 
 /// Start timing an event (using [`EventTiming`]) if we're tracing, i.e. if
-/// `log` is `Some`.
+/// `log` is `Some`, and the events of the currently running process are not
+/// filtered out (see [`enter_process`]).
 pub(crate) fn start<L>(log: &Option<L>) -> Option<EventTiming>
 where
     L: TraceLog,
 {
-    if log.is_some() {
+    if log.is_some() && !EXCLUDED_PROCESS.with(Cell::get) {
         Some(EventTiming::start())
     } else {
         None
     }
 }
 
+thread_local! {
+    /// Whether or not the events of the process currently running on this
+    /// thread are excluded by the [`TraceFilter`].
+    static EXCLUDED_PROCESS: Cell<bool> = Cell::new(false);
+}
+
+/// Mark the process with `name` as running on this thread, until
+/// [`exit_process`] is called.
+///
+/// If the process is excluded by the filter of `log` [`start`] will return
+/// `None`, which means no events are recorded until [`exit_process`] is
+/// called.
+pub(crate) fn enter_process<L>(log: &Option<L>, name: &str)
+where
+    L: TraceLog,
+{
+    if let Some(log) = log {
+        let excluded = !log.filter().allows_actor(name);
+        EXCLUDED_PROCESS.with(|cell| cell.set(excluded));
+    }
+}
+
+/// Mark the process started with [`enter_process`] as stopped.
+pub(crate) fn exit_process() {
+    EXCLUDED_PROCESS.with(|cell| cell.set(false));
+}
+
+/// Trace log of a thread, shared with [`set_flow_log`].
+pub(crate) type ThreadLog = Rc<RefCell<Option<Log>>>;
+
+thread_local! {
+    /// Log used to record the message flows on this thread, see
+    /// [`set_flow_log`].
+    static FLOW_LOG: RefCell<Option<ThreadLog>> = RefCell::new(None);
+}
+
+/// Record the message flows of the actors running on this thread in `log`,
+/// the trace log of the thread itself.
+///
+/// The events are written to `log` itself, not a clone of it, so they're part
+/// of the same stream and use the same stream counter as the other events of
+/// the thread. This sets the hooks in `heph::trace` if tracing is enabled.
+pub(crate) fn set_flow_log(log: Option<ThreadLog>) {
+    let log = log.filter(|log| log.borrow().is_some());
+    let hooks = log.as_ref().map(|_| Hooks {
+        start_flow,
+        record: record_flow,
+    });
+    FLOW_LOG.with(|flow_log| *flow_log.borrow_mut() = log);
+    heph::trace::set_hooks(hooks);
+}
+
+/// Start a new flow with `event`, see [`Hooks::start_flow`].
+fn start_flow(event: FlowEvent) -> Option<NonZeroU64> {
+    FLOW_LOG
+        .try_with(|log| {
+            let log = log.try_borrow().ok()?;
+            let mut log = log.as_ref()?.try_borrow_mut().ok()?;
+            let timing = start(&*log)?;
+            let flow_id = FlowId::new();
+            let description = flow_description(event);
+            finish_rt(
+                log.as_mut(),
+                Some(timing),
+                description,
+                &[("flow_id", &flow_id)],
+            );
+            Some(flow_id.0)
+        })
+        .ok()
+        .flatten()
+}
+
+/// Record `event` as part of the flow with `flow_id`, see [`Hooks::record`].
+fn record_flow(event: FlowEvent, flow_id: NonZeroU64) {
+    let _ = FLOW_LOG.try_with(|log| {
+        let log = match log.try_borrow() {
+            Ok(log) => log,
+            Err(_) => return,
+        };
+        let mut log = match log.as_ref().map(|log| log.try_borrow_mut()) {
+            Some(Ok(log)) => log,
+            _ => return,
+        };
+        let timing = start(&*log);
+        let description = flow_description(event);
+        finish_rt(
+            log.as_mut(),
+            timing,
+            description,
+            &[("flow_id", &FlowId(flow_id))],
+        );
+    });
+}
+
+/// Returns the description of the trace event for `event`.
+const fn flow_description(event: FlowEvent) -> &'static str {
+    match event {
+        FlowEvent::SendMessage => "Sending message",
+        FlowEvent::ReceiveMessage => "Receiving message",
+        FlowEvent::SendRpcRequest => "Sending RPC request",
+        FlowEvent::SendRpcResponse => "Sending RPC response",
+        FlowEvent::ReceiveRpcResponse => "Receiving RPC response",
+    }
+}
+
+/// Returns `true` if the `n`th (zero indexed) event should be recorded when
+/// sampling at `rate`.
+///
+/// This spreads the recorded events evenly, e.g. with a rate of 0.25 every
+/// fourth event is recorded.
+#[allow(clippy::cast_precision_loss)]
+fn sample(rate: f64, n: u64) -> bool {
+    ((n + 1) as f64 * rate).floor() > (n as f64 * rate).floor()
+}
+
 /// Trait to call [`finish`] on both [`CoordinatorLog`] and [`Log`].
 pub(crate) trait TraceLog {
+    /// Returns the filter used by the log.
+    fn filter(&self) -> &TraceFilter;
+
+    /// Returns `true` if the next event should be recorded based on the sample
+    /// rate of the stream.
+    fn sample(&mut self) -> bool;
+
     /// Append a new `event` to the log.
     fn append(&mut self, substream_id: u64, event: &Event<'_>) -> io::Result<()>;
 }
@@ -365,12 +1024,28 @@ impl<L> TraceLog for &'_ mut L
 where
     L: TraceLog,
 {
+    fn filter(&self) -> &TraceFilter {
+        L::filter(self)
+    }
+
+    fn sample(&mut self) -> bool {
+        L::sample(self)
+    }
+
     fn append(&mut self, substream_id: u64, event: &Event<'_>) -> io::Result<()> {
         L::append(self, substream_id, event)
     }
 }
 
 impl TraceLog for CoordinatorLog {
+    fn filter(&self) -> &TraceFilter {
+        &self.shared.filter
+    }
+
+    fn sample(&mut self) -> bool {
+        self.shared.sample()
+    }
+
     fn append(&mut self, substream_id: u64, event: &Event<'_>) -> io::Result<()> {
         let stream_count = self.next_stream_count();
         format_event(
@@ -382,11 +1057,21 @@ impl TraceLog for CoordinatorLog {
             event,
         );
         // TODO: buffer events? If buf.len() + packet_size >= 4k -> write first?
-        write_once(&self.shared.file, &self.buf)
+        self.shared.write(None, event.end, &self.buf)
     }
 }
 
 impl TraceLog for Log {
+    fn filter(&self) -> &TraceFilter {
+        &self.shared.filter
+    }
+
+    fn sample(&mut self) -> bool {
+        let n = self.sampled;
+        self.sampled = self.sampled.wrapping_add(1);
+        self.sample_rate >= 1.0 || sample(self.sample_rate, n)
+    }
+
     fn append(&mut self, substream_id: u64, event: &Event<'_>) -> io::Result<()> {
         let stream_count = self.next_stream_count();
         format_event(
@@ -398,7 +1083,8 @@ impl TraceLog for Log {
             event,
         );
         // TODO: buffer events? If buf.len() + packet_size >= 4k -> write first?
-        write_once(&self.shared.file, &self.buf)
+        self.shared
+            .write(self.buffer.as_deref(), event.end, &self.buf)
     }
 }
 
@@ -409,6 +1095,14 @@ impl TraceLog for Log {
 /// This uses thread-local storage, prefer to use the [`CoordinatorLog`] or
 /// [`Log`] implementations.
 impl<'a> TraceLog for &'a SharedLog {
+    fn filter(&self) -> &TraceFilter {
+        &self.filter
+    }
+
+    fn sample(&mut self) -> bool {
+        SharedLog::sample(self)
+    }
+
     fn append(&mut self, substream_id: u64, event: &Event<'_>) -> io::Result<()> {
         thread_local! {
             static BUF: RefCell<Vec<u8>> = RefCell::new(Vec::new());
@@ -427,7 +1121,7 @@ impl<'a> TraceLog for &'a SharedLog {
                 event,
             );
             // TODO: buffer events? If buf.len() + packet_size >= 4k -> write first?
-            write_once(&self.file, &buf)
+            self.write(None, event.end, &buf)
         })
     }
 }
@@ -501,6 +1195,10 @@ pub(crate) fn finish<L>(
         "description for trace event too long"
     );
     if let (Some(mut log), Some(timing)) = (log, timing) {
+        // Filter before formatting the event.
+        if !log.filter().allows_description(description) || !log.sample() {
+            return;
+        }
         let event = timing.finish(description, attributes);
         if let Err(err) = log.append(substream_id, &event) {
             warn!("error writing trace data: {}", err);
@@ -520,6 +1218,230 @@ pub(crate) fn finish_rt<L>(
     finish(log, timing, RT_SUBSTREAM_ID, description, attributes)
 }
 
+/// Identifier used to link trace events into a flow, e.g. the sending and
+/// handling of a message.
+///
+/// The flow id should be added as `flow_id` attribute to all events in the
+/// flow. See the message flows section in the [module documentation].
+///
+/// [module documentation]: crate::trace
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FlowId(NonZeroU64);
+
+impl FlowId {
+    /// Create a new flow id, unique within the process.
+    #[allow(clippy::new_without_default)] // Default would suggest a fixed value.
+    pub fn new() -> FlowId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        loop {
+            let id = NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed);
+            // The counter starts at 1, but wraps around to 0 after 2^64 flows,
+            // in which case we skip it.
+            if let Some(id) = NonZeroU64::new(id) {
+                return FlowId(id);
+            }
+        }
+    }
+
+    /// Returns the flow id as integer, as written in the trace.
+    pub const fn get(self) -> u64 {
+        self.0.get()
+    }
+}
+
+/// Filter for trace events.
+///
+/// Events can be filtered by the name of the actor that creates them, as
+/// returned by [`NewActor::name`], and by their description. If any include
+/// filter is set only matching actors or descriptions are recorded. Exclude
+/// filters are applied after the include filters. Names and descriptions must
+/// match exactly. Note that actor filters also apply to futures, using the
+/// name of the future's type, and to the events the runtime creates while
+/// running the actor, e.g. "Running thread-local process".
+///
+/// In addition to the filters events can be sampled, only recording a
+/// fraction of the events of a stream. Streams are described in the Trace
+/// Format design document, stream 0 is used by the coordinator and thread-safe
+/// actors, streams 1 to N by the worker threads and the streams after that by
+/// the synchronous actors (in order of spawning).
+///
+/// Use [`Setup::trace_filter`] to set the filter.
+///
+/// [`NewActor::name`]: heph::actor::NewActor::name
+/// [`Setup::trace_filter`]: crate::Setup::trace_filter
+///
+/// # Examples
+///
+/// Only record the events of the `my_actor` actor, except the events with the
+/// description "Receiving message", and only every other event of the first
+/// worker thread.
+///
+/// ```
+/// use heph_rt::trace::TraceFilter;
+///
+/// let filter = TraceFilter::new()
+///     .include_actor("my_actor")
+///     .exclude_description("Receiving message")
+///     .stream_sample_rate(1, 0.5);
+/// # drop(filter);
+/// ```
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct TraceFilter {
+    include_actors: Vec<String>,
+    exclude_actors: Vec<String>,
+    include_descriptions: Vec<String>,
+    exclude_descriptions: Vec<String>,
+    /// Sample rate for all streams not in `stream_sample_rates`.
+    sample_rate: f64,
+    stream_sample_rates: Vec<(u32, f64)>,
+}
+
+impl TraceFilter {
+    /// Create a new filter that records all events.
+    pub const fn new() -> TraceFilter {
+        TraceFilter {
+            include_actors: Vec::new(),
+            exclude_actors: Vec::new(),
+            include_descriptions: Vec::new(),
+            exclude_descriptions: Vec::new(),
+            sample_rate: 1.0,
+            stream_sample_rates: Vec::new(),
+        }
+    }
+
+    /// Only record the events of the actor with `name`.
+    ///
+    /// Can be called multiple times to include multiple actors.
+    pub fn include_actor<N: Into<String>>(mut self, name: N) -> Self {
+        self.include_actors.push(name.into());
+        self
+    }
+
+    /// Don't record the events of the actor with `name`.
+    pub fn exclude_actor<N: Into<String>>(mut self, name: N) -> Self {
+        self.exclude_actors.push(name.into());
+        self
+    }
+
+    /// Only record events with `description`.
+    ///
+    /// Can be called multiple times to include multiple descriptions.
+    pub fn include_description<D: Into<String>>(mut self, description: D) -> Self {
+        self.include_descriptions.push(description.into());
+        self
+    }
+
+    /// Don't record events with `description`.
+    pub fn exclude_description<D: Into<String>>(mut self, description: D) -> Self {
+        self.exclude_descriptions.push(description.into());
+        self
+    }
+
+    /// Set the sample `rate` for all streams, a number between 0 (record no
+    /// events) and 1 (record all events, the default).
+    ///
+    /// Streams with a sample rate set using
+    /// [`TraceFilter::stream_sample_rate`] are not affected.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = clamp_rate(rate);
+        self
+    }
+
+    /// Set the sample `rate` for the stream with `stream_id`, see
+    /// [`TraceFilter::sample_rate`].
+    pub fn stream_sample_rate(mut self, stream_id: u32, rate: f64) -> Self {
+        let rate = clamp_rate(rate);
+        match self
+            .stream_sample_rates
+            .iter_mut()
+            .find(|(id, _)| *id == stream_id)
+        {
+            Some((_, r)) => *r = rate,
+            None => self.stream_sample_rates.push((stream_id, rate)),
+        }
+        self
+    }
+
+    /// Returns the sample rate for the stream with `stream_id`.
+    fn get_sample_rate(&self, stream_id: u32) -> f64 {
+        self.stream_sample_rates
+            .iter()
+            .find(|(id, _)| *id == stream_id)
+            .map_or(self.sample_rate, |(_, rate)| *rate)
+    }
+
+    /// Returns `true` if include or exclude filters are set.
+    fn has_filters(&self) -> bool {
+        !(self.include_actors.is_empty()
+            && self.exclude_actors.is_empty()
+            && self.include_descriptions.is_empty()
+            && self.exclude_descriptions.is_empty())
+    }
+
+    /// Returns `true` if the events of the actor with `name` should be
+    /// recorded.
+    fn allows_actor(&self, name: &str) -> bool {
+        allows(&self.include_actors, &self.exclude_actors, name)
+    }
+
+    /// Returns `true` if events with `description` should be recorded.
+    fn allows_description(&self, description: &str) -> bool {
+        allows(
+            &self.include_descriptions,
+            &self.exclude_descriptions,
+            description,
+        )
+    }
+}
+
+impl Default for TraceFilter {
+    fn default() -> TraceFilter {
+        TraceFilter::new()
+    }
+}
+
+/// Formats the include and exclude filters, e.g.
+/// `include_actors=[my_actor] exclude_descriptions=[Receiving message]`.
+impl fmt::Display for TraceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filters = [
+            ("include_actors", &self.include_actors),
+            ("exclude_actors", &self.exclude_actors),
+            ("include_descriptions", &self.include_descriptions),
+            ("exclude_descriptions", &self.exclude_descriptions),
+        ];
+        let mut first = true;
+        for (name, values) in filters {
+            if values.is_empty() {
+                continue;
+            }
+            if !first {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}=[{}]", name, values.join(", "))?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if `value` is in `include` (or `include` is empty) and not
+/// in `exclude`.
+fn allows(include: &[String], exclude: &[String], value: &str) -> bool {
+    (include.is_empty() || include.iter().any(|v| v == value))
+        && !exclude.iter().any(|v| v == value)
+}
+
+/// Clamps the sample `rate` between 0 and 1.
+fn clamp_rate(rate: f64) -> f64 {
+    if rate.is_nan() {
+        1.0
+    } else {
+        rate.clamp(0.0, 1.0)
+    }
+}
+
 /// Timing an event.
 #[derive(Clone, Debug)]
 #[must_use = "tracing events must be finished, otherwise they aren't recorded"]
@@ -569,6 +1491,7 @@ pub(crate) struct Event<'e> {
 /// * Floating point numbers, i.e. `f32` and `f64`.
 /// * Strings, i.e. `&str` and `String`.
 /// * Array or slice of one of the types above.
+/// * [`FlowId`], written as unsigned integer.
 pub trait AttributeValue: private::AttributeValue {}
 
 impl<'a, T> AttributeValue for &'a T where T: AttributeValue + ?Sized {}
@@ -733,4 +1656,16 @@ mod private {
     }
 
     impl<T, const N: usize> super::AttributeValue for [T; N] where T: super::AttributeValue + Default {}
+
+    impl AttributeValue for super::FlowId {
+        fn type_byte(&self) -> u8 {
+            UNSIGNED_INTEGER_BYTE
+        }
+
+        fn write_attribute(&self, buf: &mut Vec<u8>) {
+            buf.extend_from_slice(&self.get().to_be_bytes());
+        }
+    }
+
+    impl super::AttributeValue for super::FlowId {}
 }
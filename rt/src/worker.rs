@@ -17,6 +17,9 @@
 
 use std::cell::RefMut;
 use std::num::NonZeroUsize;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::os::unix::thread::JoinHandleExt;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::rc::Rc;
 use std::sync::Arc;
@@ -24,11 +27,19 @@ use std::time::{Duration, Instant};
 use std::{fmt, io, thread};
 
 use crossbeam_channel::{self, Receiver};
-use heph::actor_ref::{Delivery, SendError};
+use heph::actor_ref::SendError;
+#[cfg(target_os = "linux")]
+use log::warn;
 use log::{as_debug, debug, info, trace};
+#[cfg(target_os = "linux")]
+use mio::unix::SourceFd;
+#[cfg(target_os = "linux")]
+use mio::Interest;
 use mio::{Events, Poll, Registry, Token};
 
 use crate::error::StringError;
+#[cfg(target_os = "linux")]
+use crate::io_uring::{self, Ring};
 use crate::local::waker::{self, WakerId};
 use crate::local::RuntimeInternals;
 use crate::process::{ProcessId, ProcessResult};
@@ -50,6 +61,10 @@ const COMMS: Token = Token(usize::MAX - 1);
 /// Token used to indicate the shared [`Poll`] (in [`shared::RuntimeInternals`])
 /// has events.
 const SHARED_POLL: Token = Token(usize::MAX - 2);
+/// Token used to indicate operations submitted to the io_uring [`Ring`] have
+/// completed.
+#[cfg(target_os = "linux")]
+const RING: Token = Token(usize::MAX - 3);
 
 /// Setup a new worker thread.
 ///
@@ -90,7 +105,8 @@ impl WorkerSetup {
     pub(super) fn start(
         self,
         shared_internals: Arc<shared::RuntimeInternals>,
-        auto_cpu_affinity: bool,
+        cpu: Option<usize>,
+        use_io_uring: bool,
         trace_log: Option<trace::Log>,
     ) -> io::Result<Handle> {
         rt::channel::new().and_then(move |(sender, receiver)| {
@@ -98,14 +114,9 @@ impl WorkerSetup {
             thread::Builder::new()
                 .name(format!("Worker {}", id))
                 .spawn(move || {
-                    let worker = Worker::setup(
-                        self,
-                        receiver,
-                        shared_internals,
-                        auto_cpu_affinity,
-                        trace_log,
-                    )
-                    .map_err(rt::Error::worker)?;
+                    #[rustfmt::skip]
+                    let worker = Worker::setup(self, receiver, shared_internals, cpu, use_io_uring, trace_log)
+                        .map_err(rt::Error::worker)?;
                     worker.run().map_err(rt::Error::worker)
                 })
                 .map(|handle| Handle {
@@ -155,6 +166,28 @@ impl Handle {
         self.channel.try_send(Control::Signal(signal))
     }
 
+    /// Returns the pthread id of the worker thread.
+    pub(super) fn pthread_id(&self) -> libc::pthread_t {
+        self.handle.as_pthread_t()
+    }
+
+    /// Send the worker thread the OS `signal`, interrupting whatever it's
+    /// doing, e.g. running a process.
+    pub(super) fn send_os_signal(&self, signal: libc::c_int) -> io::Result<()> {
+        // SAFETY: the thread is only joined in `Handle::join`, which consumes
+        // the handle, so the thread id is still valid (even if the thread
+        // stopped).
+        match unsafe { libc::pthread_kill(self.handle.as_pthread_t(), signal) } {
+            0 => Ok(()),
+            errno => Err(io::Error::from_raw_os_error(errno)),
+        }
+    }
+
+    /// Send the worker thread the message to shut down.
+    pub(super) fn send_shutdown(&mut self) -> io::Result<()> {
+        self.channel.try_send(Control::Shutdown)
+    }
+
     /// Send the worker thread the function `f` to run.
     pub(super) fn send_function(
         &mut self,
@@ -190,6 +223,11 @@ pub(crate) struct Worker {
     ///
     /// [`Runtime::start`]: rt::Runtime::start
     started: bool,
+    /// Whether or not the runtime is shutting down, see [`Control::Shutdown`].
+    shutdown: bool,
+    /// io_uring ring used by the I/O futures, `None` if io_uring isn't used.
+    #[cfg(target_os = "linux")]
+    ring: Option<Arc<Ring>>,
 }
 
 impl Worker {
@@ -198,16 +236,13 @@ impl Worker {
         setup: WorkerSetup,
         mut receiver: rt::channel::Receiver<Control>,
         shared_internals: Arc<shared::RuntimeInternals>,
-        auto_cpu_affinity: bool,
+        cpu: Option<usize>,
+        use_io_uring: bool,
         trace_log: Option<trace::Log>,
     ) -> Result<Worker, Error> {
         let timing = trace::start(&trace_log);
 
-        let cpu = if auto_cpu_affinity {
-            set_cpu_affinity(setup.id)
-        } else {
-            None
-        };
+        let cpu = cpu.and_then(|cpu| set_cpu_affinity(setup.id, cpu));
 
         // Register the shared poll intance.
         let poll = setup.poll;
@@ -221,6 +256,20 @@ impl Worker {
             .register(poll.registry(), COMMS)
             .map_err(Error::Init)?;
 
+        #[cfg(target_os = "linux")]
+        let ring = if use_io_uring {
+            trace!(worker_id = setup.id.get(); "setting up io_uring");
+            setup_ring(setup.id, poll.registry())
+        } else {
+            None
+        };
+        #[cfg(not(target_os = "linux"))]
+        let _ = use_io_uring; // Silence unused variables warnings.
+
+        if let Some(stats) = shared_internals.worker_stats_for(setup.id.get()) {
+            stats.started(cpu);
+        }
+
         // Finally create all the runtime internals.
         let internals = RuntimeInternals::new(
             setup.id,
@@ -230,12 +279,17 @@ impl Worker {
             cpu,
             trace_log,
         );
+        // Record the message flows of the actors running on this thread.
+        trace::set_flow_log(Some(internals.trace_log.clone()));
         let mut worker = Worker {
             internals: Rc::new(internals),
             events: Events::with_capacity(128),
             waker_events: setup.waker_events,
             channel: receiver,
             started: false,
+            shutdown: false,
+            #[cfg(target_os = "linux")]
+            ring,
         };
 
         trace::finish_rt(
@@ -273,6 +327,9 @@ impl Worker {
             waker_events,
             channel: receiver,
             started: false,
+            shutdown: false,
+            #[cfg(target_os = "linux")]
+            ring: None,
         })
     }
 
@@ -283,6 +340,13 @@ impl Worker {
         let mut runtime_ref = self.create_ref();
 
         loop {
+            // Check this before running any processes as we could have received
+            // the shutdown message while polling.
+            if self.shutdown {
+                debug!(worker_id = self.internals.id.get(); "shutting down worker");
+                return Ok(());
+            }
+
             // We first run the processes and only poll after to ensure that we
             // return if there are no processes to run.
             trace!(worker_id = self.internals.id.get(); "running processes");
@@ -306,6 +370,7 @@ impl Worker {
                 return Ok(());
             }
 
+            self.update_metrics();
             self.schedule_processes()?;
         }
     }
@@ -316,9 +381,10 @@ impl Worker {
         let process = self.internals.scheduler.borrow_mut().next_process();
         match process {
             Some(mut process) => {
-                let timing = trace::start(&*self.internals.trace_log.borrow());
                 let pid = process.as_ref().id();
                 let name = process.as_ref().name();
+                trace::enter_process(&*self.internals.trace_log.borrow(), name);
+                let timing = trace::start(&*self.internals.trace_log.borrow());
                 match process.as_mut().run(runtime_ref) {
                     ProcessResult::Complete => {
                         // Don't want to panic when dropping the process.
@@ -334,6 +400,7 @@ impl Worker {
                     "Running thread-local process",
                     &[("id", &pid.0), ("name", &name)],
                 );
+                trace::exit_process();
                 true
             }
             None => false,
@@ -346,9 +413,10 @@ impl Worker {
         let process = self.internals.shared.remove_process();
         match process {
             Some(mut process) => {
-                let timing = trace::start(&*self.internals.trace_log.borrow());
                 let pid = process.as_ref().id();
                 let name = process.as_ref().name();
+                trace::enter_process(&*self.internals.trace_log.borrow(), name);
+                let timing = trace::start(&*self.internals.trace_log.borrow());
                 match process.as_mut().run(runtime_ref) {
                     ProcessResult::Complete => {
                         self.internals.shared.complete(process);
@@ -363,6 +431,7 @@ impl Worker {
                     "Running thread-safe process",
                     &[("id", &pid.0), ("name", &name)],
                 );
+                trace::exit_process();
                 true
             }
             None => false,
@@ -434,6 +503,8 @@ impl Worker {
                 WAKER => { /* Need to wake up to handle user space events. */ }
                 COMMS => check_comms = true,
                 SHARED_POLL => check_shared_poll = true,
+                #[cfg(target_os = "linux")]
+                RING => { /* Completions are always processed below. */ }
                 token => {
                     let pid = ProcessId::from(token);
                     trace!(
@@ -452,6 +523,20 @@ impl Worker {
             &[],
         );
 
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = self.ring.as_ref() {
+            // NOTE: we always check for completions as the ring's file
+            // descriptor is edge triggered.
+            let timing = trace::start(&*self.internals.trace_log.borrow());
+            ring.complete();
+            trace::finish_rt(
+                self.internals.trace_log.borrow_mut().as_mut(),
+                timing,
+                "Processing io_uring completions",
+                &[],
+            );
+        }
+
         if check_comms {
             // Don't need this anymore.
             drop(scheduler);
@@ -584,6 +669,11 @@ impl Worker {
     /// Returns a boolean indicating if the shared timers should be checked.
     fn poll_os(&mut self) -> io::Result<()> {
         let timing = trace::start(&*self.internals.trace_log.borrow());
+        // Submit the operations started by the processes we just ran.
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = self.ring.as_ref() {
+            ring.submit()?;
+        }
         let mut timeout = self.determine_timeout();
 
         // Only mark ourselves as polling if the timeout is non zero.
@@ -614,11 +704,29 @@ impl Worker {
         };
 
         trace!(worker_id = self.internals.id.get(), timeout = as_debug!(timeout); "polling OS events");
-        let res = self
+        let stats = self
+            .internals
+            .shared
+            .worker_stats_for(self.internals.id.get());
+        if let Some(stats) = stats {
+            stats.set_polling(true);
+        }
+        let start = Instant::now();
+        let res = match self
             .internals
             .poll
             .borrow_mut()
-            .poll(&mut self.events, timeout);
+            .poll(&mut self.events, timeout)
+        {
+            // Interrupted by a signal, or by io_uring running completion work
+            // on this thread. We'll handle any events in the next poll.
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => Ok(()),
+            res => res,
+        };
+        if let Some(stats) = stats {
+            stats.record_poll(self.events.iter().count(), start.elapsed());
+            stats.set_polling(false);
+        }
 
         if marked_polling {
             waker::mark_polling(self.internals.waker_id, false);
@@ -684,6 +792,7 @@ impl Worker {
                     self.relay_signal(signal)?
                 }
                 Control::Run(f) => self.run_user_function(f)?,
+                Control::Shutdown => self.shutdown = true,
             }
         }
         trace::finish_rt(
@@ -703,7 +812,7 @@ impl Worker {
 
         let mut receivers = self.internals.signal_receivers.borrow_mut();
         receivers.remove_disconnected();
-        let res = match receivers.try_send(signal, Delivery::ToAll) {
+        let res = match receivers.try_send(signal) {
             Err(SendError) if signal.should_stop() => Err(Error::ProcessInterrupted),
             Ok(()) | Err(SendError) => Ok(()),
         };
@@ -764,6 +873,28 @@ impl Worker {
         );
     }
 
+    /// Publish the metrics of the worker, see [`WorkerStats`].
+    ///
+    /// [`WorkerStats`]: crate::metrics::WorkerStats
+    fn update_metrics(&self) {
+        let shared = &*self.internals;
+        if let Some(stats) = shared.shared.worker_stats_for(shared.id.get()) {
+            let scheduler = shared.scheduler.borrow();
+            let trace_counter = shared
+                .trace_log
+                .borrow()
+                .as_ref()
+                .map_or(0, |log| log.metrics().stream_counter);
+            stats.update(
+                scheduler.ready(),
+                scheduler.inactive(),
+                shared.timers.borrow().len(),
+                shared.signal_receivers.borrow().len(),
+                trace_counter,
+            );
+        }
+    }
+
     /// Create a new reference to this runtime.
     pub(crate) fn create_ref(&self) -> RuntimeRef {
         RuntimeRef {
@@ -777,6 +908,32 @@ impl Worker {
     }
 }
 
+/// Create the io_uring ring for the worker thread and register it with
+/// `registry`, see [`Setup::use_io_uring`].
+///
+/// Returns `None` if the ring can't be created, falling back to using `epoll`.
+///
+/// [`Setup::use_io_uring`]: crate::Setup::use_io_uring
+#[cfg(target_os = "linux")]
+fn setup_ring(id: NonZeroUsize, registry: &Registry) -> Option<Arc<Ring>> {
+    let res = Ring::new(io_uring::ENTRIES).and_then(|ring| {
+        registry
+            .register(&mut SourceFd(&ring.as_raw_fd()), RING, Interest::READABLE)
+            .map(|()| ring)
+    });
+    match res {
+        Ok(ring) => {
+            let ring = Arc::new(ring);
+            io_uring::set_current(Some(ring.clone()));
+            Some(ring)
+        }
+        Err(err) => {
+            warn!(worker_id = id.get(); "failed to setup io_uring, falling back to epoll: {}", err);
+            None
+        }
+    }
+}
+
 /// Error running a [`Runtime`].
 #[derive(Debug)]
 pub(crate) enum Error {
@@ -829,6 +986,8 @@ pub(crate) enum Control {
     Signal(Signal),
     /// Run a user defined function.
     Run(Box<dyn FnOnce(RuntimeRef) -> Result<(), String> + Send + 'static>),
+    /// Shut down the worker, dropping all actors and futures still running.
+    Shutdown,
 }
 
 impl fmt::Debug for Control {
@@ -839,6 +998,7 @@ impl fmt::Debug for Control {
             Started => f.write_str("Started"),
             Signal(signal) => f.debug_tuple("Signal").field(&signal).finish(),
             Run(..) => f.write_str("Run(..)"),
+            Shutdown => f.write_str("Shutdown"),
         }
     }
 }
@@ -21,8 +21,13 @@ mod functional {
     mod actor_group;
     mod actor_ref;
     mod bytes;
+    #[cfg(target_os = "linux")]
+    mod child;
+    mod codec;
     mod from_message;
+    mod fs;
     mod future;
+    mod lookup;
     mod pipe;
     mod restart_supervisor;
     mod runtime;
@@ -0,0 +1,102 @@
+//! Tests for child processes.
+
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::time::Duration;
+
+use heph::actor;
+use heph_rt::child::{ChildError, Command, Stdio};
+use heph_rt::spawn::ActorOptions;
+use heph_rt::test::{join, try_spawn_local, PanicSupervisor};
+use heph_rt::{Signal, ThreadLocal};
+
+const DATA: &[u8] = b"Hello world";
+
+#[test]
+fn wait() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let mut child = Command::new("true").spawn(&mut ctx)?;
+        assert!(child.id() != 0);
+        let status = child.wait().await?;
+        assert!(status.success());
+
+        let mut child = Command::new("false").spawn(&mut ctx)?;
+        let status = child.wait().await?;
+        assert_eq!(status.code(), Some(1));
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn wait_success() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let mut child = Command::new("true").spawn(&mut ctx)?;
+        child.wait_success().await.unwrap();
+
+        let mut child = Command::new("sh").args(&["-c", "exit 3"]).spawn(&mut ctx)?;
+        match child.wait_success().await {
+            Err(ChildError::Failed(status)) => assert_eq!(status.code(), Some(3)),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn stdio() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn(&mut ctx)?;
+        assert!(child.stderr.is_none());
+
+        child.stdin.as_mut().unwrap().write_all(DATA).await?;
+        let mut buf = Vec::with_capacity(DATA.len() + 1);
+        child
+            .stdout
+            .as_mut()
+            .unwrap()
+            .read_n(&mut buf, DATA.len())
+            .await?;
+        assert_eq!(buf, DATA);
+
+        // Closes stdin, causing `cat` to exit.
+        let status = child.wait().await?;
+        assert!(status.success());
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn kill() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn(&mut ctx)?;
+        child.kill(Signal::Terminate)?;
+        let status = child.wait().await?;
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+        let mut child = Command::new("sleep").arg("10").spawn(&mut ctx)?;
+        child.force_kill()?;
+        let status = child.wait().await?;
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
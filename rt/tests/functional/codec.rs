@@ -0,0 +1,188 @@
+//! Tests for the codec module.
+
+use std::io;
+use std::time::Duration;
+
+use heph::actor;
+use heph_rt::codec::{Decoder, Encoder, Framed, LengthDelimited, Lines, Varint};
+use heph_rt::pipe;
+use heph_rt::spawn::ActorOptions;
+use heph_rt::test::{join, try_spawn_local, PanicSupervisor};
+use heph_rt::ThreadLocal;
+
+const DATA: &[u8] = b"Hello world";
+
+/// Feed `buf` byte by byte to `codec`, it should only decode a frame once all
+/// bytes are available.
+fn partial_decode<C>(codec: &mut C, buf: &[u8]) -> C::Item
+where
+    C: Decoder,
+    C::Error: std::fmt::Debug,
+{
+    for n in 0..buf.len() {
+        assert!(codec.decode(&buf[..n]).unwrap().is_none());
+    }
+    let (item, n) = codec.decode(buf).unwrap().unwrap();
+    assert_eq!(n, buf.len());
+    item
+}
+
+#[test]
+fn length_delimited() {
+    let mut codec = LengthDelimited::new();
+    let mut buf = Vec::new();
+    codec.encode(DATA, &mut buf).unwrap();
+    assert_eq!(&buf[..4], &(DATA.len() as u32).to_be_bytes());
+    assert_eq!(partial_decode(&mut codec, &buf), DATA);
+
+    // Empty frame.
+    buf.clear();
+    codec.encode(b"", &mut buf).unwrap();
+    assert_eq!(partial_decode(&mut codec, &buf), b"");
+}
+
+#[test]
+fn length_delimited_max_frame_length() {
+    let mut codec = LengthDelimited::new().max_frame_length(4);
+    let mut buf = Vec::new();
+    let err = codec.encode(DATA, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Header is enough to determine the frame is too large.
+    let err = codec.decode(&[0, 0, 0, 5]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn lines() {
+    let mut codec = Lines::new();
+    let mut buf = Vec::new();
+    codec.encode("Hello world", &mut buf).unwrap();
+    assert_eq!(buf, b"Hello world\n");
+    assert_eq!(partial_decode(&mut codec, &buf), "Hello world");
+    assert_eq!(partial_decode(&mut codec, b"Hello\r\n"), "Hello");
+
+    let (line, n) = codec.decode(b"a\nb\n").unwrap().unwrap();
+    assert_eq!(line, "a");
+    assert_eq!(n, 2);
+
+    // Last line without a new line.
+    assert!(codec.decode(b"last").unwrap().is_none());
+    let (line, n) = codec.decode_eof(b"last").unwrap().unwrap();
+    assert_eq!(line, "last");
+    assert_eq!(n, 4);
+
+    let err = codec.decode(b"\xFF\n").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Lines can't contain a new line.
+    let mut buf = Vec::new();
+    let err = codec.encode("Hello\nworld", &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn lines_partial_reads() {
+    let mut codec = Lines::new();
+    let mut buf = Vec::new();
+    // Simulate reading a long line in parts, followed by the start of the next
+    // line.
+    for _ in 0..10 {
+        buf.extend_from_slice(b"0123456789");
+        assert!(codec.decode(&buf).unwrap().is_none());
+    }
+    buf.extend_from_slice(b"\nnext");
+    let (line, n) = codec.decode(&buf).unwrap().unwrap();
+    assert_eq!(line, "0123456789".repeat(10));
+    assert_eq!(n, 101);
+
+    // Must search from the start of the next line again.
+    let buf = &buf[n..];
+    assert!(codec.decode(buf).unwrap().is_none());
+    let buf = b"next\n";
+    assert_eq!(codec.decode(buf).unwrap().unwrap(), ("next".to_owned(), 5));
+}
+
+#[test]
+fn lines_max_line_length() {
+    let mut codec = Lines::new().max_line_length(4);
+    assert_eq!(codec.decode(b"1234\r\n").unwrap().unwrap().0, "1234");
+    let err = codec.decode(b"12345\n").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    // Don't wait for the new line if the line is already too long.
+    let err = codec.decode(b"123456").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn varint() {
+    let mut codec = Varint::new();
+    for length in [0, 1, 127, 128, 300, 16384] {
+        let frame = vec![123; length];
+        let mut buf = Vec::new();
+        codec.encode(&frame, &mut buf).unwrap();
+        assert_eq!(partial_decode(&mut codec, &buf), frame);
+    }
+
+    // Too many continuation bytes.
+    let err = codec.decode(&[0xFF; 11]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn varint_max_frame_length() {
+    let mut codec = Varint::new().max_frame_length(127);
+    let mut buf = Vec::new();
+    let err = codec.encode(&[0; 128], &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let err = codec.decode(&[0x80, 0x01]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn framed_pipe() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let (sender, receiver) = pipe::new(&mut ctx)?;
+        let mut sender = Framed::new(sender, Lines::new());
+        let mut receiver = Framed::new(receiver, Lines::new());
+
+        sender.feed("Hello")?;
+        sender.feed("world")?;
+        sender.flush().await?;
+        sender.send("from mars").await?;
+        drop(sender);
+
+        assert_eq!(receiver.read_frame().await?.unwrap(), "Hello");
+        assert_eq!(receiver.read_frame().await?.unwrap(), "world");
+        assert_eq!(receiver.read_frame().await?.unwrap(), "from mars");
+        assert!(receiver.read_frame().await?.is_none());
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn framed_partial_frame_at_eof() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let (mut sender, receiver) = pipe::new(&mut ctx)?;
+        let mut receiver = Framed::new(receiver, LengthDelimited::new());
+
+        // Header says 11 bytes, but only send 5.
+        sender.write_all(&[0, 0, 0, 11]).await?;
+        sender.write_all(&DATA[..5]).await?;
+        drop(sender);
+
+        let err = receiver.read_frame().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
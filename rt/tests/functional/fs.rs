@@ -0,0 +1,206 @@
+//! Tests for the filesystem module.
+
+use std::io::{self, IoSlice};
+use std::lazy::SyncLazy;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+use heph::actor;
+use heph_rt::fs::{self, File};
+use heph_rt::spawn::ActorOptions;
+use heph_rt::test::{join, try_spawn_local, PanicSupervisor};
+use heph_rt::ThreadLocal;
+
+use crate::util::temp_file;
+
+const DATA: &[u8] = b"Hello world";
+const TEST_FILE: &str = "./tests/data/hello_world";
+
+static EXPECTED: SyncLazy<Vec<u8>> =
+    SyncLazy::new(|| std::fs::read(TEST_FILE).expect("failed to read test file"));
+
+#[test]
+fn read_at() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let file = File::open(&mut ctx, TEST_FILE).await?;
+
+        let mut buf = Vec::with_capacity(EXPECTED.len() + 1);
+        let n = file.read_at(&mut buf, 0).await?;
+        assert_eq!(n, EXPECTED.len());
+        assert_eq!(buf, *EXPECTED);
+
+        // Reading at an offset.
+        buf.clear();
+        let n = file.read_at(&mut buf, 6).await?;
+        assert_eq!(n, EXPECTED.len() - 6);
+        assert_eq!(buf, &EXPECTED[6..]);
+
+        // End of the file.
+        buf.clear();
+        let n = file.read_at(&mut buf, EXPECTED.len() as u64).await?;
+        assert_eq!(n, 0);
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn read_vectored_at() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let file = File::open(&mut ctx, TEST_FILE).await?;
+
+        let mut buf1 = Vec::with_capacity(5);
+        let mut buf2 = Vec::with_capacity(EXPECTED.len());
+        let n = file.read_vectored_at([&mut buf1, &mut buf2], 0).await?;
+        assert_eq!(n, EXPECTED.len());
+        assert_eq!(buf1, &EXPECTED[..5]);
+        assert_eq!(buf2, &EXPECTED[5..]);
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn open_not_found() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) {
+        let err = File::open(&mut ctx, "./tests/data/not_found")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn write_at() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, path: PathBuf) -> io::Result<()> {
+        let file = File::create(&mut ctx, &path).await?;
+        file.write_all_at(DATA, 0).await?;
+        let n = file
+            .write_vectored_at(&[IoSlice::new(b" from"), IoSlice::new(b" mars")], 11)
+            .await?;
+        assert_eq!(n, 10);
+        file.sync_all().await?;
+
+        let metadata = file.metadata().await?;
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 21);
+        assert_eq!(fs::metadata(&mut ctx, &path).await?.len(), 21);
+
+        file.set_len(5).await?;
+        file.sync_data().await?;
+        assert_eq!(std::fs::read(&path)?, b"Hello");
+        Ok(())
+    }
+
+    let path = temp_file("fs_write_at");
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, path, ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn operations_start_on_first_poll() {
+    async fn actor(
+        mut ctx: actor::Context<!, ThreadLocal>,
+        (path1, path2): (PathBuf, PathBuf),
+    ) -> io::Result<()> {
+        // Dropping the future without polling it shouldn't create the file.
+        drop(File::create(&mut ctx, &path1));
+
+        // Same for writing to a file.
+        let file = File::create(&mut ctx, &path2).await?;
+        drop(file.write_all_at(DATA, 0));
+
+        // Give the blocking pool time to run the operations, if they were
+        // started.
+        sleep(Duration::from_millis(50));
+        Ok(())
+    }
+
+    let path1 = temp_file("fs_operations_start_on_first_poll1");
+    let path2 = temp_file("fs_operations_start_on_first_poll2");
+    let actor = actor as fn(_, _) -> _;
+    let args = (path1.clone(), path2.clone());
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, args, ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+
+    assert!(!path1.exists());
+    assert_eq!(std::fs::metadata(&path2).unwrap().len(), 0);
+}
+
+#[test]
+fn read_dir() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let entries = fs::read_dir(&mut ctx, "./tests/data").await?;
+        let mut names = entries
+            .into_iter()
+            .map(|entry| entry.file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["hello_world", "lorem_ipsum"]);
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn watcher() {
+    use heph_rt::fs::{Event, Watcher};
+
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let dir = temp_file("fs.watcher");
+        std::fs::create_dir(&dir)?;
+
+        let mut watcher = Watcher::new(&mut ctx)?;
+        watcher.watch_recursive(&dir)?;
+
+        // Created directories should be watched automatically.
+        let sub_dir = dir.join("sub");
+        std::fs::create_dir(&sub_dir)?;
+        assert_eq!(watcher.next_event().await?, Event::Created(sub_dir.clone()));
+
+        let path = sub_dir.join("file");
+        std::fs::write(&path, DATA)?;
+        assert_eq!(watcher.next_event().await?, Event::Created(path.clone()));
+        assert_eq!(watcher.next_event().await?, Event::Modified(path.clone()));
+
+        let new_path = dir.join("moved");
+        std::fs::rename(&path, &new_path)?;
+        let expected = Event::Moved {
+            from: Some(path),
+            to: Some(new_path.clone()),
+        };
+        assert_eq!(watcher.next_event().await?, expected);
+
+        std::fs::remove_file(&new_path)?;
+        assert_eq!(watcher.next_event().await?, Event::Deleted(new_path));
+
+        // No longer watching the subdirectory.
+        watcher.unwatch(&dir)?;
+        std::fs::write(sub_dir.join("file2"), DATA)?;
+        assert_eq!(
+            watcher.try_next_event().unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
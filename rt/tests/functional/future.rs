@@ -1,13 +1,13 @@
 //! Tests for spawning [`Future`]s.
 
-use std::future::Future;
+use std::future::{pending, Future};
 use std::pin::Pin;
 use std::task::{self, Poll};
 
 use heph::actor;
 use heph::supervisor::NoSupervisor;
-use heph_rt::spawn::{ActorOptions, FutureOptions};
-use heph_rt::test::poll_future;
+use heph_rt::spawn::{ActorOptions, FutureOptions, JoinError};
+use heph_rt::test::{self, poll_future};
 use heph_rt::{Runtime, ThreadSafe};
 
 use crate::util::{expect_pending, expect_ready};
@@ -87,3 +87,40 @@ fn thread_safe_waking() {
         .unwrap();
     runtime.start().unwrap();
 }
+
+#[test]
+fn join_handle() {
+    let handle = test::spawn_future(async { 1 + 1 }, FutureOptions::default());
+    assert_eq!(test::block_on(handle), Ok(2));
+
+    let handle = test::spawn_local_future(async { "local" }, FutureOptions::default());
+    assert_eq!(test::block_on(handle), Ok("local"));
+
+    let mut runtime = Runtime::new().unwrap();
+    let handle = runtime.spawn_future(async { 1 + 2 }, FutureOptions::default());
+    let output = runtime.block_on(handle).unwrap();
+    assert_eq!(output, Ok(3));
+}
+
+#[test]
+fn join_handle_abort() {
+    let handle = test::spawn_future(pending::<()>(), FutureOptions::default());
+    assert!(!handle.is_finished());
+    handle.abort();
+    assert_eq!(test::block_on(handle), Err(JoinError::Cancelled));
+
+    let handle = test::spawn_local_future(pending::<()>(), FutureOptions::default());
+    handle.abort();
+    assert_eq!(test::block_on(handle), Err(JoinError::Cancelled));
+}
+
+#[test]
+fn join_handle_panic() {
+    let handle = test::spawn_future(
+        async {
+            panic!("oops");
+        },
+        FutureOptions::default(),
+    );
+    assert_eq!(test::block_on(handle), Err(JoinError::Panicked));
+}
@@ -0,0 +1,63 @@
+//! Tests for `net::lookup_host`.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use heph::actor;
+use heph_rt::net::lookup_host;
+use heph_rt::spawn::ActorOptions;
+use heph_rt::test::{join, try_spawn_local, PanicSupervisor};
+use heph_rt::ThreadLocal;
+
+#[test]
+fn socket_address() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let expected: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let addresses = lookup_host(&mut ctx, "127.0.0.1:8080").await?;
+        assert_eq!(addresses, [expected]);
+
+        let expected: SocketAddr = "[::1]:8080".parse().unwrap();
+        let addresses = lookup_host(&mut ctx, "[::1]:8080").await?;
+        assert_eq!(addresses, [expected]);
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn localhost() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let addresses = lookup_host(&mut ctx, "localhost:8080").await?;
+        assert!(!addresses.is_empty());
+        for address in &addresses {
+            assert!(address.ip().is_loopback());
+            assert_eq!(address.port(), 8080);
+        }
+
+        // Second lookup should hit the cache, returning the same addresses.
+        let addresses2 = lookup_host(&mut ctx, "localhost:8080").await?;
+        assert_eq!(addresses, addresses2);
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn invalid_host() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) {
+        // Missing port.
+        let res = lookup_host(&mut ctx, "localhost").await;
+        assert!(res.is_err());
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(5)).unwrap();
+}
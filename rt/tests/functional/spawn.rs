@@ -2,10 +2,15 @@
 
 use std::future::Pending;
 use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::Poll;
+use std::thread::{self, sleep};
+use std::time::Duration;
 
 use heph::actor::{self, NewActor};
 use heph::supervisor::NoSupervisor;
-use heph_rt::spawn::Spawn;
+use heph_rt::spawn::{Spawn, SpawnBlocking};
+use heph_rt::test::poll_future;
 use heph_rt::{Runtime, RuntimeRef, ThreadLocal, ThreadSafe};
 
 struct TestNewActor<RT>(PhantomData<RT>);
@@ -70,3 +75,29 @@ fn thread_local() {
 fn thread_safe() {
     can_spawn_thread_safe::<ThreadSafe>();
 }
+
+/// Wait for `future` to complete.
+fn wait<T>(mut future: SpawnBlocking<T>) -> T {
+    loop {
+        if let Poll::Ready(value) = poll_future(Pin::new(&mut future)) {
+            return value;
+        }
+        sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn spawn_blocking() {
+    let mut runtime_ref = heph_rt::test::runtime();
+    let future = runtime_ref.spawn_blocking(|| thread::current().name().map(str::to_owned));
+    let name = wait(future).unwrap();
+    assert!(name.starts_with("Heph blocking"), "{}", name);
+}
+
+#[test]
+#[should_panic = "oops"]
+fn spawn_blocking_propagates_panics() {
+    let mut runtime_ref = heph_rt::test::runtime();
+    let future = runtime_ref.spawn_blocking::<_, ()>(|| panic!("oops"));
+    wait(future);
+}
@@ -157,3 +157,22 @@ fn bad_actor_supervisor(err_count: usize) -> SupervisorStrategy<usize> {
 fn bad_actor<RT>(_: SyncContext<!, RT>, count: usize) -> Result<(), usize> {
     Err(count + 1)
 }
+
+#[cfg(target_os = "linux")]
+fn cpu_affinity_actor<RT>(_: SyncContext<!, RT>) {
+    assert_eq!(crate::util::thread_cpus(), [0]);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn cpu_affinity() {
+    let cpus: heph_rt::CpuSet = (0..1).collect();
+    let (handle, _) = spawn_sync_actor(
+        NoSupervisor,
+        cpu_affinity_actor as fn(_) -> _,
+        (),
+        SyncActorOptions::default().cpu_affinity(cpus),
+    )
+    .unwrap();
+    handle.join().unwrap();
+}
@@ -12,7 +12,10 @@ use std::time::Duration;
 use heph::actor;
 use heph::actor_ref::{ActorRef, RpcMessage};
 use heph::supervisor::NoSupervisor;
+use heph_rt::net::tcp::stream::{ReuniteError, WriteHalf};
 use heph_rt::net::{TcpListener, TcpStream};
+#[cfg(target_os = "linux")]
+use heph_rt::pipe;
 use heph_rt::spawn::ActorOptions;
 use heph_rt::test::{join, join_many, try_spawn_local, PanicSupervisor};
 use heph_rt::{self as rt, Bound, Runtime, RuntimeRef, ThreadLocal};
@@ -104,6 +107,51 @@ fn connect() {
     join(&actor_ref, Duration::from_secs(1)).unwrap();
 }
 
+#[test]
+fn connect_host() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, port: u16) -> io::Result<()> {
+        let host = format!("localhost:{}", port);
+        let mut stream = TcpStream::connect_host(&mut ctx, &host).await?;
+        assert!(stream.peer_addr().unwrap().ip().is_loopback());
+        stream.send_all(DATA).await
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, port, ActorOptions::default()).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buf = Vec::with_capacity(DATA.len() + 1);
+    let n = stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(n, DATA.len());
+    assert_eq!(buf, DATA);
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn connect_host_refused() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, address: SocketAddr) {
+        let host = address.to_string();
+        let err = TcpStream::connect_host(&mut ctx, &host)
+            .await
+            .expect_err("unexpected connect success");
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref = try_spawn_local(
+        PanicSupervisor,
+        actor,
+        refused_address(),
+        ActorOptions::default(),
+    )
+    .unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
 #[test]
 #[cfg_attr(
     target_os = "freebsd",
@@ -1125,6 +1173,280 @@ fn shutdown_both() {
     join_many(&[stream_ref, listener_ref], Duration::from_secs(1)).unwrap();
 }
 
+#[test]
+fn split() {
+    async fn writer_actor(mut ctx: actor::Context<WriteHalf, ThreadLocal>) {
+        let mut write_half = ctx.receive_next().await.unwrap();
+        write_half.bind_to(&mut ctx).unwrap();
+        write_half.send_all(DATA).await.unwrap();
+        // Dropping the write half should shutdown the writing side of the
+        // connection.
+    }
+
+    async fn reader_actor(
+        mut ctx: actor::Context<!, ThreadLocal>,
+        (address, writer_ref): (SocketAddr, ActorRef<WriteHalf>),
+    ) {
+        let stream = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+        let (mut read_half, write_half) = stream.into_split(&mut ctx).unwrap();
+        writer_ref.send(write_half).await.unwrap();
+
+        let mut buf = Vec::with_capacity(DATA.len() + 1);
+        read_half.recv_n(&mut buf, DATA.len()).await.unwrap();
+        assert_eq!(buf, DATA);
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let writer_actor = writer_actor as fn(_) -> _;
+    let writer_ref =
+        try_spawn_local(NoSupervisor, writer_actor, (), ActorOptions::default()).unwrap();
+    let reader_actor = reader_actor as fn(_, _) -> _;
+    let arg = (address, writer_ref.clone());
+    let reader_ref =
+        try_spawn_local(NoSupervisor, reader_actor, arg, ActorOptions::default()).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    // This only returns once the write half is dropped.
+    let mut buf = Vec::new();
+    let n = stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(n, DATA.len());
+    assert_eq!(buf, DATA);
+    stream.write_all(&buf).unwrap();
+
+    join(&writer_ref, Duration::from_secs(1)).unwrap();
+    join(&reader_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn split_borrowed() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, address: SocketAddr) {
+        let mut stream = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+        let (mut read_half, mut write_half) = stream.split();
+
+        // Both halves can be used at the same time.
+        let mut buf = Vec::with_capacity(DATA.len() + 1);
+        let recv = read_half.recv_n(&mut buf, DATA.len());
+        write_half.send_all(DATA).await.unwrap();
+        recv.await.unwrap();
+        assert_eq!(buf, DATA);
+
+        write_half.shutdown().unwrap();
+        // Stream should still be usable after the halves are dropped.
+        let mut buf = Vec::with_capacity(1);
+        let n = stream.recv(&mut buf).await.unwrap();
+        assert_eq!(n, 0);
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref = try_spawn_local(NoSupervisor, actor, address, ActorOptions::default()).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buf = [0; DATA.len()];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, DATA);
+    stream.write_all(&buf).unwrap();
+
+    // This only returns once the write half is shutdown.
+    let mut buf = Vec::new();
+    let n = stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(n, 0);
+    drop(stream);
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn split_reunite() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, address: SocketAddr) {
+        let stream1 = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+        let stream2 = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+
+        let (read1, write1) = stream1.into_split(&mut ctx).unwrap();
+        let (read2, write2) = stream2.into_split(&mut ctx).unwrap();
+
+        // Halves of different streams can't be reunited.
+        let (read1, write2) = match read1.reunite(write2, &mut ctx) {
+            Err(ReuniteError::Mismatch(read, write)) => (read, write),
+            res => panic!("unexpected result: {:?}", res),
+        };
+
+        let mut stream1 = read1.reunite(write1, &mut ctx).unwrap();
+        let stream2 = read2.reunite(write2, &mut ctx).unwrap();
+        drop(stream2);
+
+        stream1.send_all(DATA).await.unwrap();
+        let mut buf = Vec::with_capacity(DATA.len() + 1);
+        stream1.recv_n(&mut buf, DATA.len()).await.unwrap();
+        assert_eq!(buf, DATA);
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref = try_spawn_local(NoSupervisor, actor, address, ActorOptions::default()).unwrap();
+
+    let (mut stream1, _) = listener.accept().unwrap();
+    let (mut stream2, _) = listener.accept().unwrap();
+
+    let mut buf = [0; DATA.len() + 1];
+    let n = stream1.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], DATA);
+    stream1.write_all(&buf[..n]).unwrap();
+
+    // Reuniting the stream shouldn't have shutdown the stream, only dropping
+    // it.
+    let n = stream2.read(&mut buf).unwrap();
+    assert_eq!(n, 0);
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn split_reunite_split() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, address: SocketAddr) {
+        let stream = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+
+        // The write half should be deregistered when it's dropped in reunite,
+        // otherwise splitting again (likely reusing the same file descriptor)
+        // fails as it's still registered.
+        let (read, write) = stream.into_split(&mut ctx).unwrap();
+        let stream = read.reunite(write, &mut ctx).unwrap();
+        let (mut read, mut write) = stream.into_split(&mut ctx).unwrap();
+
+        write.send_all(DATA).await.unwrap();
+        let mut buf = Vec::with_capacity(DATA.len() + 1);
+        read.recv_n(&mut buf, DATA.len()).await.unwrap();
+        assert_eq!(buf, DATA);
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref = try_spawn_local(NoSupervisor, actor, address, ActorOptions::default()).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buf = [0; DATA.len()];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, DATA);
+    stream.write_all(&buf).unwrap();
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn splice() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, address: SocketAddr) -> io::Result<()> {
+        let mut stream = TcpStream::connect(&mut ctx, address)?.await?;
+        let (mut sender, mut receiver) = pipe::new(&mut ctx)?;
+
+        // Stream -> pipe.
+        let mut n = 0;
+        while n < DATA.len() {
+            let m = stream.splice_to(&mut sender, DATA.len() - n).await?;
+            assert!(m != 0, "unexpected end of stream");
+            n += m;
+        }
+        let mut buf = Vec::with_capacity(DATA.len() + 1);
+        receiver.read_n(&mut buf, DATA.len()).await?;
+        assert_eq!(buf, DATA);
+
+        // Pipe -> stream.
+        sender.write_all(DATA).await?;
+        let mut n = 0;
+        while n < DATA.len() {
+            n += receiver.splice_to(&mut stream, DATA.len() - n).await?;
+        }
+        Ok(())
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref =
+        try_spawn_local(PanicSupervisor, actor, address, ActorOptions::default()).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.write_all(DATA).unwrap();
+    let mut buf = Vec::with_capacity(DATA.len() + 1);
+    let n = stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(n, DATA.len());
+    assert_eq!(buf, DATA);
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn copy_bidirectional() {
+    async fn actor(
+        mut ctx: actor::Context<!, ThreadLocal>,
+        addresses: (SocketAddr, SocketAddr),
+    ) -> io::Result<()> {
+        let mut a = TcpStream::connect(&mut ctx, addresses.0)?.await?;
+        let mut b = TcpStream::connect(&mut ctx, addresses.1)?.await?;
+        let (a_to_b, b_to_a) =
+            heph_rt::net::tcp::stream::copy_bidirectional(&mut ctx, &mut a, &mut b)?.await?;
+        assert_eq!(a_to_b, DATA.len() as u64);
+        assert_eq!(b_to_a, 2 * DATA.len() as u64);
+        Ok(())
+    }
+
+    let listener_a = net::TcpListener::bind(any_local_address()).unwrap();
+    let listener_b = net::TcpListener::bind(any_local_address()).unwrap();
+    let addresses = (
+        listener_a.local_addr().unwrap(),
+        listener_b.local_addr().unwrap(),
+    );
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref =
+        try_spawn_local(PanicSupervisor, actor, addresses, ActorOptions::default()).unwrap();
+
+    let (mut stream_a, _) = listener_a.accept().unwrap();
+    let (mut stream_b, _) = listener_b.accept().unwrap();
+
+    stream_a.write_all(DATA).unwrap();
+    stream_a.shutdown(Shutdown::Write).unwrap();
+    stream_b.write_all(DATA).unwrap();
+    stream_b.write_all(DATA).unwrap();
+    stream_b.shutdown(Shutdown::Write).unwrap();
+
+    let mut buf = Vec::new();
+    let _ = stream_b.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, DATA);
+    buf.clear();
+    let _ = stream_a.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), 2 * DATA.len());
+    assert_eq!(&buf[..DATA.len()], DATA);
+    assert_eq!(&buf[DATA.len()..], DATA);
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
 #[test]
 fn actor_bound() {
     type Message = RpcMessage<TcpStream, ()>;
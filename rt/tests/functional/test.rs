@@ -90,7 +90,7 @@ fn catch_panics_spawned_actor() {
 
 #[test]
 fn catch_panics_spawned_future() {
-    let future = poll_fn(|_| panic!("panic in spawned Future"));
+    let future = poll_fn::<(), _>(|_| panic!("panic in spawned Future"));
     spawn_future(future, FutureOptions::default());
 }
 
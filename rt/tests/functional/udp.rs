@@ -1,7 +1,7 @@
 //! Tests related to `UdpSocket`.
 
 use std::io::{self, IoSlice};
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::time::Duration;
 
 use heph::actor::{self, Actor, NewActor};
@@ -325,6 +325,113 @@ fn assert_read(mut got: &[u8], expected: &[&[u8]]) {
     }
 }
 
+#[test]
+fn socket_options() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) -> io::Result<()> {
+        let mut socket = UdpSocket::bind(&mut ctx, "0.0.0.0:0".parse().unwrap())?;
+
+        socket.set_broadcast(true)?;
+        assert!(socket.broadcast()?);
+        socket.set_ttl(10)?;
+        assert_eq!(socket.ttl()?, 10);
+        socket.set_multicast_ttl_v4(4)?;
+        assert_eq!(socket.multicast_ttl_v4()?, 4);
+        socket.set_multicast_loop_v4(false)?;
+        assert!(!socket.multicast_loop_v4()?);
+        socket.set_multicast_ttl(6)?;
+        assert_eq!(socket.multicast_ttl()?, 6);
+        assert_eq!(socket.multicast_ttl_v4()?, 6);
+
+        // `set_multicast_ttl` also works for IPv6 sockets, unlike
+        // `set_multicast_ttl_v4`.
+        let mut socket = UdpSocket::bind(&mut ctx, "[::1]:0".parse().unwrap())?;
+        socket.set_multicast_ttl(3)?;
+        assert_eq!(socket.multicast_ttl()?, 3);
+        assert!(socket.set_multicast_ttl_v4(3).is_err());
+
+        let multiaddr = Ipv4Addr::new(224, 0, 0, 123);
+        socket.join_multicast_v4(&multiaddr, &Ipv4Addr::UNSPECIFIED)?;
+        socket.leave_multicast_v4(&multiaddr, &Ipv4Addr::UNSPECIFIED)?;
+
+        Ok(())
+    }
+
+    let actor = actor as fn(_) -> _;
+    let actor_ref = try_spawn_local(PanicSupervisor, actor, (), ActorOptions::default()).unwrap();
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn mmsg_ipv4() {
+    test_mmsg(any_local_address())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn mmsg_ipv6() {
+    test_mmsg(any_local_ipv6_address())
+}
+
+#[cfg(target_os = "linux")]
+fn test_mmsg(local_address: SocketAddr) {
+    async fn actor(
+        mut ctx: actor::Context<!, ThreadLocal>,
+        peer_address: SocketAddr,
+    ) -> io::Result<()> {
+        let local_address = SocketAddr::new(peer_address.ip(), 0);
+        let mut socket = UdpSocket::bind(&mut ctx, local_address)?;
+
+        let msgs = DATAV
+            .iter()
+            .map(|data| (*data, peer_address))
+            .collect::<Vec<_>>();
+        let n = socket.send_mmsg(&msgs).await?;
+        assert_eq!(n, DATAV.len());
+
+        let mut bufs = DATAV
+            .iter()
+            .map(|data| Vec::with_capacity(data.len() + 1))
+            .collect::<Vec<_>>();
+        let mut addresses = Vec::new();
+        // The datagrams may not all have arrived by the time the first one is
+        // received, so keep receiving until we have all of them.
+        let mut received = 0;
+        while received < DATAV.len() {
+            let n = socket
+                .recv_mmsg(&mut bufs[received..], &mut addresses)
+                .await?;
+            assert!(n != 0);
+            received += n;
+        }
+        for (buf, expected) in bufs.iter().zip(DATAV) {
+            assert_eq!(buf, expected);
+        }
+        assert_eq!(addresses, [peer_address; DATAV.len()]);
+
+        Ok(())
+    }
+
+    let echo_socket = std::net::UdpSocket::bind(local_address).unwrap();
+    let address = echo_socket.local_addr().unwrap();
+
+    let actor = actor as fn(_, _) -> _;
+    let actor_ref =
+        try_spawn_local(PanicSupervisor, actor, address, ActorOptions::default()).unwrap();
+
+    let mut buf = [0; DATAV_LEN];
+    for expected in DATAV {
+        let (bytes_read, peer_address) = echo_socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..bytes_read], *expected);
+        let bytes_written = echo_socket
+            .send_to(&buf[..bytes_read], peer_address)
+            .unwrap();
+        assert_eq!(bytes_written, expected.len());
+    }
+
+    join(&actor_ref, Duration::from_secs(1)).unwrap();
+}
+
 #[test]
 fn actor_bound() {
     type Message = RpcMessage<UdpSocket<Unconnected>, ()>;
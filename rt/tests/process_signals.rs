@@ -7,12 +7,14 @@ use std::sync::Arc;
 use heph::actor::{self, SyncContext};
 use heph::supervisor::NoSupervisor;
 use heph_rt::spawn::options::{ActorOptions, SyncActorOptions};
-use heph_rt::{Runtime, Signal};
+use heph_rt::{Runtime, Signal, SignalSet};
 use mio_signals::send_signal;
 
 fn main() {
     no_signal_handlers();
     with_signal_handles();
+    #[cfg(target_os = "linux")]
+    filtered_signal_handlers();
 }
 
 /// Runtime without any actor to receive the signal should stop itself.
@@ -83,6 +85,74 @@ fn with_signal_handles() {
     assert_eq!(sync.load(Ordering::SeqCst), 1);
 }
 
+/// Actors should only receive the signals they asked for.
+#[cfg(target_os = "linux")]
+fn filtered_signal_handlers() {
+    let mut runtime = Runtime::setup()
+        .handle_signals(SignalSet::from(Signal::Hangup))
+        .build()
+        .unwrap();
+
+    let hangup = Arc::new(AtomicUsize::new(0));
+    let user1 = Arc::new(AtomicUsize::new(0));
+    let unfiltered = Arc::new(AtomicUsize::new(0));
+
+    let h = hangup.clone();
+    runtime
+        .run_on_workers(|mut runtime_ref| -> Result<(), !> {
+            let args = (Signal::Hangup, h);
+            let filtered_actor = filtered_actor as fn(_, _, _) -> _;
+            let actor_ref = runtime_ref.spawn_local(
+                NoSupervisor,
+                filtered_actor,
+                args,
+                ActorOptions::default(),
+            );
+            runtime_ref.receive_signals_filtered(actor_ref, SignalSet::from(Signal::Hangup));
+            Ok(())
+        })
+        .unwrap();
+
+    let actor_ref = runtime.spawn(
+        NoSupervisor,
+        filtered_actor as fn(_, _, _) -> _,
+        (Signal::User1, user1.clone()),
+        ActorOptions::default(),
+    );
+    runtime.receive_signals_filtered(actor_ref, SignalSet::from(Signal::User1));
+
+    // Unfiltered receivers shouldn't receive `SIGHUP`.
+    let actor_ref = runtime.spawn(
+        NoSupervisor,
+        filtered_actor as fn(_, _, _) -> _,
+        (Signal::User1, unfiltered.clone()),
+        ActorOptions::default(),
+    );
+    runtime.receive_signals(actor_ref);
+
+    // NOTE: not using a stopping signal (e.g. `SIGINT`) as the worker thread
+    // would stop because it has no (thread-local) receivers for it.
+    send_signal(process::id(), mio_signals::Signal::User1).expect("failed to send signal");
+    let res = unsafe { libc::kill(process::id() as libc::pid_t, libc::SIGHUP) };
+    assert_eq!(res, 0, "failed to send signal");
+    runtime.start().unwrap();
+
+    assert_eq!(hangup.load(Ordering::SeqCst), 1);
+    assert_eq!(user1.load(Ordering::SeqCst), 1);
+    assert_eq!(unfiltered.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(target_os = "linux")]
+async fn filtered_actor<RT>(
+    mut ctx: actor::Context<Signal, RT>,
+    expected: Signal,
+    got_signal: Arc<AtomicUsize>,
+) {
+    let msg = ctx.receive_next().await.unwrap();
+    assert_eq!(msg, expected);
+    got_signal.fetch_add(1, Ordering::SeqCst);
+}
+
 async fn actor<RT>(mut ctx: actor::Context<Signal, RT>, got_signal: Arc<AtomicUsize>) {
     let _msg = ctx.receive_next().await.unwrap();
     got_signal.fetch_add(1, Ordering::SeqCst);
@@ -0,0 +1,21 @@
+//! Tests that build a [`Runtime`].
+//!
+//! Only a limited number of runtimes can be created per process, so these are
+//! kept separate from the functional tests.
+//!
+//! [`Runtime`]: heph_rt::Runtime
+
+#![feature(async_iterator, never_type)]
+
+#[path = "util/mod.rs"] // rustfmt can't find the file.
+#[macro_use]
+mod util;
+
+#[path = "runtime"] // rustfmt can't find the files.
+mod runtime {
+    mod block_on;
+    mod io_uring;
+    mod metrics;
+    mod setup;
+    mod trace;
+}
@@ -0,0 +1,37 @@
+//! Tests for `Runtime::block_on`.
+
+use std::thread;
+use std::time::Duration;
+
+use heph_rt::spawn::FutureOptions;
+use heph_rt::Runtime;
+
+#[test]
+fn block_on() {
+    let mut runtime = Runtime::setup().num_threads(2).build().unwrap();
+    // Futures that are still running should be stopped once the future passed
+    // to `block_on` completes.
+    runtime.spawn_future(std::future::pending::<()>(), FutureOptions::default());
+    runtime
+        .run_on_workers(|mut runtime_ref| -> Result<(), !> {
+            runtime_ref.spawn_local_future(std::future::pending::<()>(), FutureOptions::default());
+            Ok(())
+        })
+        .unwrap();
+    // The future doesn't have to be `Sync`.
+    let not_sync = std::cell::Cell::new("done");
+    let output = runtime
+        .block_on(async move {
+            thread::sleep(Duration::from_millis(10));
+            not_sync.get()
+        })
+        .unwrap();
+    assert_eq!(output, "done");
+}
+
+#[test]
+fn block_on_panic() {
+    let runtime = Runtime::setup().build().unwrap();
+    let err = runtime.block_on(async { panic!("oops") }).unwrap_err();
+    assert!(err.to_string().contains("didn't complete"), "{}", err);
+}
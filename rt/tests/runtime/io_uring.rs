@@ -0,0 +1,151 @@
+//! Tests for the io_uring backend, see `Setup::use_io_uring`.
+
+#![cfg(target_os = "linux")]
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::net::{self, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use heph::actor;
+use heph::supervisor::NoSupervisor;
+use heph_rt::net::{TcpListener, TcpStream};
+use heph_rt::spawn::ActorOptions;
+use heph_rt::timer::Deadline;
+use heph_rt::{Runtime, RuntimeRef, ThreadLocal, ThreadSafe};
+
+use crate::util::{any_local_address, temp_file};
+
+const DATA: &[u8] = b"Hello world";
+/// Size of the file send by the server, larger than the buffer used to send
+/// files using io_uring.
+const FILE_SIZE: usize = 200 * 1024;
+
+fn file_contents() -> Vec<u8> {
+    (0..FILE_SIZE).map(|n| (n % 251) as u8).collect()
+}
+
+#[test]
+fn tcp_stream_and_listener() {
+    static DONE: AtomicBool = AtomicBool::new(false);
+
+    async fn server(mut ctx: actor::Context<!, ThreadLocal>) {
+        let path = temp_file("io_uring_send_file");
+        fs::write(&path, file_contents()).unwrap();
+        let file = File::open(&path).unwrap();
+
+        let mut listener = TcpListener::bind(&mut ctx, any_local_address()).unwrap();
+        let address = listener.local_addr().unwrap();
+        // Use a thread-safe actor for the client so that the operations are
+        // also started from the thread-safe runtime.
+        let client = client as fn(_, _) -> _;
+        let _ = ctx
+            .runtime()
+            .spawn(NoSupervisor, client, address, ActorOptions::default());
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = stream.bind_to(&mut ctx).unwrap();
+        let mut buf = Vec::with_capacity(128);
+        stream.recv_n(&mut buf, DATA.len()).await.unwrap();
+        assert_eq!(buf, DATA);
+        stream.send_entire_file(&file).await.unwrap();
+    }
+
+    async fn client(mut ctx: actor::Context<!, ThreadSafe>, address: SocketAddr) {
+        let mut stream = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), address);
+        stream.send_all(DATA).await.unwrap();
+        let mut buf = Vec::with_capacity(FILE_SIZE + 1);
+        stream.recv_n(&mut buf, FILE_SIZE).await.unwrap();
+        assert!(buf == file_contents(), "received file doesn't match");
+        DONE.store(true, Ordering::SeqCst);
+    }
+
+    let mut runtime = Runtime::setup().use_io_uring().build().unwrap();
+    runtime
+        .run_on_workers(|mut runtime_ref: RuntimeRef| -> Result<(), !> {
+            let server = server as fn(_) -> _;
+            let _ = runtime_ref.spawn_local(NoSupervisor, server, (), ActorOptions::default());
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+    assert!(DONE.load(Ordering::SeqCst));
+}
+
+#[test]
+fn dropped_operations_are_cancelled() {
+    static DONE: AtomicBool = AtomicBool::new(false);
+
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>, address: SocketAddr) {
+        let mut stream = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+
+        // Drop a receive operation before any data arrives.
+        let mut buf = Vec::with_capacity(128);
+        let recv = stream.recv(&mut buf);
+        let err = Deadline::after(&mut ctx, Duration::from_millis(50), recv)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        // The data should be received by the new operation, not the cancelled
+        // one.
+        stream.send_all(b"1").await.unwrap();
+        stream.recv_n(&mut buf, DATA.len()).await.unwrap();
+        assert_eq!(buf, DATA);
+
+        // Same for accepting connections.
+        let mut listener = TcpListener::bind(&mut ctx, any_local_address()).unwrap();
+        let address = listener.local_addr().unwrap();
+        let accept = listener.accept();
+        let err = Deadline::after(&mut ctx, Duration::from_millis(50), accept)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        let mut client = TcpStream::connect(&mut ctx, address)
+            .unwrap()
+            .await
+            .unwrap();
+        let (server, peer_address) = listener.accept().await.unwrap();
+        assert_eq!(peer_address, client.local_addr().unwrap());
+        let mut server = server.bind_to(&mut ctx).unwrap();
+        client.send_all(DATA).await.unwrap();
+        buf.clear();
+        server.recv_n(&mut buf, DATA.len()).await.unwrap();
+        assert_eq!(buf, DATA);
+
+        DONE.store(true, Ordering::SeqCst);
+    }
+
+    let listener = net::TcpListener::bind(any_local_address()).unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 1];
+        stream.read_exact(&mut buf).unwrap();
+        stream.write_all(DATA).unwrap();
+        // Wait for the actor to close the connection.
+        let _ = stream.read(&mut buf);
+    });
+
+    let mut runtime = Runtime::setup().use_io_uring().build().unwrap();
+    runtime
+        .run_on_workers(move |mut runtime_ref: RuntimeRef| -> Result<(), !> {
+            let actor = actor as fn(_, _) -> _;
+            let _ = runtime_ref.spawn_local(NoSupervisor, actor, address, ActorOptions::default());
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+    peer.join().unwrap();
+    assert!(DONE.load(Ordering::SeqCst));
+}
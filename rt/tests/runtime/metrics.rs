@@ -0,0 +1,208 @@
+//! Tests for the metrics module.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+
+use heph::actor::{self, SyncContext};
+use heph::messages::Terminate;
+use heph::supervisor::NoSupervisor;
+use heph::ActorRef;
+use heph_rt::net::tcp::server;
+use heph_rt::net::TcpStream;
+use heph_rt::spawn::{ActorOptions, SyncActorOptions};
+use heph_rt::test::PanicSupervisor;
+use heph_rt::timer::Timer;
+use heph_rt::{metrics, Runtime, ThreadLocal};
+
+use crate::util::any_local_address;
+
+#[test]
+fn registry_returns_same_metric() {
+    let runtime = Runtime::new().unwrap();
+    let registry = runtime.metrics_registry();
+
+    let counter = registry.counter("test_total", "Test counter.");
+    counter.inc();
+    registry.counter("test_total", "Test counter.").add(2);
+    assert_eq!(counter.get(), 3);
+
+    let gauge = registry.gauge("test_gauge", "Test gauge.");
+    gauge.set(10);
+    gauge.dec();
+    assert_eq!(registry.gauge("test_gauge", "Test gauge.").get(), 9);
+
+    let histogram = registry.histogram("test_seconds", "Test.", metrics::DEFAULT_BUCKETS);
+    histogram.observe(0.5);
+    histogram.observe(1.5);
+    assert_eq!(histogram.count(), 2);
+    assert_eq!(histogram.sum(), 2.0);
+}
+
+#[test]
+#[should_panic = "metric 'test_total' already registered as counter"]
+fn registry_different_kind() {
+    let runtime = Runtime::new().unwrap();
+    let registry = runtime.metrics_registry();
+    let _ = registry.counter("test_total", "Test counter.");
+    let _ = registry.gauge("test_total", "Test gauge.");
+}
+
+#[test]
+#[should_panic = "invalid metric name: 'not valid'"]
+fn registry_invalid_name() {
+    let runtime = Runtime::new().unwrap();
+    let _ = runtime.metrics_registry().counter("not valid", "");
+}
+
+#[test]
+fn runtime_metrics() {
+    fn sync_actor<RT>(_: SyncContext<!, RT>) {}
+
+    let mut runtime = Runtime::setup()
+        .with_name("metrics_test".to_owned())
+        .num_threads(2)
+        .build()
+        .unwrap();
+    let _ = runtime
+        .spawn_sync_actor(
+            NoSupervisor,
+            sync_actor as fn(_) -> _,
+            (),
+            SyncActorOptions::default(),
+        )
+        .unwrap();
+
+    let metrics = runtime.metrics();
+    assert_eq!(metrics.app_name, "metrics_test");
+    assert_eq!(metrics.process_id, std::process::id());
+    assert_eq!(metrics.trace_counter, None);
+    assert_eq!(metrics.workers.len(), 2);
+    assert_eq!(metrics.workers[0].id, 1);
+    assert_eq!(metrics.workers[1].id, 2);
+    assert_eq!(metrics.sync_workers.len(), 1);
+    assert_eq!(metrics.sync_workers[0].id, 10000);
+
+    runtime
+        .run_on_workers(|runtime_ref| -> Result<(), !> {
+            let metrics = runtime_ref.metrics();
+            assert_eq!(metrics.workers.len(), 2);
+            assert_eq!(metrics.sync_workers.len(), 1);
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+}
+
+#[test]
+fn slow_poll() {
+    static SLOW_POLLS: AtomicU64 = AtomicU64::new(0);
+
+    async fn blocking_actor(
+        mut ctx: actor::Context<!, ThreadLocal>,
+        slow_polls: &'static AtomicU64,
+    ) {
+        // Block the worker thread, should be reported as slow poll.
+        sleep(Duration::from_millis(50));
+        // The slow poll is recorded after the poll returns.
+        Timer::after(&mut ctx, Duration::from_millis(1)).await;
+
+        let metrics = ctx.runtime_ref().metrics();
+        let polls = metrics.actors.iter().map(|actor| actor.slow_polls).sum();
+        slow_polls.store(polls, Ordering::SeqCst);
+    }
+
+    let mut runtime = Runtime::setup()
+        .slow_poll_threshold(Duration::from_millis(20))
+        .build()
+        .unwrap();
+    runtime
+        .run_on_workers(|mut runtime_ref| -> Result<(), !> {
+            let _ = runtime_ref.spawn_local(
+                NoSupervisor,
+                blocking_actor as fn(_, _) -> _,
+                &SLOW_POLLS,
+                ActorOptions::default(),
+            );
+            Ok(())
+        })
+        .unwrap();
+    assert!(runtime.metrics().actors.is_empty());
+    runtime.start().unwrap();
+    assert_eq!(SLOW_POLLS.load(Ordering::SeqCst), 1);
+}
+
+async fn request(
+    ctx: &mut actor::Context<!, ThreadLocal>,
+    address: SocketAddr,
+    data: &[u8],
+) -> String {
+    let mut stream = TcpStream::connect(ctx, address).unwrap().await.unwrap();
+    stream.send_all(data).await.unwrap();
+    let mut buf = Vec::with_capacity(8 * 1024);
+    loop {
+        if buf.len() == buf.capacity() {
+            buf.reserve(8 * 1024);
+        }
+        if stream.recv(&mut buf).await.unwrap() == 0 {
+            break;
+        }
+    }
+    String::from_utf8(buf).unwrap()
+}
+
+async fn client_actor(
+    mut ctx: actor::Context<!, ThreadLocal>,
+    address: SocketAddr,
+    server_ref: ActorRef<server::Message>,
+) {
+    let response = request(&mut ctx, address, b"GET /metrics HTTP/1.1\r\n\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("\n# TYPE heph_worker_threads gauge\nheph_worker_threads 1\n"));
+    assert!(response.contains("\nheph_worker_scheduler_ready{worker=\"1\"} "));
+    assert!(response.contains("\n# HELP test_requests_total Test requests.\n"));
+    assert!(response.contains("\ntest_requests_total 3\n"));
+
+    let response = request(&mut ctx, address, b"GET /other HTTP/1.1\r\n\r\n").await;
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+    let response = request(&mut ctx, address, b"POST /metrics HTTP/1.1\r\n\r\n").await;
+    assert!(
+        response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+        "{}",
+        response
+    );
+
+    server_ref.send(Terminate).await.unwrap();
+}
+
+#[test]
+fn server() {
+    let server = metrics::server(any_local_address()).unwrap();
+    let address = server.local_addr();
+
+    let mut runtime = Runtime::setup().num_threads(1).build().unwrap();
+    runtime
+        .metrics_registry()
+        .counter("test_requests_total", "Test requests.")
+        .add(3);
+    runtime
+        .run_on_workers(move |mut runtime_ref| -> Result<(), !> {
+            let server_ref = runtime_ref
+                .try_spawn_local(PanicSupervisor, server, (), ActorOptions::default())
+                .unwrap();
+            let _ = runtime_ref.spawn_local(
+                NoSupervisor,
+                client_actor as fn(_, _, _) -> _,
+                (address, server_ref),
+                ActorOptions::default(),
+            );
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+}
@@ -0,0 +1,156 @@
+//! Tests for building a runtime using `Setup`.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Duration;
+
+use heph::actor;
+use heph::supervisor::NoSupervisor;
+use heph_rt::spawn::ActorOptions;
+use heph_rt::timer::Timer;
+use heph_rt::{Runtime, Setup, ThreadLocal};
+
+use crate::util::temp_file;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn worker_cpus() {
+    use heph_rt::CpuSet;
+
+    use crate::util::thread_cpus;
+
+    let cpus: CpuSet = (0..1).collect();
+    let mut runtime = Runtime::setup()
+        .num_threads(2)
+        .worker_cpus(cpus)
+        .build()
+        .unwrap();
+    let checked = Arc::new(AtomicUsize::new(0));
+    let c = checked.clone();
+    runtime
+        .run_on_workers(move |_| -> Result<(), !> {
+            // Both workers should share the single CPU.
+            assert_eq!(thread_cpus(), [0]);
+            let _ = c.fetch_add(1, Ordering::AcqRel);
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+    assert_eq!(checked.load(Ordering::Acquire), 2);
+}
+
+#[test]
+fn setup_from_config_file() {
+    let path = temp_file("setup_from_config_file.conf");
+    let trace_path = temp_file("setup_from_config_file.bin.log");
+    let config = format!(
+        "# Comment.\n\nHEPH_APP_NAME=config_file\nHEPH_THREADS = 2\nHEPH_CPU_AFFINITY=0\nHEPH_TRACE_PATH={}\n",
+        trace_path.display()
+    );
+    std::fs::write(&path, config).unwrap();
+
+    let setup = Setup::from_config_file(&path).unwrap();
+    assert_eq!(setup.name(), Some("config_file"));
+    assert_eq!(setup.get_threads(), 2);
+    let runtime = setup.build().unwrap();
+    runtime.start().unwrap();
+    assert!(trace_path.exists());
+
+    std::fs::write(&path, "HEPH_UNKNOWN=1\n").unwrap();
+    let err = Setup::from_config_file(&path).unwrap_err();
+    assert!(err.to_string().contains("HEPH_UNKNOWN"), "{}", err);
+
+    std::fs::write(&path, "HEPH_CPU_AFFINITY=3-1\n").unwrap();
+    let err = Setup::from_config_file(&path).unwrap_err();
+    assert!(err.to_string().contains("HEPH_CPU_AFFINITY"), "{}", err);
+}
+
+#[test]
+fn setup_from_config_file_trace_path_overwritten() {
+    let path = temp_file("setup_from_config_file_trace_path_overwritten.conf");
+    let env_trace_path = temp_file("setup_from_config_file_trace_path_overwritten.env.bin.log");
+    let trace_path = temp_file("setup_from_config_file_trace_path_overwritten.bin.log");
+    let config = format!("HEPH_TRACE_PATH={}\n", env_trace_path.display());
+    std::fs::write(&path, config).unwrap();
+
+    let mut setup = Setup::from_config_file(&path).unwrap();
+    assert!(!env_trace_path.exists());
+    setup.enable_tracing(&trace_path).unwrap();
+    let runtime = setup.build().unwrap();
+    runtime.start().unwrap();
+    assert!(trace_path.exists());
+    // The path from the configuration should never be created.
+    assert!(!env_trace_path.exists());
+}
+
+#[test]
+fn watchdog_ignores_idle_workers() {
+    async fn actor(mut ctx: actor::Context<!, ThreadLocal>) {
+        // Waiting for a timer is not considered stuck, otherwise the watchdog
+        // would abort the test.
+        Timer::after(&mut ctx, Duration::from_millis(300)).await;
+    }
+
+    let mut runtime = Runtime::setup()
+        .num_threads(2)
+        .watchdog(Duration::from_millis(100), true)
+        .build()
+        .unwrap();
+    runtime
+        .run_on_workers(|mut runtime_ref| -> Result<(), !> {
+            let _ = runtime_ref.spawn_local(
+                NoSupervisor,
+                actor as fn(_) -> _,
+                (),
+                ActorOptions::default(),
+            );
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+}
+
+#[test]
+fn watchdog_reports_stuck_worker() {
+    static STUCK: AtomicU64 = AtomicU64::new(u64::MAX);
+
+    async fn stuck_actor(mut ctx: actor::Context<!, ThreadLocal>) {
+        assert_eq!(stuck(&ctx), 0);
+        // Block the worker thread, should be reported as stuck (once).
+        sleep(Duration::from_millis(400));
+        // Unblocked worker threads are no longer stuck.
+        Timer::after(&mut ctx, Duration::from_millis(200)).await;
+        STUCK.store(stuck(&ctx), Ordering::SeqCst);
+    }
+
+    fn stuck(ctx: &actor::Context<!, ThreadLocal>) -> u64 {
+        let metrics = ctx.runtime_ref().metrics();
+        metrics.workers.iter().map(|worker| worker.stuck).sum()
+    }
+
+    let mut runtime = Runtime::setup()
+        .num_threads(2)
+        // Don't abort, that would abort the test.
+        .watchdog(Duration::from_millis(100), false)
+        .build()
+        .unwrap();
+    // Only one worker thread gets stuck, the other is idle and shouldn't be
+    // reported.
+    let spawned = Arc::new(AtomicUsize::new(0));
+    runtime
+        .run_on_workers(move |mut runtime_ref| -> Result<(), !> {
+            if spawned.fetch_add(1, Ordering::AcqRel) == 0 {
+                let _ = runtime_ref.spawn_local(
+                    NoSupervisor,
+                    stuck_actor as fn(_) -> _,
+                    (),
+                    ActorOptions::default(),
+                );
+            }
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+    assert_eq!(STUCK.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,214 @@
+//! Tests for tracing the runtime.
+
+use std::collections::HashSet;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use heph::actor;
+use heph::actor_ref::{ActorRef, RpcMessage};
+use heph::supervisor::NoSupervisor;
+use heph_rt::spawn::ActorOptions;
+use heph_rt::trace::{FlowId, RecorderLimit, TraceFilter};
+use heph_rt::{Runtime, ThreadLocal};
+
+use crate::util::temp_file;
+
+#[test]
+fn tracing_filter() {
+    let trace_path = temp_file("runtime_trace_filter.bin.log");
+
+    let filter = TraceFilter::new()
+        .exclude_description("Spawning worker threads")
+        .sample_rate(0.5);
+    let mut setup = Runtime::setup().trace_filter(filter);
+    setup.enable_tracing(&trace_path).unwrap();
+    setup.build().unwrap().start().unwrap();
+
+    let contains = |trace: &[u8], value: &[u8]| trace.windows(value.len()).any(|w| w == value);
+    let trace = fs::read(&trace_path).unwrap();
+    // Filter should be recorded in the metadata.
+    assert!(contains(
+        &trace,
+        b"exclude_descriptions=[Spawning worker threads]"
+    ));
+    assert!(contains(&trace, b"sample_rate"));
+    // But no event with the description (prefixed with its length).
+    assert!(!contains(&trace, b"\x00\x17Spawning worker threads"));
+}
+
+#[test]
+fn tracing_process_metadata() {
+    let trace_path = temp_file("runtime_trace_process.bin.log");
+
+    let mut setup = Runtime::setup().with_name("trace_app".to_owned());
+    setup.enable_tracing(&trace_path).unwrap();
+    setup.build().unwrap().start().unwrap();
+
+    let contains = |trace: &[u8], value: &[u8]| trace.windows(value.len()).any(|w| w == value);
+    let trace = fs::read(&trace_path).unwrap();
+    assert!(contains(&trace, b"host_id"));
+    assert!(contains(&trace, b"\x00\x09trace_app"));
+    let mut process_id = b"\x00\x0Aprocess_id".to_vec();
+    process_id.extend_from_slice(&std::process::id().to_be_bytes());
+    assert!(contains(&trace, &process_id));
+    // Names of the coordinator and worker streams.
+    assert!(contains(&trace, b"\x00\x00\x00\x00\x00\x0BCoordinator"));
+    assert!(contains(&trace, b"\x00\x00\x00\x01\x00\x08Worker 1"));
+}
+
+#[test]
+fn flight_recorder_dump_trace() {
+    let trace_path = temp_file("flight_recorder.bin.log");
+
+    let mut runtime = Runtime::setup()
+        .enable_flight_recorder(&trace_path, RecorderLimit::Events(100), None)
+        .build()
+        .unwrap();
+    runtime
+        .run_on_workers(|runtime_ref| runtime_ref.dump_trace())
+        .unwrap();
+    runtime.start().unwrap();
+
+    // Should start with the epoch metadata packet.
+    let trace = fs::read(&trace_path).unwrap();
+    assert_eq!(&trace[..4], &0x75D11D4D_u32.to_be_bytes());
+    let contains = |trace: &[u8], value: &[u8]| trace.windows(value.len()).any(|w| w == value);
+    assert!(contains(&trace, b"\x00\x00\x00\x01\x00\x08Worker 1"));
+    // Only dumped once.
+    assert!(!temp_file("flight_recorder.1.bin.log").exists());
+}
+
+#[test]
+fn flight_recorder_dump_on_panic() {
+    static PANIC_RAN: AtomicBool = AtomicBool::new(false);
+
+    async fn panic_actor<RT>(_: actor::Context<!, RT>, mark: &'static AtomicBool) {
+        mark.store(true, Ordering::SeqCst);
+        panic!("on purpose panic");
+    }
+
+    let trace_path = temp_file("flight_recorder_panic.bin.log");
+    let limit = RecorderLimit::Duration(Duration::from_secs(60));
+    let mut runtime = Runtime::setup()
+        .enable_flight_recorder(&trace_path, limit, None)
+        .build()
+        .unwrap();
+    runtime
+        .run_on_workers(|mut runtime_ref| -> Result<(), !> {
+            runtime_ref.spawn_local(
+                NoSupervisor,
+                panic_actor as fn(_, _) -> _,
+                &PANIC_RAN,
+                ActorOptions::default(),
+            );
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+
+    assert!(PANIC_RAN.load(Ordering::SeqCst));
+    assert!(trace_path.exists());
+}
+
+#[test]
+fn flow_id_unique() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| (0..1000).map(|_| FlowId::new()).collect::<Vec<_>>()))
+        .collect();
+    let mut ids = HashSet::new();
+    for handle in handles {
+        let flow_ids = handle.join().unwrap();
+        // Ids are increasing within a thread.
+        assert!(flow_ids.windows(2).all(|w| w[0].get() < w[1].get()));
+        for flow_id in flow_ids {
+            assert!(ids.insert(flow_id), "duplicate flow id: {:?}", flow_id);
+        }
+    }
+    assert_eq!(ids.len(), 4000);
+}
+
+#[test]
+fn tracing_message_flows() {
+    enum Message {
+        Hello(usize),
+        Rpc(RpcMessage<usize, usize>),
+    }
+
+    impl From<RpcMessage<usize, usize>> for Message {
+        fn from(msg: RpcMessage<usize, usize>) -> Message {
+            Message::Rpc(msg)
+        }
+    }
+
+    async fn receiver_actor(mut ctx: actor::Context<Message, ThreadLocal>) {
+        for _ in 0..2 {
+            match ctx.receive_next().await.unwrap() {
+                Message::Hello(value) => assert_eq!(value, 1),
+                Message::Rpc(msg) => msg.handle(|value| value + 1).unwrap(),
+            }
+        }
+    }
+
+    async fn sender_actor(_: actor::Context<!, ThreadLocal>, actor_ref: ActorRef<Message>) {
+        actor_ref.send(Message::Hello(1)).await.unwrap();
+        let response: usize = actor_ref.rpc(1).await.unwrap();
+        assert_eq!(response, 2);
+        FLOWS_RAN.store(true, Ordering::SeqCst);
+    }
+
+    static FLOWS_RAN: AtomicBool = AtomicBool::new(false);
+
+    let trace_path = temp_file("runtime_trace_flows.bin.log");
+    let mut setup = Runtime::setup().num_threads(1);
+    setup.enable_tracing(&trace_path).unwrap();
+    let mut runtime = setup.build().unwrap();
+    runtime
+        .run_on_workers(|mut runtime_ref| -> Result<(), !> {
+            let actor_ref = runtime_ref.spawn_local(
+                NoSupervisor,
+                receiver_actor as fn(_) -> _,
+                (),
+                ActorOptions::default(),
+            );
+            let _ = runtime_ref.spawn_local(
+                NoSupervisor,
+                sender_actor as fn(_, _) -> _,
+                actor_ref,
+                ActorOptions::default(),
+            );
+            Ok(())
+        })
+        .unwrap();
+    runtime.start().unwrap();
+    assert!(FLOWS_RAN.load(Ordering::SeqCst));
+
+    let trace = fs::read(&trace_path).unwrap();
+    let received = flow_ids(&trace, "Receiving message");
+    // Message send using `ActorRef::send`.
+    let sent = flow_ids(&trace, "Sending message");
+    assert_eq!(sent.len(), 1, "{:?}", sent);
+    assert!(received.contains(&sent[0]));
+    // RPC request and response are part of the same flow.
+    let rpc = flow_ids(&trace, "Sending RPC request");
+    assert_eq!(rpc.len(), 1, "{:?}", rpc);
+    assert!(received.contains(&rpc[0]));
+    assert_eq!(flow_ids(&trace, "Sending RPC response"), rpc);
+    assert_eq!(flow_ids(&trace, "Receiving RPC response"), rpc);
+    assert_ne!(sent, rpc);
+}
+
+/// Returns the flow ids of the events with `description` in `trace`, assuming
+/// the flow id is the first attribute.
+fn flow_ids(trace: &[u8], description: &str) -> Vec<u64> {
+    let mut pattern = (description.len() as u16).to_be_bytes().to_vec();
+    pattern.extend_from_slice(description.as_bytes());
+    // Attribute name (length prefixed) and type (unsigned integer).
+    pattern.extend_from_slice(b"\x00\x07flow_id\x01");
+    trace
+        .windows(pattern.len() + 8)
+        .filter(|window| window.starts_with(&pattern))
+        .map(|window| u64::from_be_bytes(window[pattern.len()..].try_into().unwrap()))
+        .collect()
+}
@@ -67,6 +67,20 @@ pub fn temp_file(name: &str) -> PathBuf {
     dir
 }
 
+/// Returns the CPUs the current thread is allowed to run on.
+#[cfg(target_os = "linux")]
+pub fn thread_cpus() -> Vec<usize> {
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let thread = unsafe { libc::pthread_self() };
+    let res = unsafe {
+        libc::pthread_getaffinity_np(thread, std::mem::size_of_val(&cpu_set), &mut cpu_set)
+    };
+    assert_eq!(res, 0, "failed to get thread affinity");
+    (0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &cpu_set) })
+        .collect()
+}
+
 #[track_caller]
 pub fn expect_pending<T>(poll: Poll<T>)
 where
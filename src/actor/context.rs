@@ -7,7 +7,7 @@ use std::task::{self, Poll};
 
 use heph_inbox::{self as inbox, Receiver, RecvValue};
 
-use crate::actor_ref::ActorRef;
+use crate::actor_ref::{ActorRef, Envelope};
 
 /// The context in which an actor is executed.
 ///
@@ -28,7 +28,7 @@ pub struct Context<M, RT> {
     ///
     /// This field is public because it is used by `TcpServer`, as we don't need
     /// entire context there.
-    pub(crate) inbox: Receiver<M>,
+    pub(crate) inbox: Receiver<Envelope<M>>,
     /// Runtime access.
     rt: RT,
 }
@@ -36,7 +36,7 @@ pub struct Context<M, RT> {
 impl<M, RT> Context<M, RT> {
     /// Create a new `actor::Context`.
     #[doc(hidden)] // Not part of the stable API.
-    pub const fn new(inbox: Receiver<M>, rt: RT) -> Context<M, RT> {
+    pub const fn new(inbox: Receiver<Envelope<M>>, rt: RT) -> Context<M, RT> {
         Context { inbox, rt }
     }
 
@@ -68,7 +68,10 @@ impl<M, RT> Context<M, RT> {
     /// # drop(greeter_actor);
     /// ```
     pub fn try_receive_next(&mut self) -> Result<M, RecvError> {
-        self.inbox.try_recv().map_err(RecvError::from)
+        self.inbox
+            .try_recv()
+            .map(Envelope::receive)
+            .map_err(RecvError::from)
     }
 
     /// Receive the next message.
@@ -176,7 +179,7 @@ impl RecvError {
 #[derive(Debug)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct ReceiveMessage<'ctx, M> {
-    recv: RecvValue<'ctx, M>,
+    recv: RecvValue<'ctx, Envelope<M>>,
 }
 
 impl<'ctx, M> Future for ReceiveMessage<'ctx, M> {
@@ -185,7 +188,7 @@ impl<'ctx, M> Future for ReceiveMessage<'ctx, M> {
     fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
         Pin::new(&mut self.recv)
             .poll(ctx)
-            .map(|r| r.ok_or(NoMessages))
+            .map(|r| r.map(Envelope::receive).ok_or(NoMessages))
     }
 }
 
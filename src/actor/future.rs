@@ -11,7 +11,7 @@ use heph_inbox::{Manager, ReceiverConnected};
 use log::error;
 
 use crate::actor::{self, Actor, NewActor};
-use crate::actor_ref::ActorRef;
+use crate::actor_ref::{ActorRef, Envelope};
 use crate::supervisor::{Supervisor, SupervisorStrategy};
 
 /// A [`Future`] that represent an [`Actor`].
@@ -23,7 +23,7 @@ pub struct ActorFuture<S, NA: NewActor, RT> {
     new_actor: NA,
     /// The inbox of the actor, used in creating a new [`actor::Context`]
     /// if the actor is restarted.
-    inbox: Manager<NA::Message>,
+    inbox: Manager<Envelope<NA::Message>>,
     /// The running actor.
     actor: NA::Actor,
     /// Runtime access.
@@ -13,7 +13,7 @@ use heph_inbox::{self as inbox, ReceiverConnected};
 use log::trace;
 
 use crate::actor::{NoMessages, RecvError};
-use crate::actor_ref::ActorRef;
+use crate::actor_ref::{ActorRef, Envelope};
 use crate::supervisor::{SupervisorStrategy, SyncSupervisor};
 
 /// Synchronous actor.
@@ -165,7 +165,7 @@ impl_sync_actor!(
 /// messages.
 #[derive(Debug)]
 pub struct SyncContext<M, RT> {
-    inbox: Receiver<M>,
+    inbox: Receiver<Envelope<M>>,
     future_waker: Option<Arc<SyncWaker>>,
     /// Runtime access.
     rt: RT,
@@ -174,7 +174,7 @@ pub struct SyncContext<M, RT> {
 impl<M, RT> SyncContext<M, RT> {
     /// Create a new `SyncContext`.
     #[doc(hidden)] // Not part of the stable API.
-    pub const fn new(inbox: Receiver<M>, rt: RT) -> SyncContext<M, RT> {
+    pub const fn new(inbox: Receiver<Envelope<M>>, rt: RT) -> SyncContext<M, RT> {
         SyncContext {
             inbox,
             future_waker: None,
@@ -210,7 +210,10 @@ impl<M, RT> SyncContext<M, RT> {
     /// # assert_sync_actor(greeter_actor as fn(_) -> _);
     /// ```
     pub fn try_receive_next(&mut self) -> Result<M, RecvError> {
-        self.inbox.try_recv().map_err(RecvError::from)
+        self.inbox
+            .try_recv()
+            .map(Envelope::receive)
+            .map_err(RecvError::from)
     }
 
     /// Receive the next message.
@@ -239,7 +242,10 @@ impl<M, RT> SyncContext<M, RT> {
     /// ```
     pub fn receive_next(&mut self) -> Result<M, NoMessages> {
         let waker = self.future_waker();
-        waker.block_on(self.inbox.recv()).ok_or(NoMessages)
+        waker
+            .block_on(self.inbox.recv())
+            .map(Envelope::receive)
+            .ok_or(NoMessages)
     }
 
     /// Block on a [`Future`] waiting for it's completion.
@@ -398,7 +404,7 @@ where
 struct SyncWorker<S, A: SyncActor> {
     supervisor: S,
     actor: A,
-    inbox: inbox::Manager<A::Message>,
+    inbox: inbox::Manager<Envelope<A::Message>>,
 }
 
 impl<S, A> SyncWorker<S, A>
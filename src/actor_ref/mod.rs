@@ -110,6 +110,7 @@ use std::error::Error;
 use std::fmt;
 use std::future::Future;
 use std::iter::FromIterator;
+use std::num::NonZeroU64;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -117,6 +118,8 @@ use std::task::{self, Poll};
 
 use heph_inbox::{self as inbox, Sender};
 
+use crate::trace::{self, FlowEvent};
+
 pub mod rpc;
 #[doc(no_inline)]
 pub use rpc::{Rpc, RpcError, RpcMessage, RpcResponse};
@@ -133,7 +136,7 @@ pub struct ActorRef<M> {
 
 enum ActorRefKind<M> {
     /// Reference to an actor running on the same node.
-    Local(Sender<M>),
+    Local(Sender<Envelope<M>>),
     /// Reference that attempts to map the message to a different type first.
     Mapped(Arc<dyn MappedActorRef<M>>),
 }
@@ -153,7 +156,7 @@ impl<M> Unpin for ActorRefKind<M> {}
 impl<M> ActorRef<M> {
     /// Create a new `ActorRef` for an actor using `sender`.
     #[doc(hidden)] // Not part of the stable API.
-    pub const fn local(sender: Sender<M>) -> ActorRef<M> {
+    pub const fn local(sender: Sender<Envelope<M>>) -> ActorRef<M> {
         ActorRef {
             kind: ActorRefKind::Local(sender),
         }
@@ -176,12 +179,17 @@ impl<M> ActorRef<M> {
     where
         Msg: Into<M>,
     {
+        let flow_id = trace::start_flow(FlowEvent::SendMessage);
+        self.send_flow(msg.into(), flow_id)
+    }
+
+    /// Send a message to the actor, as part of the flow with `flow_id`.
+    fn send_flow(&self, msg: M, flow_id: Option<NonZeroU64>) -> SendValue<'_, M> {
         use ActorRefKind::*;
-        let msg = msg.into();
         SendValue {
             kind: match &self.kind {
-                Local(sender) => SendValueKind::Local(sender.send(msg)),
-                Mapped(actor_ref) => SendValueKind::Mapped(actor_ref.mapped_send(msg)),
+                Local(sender) => SendValueKind::Local(sender.send(Envelope { msg, flow_id })),
+                Mapped(actor_ref) => SendValueKind::Mapped(actor_ref.mapped_send(msg, flow_id)),
             },
         }
     }
@@ -200,17 +208,25 @@ impl<M> ActorRef<M> {
     where
         Msg: Into<M>,
     {
-        use ActorRefKind::*;
         #[cfg(any(test, feature = "test"))]
         if crate::test::should_lose_msg() {
             log::debug!("dropping message on purpose");
             return Ok(());
         }
 
-        let msg = msg.into();
+        let flow_id = trace::start_flow(FlowEvent::SendMessage);
+        self.try_send_flow(msg.into(), flow_id)
+    }
+
+    /// Attempt to send a message to the actor, as part of the flow with
+    /// `flow_id`.
+    fn try_send_flow(&self, msg: M, flow_id: Option<NonZeroU64>) -> Result<(), SendError> {
+        use ActorRefKind::*;
         match &self.kind {
-            Local(sender) => sender.try_send(msg).map_err(|_| SendError),
-            Mapped(actor_ref) => actor_ref.try_mapped_send(msg),
+            Local(sender) => sender
+                .try_send(Envelope { msg, flow_id })
+                .map_err(|_| SendError),
+            Mapped(actor_ref) => actor_ref.try_mapped_send(msg, flow_id),
         }
     }
 
@@ -406,6 +422,24 @@ impl<M> fmt::Debug for ActorRef<M> {
     }
 }
 
+/// Message in the inbox of an actor.
+#[doc(hidden)] // Not part of the stable API.
+#[derive(Debug)]
+pub struct Envelope<M> {
+    msg: M,
+    /// Id of the flow the message is part of, if it's traced. See the `trace`
+    /// module.
+    flow_id: Option<NonZeroU64>,
+}
+
+impl<M> Envelope<M> {
+    /// Returns the message, recording the receiving of it if it's traced.
+    pub(crate) fn receive(self) -> M {
+        trace::record(FlowEvent::ReceiveMessage, self.flow_id);
+        self.msg
+    }
+}
+
 /// Trait to erase the original message type of the actor reference.
 ///
 /// # Notes
@@ -413,9 +447,9 @@ impl<M> fmt::Debug for ActorRef<M> {
 /// For correctness this may only be implemented on [`ActorRef`].
 trait MappedActorRef<M> {
     /// Same as [`ActorRef::try_send`] but converts the message first.
-    fn try_mapped_send(&self, msg: M) -> Result<(), SendError>;
+    fn try_mapped_send(&self, msg: M, flow_id: Option<NonZeroU64>) -> Result<(), SendError>;
 
-    fn mapped_send<'r>(&'r self, msg: M) -> MappedSendValue<'r>;
+    fn mapped_send<'r>(&'r self, msg: M, flow_id: Option<NonZeroU64>) -> MappedSendValue<'r>;
 
     fn mapped_join<'r>(&'r self) -> MappedJoin<'r>;
 
@@ -428,23 +462,23 @@ impl<M, Msg> MappedActorRef<Msg> for ActorRef<M>
 where
     M: TryFrom<Msg>,
 {
-    fn try_mapped_send(&self, msg: Msg) -> Result<(), SendError> {
+    fn try_mapped_send(&self, msg: Msg, flow_id: Option<NonZeroU64>) -> Result<(), SendError> {
         M::try_from(msg)
             .map_err(|_| SendError)
-            .and_then(|msg| self.try_send(msg))
+            .and_then(|msg| self.try_send_flow(msg, flow_id))
     }
 
-    fn mapped_send<'r>(&'r self, msg: Msg) -> MappedSendValue<'r> {
+    fn mapped_send<'r>(&'r self, msg: Msg, flow_id: Option<NonZeroU64>) -> MappedSendValue<'r> {
         match M::try_from(msg) {
             Ok(msg) => match &self.kind {
-                ActorRefKind::Local(sender) => match sender.try_send(msg) {
+                ActorRefKind::Local(sender) => match sender.try_send(Envelope { msg, flow_id }) {
                     Ok(()) => MappedSendValue::Send,
-                    Err(heph_inbox::SendError::Full(msg)) => {
-                        MappedSendValue::Sending(Box::pin(self.send(msg)))
-                    }
+                    Err(heph_inbox::SendError::Full(envelope)) => MappedSendValue::Sending(
+                        Box::pin(self.send_flow(envelope.msg, envelope.flow_id)),
+                    ),
                     Err(heph_inbox::SendError::Disconnected(_)) => MappedSendValue::SendErr,
                 },
-                ActorRefKind::Mapped(sender) => sender.mapped_send(msg),
+                ActorRefKind::Mapped(sender) => sender.mapped_send(msg, flow_id),
             },
             Err(..) => MappedSendValue::SendErr,
         }
@@ -479,24 +513,24 @@ impl<M, Msg, F, E> MappedActorRef<Msg> for MappedActorRefFn<M, F>
 where
     F: Fn(Msg) -> Result<M, E>,
 {
-    fn try_mapped_send(&self, msg: Msg) -> Result<(), SendError> {
+    fn try_mapped_send(&self, msg: Msg, flow_id: Option<NonZeroU64>) -> Result<(), SendError> {
         match (self.map)(msg) {
-            Ok(msg) => self.actor_ref.try_send(msg),
+            Ok(msg) => self.actor_ref.try_send_flow(msg, flow_id),
             Err(..) => Err(SendError),
         }
     }
 
-    fn mapped_send<'r>(&'r self, msg: Msg) -> MappedSendValue<'r> {
+    fn mapped_send<'r>(&'r self, msg: Msg, flow_id: Option<NonZeroU64>) -> MappedSendValue<'r> {
         match (self.map)(msg) {
             Ok(msg) => match &self.actor_ref.kind {
-                ActorRefKind::Local(sender) => match sender.try_send(msg) {
+                ActorRefKind::Local(sender) => match sender.try_send(Envelope { msg, flow_id }) {
                     Ok(()) => MappedSendValue::Send,
-                    Err(heph_inbox::SendError::Full(msg)) => {
-                        MappedSendValue::Sending(Box::pin(self.actor_ref.send(msg)))
-                    }
+                    Err(heph_inbox::SendError::Full(envelope)) => MappedSendValue::Sending(
+                        Box::pin(self.actor_ref.send_flow(envelope.msg, envelope.flow_id)),
+                    ),
                     Err(heph_inbox::SendError::Disconnected(_)) => MappedSendValue::SendErr,
                 },
-                ActorRefKind::Mapped(sender) => sender.mapped_send(msg),
+                ActorRefKind::Mapped(sender) => sender.mapped_send(msg, flow_id),
             },
             Err(..) => MappedSendValue::SendErr,
         }
@@ -573,7 +607,7 @@ pub struct SendValue<'r, M> {
 }
 
 enum SendValueKind<'r, M> {
-    Local(inbox::SendValue<'r, M>),
+    Local(inbox::SendValue<'r, Envelope<M>>),
     Mapped(MappedSendValue<'r>),
 }
 
@@ -637,7 +671,7 @@ pub struct Join<'r, M> {
 }
 
 enum JoinKind<'r, M> {
-    Local(inbox::Join<'r, M>),
+    Local(inbox::Join<'r, Envelope<M>>),
     Mapped(MappedJoin<'r>),
 }
 
@@ -159,12 +159,14 @@
 use std::error::Error;
 use std::fmt;
 use std::future::Future;
+use std::num::NonZeroU64;
 use std::pin::Pin;
 use std::task::{self, Poll};
 
 use heph_inbox::oneshot::{new_oneshot, RecvOnce, Sender};
 
 use crate::actor_ref::{ActorRef, SendError, SendValue};
+use crate::trace::{self, FlowEvent};
 
 /// [`Future`] that resolves to a Remote Procedure Call (RPC) response.
 ///
@@ -174,6 +176,8 @@ use crate::actor_ref::{ActorRef, SendError, SendValue};
 pub struct Rpc<'r, M, Res> {
     send: Option<SendValue<'r, M>>,
     recv: RecvOnce<Res>,
+    /// Id of the flow the RPC is part of, if it's traced.
+    flow_id: Option<NonZeroU64>,
 }
 
 impl<'r, M, Res> Rpc<'r, M, Res> {
@@ -182,13 +186,15 @@ impl<'r, M, Res> Rpc<'r, M, Res> {
     where
         M: From<RpcMessage<Req, Res>>,
     {
+        let flow_id = trace::start_flow(FlowEvent::SendRpcRequest);
         let (sender, receiver) = new_oneshot();
-        let response = RpcResponse { sender };
+        let response = RpcResponse { sender, flow_id };
         let msg = RpcMessage { request, response };
-        let send = actor_ref.send(msg);
+        let send = actor_ref.send_flow(msg.into(), flow_id);
         Rpc {
             send: Some(send),
             recv: receiver.recv_once(),
+            flow_id,
         }
     }
 }
@@ -211,9 +217,13 @@ impl<'r, M, Res> Future for Rpc<'r, M, Res> {
             unsafe { self.as_mut().map_unchecked_mut(|s| &mut s.send) }.set(None);
         }
 
+        let flow_id = self.flow_id;
         // Safety: we're not moving `recv` so this is safe.
         match unsafe { self.map_unchecked_mut(|s| &mut s.recv) }.poll(ctx) {
-            Poll::Ready(Some(response)) => Poll::Ready(Ok(response)),
+            Poll::Ready(Some(response)) => {
+                trace::record(FlowEvent::ReceiveRpcResponse, flow_id);
+                Poll::Ready(Ok(response))
+            }
             Poll::Ready(None) => Poll::Ready(Err(RpcError::NoResponse)),
             Poll::Pending => Poll::Pending,
         }
@@ -294,11 +304,14 @@ impl<Req, Res> RpcMessage<Req, Res> {
 #[derive(Debug)]
 pub struct RpcResponse<Res> {
     sender: Sender<Res>,
+    /// Id of the flow the RPC is part of, if it's traced.
+    flow_id: Option<NonZeroU64>,
 }
 
 impl<Res> RpcResponse<Res> {
     /// Respond to a RPC request.
     pub fn respond(self, response: Res) -> Result<(), SendError> {
+        trace::record(FlowEvent::SendRpcResponse, self.flow_id);
         self.sender.try_send(response).map_err(|_| SendError)
     }
 
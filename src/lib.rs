@@ -95,6 +95,8 @@ pub mod quick_start;
 pub mod supervisor;
 #[cfg(any(test, feature = "test"))]
 pub mod test;
+#[doc(hidden)] // Not part of the stable API.
+pub mod trace;
 
 #[doc(no_inline)]
 pub use actor::{Actor, NewActor};
@@ -0,0 +1,75 @@
+//! Tracing of message flows.
+//!
+//! Messages send using [`ActorRef`]s and [`Rpc`]s are linked to the receiving
+//! of the message (and the response) using a flow id. This module allows the
+//! runtime to record these events by setting [`Hooks`] for the current thread,
+//! see [`set_hooks`].
+//!
+//! [`ActorRef`]: crate::actor_ref::ActorRef
+//! [`Rpc`]: crate::actor_ref::Rpc
+//!
+//! Not part of the stable API.
+
+use std::cell::Cell;
+use std::num::NonZeroU64;
+
+/// Hooks called to trace message flows.
+#[derive(Copy, Clone, Debug)]
+pub struct Hooks {
+    /// Start a new flow with `event`, returning the flow id. Returns `None` if
+    /// the flow is not traced.
+    pub start_flow: fn(event: FlowEvent) -> Option<NonZeroU64>,
+    /// Record `event` as part of the flow with `flow_id`.
+    pub record: fn(event: FlowEvent, flow_id: NonZeroU64),
+}
+
+/// Event in a message flow.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FlowEvent {
+    /// Sending of a message, using [`ActorRef::send`] or
+    /// [`ActorRef::try_send`].
+    ///
+    /// [`ActorRef::send`]: crate::actor_ref::ActorRef::send
+    /// [`ActorRef::try_send`]: crate::actor_ref::ActorRef::try_send
+    SendMessage,
+    /// Receiving of a message, including RPC requests.
+    ReceiveMessage,
+    /// Sending of a RPC request, using [`ActorRef::rpc`].
+    ///
+    /// [`ActorRef::rpc`]: crate::actor_ref::ActorRef::rpc
+    SendRpcRequest,
+    /// Sending of a RPC response, using [`RpcResponse::respond`].
+    ///
+    /// [`RpcResponse::respond`]: crate::actor_ref::RpcResponse::respond
+    SendRpcResponse,
+    /// Receiving of a RPC response.
+    ReceiveRpcResponse,
+}
+
+thread_local! {
+    /// Hooks set for the current thread.
+    static HOOKS: Cell<Option<Hooks>> = Cell::new(None);
+}
+
+/// Set the `hooks` used to trace message flows on the current thread.
+///
+/// Use `None` to stop tracing.
+pub fn set_hooks(hooks: Option<Hooks>) {
+    HOOKS.with(|h| h.set(hooks));
+}
+
+/// Start a new flow with `event`, if tracing on this thread.
+pub(crate) fn start_flow(event: FlowEvent) -> Option<NonZeroU64> {
+    HOOKS
+        .with(Cell::get)
+        .and_then(|hooks| (hooks.start_flow)(event))
+}
+
+/// Record `event` as part of the flow with `flow_id`, if any.
+pub(crate) fn record(event: FlowEvent, flow_id: Option<NonZeroU64>) {
+    if let Some(flow_id) = flow_id {
+        if let Some(hooks) = HOOKS.with(Cell::get) {
+            (hooks.record)(event, flow_id);
+        }
+    }
+}
@@ -23,4 +23,5 @@ mod functional {
     mod restart_supervisor;
     mod sync_actor;
     mod test;
+    mod trace;
 }
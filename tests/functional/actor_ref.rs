@@ -7,7 +7,7 @@ use crate::util::{assert_send, assert_size, assert_sync};
 #[test]
 fn size() {
     assert_size::<ActorRef<()>>(24);
-    assert_size::<SendValue<'_, ()>>(48);
+    assert_size::<SendValue<'_, ()>>(56);
     assert_size::<Join<'_, ()>>(40);
 }
 
@@ -0,0 +1,172 @@
+//! Tests for tracing message flows.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::num::NonZeroU64;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll, Wake, Waker};
+
+use heph::actor::{self, RecvError};
+use heph::actor_ref::{ActorRef, Envelope, RpcMessage};
+use heph::trace::{self, FlowEvent, Hooks};
+use heph_inbox::Manager;
+
+use FlowEvent::*;
+
+thread_local! {
+    /// Next flow id returned by `start_flow`.
+    static NEXT_ID: Cell<u64> = Cell::new(1);
+    /// Events recorded by the hooks.
+    static EVENTS: RefCell<Vec<(FlowEvent, u64)>> = RefCell::new(Vec::new());
+}
+
+fn start_flow(event: FlowEvent) -> Option<NonZeroU64> {
+    let id = NEXT_ID.with(|id| id.replace(id.get() + 1));
+    EVENTS.with(|events| events.borrow_mut().push((event, id)));
+    NonZeroU64::new(id)
+}
+
+fn record(event: FlowEvent, flow_id: NonZeroU64) {
+    EVENTS.with(|events| events.borrow_mut().push((event, flow_id.get())));
+}
+
+/// Sets the hooks for the current thread, until the returned guard is dropped.
+#[must_use]
+fn set_test_hooks() -> HooksGuard {
+    NEXT_ID.with(|id| id.set(1));
+    EVENTS.with(|events| events.borrow_mut().clear());
+    trace::set_hooks(Some(Hooks { start_flow, record }));
+    HooksGuard
+}
+
+/// Unsets the hooks when dropped, tests may run on the same thread.
+struct HooksGuard;
+
+impl Drop for HooksGuard {
+    fn drop(&mut self) {
+        trace::set_hooks(None);
+    }
+}
+
+/// Returns the events recorded so far.
+fn take_events() -> Vec<(FlowEvent, u64)> {
+    EVENTS.with(RefCell::take)
+}
+
+/// Returns an actor reference and context of a new actor.
+fn new_actor<M>() -> (Manager<Envelope<M>>, ActorRef<M>, actor::Context<M, ()>) {
+    let (manager, sender, receiver) = Manager::new_small_channel();
+    let ctx = actor::Context::new(receiver, ());
+    (manager, ActorRef::local(sender), ctx)
+}
+
+/// Poll `future` once.
+fn poll<Fut: Future + Unpin>(future: &mut Fut) -> Poll<Fut::Output> {
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut ctx = task::Context::from_waker(&waker);
+    Pin::new(future).poll(&mut ctx)
+}
+
+#[test]
+fn try_send() {
+    let _hooks = set_test_hooks();
+    let (_manager, actor_ref, mut ctx) = new_actor::<usize>();
+
+    actor_ref.try_send(1_usize).unwrap();
+    actor_ref.try_send(2_usize).unwrap();
+    assert_eq!(ctx.try_receive_next(), Ok(1));
+    assert_eq!(ctx.try_receive_next(), Ok(2));
+    assert_eq!(ctx.try_receive_next(), Err(RecvError::Empty));
+    assert_eq!(
+        take_events(),
+        [
+            (SendMessage, 1),
+            (SendMessage, 2),
+            (ReceiveMessage, 1),
+            (ReceiveMessage, 2)
+        ]
+    );
+}
+
+#[test]
+fn send() {
+    let _hooks = set_test_hooks();
+    let (_manager, actor_ref, mut ctx) = new_actor::<usize>();
+
+    let mut send = actor_ref.send(1_usize);
+    assert_eq!(poll(&mut send), Poll::Ready(Ok(())));
+    let mut receive = ctx.receive_next();
+    assert_eq!(poll(&mut receive), Poll::Ready(Ok(1)));
+    assert_eq!(take_events(), [(SendMessage, 1), (ReceiveMessage, 1)]);
+}
+
+#[test]
+fn mapped_actor_ref() {
+    let _hooks = set_test_hooks();
+    let (_manager, actor_ref, mut ctx) = new_actor::<usize>();
+    let actor_ref: ActorRef<u8> = actor_ref.map();
+    let actor_ref: ActorRef<u8> = actor_ref.map_fn(|msg: u8| msg + 1);
+
+    // A mapped actor reference should still only start a single flow.
+    actor_ref.try_send(1_u8).unwrap();
+    let mut send = actor_ref.send(2_u8);
+    assert_eq!(poll(&mut send), Poll::Ready(Ok(())));
+    assert_eq!(ctx.try_receive_next(), Ok(2));
+    assert_eq!(ctx.try_receive_next(), Ok(3));
+    assert_eq!(
+        take_events(),
+        [
+            (SendMessage, 1),
+            (SendMessage, 2),
+            (ReceiveMessage, 1),
+            (ReceiveMessage, 2)
+        ]
+    );
+}
+
+#[test]
+fn rpc() {
+    let _hooks = set_test_hooks();
+    let (_manager, actor_ref, mut ctx) = new_actor::<RpcMessage<usize, usize>>();
+
+    let mut rpc = actor_ref.rpc(1);
+    assert_eq!(poll(&mut rpc), Poll::Pending);
+    let msg = ctx.try_receive_next().unwrap();
+    msg.handle(|request| request + 1).unwrap();
+    assert_eq!(poll(&mut rpc), Poll::Ready(Ok(2)));
+    // The request and response are part of the same flow.
+    assert_eq!(
+        take_events(),
+        [
+            (SendRpcRequest, 1),
+            (ReceiveMessage, 1),
+            (SendRpcResponse, 1),
+            (ReceiveRpcResponse, 1)
+        ]
+    );
+}
+
+#[test]
+fn not_traced() {
+    let (_manager, actor_ref, mut ctx) = new_actor::<usize>();
+
+    // Messages send without hooks set are not part of a flow, so receiving
+    // them is not recorded either.
+    actor_ref.try_send(1_usize).unwrap();
+    let _hooks = set_test_hooks();
+    assert_eq!(ctx.try_receive_next(), Ok(1));
+    assert_eq!(take_events(), []);
+
+    // Same for stopping tracing.
+    actor_ref.try_send(2_usize).unwrap();
+    trace::set_hooks(None);
+    assert_eq!(ctx.try_receive_next(), Ok(2));
+    assert_eq!(take_events(), [(SendMessage, 1)]);
+}
@@ -0,0 +1,169 @@
+//! Conversion to [Chrome's Trace Event Format].
+//!
+//! Events with the same flow id are linked using flow events, which are shown
+//! as arrows from one event to the next.
+//!
+//! [Chrome's Trace Event Format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU/preview
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use heph_tools::json::{Escape, Float};
+use heph_tools::trace::{Event, Value};
+
+use crate::merge::Names;
+
+/// Write `events` to `output` in Chrome's Trace Event Format.
+pub(crate) fn convert<I, W>(events: I, names: &Names, output: &mut W) -> io::Result<()>
+where
+    I: Iterator<Item = Event>,
+    W: Write,
+{
+    output.write_all(b"{\n\t\"displayTimeUnit\": \"ns\",\n\t\"traceEvents\": [\n")?;
+
+    // Sometimes `Instant` returns a value that is equal to a previously
+    // returned value. Catapult can't really deal with this and creates two
+    // overlapping events, making them both unreadable and unusable.
+    // To fix this change the starting time and duration by a few microseconds
+    // to ensure the two events don't start at the same time.
+    //
+    // Maps `(pid, tid)` -> `timestamp` -> `duration`.
+    let mut times: HashMap<(u32, u64), HashMap<u128, u128>> = HashMap::new();
+
+    // Maps flow id -> `(pid, tid, timestamp)` of the events in the flow.
+    let mut flows: BTreeMap<u64, Vec<(u32, u64, u128)>> = BTreeMap::new();
+
+    let mut first = true;
+    for event in events {
+        let mut timestamp = event
+            .start
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_micros();
+        let mut duration = event.end.duration_since(event.start).unwrap().as_micros();
+
+        let process_id = event.stream_id;
+        let thread_id = event.substream_id;
+        loop {
+            let key = (process_id, thread_id);
+            match times.entry(key).or_default().entry(timestamp) {
+                Entry::Vacant(entry) => {
+                    entry.insert(duration);
+                    break;
+                }
+                Entry::Occupied(entry) => {
+                    let other_duration = *entry.get();
+                    if other_duration > duration {
+                        // Other event is the *overlapping* event. Delay the
+                        // start of this event and decrease it's duration.
+                        timestamp += 1;
+                        duration -= 1;
+                    } else {
+                        // This is the *overlapping* event, grow it.
+                        timestamp -= 1;
+                        duration += 1;
+                    }
+                }
+            }
+        }
+
+        write!(
+            output,
+            "{}\t\t{{\"pid\": {}, \"tid\": {}, \"ts\": {}, \"dur\": {}, \"name\": \"{}\"",
+            if first { "" } else { ",\n" },
+            process_id,
+            thread_id,
+            timestamp,
+            duration,
+            Escape(&event.description),
+        )?;
+        first = false;
+        let mut first_attribute = true;
+        if !event.attributes.is_empty() {
+            output.write_all(b", \"args\": {")?;
+            for (name, value) in &event.attributes {
+                let name = Escape(name);
+                let fmt_args = match value {
+                    // NOTE: `format_args!` is useless.
+                    Value::Unsigned(value) => format!("\"{}\": {}", name, value),
+                    Value::Signed(value) => format!("\"{}\": {}", name, value),
+                    Value::Float(value) => format!("\"{}\": {}", name, Float(*value)),
+                    Value::String(value) => format!("\"{}\": \"{}\"", name, Escape(value)),
+                };
+                write!(
+                    output,
+                    "{}{}",
+                    if first_attribute { "" } else { ", " },
+                    fmt_args
+                )?;
+                first_attribute = false;
+            }
+            output.write_all(b"}")?;
+        }
+        output.write_all(b", \"ph\": \"X\", \"cat\": \"\"}")?;
+
+        if let Some(flow_id) = event.flow_id() {
+            flows
+                .entry(flow_id)
+                .or_default()
+                .push((process_id, thread_id, timestamp));
+        }
+    }
+
+    // Name the processes and threads, if names are set.
+    let processes = names
+        .streams()
+        .map(|(stream_id, name)| ("process_name", stream_id, 0, name));
+    let threads = names
+        .substreams()
+        .map(|(stream_id, substream_id, name)| ("thread_name", stream_id, substream_id, name));
+    for (kind, process_id, thread_id, name) in processes.chain(threads) {
+        write!(
+            output,
+            "{}\t\t{{\"pid\": {}, \"tid\": {}, \"name\": \"{}\", \"ph\": \"M\", \
+            \"args\": {{\"name\": \"{}\"}}}}",
+            if first { "" } else { ",\n" },
+            process_id,
+            thread_id,
+            kind,
+            Escape(name),
+        )?;
+        first = false;
+    }
+
+    // Link the events in each flow in order of their start time. Each arrow
+    // is a separate flow in Chrome's format, which makes it easier to link
+    // events on the same thread.
+    let mut arrow_id: u64 = 0;
+    for (flow_id, mut events) in flows {
+        events.sort_by_key(|(_, _, timestamp)| *timestamp);
+        for pair in events.windows(2) {
+            write_arrow(output, arrow_id, flow_id, pair[0], pair[1])?;
+            arrow_id += 1;
+        }
+    }
+
+    output.write_all(b"\n\t]\n}")
+}
+
+/// Write a flow start and end event, linking the event at `from` to the event
+/// at `to`. Both are `(pid, tid, timestamp)`.
+fn write_arrow<W: Write>(
+    output: &mut W,
+    arrow_id: u64,
+    flow_id: u64,
+    from: (u32, u64, u128),
+    to: (u32, u64, u128),
+) -> io::Result<()> {
+    for (phase, (process_id, thread_id, timestamp)) in [("s", from), ("f", to)] {
+        write!(
+            output,
+            ",\n\t\t{{\"pid\": {}, \"tid\": {}, \"ts\": {}, \"id\": {}, \
+            \"name\": \"flow {}\", \"ph\": \"{}\", \"bp\": \"e\", \"cat\": \"flow\"}}",
+            process_id, thread_id, timestamp, arrow_id, flow_id, phase,
+        )?;
+    }
+    Ok(())
+}
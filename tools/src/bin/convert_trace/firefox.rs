@@ -0,0 +1,240 @@
+//! Conversion to [Firefox Profiler's processed profile format].
+//!
+//! Each stream is converted into a process and each substream into a thread
+//! of that process. Events are converted into interval markers on the thread
+//! of the substream, the attributes of the event are added as marker data.
+//!
+//! The profile is written using an older version of the format, the Firefox
+//! Profiler upgrades it to the latest version when it's loaded.
+//!
+//! [Firefox Profiler's processed profile format]: https://github.com/firefox-devtools/profiler/blob/main/docs-developer/PROCESSED-PROFILE-FORMAT.md
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+use heph_tools::json::{Escape, Float};
+use heph_tools::trace::{Event, Value};
+
+use crate::merge::Names;
+
+/// Version of the Gecko profile format the processed profile is based on.
+const GECKO_VERSION: u32 = 27;
+/// Version of the processed profile format.
+const PROCESSED_VERSION: u32 = 47;
+
+/// Name of the marker schema used for all events.
+const MARKER_TYPE: &str = "HephEvent";
+
+/// Write `events` to `output` in Firefox Profiler's processed profile format.
+pub(crate) fn convert<I, W>(events: I, names: &Names, output: &mut W) -> io::Result<()>
+where
+    I: Iterator<Item = Event>,
+    W: Write,
+{
+    // Group the events per stream and substream. Using a `BTreeMap` to get a
+    // stable order of the threads.
+    let mut threads: BTreeMap<(u32, u64), Vec<Event>> = BTreeMap::new();
+    // Format of all attributes, used in the marker schema.
+    let mut attributes: Vec<(String, &'static str)> = Vec::new();
+    let mut attribute_index: HashMap<String, usize> = HashMap::new();
+    let mut start_time = None;
+    for event in events {
+        for (name, value) in &event.attributes {
+            let format = match value {
+                Value::Unsigned(..) | Value::Signed(..) => "integer",
+                Value::Float(..) => "decimal",
+                Value::String(..) => "string",
+            };
+            match attribute_index.entry(name.clone()) {
+                Entry::Occupied(entry) => {
+                    let attribute = &mut attributes[*entry.get()];
+                    if attribute.1 != format {
+                        // Mixed types, fall back to displaying it as string.
+                        attribute.1 = "string";
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    let _ = entry.insert(attributes.len());
+                    attributes.push((name.clone(), format));
+                }
+            }
+        }
+        start_time = match start_time {
+            Some(start_time) if start_time <= event.start => Some(start_time),
+            _ => Some(event.start),
+        };
+        threads
+            .entry((event.stream_id, event.substream_id))
+            .or_default()
+            .push(event);
+    }
+    let start_time = start_time.unwrap_or(SystemTime::UNIX_EPOCH);
+
+    write!(
+        output,
+        "{{\"meta\":{{\"version\":{},\"preprocessedProfileVersion\":{},\"interval\":1,\
+        \"startTime\":{},\"processType\":0,\"product\":\"Heph\",\"stackwalk\":0,\
+        \"debug\":false,\"symbolicated\":true,\
+        \"categories\":[{{\"name\":\"Other\",\"color\":\"grey\",\"subcategories\":[\"Other\"]}}],\
+        \"extensions\":{{\"baseURL\":[],\"id\":[],\"name\":[],\"length\":0}},\
+        \"markerSchema\":[{{\"name\":\"{}\",\"display\":[\"marker-chart\",\"marker-table\",\"timeline-overview\"],\
+        \"chartLabel\":\"{{marker.name}}\",\"tooltipLabel\":\"{{marker.name}}\",\
+        \"tableLabel\":\"{{marker.name}}\",\"data\":[",
+        GECKO_VERSION,
+        PROCESSED_VERSION,
+        Float(millis(start_time.duration_since(SystemTime::UNIX_EPOCH).unwrap())),
+        MARKER_TYPE,
+    )?;
+    for (i, (name, format)) in attributes.iter().enumerate() {
+        write!(
+            output,
+            "{}{{\"key\":\"{}\",\"label\":\"{}\",\"format\":\"{}\",\"searchable\":true}}",
+            if i == 0 { "" } else { "," },
+            Escape(&marker_key(name)),
+            Escape(name),
+            format,
+        )?;
+    }
+    output.write_all(b"]}]},\"libs\":[],\"pages\":[],\"counters\":[],\"threads\":[")?;
+
+    for (i, ((stream_id, substream_id), events)) in threads.iter().enumerate() {
+        if i != 0 {
+            output.write_all(b",")?;
+        }
+        write_thread(output, names, start_time, *stream_id, *substream_id, events)?;
+    }
+    output.write_all(b"]}")
+}
+
+/// Write a single thread, with all `events` as markers.
+fn write_thread<W: Write>(
+    output: &mut W,
+    names: &Names,
+    start_time: SystemTime,
+    stream_id: u32,
+    substream_id: u64,
+    events: &[Event],
+) -> io::Result<()> {
+    write!(
+        output,
+        "{{\"processType\":\"default\",\"processStartupTime\":0,\"processShutdownTime\":null,\
+        \"registerTime\":0,\"unregisterTime\":null,\"pausedRanges\":[],\
+        \"name\":\"{}\",\"processName\":\"{}\",\"isMainThread\":{},\
+        \"pid\":\"{}\",\"tid\":{},",
+        Escape(&names.substream(stream_id, substream_id)),
+        Escape(&names.stream(stream_id)),
+        substream_id == 0,
+        stream_id,
+        substream_id,
+    )?;
+
+    // We don't have any samples, so all tables are empty.
+    output.write_all(
+        b"\"samples\":{\"weightType\":\"samples\",\"weight\":null,\"stack\":[],\"time\":[],\"length\":0},\
+        \"stackTable\":{\"frame\":[],\"prefix\":[],\"category\":[],\"subcategory\":[],\"length\":0},\
+        \"frameTable\":{\"address\":[],\"inlineDepth\":[],\"category\":[],\"subcategory\":[],\
+        \"func\":[],\"nativeSymbol\":[],\"innerWindowID\":[],\"implementation\":[],\"line\":[],\
+        \"column\":[],\"length\":0},\
+        \"funcTable\":{\"isJS\":[],\"relevantForJS\":[],\"name\":[],\"resource\":[],\
+        \"fileName\":[],\"lineNumber\":[],\"columnNumber\":[],\"length\":0},\
+        \"resourceTable\":{\"lib\":[],\"name\":[],\"host\":[],\"type\":[],\"length\":0},\
+        \"nativeSymbols\":{\"libIndex\":[],\"address\":[],\"name\":[],\"functionSize\":[],\"length\":0},",
+    )?;
+
+    // Marker names are indices into the string array, deduplicate them.
+    let mut strings: Vec<&str> = Vec::new();
+    let mut string_index: HashMap<&str, usize> = HashMap::new();
+    let mut names = Vec::with_capacity(events.len());
+    for event in events {
+        let index = *string_index
+            .entry(event.description.as_str())
+            .or_insert_with(|| {
+                strings.push(event.description.as_str());
+                strings.len() - 1
+            });
+        names.push(index);
+    }
+
+    output.write_all(b"\"markers\":{\"data\":[")?;
+    for (i, event) in events.iter().enumerate() {
+        write!(
+            output,
+            "{}{{\"type\":\"{}\"",
+            if i == 0 { "" } else { "," },
+            MARKER_TYPE
+        )?;
+        for (name, value) in &event.attributes {
+            write!(output, ",\"{}\":", Escape(&marker_key(name)))?;
+            match value {
+                Value::Unsigned(value) => write!(output, "{}", value)?,
+                Value::Signed(value) => write!(output, "{}", value)?,
+                Value::Float(value) => write!(output, "{}", Float(*value))?,
+                Value::String(value) => write!(output, "\"{}\"", Escape(value))?,
+            }
+        }
+        output.write_all(b"}")?;
+    }
+    output.write_all(b"],\"name\":")?;
+    write_array(output, names.iter())?;
+    output.write_all(b",\"startTime\":")?;
+    write_array(
+        output,
+        events.iter().map(|e| Float(relative(start_time, e.start))),
+    )?;
+    output.write_all(b",\"endTime\":")?;
+    write_array(
+        output,
+        events.iter().map(|e| Float(relative(start_time, e.end))),
+    )?;
+    // All markers are interval markers (phase 1) in the "Other" category.
+    output.write_all(b",\"phase\":")?;
+    write_array(output, events.iter().map(|_| 1))?;
+    output.write_all(b",\"category\":")?;
+    write_array(output, events.iter().map(|_| 0))?;
+    write!(output, ",\"length\":{}}},\"stringArray\":[", events.len())?;
+    for (i, string) in strings.iter().enumerate() {
+        write!(
+            output,
+            "{}\"{}\"",
+            if i == 0 { "" } else { "," },
+            Escape(string)
+        )?;
+    }
+    output.write_all(b"]}")
+}
+
+/// Write a JSON array of `values`.
+fn write_array<W, I>(output: &mut W, values: I) -> io::Result<()>
+where
+    W: Write,
+    I: Iterator,
+    I::Item: std::fmt::Display,
+{
+    output.write_all(b"[")?;
+    for (i, value) in values.enumerate() {
+        write!(output, "{}{}", if i == 0 { "" } else { "," }, value)?;
+    }
+    output.write_all(b"]")
+}
+
+/// Returns the key used in the marker data for the attribute `name`. `type` is
+/// used by the Firefox Profiler itself, so that is renamed.
+fn marker_key(name: &str) -> String {
+    if name == "type" {
+        "type_".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Returns the time of `time` relative to `start_time` in milliseconds.
+fn relative(start_time: SystemTime, time: SystemTime) -> f64 {
+    millis(time.duration_since(start_time).unwrap_or_default())
+}
+
+/// Returns `duration` in milliseconds, with nanosecond precision.
+fn millis(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / 1_000_000.0
+}
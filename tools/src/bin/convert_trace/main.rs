@@ -0,0 +1,169 @@
+//! Tool to convert a Heph trace into a format that can be opened by a trace
+//! viewer.
+//!
+//! The following formats are supported, selected using the `--format` flag:
+//!
+//!  * `chrome` (default): [Chrome's Trace Event Format], which can be opened
+//!    by [Catapult trace view] and [Perfetto UI].
+//!  * `perfetto`: [Perfetto's protobuf trace format], which can be opened by
+//!    [Perfetto UI]. Unlike the Chrome format this has nanosecond precision
+//!    and shows substreams as tracks nested under their stream.
+//!  * `firefox`: [Firefox Profiler's processed profile format], which can be
+//!    opened by the [Firefox Profiler].
+//!
+//! Usage:
+//!
+//! ```text
+//! convert_trace [--format chrome|perfetto|firefox] <input> [<output>]
+//! convert_trace [--format chrome|perfetto|firefox] --merge <output> <input>...
+//! ```
+//!
+//! Using `--merge` multiple traces, e.g. of different processes or hosts, are
+//! merged into a single timeline. Each input trace is shown as a separate
+//! process, labelled using the application name, process id and host id
+//! recorded in the trace. See the [`merge`] module for details.
+//!
+//! [Chrome's Trace Event Format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU/preview
+//! [Catapult trace view]: https://chromium.googlesource.com/catapult/+/refs/heads/master/tracing/README.md
+//! [Perfetto's protobuf trace format]: https://perfetto.dev/docs/reference/trace-packet-proto
+//! [Perfetto UI]: https://ui.perfetto.dev
+//! [Firefox Profiler's processed profile format]: https://github.com/firefox-devtools/profiler/blob/main/docs-developer/PROCESSED-PROFILE-FORMAT.md
+//! [Firefox Profiler]: https://profiler.firefox.com
+
+use std::env::args;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use heph_tools::trace::{Event, Trace};
+
+mod chrome;
+mod firefox;
+mod merge;
+mod perfetto;
+#[cfg(test)]
+mod tests;
+
+const USAGE: &str = "usage: convert_trace [--format chrome|perfetto|firefox] <input> [<output>]
+       convert_trace [--format chrome|perfetto|firefox] --merge <output> <input>...";
+
+/// Output format.
+#[derive(Copy, Clone, Debug)]
+enum Format {
+    Chrome,
+    Perfetto,
+    Firefox,
+}
+
+impl Format {
+    fn parse(format: &str) -> Option<Format> {
+        match format {
+            "chrome" => Some(Format::Chrome),
+            "perfetto" => Some(Format::Perfetto),
+            "firefox" => Some(Format::Firefox),
+            _ => None,
+        }
+    }
+
+    /// Extension used for the output file if no output path is given.
+    const fn extension(self) -> &'static str {
+        match self {
+            Format::Chrome => "json",
+            Format::Perfetto => "perfetto-trace",
+            Format::Firefox => "profile.json",
+        }
+    }
+}
+
+fn main() {
+    let mut format = Format::Chrome;
+    let mut merge = false;
+    let mut paths = Vec::with_capacity(2);
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = if arg == "--format" || arg == "-f" {
+            args.next()
+        } else if let Some(value) = arg.strip_prefix("--format=") {
+            Some(value.to_owned())
+        } else if arg == "--merge" {
+            merge = true;
+            continue;
+        } else if arg == "--help" || arg == "-h" {
+            println!("{}", USAGE);
+            return;
+        } else {
+            paths.push(arg);
+            continue;
+        };
+        format = match value.as_deref().and_then(Format::parse) {
+            Some(format) => format,
+            None => exit_usage("invalid or missing format"),
+        };
+    }
+
+    if merge {
+        if paths.len() < 2 {
+            exit_usage("missing output or input trace file paths");
+        }
+        let inputs = paths.split_off(1);
+        let (events, names) = merge::merge(&inputs);
+        convert(format, events.into_iter(), &names, &paths[0]);
+        return;
+    }
+
+    let mut paths = paths.into_iter();
+    let input = match paths.next() {
+        Some(input) => input,
+        None => exit_usage("missing input trace file path"),
+    };
+    let output = if let Some(output) = paths.next() {
+        PathBuf::from(output)
+    } else {
+        let end_idx = input.rfind('.').unwrap_or(input.len());
+        let mut output = PathBuf::from(&input[..end_idx]);
+        // If the input has a single extension this will add the extension to
+        // it. If however it has two extensions, e.g. `.bin.log` this will
+        // overwrite the extension.
+        output.set_extension(format.extension());
+        output
+    };
+    if paths.next().is_some() {
+        exit_usage("too many arguments");
+    }
+
+    let mut trace = Trace::open(input).expect("can't open trace file");
+    let events = trace
+        .events()
+        .map(|event| event.expect("error reading trace file"));
+    convert(format, events, &merge::Names::default(), output);
+}
+
+/// Convert `events` into `format`, writing it to a new file at `output`.
+fn convert<I, P>(format: Format, events: I, names: &merge::Names, output: P)
+where
+    I: Iterator<Item = Event>,
+    P: AsRef<Path>,
+{
+    let output = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output)
+        .expect("can't open output file");
+    let mut output = BufWriter::new(output);
+
+    match format {
+        Format::Chrome => chrome::convert(events, names, &mut output),
+        Format::Perfetto => perfetto::convert(events, names, &mut output),
+        Format::Firefox => firefox::convert(events, names, &mut output),
+    }
+    .expect("failed to write output");
+    output.flush().expect("failed to write output");
+
+    println!("OK.");
+}
+
+fn exit_usage(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    exit(1)
+}
@@ -0,0 +1,133 @@
+//! Merging of multiple traces into a single timeline.
+//!
+//! Each input trace is converted into a single process, with a thread (or
+//! track) for each stream and substream of the trace. The threads are named
+//! after the stream names in the metadata of the trace, if any. The timestamps of the
+//! events are already aligned as the parser uses the `epoch` metadata of each
+//! trace. Flow ids are only unique within a single trace, so they're made
+//! unique across traces as well.
+
+use std::borrow::Cow;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+use heph_tools::trace::{Event, Trace, Value, FLOW_ID_ATTRIBUTE};
+
+/// Number of bits the index of the trace is shifted when creating unique flow
+/// ids.
+pub(crate) const FLOW_ID_SHIFT: u32 = 48;
+
+/// Names of the streams and substreams.
+///
+/// If no name is set the stream or substream is named after its id.
+#[derive(Debug, Default)]
+pub(crate) struct Names {
+    streams: HashMap<u32, String>,
+    substreams: HashMap<(u32, u64), String>,
+}
+
+impl Names {
+    /// Returns the name of the stream with `stream_id`.
+    pub(crate) fn stream(&self, stream_id: u32) -> Cow<'_, str> {
+        match self.streams.get(&stream_id) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("Stream {}", stream_id)),
+        }
+    }
+
+    /// Returns the name of the substream with `substream_id` in the stream
+    /// with `stream_id`.
+    pub(crate) fn substream(&self, stream_id: u32, substream_id: u64) -> Cow<'_, str> {
+        match self.substreams.get(&(stream_id, substream_id)) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("Substream {}", substream_id)),
+        }
+    }
+
+    /// Returns all explicitly set stream names.
+    pub(crate) fn streams(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.streams.iter().map(|(id, name)| (*id, &**name))
+    }
+
+    /// Returns all explicitly set substream names.
+    pub(crate) fn substreams(&self) -> impl Iterator<Item = (u32, u64, &str)> {
+        self.substreams
+            .iter()
+            .map(|((stream_id, substream_id), name)| (*stream_id, *substream_id, &**name))
+    }
+}
+
+/// Read all events from the traces at `inputs`, returning the merged events
+/// and the names of the streams (one per input) and substreams.
+///
+/// The stream id of the events is set to the index of the input and the
+/// substream id to a unique id for the original stream and substream within
+/// the input.
+pub(crate) fn merge(inputs: &[String]) -> (Vec<Event>, Names) {
+    let mut events = Vec::new();
+    let mut names = Names::default();
+    for (index, input) in inputs.iter().enumerate() {
+        #[allow(clippy::cast_possible_truncation)]
+        let process_id = index as u32;
+        let mut trace = Trace::open(input).expect("can't open trace file");
+        // Maps `(stream_id, substream_id)` -> unique substream id.
+        let mut substreams: HashMap<(u32, u64), u64> = HashMap::new();
+        let mut trace_events = trace.events();
+        while let Some(event) = trace_events.next() {
+            let mut event = event.expect("error reading trace file");
+            let next_id = substreams.len() as u64;
+            let substream_id = match substreams.entry((event.stream_id, event.substream_id)) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    // Keep the original names of the stream (set before its
+                    // first event) and substream.
+                    let original = Names::default();
+                    let stream_name = match trace_events.trace().stream_name(event.stream_id) {
+                        Some(name) => Cow::Borrowed(name),
+                        None => original.stream(event.stream_id),
+                    };
+                    let name = format!(
+                        "{} / {}",
+                        stream_name,
+                        original.substream(event.stream_id, event.substream_id)
+                    );
+                    let _ = names.substreams.insert((process_id, next_id), name);
+                    *entry.insert(next_id)
+                }
+            };
+            event.stream_id = process_id;
+            event.substream_id = substream_id;
+            for (name, value) in &mut event.attributes {
+                if let (FLOW_ID_ATTRIBUTE, Value::Unsigned(flow_id)) = (name.as_str(), value) {
+                    *flow_id |= (index as u64) << FLOW_ID_SHIFT;
+                }
+            }
+            events.push(event);
+        }
+        let _ = names.streams.insert(process_id, label(&trace, input));
+    }
+    (events, names)
+}
+
+/// Returns a label for the process that created `trace`, falling back to the
+/// file name of `path` if the trace doesn't contain the process metadata.
+fn label<R>(trace: &Trace<R>, path: &str) -> String {
+    let mut label = match trace.app_name() {
+        Some(app_name) => app_name.to_owned(),
+        None => Path::new(path).file_name().map_or_else(
+            || path.to_owned(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+    };
+    if let Some(process_id) = trace.process_id() {
+        let _ = write!(label, " (pid {})", process_id);
+    }
+    if let Some(host_id) = trace.host_id() {
+        // Similar to Git's short commit hashes the first 8 characters are
+        // enough to tell hosts apart.
+        let short = host_id.get(..8).unwrap_or(host_id);
+        let _ = write!(label, " on host {}", short);
+    }
+    label
+}
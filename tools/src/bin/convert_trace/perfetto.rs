@@ -0,0 +1,306 @@
+//! Conversion to [Perfetto's protobuf trace format].
+//!
+//! Each stream is converted into a track, with a child track for each of its
+//! substreams. Events are converted into slices on the track of the substream.
+//! Events with the same flow id are linked using Perfetto's flows, which are
+//! shown as arrows from one event to the next.
+//!
+//! [Perfetto's protobuf trace format]: https://perfetto.dev/docs/reference/trace-packet-proto
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use heph_tools::trace::{Event, Value};
+
+use crate::merge::Names;
+
+// Field numbers of the messages we use, see the protobuf definitions in
+// <https://github.com/google/perfetto/tree/master/protos/perfetto/trace>.
+
+/// `Trace.packet`.
+const TRACE_PACKET: u32 = 1;
+
+/// `TracePacket.timestamp`.
+const PACKET_TIMESTAMP: u32 = 8;
+/// `TracePacket.trusted_packet_sequence_id`.
+const PACKET_SEQUENCE_ID: u32 = 10;
+/// `TracePacket.track_event`.
+const PACKET_TRACK_EVENT: u32 = 11;
+/// `TracePacket.sequence_flags`.
+const PACKET_SEQUENCE_FLAGS: u32 = 13;
+/// `TracePacket.track_descriptor`.
+const PACKET_TRACK_DESCRIPTOR: u32 = 60;
+
+/// `TrackDescriptor.uuid`.
+const TRACK_UUID: u32 = 1;
+/// `TrackDescriptor.name`.
+const TRACK_NAME: u32 = 2;
+/// `TrackDescriptor.process`.
+const TRACK_PROCESS: u32 = 3;
+/// `TrackDescriptor.parent_uuid`.
+const TRACK_PARENT_UUID: u32 = 5;
+
+/// `ProcessDescriptor.pid`.
+const PROCESS_PID: u32 = 1;
+/// `ProcessDescriptor.process_name`.
+const PROCESS_NAME: u32 = 6;
+
+/// `TrackEvent.debug_annotations`.
+const EVENT_DEBUG_ANNOTATIONS: u32 = 4;
+/// `TrackEvent.type`.
+const EVENT_TYPE: u32 = 9;
+/// `TrackEvent.track_uuid`.
+const EVENT_TRACK_UUID: u32 = 11;
+/// `TrackEvent.name`.
+const EVENT_NAME: u32 = 23;
+/// `TrackEvent.flow_ids`.
+const EVENT_FLOW_IDS: u32 = 47;
+
+/// `TrackEvent.Type.TYPE_SLICE_BEGIN`.
+const TYPE_SLICE_BEGIN: u64 = 1;
+/// `TrackEvent.Type.TYPE_SLICE_END`.
+const TYPE_SLICE_END: u64 = 2;
+
+/// `DebugAnnotation.uint_value`.
+const ANNOTATION_UINT: u32 = 3;
+/// `DebugAnnotation.int_value`.
+const ANNOTATION_INT: u32 = 4;
+/// `DebugAnnotation.double_value`.
+const ANNOTATION_DOUBLE: u32 = 5;
+/// `DebugAnnotation.string_value`.
+const ANNOTATION_STRING: u32 = 6;
+/// `DebugAnnotation.name`.
+const ANNOTATION_NAME: u32 = 10;
+
+/// `TracePacket.SequenceFlags.SEQ_INCREMENTAL_STATE_CLEARED`.
+const SEQ_INCREMENTAL_STATE_CLEARED: u64 = 1;
+
+/// Sequence id used for all packets, we only have a single writer.
+const SEQUENCE_ID: u64 = 1;
+
+/// Write `events` to `output` in Perfetto's protobuf trace format.
+pub(crate) fn convert<I, W>(events: I, names: &Names, output: &mut W) -> io::Result<()>
+where
+    I: Iterator<Item = Event>,
+    W: Write,
+{
+    // Group the events per stream and substream. Using a `BTreeMap` to get a
+    // stable order of the tracks.
+    let mut streams: BTreeMap<u32, BTreeMap<u64, Vec<Event>>> = BTreeMap::new();
+    for event in events {
+        streams
+            .entry(event.stream_id)
+            .or_default()
+            .entry(event.substream_id)
+            .or_default()
+            .push(event);
+    }
+
+    let mut packet = Message::new();
+    // Start of our (only) sequence of packets.
+    packet.uint(PACKET_SEQUENCE_FLAGS, SEQ_INCREMENTAL_STATE_CLEARED);
+    let mut next_uuid = 1;
+    // Slice begin and end packets and their timestamp.
+    let mut slices = Vec::new();
+    for (stream_id, substreams) in streams {
+        let stream_uuid = next_uuid;
+        next_uuid += 1;
+        let mut process = Message::new();
+        process.int(PROCESS_PID, i64::from(stream_id));
+        process.string(PROCESS_NAME, &names.stream(stream_id));
+        let mut track = Message::new();
+        track.uint(TRACK_UUID, stream_uuid);
+        track.message(TRACK_PROCESS, &process);
+        packet.message(PACKET_TRACK_DESCRIPTOR, &track);
+        write_packet(output, &mut packet)?;
+
+        for (substream_id, mut events) in substreams {
+            let track_uuid = next_uuid;
+            next_uuid += 1;
+            let mut track = Message::new();
+            track.uint(TRACK_UUID, track_uuid);
+            track.uint(TRACK_PARENT_UUID, stream_uuid);
+            track.string(TRACK_NAME, &names.substream(stream_id, substream_id));
+            packet.message(PACKET_TRACK_DESCRIPTOR, &track);
+            write_packet(output, &mut packet)?;
+
+            add_slices(&mut slices, track_uuid, &mut events);
+        }
+    }
+
+    // Perfetto requires the slices to be properly nested, for which the begin
+    // and end of the slices of a single track need to be in order. Sorting is
+    // stable so slices with the same timestamp stay in the order determined by
+    // `add_slices`.
+    slices.sort_by_key(|slice| slice.timestamp);
+    for slice in slices {
+        packet.uint(PACKET_TIMESTAMP, slice.timestamp);
+        packet.message(PACKET_TRACK_EVENT, &slice.track_event);
+        write_packet(output, &mut packet)?;
+    }
+    Ok(())
+}
+
+/// Begin or end of a slice.
+struct Slice {
+    timestamp: u64,
+    /// `TrackEvent` message.
+    track_event: Message,
+}
+
+/// Add the slice begin and end events for `events` on track `track_uuid` to
+/// `slices`.
+///
+/// Slices in a track must be properly nested, i.e. a child slice must end
+/// before its parent ends. Events that partially overlap their parent are
+/// truncated to end with their parent.
+fn add_slices(slices: &mut Vec<Slice>, track_uuid: u64, events: &mut [Event]) {
+    // Parents before their children: sort by start time, with the longest
+    // event first.
+    events.sort_by_key(|event| (event.start, Reverse(event.end)));
+
+    // End times of the open slices.
+    let mut open: Vec<u64> = Vec::new();
+    for event in events.iter() {
+        let start = nanos(event.start);
+        let mut end = nanos(event.end);
+        while let Some(parent_end) = open.last().copied() {
+            if parent_end <= start {
+                let _ = open.pop();
+                slices.push(slice_end(track_uuid, parent_end));
+            } else {
+                end = end.min(parent_end);
+                break;
+            }
+        }
+
+        let mut track_event = Message::new();
+        track_event.uint(EVENT_TYPE, TYPE_SLICE_BEGIN);
+        track_event.uint(EVENT_TRACK_UUID, track_uuid);
+        track_event.string(EVENT_NAME, &event.description);
+        if let Some(flow_id) = event.flow_id() {
+            track_event.fixed64(EVENT_FLOW_IDS, flow_id);
+        }
+        for (name, value) in &event.attributes {
+            let mut annotation = Message::new();
+            annotation.string(ANNOTATION_NAME, name);
+            match value {
+                Value::Unsigned(value) => annotation.uint(ANNOTATION_UINT, *value),
+                Value::Signed(value) => annotation.int(ANNOTATION_INT, *value),
+                Value::Float(value) => annotation.double(ANNOTATION_DOUBLE, *value),
+                Value::String(value) => annotation.string(ANNOTATION_STRING, value),
+            }
+            track_event.message(EVENT_DEBUG_ANNOTATIONS, &annotation);
+        }
+        slices.push(Slice {
+            timestamp: start,
+            track_event,
+        });
+        open.push(end);
+    }
+
+    while let Some(end) = open.pop() {
+        slices.push(slice_end(track_uuid, end));
+    }
+}
+
+/// Create a slice end event.
+fn slice_end(track_uuid: u64, timestamp: u64) -> Slice {
+    let mut track_event = Message::new();
+    track_event.uint(EVENT_TYPE, TYPE_SLICE_END);
+    track_event.uint(EVENT_TRACK_UUID, track_uuid);
+    Slice {
+        timestamp,
+        track_event,
+    }
+}
+
+/// Returns the number of nanoseconds since the Unix epoch.
+#[allow(clippy::cast_possible_truncation)] // Only overflows in the year 2554.
+fn nanos(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
+}
+
+/// Write `packet` as `TracePacket` to `output`, clearing `packet`.
+fn write_packet<W: Write>(output: &mut W, packet: &mut Message) -> io::Result<()> {
+    packet.uint(PACKET_SEQUENCE_ID, SEQUENCE_ID);
+    let mut trace = Message::new();
+    trace.message(TRACE_PACKET, packet);
+    packet.0.clear();
+    output.write_all(&trace.0)
+}
+
+/// Encoded protobuf message.
+struct Message(Vec<u8>);
+
+impl Message {
+    const fn new() -> Message {
+        Message(Vec::new())
+    }
+
+    /// Add a `uint64` (or `uint32` or enum) field.
+    fn uint(&mut self, field: u32, value: u64) {
+        self.key(field, WireType::Varint);
+        self.varint(value);
+    }
+
+    /// Add a `int64` (or `int32`) field.
+    fn int(&mut self, field: u32, value: i64) {
+        // Negative values are encoded as two's complement.
+        #[allow(clippy::cast_sign_loss)]
+        let value = value as u64;
+        self.uint(field, value);
+    }
+
+    /// Add a `fixed64` field.
+    fn fixed64(&mut self, field: u32, value: u64) {
+        self.key(field, WireType::I64);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Add a `double` field.
+    fn double(&mut self, field: u32, value: f64) {
+        self.key(field, WireType::I64);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Add a `string` field.
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    /// Add an embedded message field.
+    fn message(&mut self, field: u32, value: &Message) {
+        self.bytes(field, &value.0);
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, WireType::Len);
+        self.varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+    }
+
+    fn key(&mut self, field: u32, wire_type: WireType) {
+        self.varint(u64::from(field) << 3 | wire_type as u64);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+}
+
+/// Protobuf wire types.
+#[derive(Copy, Clone)]
+enum WireType {
+    Varint = 0,
+    I64 = 1,
+    Len = 2,
+}
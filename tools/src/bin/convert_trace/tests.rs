@@ -0,0 +1,656 @@
+//! Tests for the conversion of traces.
+
+use std::collections::HashMap;
+use std::env::temp_dir;
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use heph_tools::trace::{Event, Value, FLOW_ID_ATTRIBUTE};
+
+use crate::merge::{self, Names};
+use crate::{chrome, firefox, perfetto};
+
+/// Create a new event starting at `start` microseconds, with an optional
+/// `flow_id`.
+fn event(stream_id: u32, substream_id: u64, start: u64, flow_id: Option<u64>) -> Event {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_micros(start);
+    let mut attributes = vec![("id".to_owned(), Value::Unsigned(substream_id))];
+    if let Some(flow_id) = flow_id {
+        attributes.push((FLOW_ID_ATTRIBUTE.to_owned(), Value::Unsigned(flow_id)));
+    }
+    Event {
+        stream_id,
+        stream_counter: 0,
+        substream_id,
+        start,
+        end: start + Duration::from_micros(5),
+        description: "event".to_owned(),
+        attributes,
+    }
+}
+
+/// Events in two flows (7 and 8), not in order of start time.
+fn events() -> Vec<Event> {
+    vec![
+        event(1, 0, 10, Some(7)),
+        event(1, 1, 30, Some(7)),
+        event(2, 0, 20, Some(7)),
+        event(2, 0, 40, None),
+        event(2, 1, 50, Some(8)),
+        event(1, 0, 60, Some(8)),
+    ]
+}
+
+#[test]
+fn event_flow_id() {
+    assert_eq!(event(1, 0, 0, None).flow_id(), None);
+    assert_eq!(event(1, 0, 0, Some(123)).flow_id(), Some(123));
+
+    // Flow id must be an unsigned integer.
+    let mut event = event(1, 0, 0, None);
+    event
+        .attributes
+        .push((FLOW_ID_ATTRIBUTE.to_owned(), Value::Signed(1)));
+    assert_eq!(event.flow_id(), None);
+}
+
+#[test]
+fn chrome_flows() {
+    let mut output = Vec::new();
+    chrome::convert(events().into_iter(), &Names::default(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    // Events in the flow are linked in order of start time, each arrow has a
+    // unique id.
+    let want = [
+        (0, 7, (1, 0, 10), (2, 0, 20)),
+        (1, 7, (2, 0, 20), (1, 1, 30)),
+        (2, 8, (2, 1, 50), (1, 0, 60)),
+    ];
+    for (arrow_id, flow_id, from, to) in want {
+        for (phase, (pid, tid, ts)) in [("s", from), ("f", to)] {
+            let flow_event = format!(
+                "{{\"pid\": {}, \"tid\": {}, \"ts\": {}, \"id\": {}, \"name\": \"flow {}\", \
+                \"ph\": \"{}\", \"bp\": \"e\", \"cat\": \"flow\"}}",
+                pid, tid, ts, arrow_id, flow_id, phase
+            );
+            assert!(
+                output.contains(&flow_event),
+                "missing: {}\n{}",
+                flow_event,
+                output
+            );
+        }
+    }
+    assert_eq!(output.matches("\"cat\": \"flow\"").count(), want.len() * 2);
+    // Output must still be valid JSON, i.e. properly closed.
+    assert!(output.ends_with("}\n\t]\n}"), "{}", output);
+}
+
+#[test]
+fn chrome_no_flows() {
+    let events = vec![event(1, 0, 10, None), event(1, 0, 20, Some(1))];
+    let mut output = Vec::new();
+    chrome::convert(events.into_iter(), &Names::default(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // A flow with a single event has no arrows.
+    assert!(!output.contains("\"cat\": \"flow\""), "{}", output);
+}
+
+#[test]
+fn perfetto_flows() {
+    let mut output = Vec::new();
+    perfetto::convert(events().into_iter(), &Names::default(), &mut output).unwrap();
+
+    // `TrackEvent.flow_ids` field key (field 47, wire type `I64`) as varint,
+    // followed by the flow id in little-endian.
+    let flow_ids = |flow_id: u64| {
+        let mut field = vec![0xF9, 0x02];
+        field.extend_from_slice(&flow_id.to_le_bytes());
+        output
+            .windows(field.len())
+            .filter(|window| *window == field)
+            .count()
+    };
+    assert_eq!(flow_ids(7), 3);
+    assert_eq!(flow_ids(8), 2);
+}
+
+/// Create a new event with `start` and `end` in nanoseconds.
+fn event_ns(stream_id: u32, substream_id: u64, start: u64, end: u64, description: &str) -> Event {
+    Event {
+        stream_id,
+        stream_counter: 0,
+        substream_id,
+        start: SystemTime::UNIX_EPOCH + Duration::from_nanos(start),
+        end: SystemTime::UNIX_EPOCH + Duration::from_nanos(end),
+        description: description.to_owned(),
+        attributes: vec![("type".to_owned(), Value::String("test".to_owned()))],
+    }
+}
+
+/// Events in two streams, with sub-microsecond timestamps. In stream 1,
+/// substream 0 `child` is nested in `parent` and `overlap` partially overlaps
+/// `parent`.
+fn nested_events() -> Vec<Event> {
+    const BASE: u64 = 1_000_000_000;
+    vec![
+        event_ns(1, 0, BASE + 1, BASE + 1_001, "parent"),
+        event_ns(1, 0, BASE + 11, BASE + 501, "child"),
+        event_ns(1, 0, BASE + 801, BASE + 1_501, "overlap"),
+        event_ns(1, 1, BASE + 123, BASE + 457, "other substream"),
+        event_ns(2, 0, BASE, BASE + 999, "other stream"),
+    ]
+}
+
+/// Decoded protobuf field.
+#[derive(Debug)]
+enum Field {
+    Varint(u64),
+    I64(u64),
+    Len(Vec<u8>),
+}
+
+impl Field {
+    fn uint(&self) -> u64 {
+        match self {
+            Field::Varint(value) | Field::I64(value) => *value,
+            Field::Len(..) => panic!("not an integer field: {:?}", self),
+        }
+    }
+
+    fn message(&self) -> Vec<(u32, Field)> {
+        match self {
+            Field::Len(bytes) => decode(bytes),
+            _ => panic!("not a message field: {:?}", self),
+        }
+    }
+
+    fn string(&self) -> String {
+        match self {
+            Field::Len(bytes) => String::from_utf8(bytes.clone()).unwrap(),
+            _ => panic!("not a string field: {:?}", self),
+        }
+    }
+}
+
+/// Decode a protobuf message into its fields.
+fn decode(mut bytes: &[u8]) -> Vec<(u32, Field)> {
+    fn varint(bytes: &mut &[u8]) -> u64 {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = bytes[0];
+            *bytes = &bytes[1..];
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        value
+    }
+
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes);
+        let field = match key & 0b111 {
+            0 => Field::Varint(varint(&mut bytes)),
+            1 => {
+                let (value, rest) = bytes.split_at(8);
+                bytes = rest;
+                Field::I64(u64::from_le_bytes(value.try_into().unwrap()))
+            }
+            2 => {
+                let len = varint(&mut bytes) as usize;
+                let (value, rest) = bytes.split_at(len);
+                bytes = rest;
+                Field::Len(value.to_vec())
+            }
+            wire_type => panic!("unexpected wire type: {}", wire_type),
+        };
+        fields.push(((key >> 3) as u32, field));
+    }
+    fields
+}
+
+/// Returns the first field `number` in `fields`.
+fn field(fields: &[(u32, Field)], number: u32) -> Option<&Field> {
+    fields.iter().find(|(n, _)| *n == number).map(|(_, f)| f)
+}
+
+#[test]
+fn perfetto_tracks_and_slices() {
+    let mut output = Vec::new();
+    perfetto::convert(nested_events().into_iter(), &Names::default(), &mut output).unwrap();
+    let names = Names::default();
+
+    // Track uuid -> (parent uuid, name, pid).
+    let mut tracks: HashMap<u64, (Option<u64>, String, Option<u64>)> = HashMap::new();
+    // Track uuid -> (timestamp, type, name) of the slice events.
+    let mut slices: HashMap<u64, Vec<(u64, u64, Option<String>)>> = HashMap::new();
+    let mut last_timestamp = 0;
+    for (number, packet) in decode(&output) {
+        assert_eq!(number, 1, "expected `Trace.packet`");
+        let packet = packet.message();
+        assert_eq!(field(&packet, 10).unwrap().uint(), 1, "sequence id");
+        if let Some(track) = field(&packet, 60) {
+            let track = track.message();
+            let uuid = field(&track, 1).unwrap().uint();
+            let parent = field(&track, 5).map(Field::uint);
+            let (name, pid) = match field(&track, 3) {
+                Some(process) => {
+                    let process = process.message();
+                    (
+                        field(&process, 6).unwrap().string(),
+                        Some(field(&process, 1).unwrap().uint()),
+                    )
+                }
+                None => (field(&track, 2).unwrap().string(), None),
+            };
+            assert!(tracks.insert(uuid, (parent, name, pid)).is_none());
+        } else if let Some(event) = field(&packet, 11) {
+            let timestamp = field(&packet, 8).unwrap().uint();
+            assert!(timestamp >= last_timestamp, "slices not sorted by time");
+            last_timestamp = timestamp;
+            let event = event.message();
+            let track_uuid = field(&event, 11).unwrap().uint();
+            let ty = field(&event, 9).unwrap().uint();
+            let name = field(&event, 23).map(Field::string);
+            slices
+                .entry(track_uuid)
+                .or_default()
+                .push((timestamp, ty, name));
+        }
+    }
+
+    // Substream tracks are nested under the track of their stream.
+    let find = |name: &str| {
+        tracks
+            .iter()
+            .find(|(_, (_, n, _))| n == name)
+            .map(|(uuid, (parent, _, pid))| (*uuid, *parent, *pid))
+            .unwrap_or_else(|| panic!("missing track {}: {:?}", name, tracks))
+    };
+    let (stream1, parent, pid) = find(&names.stream(1));
+    assert_eq!((parent, pid), (None, Some(1)));
+    let (stream2, parent, pid) = find(&names.stream(2));
+    assert_eq!((parent, pid), (None, Some(2)));
+    assert_eq!(tracks.len(), 5);
+    let substream_tracks: Vec<_> = tracks
+        .iter()
+        .filter(|(_, (_, _, pid))| pid.is_none())
+        .map(|(uuid, (parent, name, _))| (*uuid, parent.unwrap(), name.clone()))
+        .collect();
+    assert_eq!(substream_tracks.len(), 3);
+    let track = |(stream_id, stream_uuid): (u32, u64), substream_id: u64| {
+        let name = names.substream(stream_id, substream_id);
+        substream_tracks
+            .iter()
+            .find(|(_, parent, n)| *parent == stream_uuid && *n == name)
+            .map(|(uuid, _, _)| *uuid)
+            .unwrap()
+    };
+
+    // Timestamps keep nanosecond precision and slices are properly nested,
+    // `overlap` is truncated to end with `parent`.
+    const BASE: u64 = 1_000_000_000;
+    let begin = |ts: u64, name: &str| (BASE + ts, 1, Some(name.to_owned()));
+    let end = |ts: u64| (BASE + ts, 2, None);
+    assert_eq!(
+        slices[&track((1, stream1), 0)],
+        [
+            begin(1, "parent"),
+            begin(11, "child"),
+            end(501),
+            begin(801, "overlap"),
+            end(1_001),
+            end(1_001),
+        ]
+    );
+    assert_eq!(
+        slices[&track((1, stream1), 1)],
+        [begin(123, "other substream"), end(457)]
+    );
+    assert_eq!(
+        slices[&track((2, stream2), 0)],
+        [begin(0, "other stream"), end(999)]
+    );
+}
+
+/// Minimal JSON value, used to check the Firefox profile.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(input: &str) -> Json {
+        let mut parser = JsonParser(input.trim().as_bytes());
+        let value = parser.value();
+        assert!(parser.0.is_empty(), "trailing input");
+        value
+    }
+
+    fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v)
+                .unwrap_or_else(|| panic!("missing key {}", key)),
+            _ => panic!("not an object: {:?}", self),
+        }
+    }
+
+    fn array(&self) -> &[Json] {
+        match self {
+            Json::Array(values) => values,
+            _ => panic!("not an array: {:?}", self),
+        }
+    }
+
+    fn number(&self) -> f64 {
+        match self {
+            Json::Number(value) => *value,
+            _ => panic!("not a number: {:?}", self),
+        }
+    }
+
+    fn str(&self) -> &str {
+        match self {
+            Json::String(value) => value,
+            _ => panic!("not a string: {:?}", self),
+        }
+    }
+}
+
+struct JsonParser<'a>(&'a [u8]);
+
+impl<'a> JsonParser<'a> {
+    fn next(&mut self) -> u8 {
+        let byte = self.0[0];
+        self.0 = &self.0[1..];
+        byte
+    }
+
+    fn expect(&mut self, expected: &[u8]) {
+        assert!(self.0.starts_with(expected), "expected {:?}", expected);
+        self.0 = &self.0[expected.len()..];
+    }
+
+    fn value(&mut self) -> Json {
+        match self.0[0] {
+            b'n' => {
+                self.expect(b"null");
+                Json::Null
+            }
+            b't' => {
+                self.expect(b"true");
+                Json::Bool(true)
+            }
+            b'f' => {
+                self.expect(b"false");
+                Json::Bool(false)
+            }
+            b'"' => Json::String(self.string()),
+            b'[' => {
+                let _ = self.next();
+                let mut values = Vec::new();
+                while self.0[0] != b']' {
+                    values.push(self.value());
+                    if self.0[0] == b',' {
+                        let _ = self.next();
+                    }
+                }
+                let _ = self.next();
+                Json::Array(values)
+            }
+            b'{' => {
+                let _ = self.next();
+                let mut fields = Vec::new();
+                while self.0[0] != b'}' {
+                    let key = self.string();
+                    self.expect(b":");
+                    fields.push((key, self.value()));
+                    if self.0[0] == b',' {
+                        let _ = self.next();
+                    }
+                }
+                let _ = self.next();
+                Json::Object(fields)
+            }
+            _ => {
+                let len = self
+                    .0
+                    .iter()
+                    .position(|b| !matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+                    .unwrap_or(self.0.len());
+                let number = std::str::from_utf8(&self.0[..len]).unwrap();
+                self.0 = &self.0[len..];
+                Json::Number(number.parse().expect("invalid number"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> String {
+        self.expect(b"\"");
+        let mut string = Vec::new();
+        loop {
+            match self.next() {
+                b'"' => break,
+                b'\\' => match self.next() {
+                    b'n' => string.push(b'\n'),
+                    b'r' => string.push(b'\r'),
+                    b't' => string.push(b'\t'),
+                    b'u' => {
+                        let hex = std::str::from_utf8(&self.0[..4]).unwrap();
+                        let c = char::from_u32(u32::from_str_radix(hex, 16).unwrap()).unwrap();
+                        self.0 = &self.0[4..];
+                        string.extend_from_slice(c.to_string().as_bytes());
+                    }
+                    byte => string.push(byte),
+                },
+                byte => string.push(byte),
+            }
+        }
+        String::from_utf8(string).unwrap()
+    }
+}
+
+#[test]
+fn firefox_profile() {
+    let mut output = Vec::new();
+    firefox::convert(nested_events().into_iter(), &Names::default(), &mut output).unwrap();
+    let profile = Json::parse(std::str::from_utf8(&output).unwrap());
+    let names = Names::default();
+
+    // Start time (in milliseconds since the Unix epoch) is the start of the
+    // first event.
+    let meta = profile.get("meta");
+    assert_eq!(meta.get("startTime").number(), 1_000.0);
+    assert_eq!(meta.get("product").str(), "Heph");
+    // `type` is used by the Firefox Profiler, so it's renamed in the schema.
+    let schema = &meta.get("markerSchema").array()[0];
+    assert_eq!(schema.get("name").str(), "HephEvent");
+    let data = schema.get("data").array();
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].get("key").str(), "type_");
+    assert_eq!(data[0].get("label").str(), "type");
+    assert_eq!(data[0].get("format").str(), "string");
+
+    // One thread per substream, in order of stream and substream id.
+    let threads = profile.get("threads").array();
+    let want = [
+        (
+            1,
+            0,
+            vec![
+                ("parent", 1, 1_001),
+                ("child", 11, 501),
+                ("overlap", 801, 1_501),
+            ],
+        ),
+        (1, 1, vec![("other substream", 123, 457)]),
+        (2, 0, vec![("other stream", 0, 999)]),
+    ];
+    assert_eq!(threads.len(), want.len());
+    for (thread, (stream_id, substream_id, events)) in threads.iter().zip(want) {
+        assert_eq!(thread.get("pid").str(), stream_id.to_string());
+        assert_eq!(thread.get("tid").number(), substream_id as f64);
+        assert_eq!(thread.get("processName").str(), names.stream(stream_id));
+        assert_eq!(
+            thread.get("name").str(),
+            names.substream(stream_id, substream_id)
+        );
+        assert_eq!(thread.get("isMainThread"), &Json::Bool(substream_id == 0));
+        assert_eq!(thread.get("samples").get("length").number(), 0.0);
+
+        let strings = thread.get("stringArray").array();
+        let markers = thread.get("markers");
+        assert_eq!(markers.get("length").number(), events.len() as f64);
+        let marker_names = markers.get("name").array();
+        let start_times = markers.get("startTime").array();
+        let end_times = markers.get("endTime").array();
+        let data = markers.get("data").array();
+        for (i, (name, start, end)) in events.into_iter().enumerate() {
+            let name_index = marker_names[i].number() as usize;
+            assert_eq!(strings[name_index].str(), name);
+            // Relative to the profile's start time, in milliseconds with
+            // nanosecond precision.
+            assert_eq!(start_times[i].number(), start as f64 / 1_000_000.0);
+            assert_eq!(end_times[i].number(), end as f64 / 1_000_000.0);
+            assert_eq!(markers.get("phase").array()[i].number(), 1.0);
+            assert_eq!(data[i].get("type").str(), "HephEvent");
+            assert_eq!(data[i].get("type_").str(), "test");
+        }
+    }
+}
+
+/// Append a string, as used in the Trace Format, to `buf`.
+fn put_str(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+/// Append a metadata packet setting `option` to `value` to `trace`.
+fn put_metadata(trace: &mut Vec<u8>, option: &str, value: &[u8]) {
+    trace.extend_from_slice(&0x75D1_1D4D_u32.to_be_bytes());
+    let size = 4 + 4 + 2 + option.len() + value.len();
+    trace.extend_from_slice(&(size as u32).to_be_bytes());
+    put_str(trace, option);
+    trace.extend_from_slice(value);
+}
+
+/// Append an event packet to `trace`, with times in nanoseconds relative to
+/// the epoch and an optional `flow_id`.
+fn put_event(
+    trace: &mut Vec<u8>,
+    stream_id: u32,
+    substream_id: u64,
+    start: u64,
+    flow_id: Option<u64>,
+) {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&stream_id.to_be_bytes());
+    packet.extend_from_slice(&0_u32.to_be_bytes());
+    packet.extend_from_slice(&substream_id.to_be_bytes());
+    packet.extend_from_slice(&start.to_be_bytes());
+    packet.extend_from_slice(&(start + 100).to_be_bytes());
+    put_str(&mut packet, "event");
+    if let Some(flow_id) = flow_id {
+        put_str(&mut packet, FLOW_ID_ATTRIBUTE);
+        packet.push(0b0000_0001);
+        packet.extend_from_slice(&flow_id.to_be_bytes());
+    }
+    trace.extend_from_slice(&0xC1FC_1FB7_u32.to_be_bytes());
+    trace.extend_from_slice(&(4 + 4 + packet.len() as u32).to_be_bytes());
+    trace.extend_from_slice(&packet);
+}
+
+/// Write `trace` to a new file in the temporary directory, returning its path.
+fn write_trace(name: &str, trace: &[u8]) -> String {
+    let path = temp_dir().join(name);
+    fs::write(&path, trace).unwrap();
+    path.into_os_string().into_string().unwrap()
+}
+
+#[test]
+fn merge_traces() {
+    // Epochs of the two traces, 2 milliseconds apart.
+    let epoch_a = Duration::from_secs(1_600_000_000);
+    let epoch_b = epoch_a + Duration::from_millis(2);
+
+    let mut trace = Vec::new();
+    put_metadata(
+        &mut trace,
+        "epoch",
+        &(epoch_a.as_nanos() as u64).to_be_bytes(),
+    );
+    let mut value = Vec::new();
+    put_str(&mut value, "0123456789abcdef");
+    put_metadata(&mut trace, "host_id", &value);
+    value.clear();
+    put_str(&mut value, "app_a");
+    put_metadata(&mut trace, "app_name", &value);
+    put_metadata(&mut trace, "process_id", &123_u32.to_be_bytes());
+    for (stream_id, name) in [(0_u32, "Coordinator"), (1, "Worker 1")] {
+        value.clear();
+        value.extend_from_slice(&stream_id.to_be_bytes());
+        put_str(&mut value, name);
+        put_metadata(&mut trace, "stream_name", &value);
+    }
+    put_event(&mut trace, 0, 0, 1_000, None);
+    put_event(&mut trace, 1, 5, 2_000, Some(7));
+    put_event(&mut trace, 1, 6, 3_000, Some(8));
+    let input_a = write_trace("convert_trace_merge_a.bin.log", &trace);
+
+    // Without the application name and stream names.
+    let mut trace = Vec::new();
+    put_metadata(
+        &mut trace,
+        "epoch",
+        &(epoch_b.as_nanos() as u64).to_be_bytes(),
+    );
+    put_metadata(&mut trace, "process_id", &456_u32.to_be_bytes());
+    put_event(&mut trace, 1, 5, 1_000, Some(7));
+    let input_b = write_trace("convert_trace_merge_b.bin.log", &trace);
+
+    let (events, names) = merge::merge(&[input_a, input_b]);
+
+    // Stream id is the index of the input, the substream id unique per
+    // original stream and substream, the times are aligned on the epochs and
+    // the flow ids made unique across the inputs.
+    let got: Vec<_> = events
+        .iter()
+        .map(|event| {
+            let start = event.start.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+            (event.stream_id, event.substream_id, start, event.flow_id())
+        })
+        .collect();
+    let nanos = Duration::from_nanos;
+    let want = [
+        (0, 0, epoch_a + nanos(1_000), None),
+        (0, 1, epoch_a + nanos(2_000), Some(7)),
+        (0, 2, epoch_a + nanos(3_000), Some(8)),
+        (
+            1,
+            0,
+            epoch_b + nanos(1_000),
+            Some((1 << merge::FLOW_ID_SHIFT) | 7),
+        ),
+    ];
+    assert_eq!(got, want);
+
+    // Process labels.
+    assert_eq!(names.stream(0), "app_a (pid 123) on host 01234567");
+    assert_eq!(names.stream(1), "convert_trace_merge_b.bin.log (pid 456)");
+    // Original stream (if any) and substream names.
+    assert_eq!(names.substream(0, 0), "Coordinator / Substream 0");
+    assert_eq!(names.substream(0, 1), "Worker 1 / Substream 5");
+    assert_eq!(names.substream(0, 2), "Worker 1 / Substream 6");
+    assert_eq!(names.substream(1, 0), "Stream 1 / Substream 5");
+}
@@ -0,0 +1,701 @@
+//! Tool to print statistics about a Heph trace.
+//!
+//! For each combination of stream, substream and event description it prints
+//! the number of events, the total, mean and maximum duration and the 50th,
+//! 90th and 99th percentile of the duration. Followed by the slowest individual
+//! events, including their attributes.
+//!
+//! Usage:
+//!
+//! ```text
+//! trace_stats [--format text|csv|json] [--top <n>] <input>
+//! ```
+//!
+//! The statistics are written to standard out. In the CSV and JSON formats all
+//! durations are in nanoseconds.
+//!
+//! The percentiles are determined using a histogram, rather than keeping the
+//! duration of every event in memory, which makes them accurate to within 1%.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::env::args;
+use std::fmt;
+use std::io::{self, stdout, BufWriter, Write};
+use std::process::exit;
+use std::time::{Duration, SystemTime};
+
+use heph_tools::json::{self, Float};
+use heph_tools::trace::{Event, Trace, Value};
+
+const USAGE: &str = "usage: trace_stats [--format text|csv|json] [--top <n>] <input>";
+
+/// Default number of slowest events to print.
+const DEFAULT_TOP: usize = 10;
+
+/// Output format.
+#[derive(Copy, Clone, Debug)]
+enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn parse(format: &str) -> Option<Format> {
+        match format {
+            "text" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+fn main() {
+    let mut format = Format::Text;
+    let mut top = DEFAULT_TOP;
+    let mut input = None;
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" | "-f" => {
+                format = match args.next().as_deref().and_then(Format::parse) {
+                    Some(format) => format,
+                    None => exit_usage("invalid or missing format"),
+                }
+            }
+            "--top" | "-n" => {
+                top = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => n,
+                    None => exit_usage("invalid or missing number of events"),
+                }
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => exit_usage("too many arguments"),
+        }
+    }
+    let input = match input {
+        Some(input) => input,
+        None => exit_usage("missing input trace file path"),
+    };
+
+    let mut trace = Trace::open(input).expect("can't open trace file");
+    let mut stats = Stats::new(top);
+    for event in trace.events() {
+        stats.add(event.expect("error reading trace file"));
+    }
+    let (groups, slowest) = stats.finish();
+
+    let output = stdout();
+    let mut output = BufWriter::new(output.lock());
+    match format {
+        Format::Text => write_text(&mut output, &groups, &slowest),
+        Format::Csv => write_csv(&mut output, &groups, &slowest),
+        Format::Json => write_json(&mut output, &groups, &slowest),
+    }
+    .and_then(|()| output.flush())
+    .expect("failed to write output");
+}
+
+fn exit_usage(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    exit(1)
+}
+
+/// Key used to group events.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct Key {
+    stream_id: u32,
+    substream_id: u64,
+    description: String,
+}
+
+/// Collects the statistics of all events.
+struct Stats {
+    /// Histogram of the durations of the events per group.
+    durations: HashMap<Key, Histogram>,
+    /// Slowest events, the heap is used to quickly remove the fastest event
+    /// once we have more than `top` events.
+    slowest: BinaryHeap<Reverse<SlowEvent>>,
+    top: usize,
+}
+
+impl Stats {
+    fn new(top: usize) -> Stats {
+        Stats {
+            durations: HashMap::new(),
+            slowest: BinaryHeap::with_capacity(top + 1),
+            top,
+        }
+    }
+
+    fn add(&mut self, event: Event) {
+        let duration = nanos(event.end.duration_since(event.start).unwrap_or_default());
+        let key = Key {
+            stream_id: event.stream_id,
+            substream_id: event.substream_id,
+            description: event.description,
+        };
+
+        let is_slow = match self.slowest.peek() {
+            Some(fastest) if self.slowest.len() >= self.top => fastest.0.duration < duration,
+            _ => self.top != 0,
+        };
+        if is_slow {
+            self.slowest.push(Reverse(SlowEvent {
+                duration,
+                start: event.start,
+                key: key.clone(),
+                attributes: event.attributes,
+            }));
+            if self.slowest.len() > self.top {
+                let _ = self.slowest.pop();
+            }
+        }
+
+        self.durations.entry(key).or_default().add(duration);
+    }
+
+    /// Returns the statistics per group, sorted by total duration, and the
+    /// slowest events, slowest first.
+    fn finish(self) -> (Vec<Group>, Vec<SlowEvent>) {
+        let mut groups: Vec<Group> = self
+            .durations
+            .into_iter()
+            .map(|(key, durations)| Group::new(key, durations))
+            .collect();
+        groups.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.key.cmp(&b.key)));
+        let slowest = self
+            .slowest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|e| e.0)
+            .collect();
+        (groups, slowest)
+    }
+}
+
+/// Statistics for a single group of events.
+struct Group {
+    key: Key,
+    count: u64,
+    /// All durations are in nanoseconds.
+    total: u64,
+    mean: u64,
+    max: u64,
+    p50: u64,
+    p90: u64,
+    p99: u64,
+}
+
+impl Group {
+    fn new(key: Key, durations: Histogram) -> Group {
+        Group {
+            key,
+            count: durations.count,
+            total: durations.total,
+            mean: durations.total / durations.count,
+            max: durations.max,
+            p50: percentile(&durations, 50),
+            p90: percentile(&durations, 90),
+            p99: percentile(&durations, 99),
+        }
+    }
+}
+
+/// Number of significant bits kept for each value in the [`Histogram`].
+const PRECISION_BITS: u32 = 8;
+/// Number of buckets per power of two, once values are larger than
+/// `2^PRECISION_BITS`.
+const HALF_BUCKETS: u64 = 1 << (PRECISION_BITS - 1);
+
+/// Histogram of durations (in nanoseconds), similar to an HDR histogram.
+///
+/// Values below `2^PRECISION_BITS` are recorded exactly, larger values are
+/// recorded in buckets that keep the `PRECISION_BITS` most significant bits of
+/// the value. This limits the relative error to `2^-(PRECISION_BITS - 1)`
+/// (less than 1%), while using a fixed amount of memory regardless of the
+/// number of values.
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Number of values per bucket, see [`bucket_index`].
+    counts: Vec<u64>,
+    /// Total number of values.
+    count: u64,
+    /// Sum of all values.
+    total: u64,
+    /// Maximum value.
+    max: u64,
+}
+
+impl Histogram {
+    fn add(&mut self, value: u64) {
+        let index = bucket_index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.count += 1;
+        self.total = self.total.saturating_add(value);
+        self.max = self.max.max(value);
+    }
+}
+
+/// Returns the index of the bucket for `value` in [`Histogram::counts`].
+fn bucket_index(value: u64) -> usize {
+    // Number of bits to shift `value` to keep `PRECISION_BITS` bits.
+    let shift = (u64::BITS - value.leading_zeros()).saturating_sub(PRECISION_BITS);
+    // For `shift > 0` the most significant bit of `value >> shift` is always
+    // set, so it's in the range `HALF_BUCKETS..2 * HALF_BUCKETS`.
+    (u64::from(shift) * HALF_BUCKETS + (value >> shift)) as usize
+}
+
+/// Returns the highest value that is recorded in the bucket with `index`.
+fn bucket_high(index: usize) -> u64 {
+    let index = index as u64;
+    if index < 2 * HALF_BUCKETS {
+        index
+    } else {
+        let shift = index / HALF_BUCKETS - 1;
+        let significant = index % HALF_BUCKETS + HALF_BUCKETS;
+        (significant << shift) | ((1 << shift) - 1)
+    }
+}
+
+/// Returns the `p`th percentile of the `durations`, using the nearest rank
+/// method. Returns the highest value of the bucket the rank falls in, but at
+/// most the maximum recorded value.
+fn percentile(durations: &Histogram, p: u64) -> u64 {
+    // Same as `div_ceil(p * count, 100)`.
+    let rank = ((p * durations.count + 99) / 100).max(1);
+    let mut seen = 0;
+    for (index, count) in durations.counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return bucket_high(index).min(durations.max);
+        }
+    }
+    durations.max
+}
+
+/// A single (slow) event.
+struct SlowEvent {
+    /// Duration in nanoseconds.
+    duration: u64,
+    start: SystemTime,
+    key: Key,
+    attributes: Vec<(String, Value)>,
+}
+
+impl Eq for SlowEvent {}
+
+impl PartialEq for SlowEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Ord for SlowEvent {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.duration
+            .cmp(&other.duration)
+            .then_with(|| self.start.cmp(&other.start))
+    }
+}
+
+impl PartialOrd for SlowEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)] // Only overflows after 584 years.
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos() as u64
+}
+
+/// Nanoseconds since the Unix epoch.
+fn timestamp(time: SystemTime) -> u64 {
+    nanos(
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
+
+/// Formats a duration (in nanoseconds) in a human readable way.
+struct HumanDuration(u64);
+
+impl fmt::Display for HumanDuration {
+    #[allow(clippy::cast_precision_loss)] // Only used for display.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.0;
+        // Using `pad` to support width and alignment.
+        f.pad(&if nanos < 1_000 {
+            format!("{}ns", nanos)
+        } else if nanos < 1_000_000 {
+            format!("{:.2}µs", nanos as f64 / 1_000.0)
+        } else if nanos < 1_000_000_000 {
+            format!("{:.2}ms", nanos as f64 / 1_000_000.0)
+        } else {
+            format!("{:.2}s", nanos as f64 / 1_000_000_000.0)
+        })
+    }
+}
+
+/// Formats a value of an attribute, without quotes for strings.
+struct DisplayValue<'a>(&'a Value);
+
+impl<'a> fmt::Display for DisplayValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Signed(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::String(value) => f.write_str(value),
+        }
+    }
+}
+
+fn write_text<W: Write>(output: &mut W, groups: &[Group], slowest: &[SlowEvent]) -> io::Result<()> {
+    writeln!(
+        output,
+        "{:>6} {:>20} {:>10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}  Description",
+        "Stream", "Substream", "Count", "Total", "Mean", "Max", "p50", "p90", "p99"
+    )?;
+    for group in groups {
+        writeln!(
+            output,
+            "{:>6} {:>20} {:>10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}  {}",
+            group.key.stream_id,
+            group.key.substream_id,
+            group.count,
+            HumanDuration(group.total),
+            HumanDuration(group.mean),
+            HumanDuration(group.max),
+            HumanDuration(group.p50),
+            HumanDuration(group.p90),
+            HumanDuration(group.p99),
+            group.key.description,
+        )?;
+    }
+
+    if slowest.is_empty() {
+        return Ok(());
+    }
+    writeln!(output, "\nSlowest events:")?;
+    for event in slowest {
+        write!(
+            output,
+            "{:>12}  stream={} substream={} start={} {}",
+            HumanDuration(event.duration),
+            event.key.stream_id,
+            event.key.substream_id,
+            timestamp(event.start),
+            event.key.description,
+        )?;
+        for (name, value) in &event.attributes {
+            write!(output, " {}={}", name, DisplayValue(value))?;
+        }
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+/// Writes the groups as CSV table, followed by an empty line and a CSV table
+/// of the slowest events.
+fn write_csv<W: Write>(output: &mut W, groups: &[Group], slowest: &[SlowEvent]) -> io::Result<()> {
+    writeln!(
+        output,
+        "stream,substream,description,count,total_ns,mean_ns,max_ns,p50_ns,p90_ns,p99_ns"
+    )?;
+    for group in groups {
+        writeln!(
+            output,
+            "{},{},{},{},{},{},{},{},{},{}",
+            group.key.stream_id,
+            group.key.substream_id,
+            CsvEscape(&group.key.description),
+            group.count,
+            group.total,
+            group.mean,
+            group.max,
+            group.p50,
+            group.p90,
+            group.p99,
+        )?;
+    }
+
+    writeln!(
+        output,
+        "\nstream,substream,description,start_ns,duration_ns,attributes"
+    )?;
+    for event in slowest {
+        let attributes = event
+            .attributes
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, DisplayValue(value)))
+            .collect::<Vec<_>>()
+            .join(";");
+        writeln!(
+            output,
+            "{},{},{},{},{},{}",
+            event.key.stream_id,
+            event.key.substream_id,
+            CsvEscape(&event.key.description),
+            timestamp(event.start),
+            event.duration,
+            CsvEscape(&attributes),
+        )?;
+    }
+    Ok(())
+}
+
+/// Escapes a CSV field, quoting it if needed.
+struct CsvEscape<'a>(&'a str);
+
+impl<'a> fmt::Display for CsvEscape<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.contains([',', '"', '\n', '\r']) {
+            write!(f, "\"{}\"", self.0.replace('"', "\"\""))
+        } else {
+            f.write_str(self.0)
+        }
+    }
+}
+
+fn write_json<W: Write>(output: &mut W, groups: &[Group], slowest: &[SlowEvent]) -> io::Result<()> {
+    output.write_all(b"{\n\t\"groups\": [")?;
+    for (i, group) in groups.iter().enumerate() {
+        write!(
+            output,
+            "{}\n\t\t{{\"stream\": {}, \"substream\": {}, \"description\": \"{}\", \
+            \"count\": {}, \"total_ns\": {}, \"mean_ns\": {}, \"max_ns\": {}, \
+            \"p50_ns\": {}, \"p90_ns\": {}, \"p99_ns\": {}}}",
+            if i == 0 { "" } else { "," },
+            group.key.stream_id,
+            group.key.substream_id,
+            json::Escape(&group.key.description),
+            group.count,
+            group.total,
+            group.mean,
+            group.max,
+            group.p50,
+            group.p90,
+            group.p99,
+        )?;
+    }
+    output.write_all(b"\n\t],\n\t\"slowest\": [")?;
+    for (i, event) in slowest.iter().enumerate() {
+        write!(
+            output,
+            "{}\n\t\t{{\"stream\": {}, \"substream\": {}, \"description\": \"{}\", \
+            \"start_ns\": {}, \"duration_ns\": {}, \"attributes\": {{",
+            if i == 0 { "" } else { "," },
+            event.key.stream_id,
+            event.key.substream_id,
+            json::Escape(&event.key.description),
+            timestamp(event.start),
+            event.duration,
+        )?;
+        for (i, (name, value)) in event.attributes.iter().enumerate() {
+            write!(
+                output,
+                "{}\"{}\": ",
+                if i == 0 { "" } else { ", " },
+                json::Escape(name)
+            )?;
+            match value {
+                Value::Unsigned(value) => write!(output, "{}", value)?,
+                Value::Signed(value) => write!(output, "{}", value)?,
+                Value::Float(value) => write!(output, "{}", Float(*value))?,
+                Value::String(value) => write!(output, "\"{}\"", json::Escape(value))?,
+            }
+        }
+        output.write_all(b"}}")?;
+    }
+    output.write_all(b"\n\t]\n}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use heph_tools::trace::{Event, Value};
+
+    use super::{
+        bucket_high, bucket_index, percentile, write_csv, write_json, Histogram, HumanDuration,
+        Stats, PRECISION_BITS,
+    };
+
+    fn histogram(values: impl IntoIterator<Item = u64>) -> Histogram {
+        let mut histogram = Histogram::default();
+        for value in values {
+            histogram.add(value);
+        }
+        histogram
+    }
+
+    fn event(substream_id: u64, description: &str, start: u64, duration: u64) -> Event {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_nanos(start);
+        Event {
+            stream_id: 1,
+            stream_counter: 0,
+            substream_id,
+            start,
+            end: start + Duration::from_nanos(duration),
+            description: description.to_owned(),
+            attributes: vec![
+                ("id".to_owned(), Value::Unsigned(substream_id)),
+                ("msg".to_owned(), Value::String("a, \"b\"".to_owned())),
+            ],
+        }
+    }
+
+    /// Three groups of events, with `slow` having the highest total duration.
+    fn stats(top: usize) -> Stats {
+        let mut stats = Stats::new(top);
+        stats.add(event(0, "fast", 0, 10));
+        stats.add(event(0, "slow", 10, 1_000));
+        stats.add(event(1, "medium", 20, 300));
+        stats.add(event(0, "fast", 30, 20));
+        stats.add(event(0, "slow", 40, 2_000));
+        stats.add(event(1, "medium", 50, 400));
+        stats
+    }
+
+    #[test]
+    fn bucket_index_and_high() {
+        // Small values are exact.
+        for value in 0..(1 << PRECISION_BITS) {
+            assert_eq!(bucket_high(bucket_index(value)), value);
+        }
+        // Buckets are contiguous and the relative error is bounded.
+        let mut last_index = bucket_index((1 << PRECISION_BITS) - 1);
+        for value in (1 << PRECISION_BITS)..100_000 {
+            let index = bucket_index(value);
+            assert!(index == last_index || index == last_index + 1);
+            last_index = index;
+            let high = bucket_high(index);
+            assert!(
+                high >= value && high - value <= value / 64,
+                "{} {}",
+                value,
+                high
+            );
+        }
+        let high = bucket_high(bucket_index(u64::MAX));
+        assert_eq!(high, u64::MAX);
+    }
+
+    #[test]
+    fn human_duration() {
+        assert_eq!(format!("{:>8}", HumanDuration(999)), "   999ns");
+        assert_eq!(format!("{}", HumanDuration(1_500)), "1.50µs");
+        assert_eq!(format!("{}", HumanDuration(2_000_000)), "2.00ms");
+        assert_eq!(format!("{}", HumanDuration(3_250_000_000)), "3.25s");
+    }
+
+    #[test]
+    fn percentile_exact() {
+        let durations = histogram(1..=100);
+        assert_eq!(percentile(&durations, 50), 50);
+        assert_eq!(percentile(&durations, 90), 90);
+        assert_eq!(percentile(&durations, 99), 99);
+        assert_eq!(percentile(&durations, 100), 100);
+        assert_eq!(percentile(&durations, 0), 1);
+
+        let durations = histogram([7]);
+        assert_eq!(percentile(&durations, 50), 7);
+        assert_eq!(percentile(&durations, 99), 7);
+    }
+
+    #[test]
+    fn percentile_large_values() {
+        // Values in microseconds, with nanosecond resolution.
+        let durations = histogram((1..=1000).map(|n| n * 1_000 + 1));
+        for (p, want) in [(50, 500_001), (90, 900_001), (99, 990_001)] {
+            let got = percentile(&durations, p);
+            assert!(got >= want && got - want <= want / 64, "p{}: {}", p, got);
+        }
+        // Never larger than the maximum.
+        assert_eq!(percentile(&durations, 100), 1_000_001);
+    }
+
+    #[test]
+    fn stats_groups_and_slowest() {
+        let (groups, slowest) = stats(2).finish();
+        // Sorted by total duration.
+        let got: Vec<_> = groups
+            .iter()
+            .map(|g| (g.key.description.as_str(), g.count, g.total, g.mean, g.max))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                ("slow", 2, 3_000, 1_500, 2_000),
+                ("medium", 2, 700, 350, 400),
+                ("fast", 2, 30, 15, 20),
+            ]
+        );
+        assert_eq!(groups[1].key.substream_id, 1);
+        assert_eq!((groups[2].p50, groups[2].p99), (10, 20));
+        // Larger values are rounded up to the highest value of their bucket.
+        assert_eq!((groups[0].p50, groups[0].p99), (1_003, 2_000));
+
+        // Only the `top` slowest events are kept, slowest first.
+        let got: Vec<_> = slowest.iter().map(|e| e.duration).collect();
+        assert_eq!(got, [2_000, 1_000]);
+
+        let (_, slowest) = stats(0).finish();
+        assert!(slowest.is_empty());
+        let (_, slowest) = stats(10).finish();
+        let got: Vec<_> = slowest.iter().map(|e| e.duration).collect();
+        assert_eq!(got, [2_000, 1_000, 400, 300, 20, 10]);
+    }
+
+    #[test]
+    fn csv_output() {
+        let (groups, slowest) = stats(1).finish();
+        let mut output = Vec::new();
+        write_csv(&mut output, &groups, &slowest).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let want =
+            "stream,substream,description,count,total_ns,mean_ns,max_ns,p50_ns,p90_ns,p99_ns\n\
+            1,0,slow,2,3000,1500,2000,1003,2000,2000\n\
+            1,1,medium,2,700,350,400,301,400,400\n\
+            1,0,fast,2,30,15,20,10,20,20\n\
+            \n\
+            stream,substream,description,start_ns,duration_ns,attributes\n\
+            1,0,slow,40,2000,\"id=0;msg=a, \"\"b\"\"\"\n";
+        assert_eq!(output, want);
+    }
+
+    #[test]
+    fn json_output() {
+        let (groups, slowest) = stats(1).finish();
+        let mut output = Vec::new();
+        write_json(&mut output, &groups[..1], &slowest).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let want = "{\n\t\"groups\": [\n\
+            \t\t{\"stream\": 1, \"substream\": 0, \"description\": \"slow\", \"count\": 2, \
+            \"total_ns\": 3000, \"mean_ns\": 1500, \"max_ns\": 2000, \"p50_ns\": 1003, \
+            \"p90_ns\": 2000, \"p99_ns\": 2000}\n\
+            \t],\n\t\"slowest\": [\n\
+            \t\t{\"stream\": 1, \"substream\": 0, \"description\": \"slow\", \"start_ns\": 40, \
+            \"duration_ns\": 2000, \"attributes\": {\"id\": 0, \"msg\": \"a, \\\"b\\\"\"}}\n\
+            \t]\n}\n";
+        assert_eq!(output, want);
+
+        let mut output = Vec::new();
+        write_json(&mut output, &[], &[]).unwrap();
+        assert_eq!(
+            output,
+            b"{\n\t\"groups\": [\n\t],\n\t\"slowest\": [\n\t]\n}\n"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+//! Library shared between the Heph tools.
+
+pub mod json;
+pub mod trace;
@@ -1,142 +1,30 @@
-//! Tool to convert a Heph trace to [Chrome's Trace Event Format] so it can be
-//! opened by [Catapult trace view].
+//! Parser for Heph traces.
 //!
-//! [Chrome's Trace Event Format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU/preview
-//! [Catapult trace view]: https://chromium.googlesource.com/catapult/+/refs/heads/master/tracing/README.md
+//! The layout of a trace is described in the Trace Format design document,
+//! found in the `doc` directory of the repository.
 
-use std::collections::hash_map::{Entry, HashMap};
 use std::convert::TryInto;
-use std::env::args;
-use std::fs::{File, OpenOptions};
-use std::io::{self, stdin, Read, Stdin, Write};
-use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{self, stdin, Read, Stdin};
+use std::path::Path;
 use std::time::{Duration, SystemTime};
 use std::{fmt, str};
 
-fn main() {
-    let mut args = args().skip(1);
-    let input = args.next().expect("missing input trace file path");
-    let output = if let Some(output) = args.next() {
-        PathBuf::from(output)
-    } else {
-        let end_idx = input.rfind('.').unwrap_or(input.len());
-        let mut output = PathBuf::from(&input[..end_idx]);
-        // If the input has a single extension this will add `json` to it.
-        // If however it has two extensions, e.g. `.bin.log` this will
-        // overwrite the extension.
-        output.set_extension("json");
-        output
-    };
-
-    let mut trace = Trace::open(input).expect("can't open trace file");
-    let mut output = OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(output)
-        .expect("can't open output file");
-
-    output
-        .write_all(b"{\n\t\"displayTimeUnit\": \"ns\",\n\t\"traceEvents\": [\n")
-        .expect("failed to write header to output");
-
-    // Sometimes `Instant` returns a value that is equal to a previously
-    // returned value. Catapult can't really deal with this and creates two
-    // overlapping events, making them both unreadable and unusable.
-    // To fix this change the starting time and duration by a few microseconds
-    // to ensure the two events don't start at the same time.
-    //
-    // Maps `(pid, tid)` -> `timestamp` -> `duration`.
-    let mut times: HashMap<(u32, u64), HashMap<u128, u128>> = HashMap::new();
-
-    let mut first = true;
-    for event in trace.events() {
-        let event = event.expect("error reading trace file");
-
-        let mut timestamp = event
-            .start
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_micros();
-        let mut duration = event.end.duration_since(event.start).unwrap().as_micros();
-
-        let process_id = event.stream_id;
-        let thread_id = event.substream_id;
-        loop {
-            let key = (process_id, thread_id);
-            match times.entry(key).or_default().entry(timestamp) {
-                Entry::Vacant(entry) => {
-                    entry.insert(duration);
-                    break;
-                }
-                Entry::Occupied(entry) => {
-                    let other_duration = *entry.get();
-                    if other_duration > duration {
-                        // Other event is the *overlapping* event. Delay the
-                        // start of this event and decrease it's duration.
-                        timestamp += 1;
-                        duration -= 1;
-                    } else {
-                        // This is the *overlapping* event, grow it.
-                        timestamp -= 1;
-                        duration += 1;
-                    }
-                }
-            }
-        }
-
-        write!(
-            output,
-            "{}\t\t{{\"pid\": {}, \"tid\": {}, \"ts\": {}, \"dur\": {}, \"name\": \"{}\"",
-            if first { "" } else { ",\n" },
-            process_id,
-            thread_id,
-            timestamp,
-            duration,
-            event.description,
-        )
-        .expect("failed to write event to output");
-        first = false;
-        let mut first_attribute = true;
-        if !event.attributes.is_empty() {
-            output
-                .write_all(b", \"args\": {")
-                .expect("failed to write event to output");
-            for (name, value) in &event.attributes {
-                let fmt_args = match value {
-                    // NOTE: `format_args!` is useless.
-                    Value::Unsigned(value) => format!("\"{}\": {}", name, value),
-                    Value::Signed(value) => format!("\"{}\": {}", name, value),
-                    Value::Float(value) => format!("\"{}\": {}", name, value),
-                    Value::String(value) => format!("\"{}\": \"{}\"", name, value),
-                };
-                write!(
-                    output,
-                    "{}{}",
-                    if first_attribute { "" } else { ", " },
-                    fmt_args
-                )
-                .expect("failed to write event to output");
-                first_attribute = false;
-            }
-            output
-                .write_all(b"}")
-                .expect("failed to write event to output");
-        }
-        output
-            .write_all(b", \"ph\": \"X\", \"cat\": \"\"}")
-            .expect("failed to write event to output");
-    }
-
-    output
-        .write_all(b"\n\t]\n}")
-        .expect("failed to write footer to output");
-
-    println!("OK.");
-}
-
+/// Parser for a Heph trace, see the Trace Format design document.
 pub struct Trace<R> {
     reader: R,
     epoch: SystemTime,
+    /// Include and exclude filters used, see [`Trace::filter`].
+    filter: Option<String>,
+    sample_rate: f64,
+    stream_sample_rates: Vec<(u32, f64)>,
+    /// Information about the process that created the trace, see
+    /// [`Trace::app_name`].
+    host_id: Option<String>,
+    app_name: Option<String>,
+    process_id: Option<u32>,
+    /// Names of the streams, see [`Trace::stream_name`].
+    stream_names: Vec<(u32, String)>,
     // TODO: use VecDeque?
     buf: Vec<u8>,
 }
@@ -158,6 +46,13 @@ impl<R> Trace<R> {
         Trace {
             reader,
             epoch: SystemTime::now(),
+            filter: None,
+            sample_rate: 1.0,
+            stream_sample_rates: Vec::new(),
+            host_id: None,
+            app_name: None,
+            process_id: None,
+            stream_names: Vec::new(),
             buf: Vec::with_capacity(4096),
         }
     }
@@ -165,6 +60,71 @@ impl<R> Trace<R> {
     pub fn events<'t>(&'t mut self) -> TraceEvents<'t, R> {
         TraceEvents { trace: self }
     }
+
+    /// Returns the (human readable) include and exclude filters used when
+    /// creating the trace, if any.
+    ///
+    /// # Notes
+    ///
+    /// This is only set once the metadata is read, i.e. after the first call
+    /// to the [`TraceEvents`] iterator.
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Returns the sample rate used for the stream with `stream_id`, between 0
+    /// and 1 where 1 means that all events were recorded.
+    ///
+    /// # Notes
+    ///
+    /// See [`Trace::filter`].
+    pub fn sample_rate(&self, stream_id: u32) -> f64 {
+        self.stream_sample_rates
+            .iter()
+            .find(|(id, _)| *id == stream_id)
+            .map_or(self.sample_rate, |(_, rate)| *rate)
+    }
+
+    /// Returns the name of the application that created the trace, if known.
+    ///
+    /// # Notes
+    ///
+    /// See [`Trace::filter`].
+    pub fn app_name(&self) -> Option<&str> {
+        self.app_name.as_deref()
+    }
+
+    /// Returns the id of the host the trace was created on, if known.
+    ///
+    /// # Notes
+    ///
+    /// See [`Trace::filter`].
+    pub fn host_id(&self) -> Option<&str> {
+        self.host_id.as_deref()
+    }
+
+    /// Returns the id of the OS process that created the trace, if known.
+    ///
+    /// # Notes
+    ///
+    /// See [`Trace::filter`].
+    pub fn process_id(&self) -> Option<u32> {
+        self.process_id
+    }
+
+    /// Returns the name of the stream with `stream_id`, e.g. the name of the
+    /// thread, if known.
+    ///
+    /// # Notes
+    ///
+    /// Unlike the other metadata the name of a stream can be set anywhere in
+    /// the trace, but always before the first event of the stream.
+    pub fn stream_name(&self, stream_id: u32) -> Option<&str> {
+        self.stream_names
+            .iter()
+            .find(|(id, _)| *id == stream_id)
+            .map(|(_, name)| &**name)
+    }
 }
 
 impl<R> Trace<R>
@@ -188,12 +148,21 @@ where
     }
 }
 
+/// Iterator over the events in a [`Trace`].
 // TODO: when hitting error maybe seek until the next magic value and continue
 // from there?
 pub struct TraceEvents<'t, R> {
     trace: &'t mut Trace<R>,
 }
 
+impl<'t, R> TraceEvents<'t, R> {
+    /// Returns the trace the events are read from, e.g. to read the metadata
+    /// while iterating over the events.
+    pub fn trace(&self) -> &Trace<R> {
+        self.trace
+    }
+}
+
 #[allow(clippy::unreadable_literal)]
 const METADATA_MAGIC: u32 = 0x75D11D4D;
 #[allow(clippy::unreadable_literal)]
@@ -291,20 +260,60 @@ where
         };
 
         match option_name {
-            "epoch" if left.len() <= 8 => Err(ParseError::MissingPacketData {
-                got: left.len(),
-                want: 8,
-            }),
+            "epoch" if left.len() <= 8 => {
+                return Err(ParseError::MissingPacketData {
+                    got: left.len(),
+                    want: 8,
+                })
+            }
             "epoch" => {
                 let (_, nanos) = parse_u64(left);
                 trace.epoch = SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos);
-                // TODO: check that all bytes according to packet_size are
-                // processed.
-                trace.buf.drain(..packet_size);
-                Ok(())
             }
-            _ => Err(ParseError::UnknownOption(option_name.to_owned())),
+            "filter" => trace.filter = Some(parse_metadata_string(left, "filter")?),
+            "sample_rate" if left.len() < 8 => {
+                return Err(ParseError::MissingPacketData {
+                    got: left.len(),
+                    want: 8,
+                })
+            }
+            "sample_rate" => trace.sample_rate = parse_f64(left).1,
+            "stream_sample_rate" if left.len() < 12 => {
+                return Err(ParseError::MissingPacketData {
+                    got: left.len(),
+                    want: 12,
+                })
+            }
+            "stream_sample_rate" => {
+                let (left, stream_id) = parse_u32(left);
+                let (_, rate) = parse_f64(left);
+                trace.stream_sample_rates.push((stream_id, rate));
+            }
+            "host_id" => trace.host_id = Some(parse_metadata_string(left, "host_id")?),
+            "app_name" => trace.app_name = Some(parse_metadata_string(left, "app_name")?),
+            "process_id" if left.len() < 4 => {
+                return Err(ParseError::MissingPacketData {
+                    got: left.len(),
+                    want: 4,
+                })
+            }
+            "process_id" => trace.process_id = Some(parse_u32(left).1),
+            "stream_name" if left.len() < 6 => {
+                return Err(ParseError::MissingPacketData {
+                    got: left.len(),
+                    want: 6,
+                })
+            }
+            "stream_name" => {
+                let (left, stream_id) = parse_u32(left);
+                let name = parse_metadata_string(left, "stream_name")?;
+                trace.stream_names.push((stream_id, name));
+            }
+            _ => return Err(ParseError::UnknownOption(option_name.to_owned())),
         }
+        // TODO: check that all bytes according to packet_size are processed.
+        trace.buf.drain(..packet_size);
+        Ok(())
     }
 
     fn parse_event_packet(&mut self) -> Result<Event, ParseError> {
@@ -457,6 +466,25 @@ fn parse_timestamp(bytes: &[u8], epoch: SystemTime) -> (&[u8], SystemTime) {
     (left, timestamp)
 }
 
+/// Parse a string value of the metadata option `field`.
+///
+/// # Panics
+///
+/// See [`parse_string`].
+fn parse_metadata_string(bytes: &[u8], field: &'static str) -> Result<String, ParseError> {
+    match parse_string(bytes) {
+        Ok((_, value)) => Ok(value.to_owned()),
+        Err(StringParseError::TooSmall) => Err(ParseError::StringTooSmall {
+            packet_kind: "metadata",
+            field,
+        }),
+        Err(StringParseError::InvalidUTF8) => Err(ParseError::InvalidString {
+            packet_kind: "metadata",
+            field,
+        }),
+    }
+}
+
 /// Parse a single string from `bytes`.
 ///
 /// # Panics
@@ -509,6 +537,7 @@ enum ValueParseError {
     UnknownType(u8),
 }
 
+/// Error returned by parsing a [`Trace`].
 #[derive(Debug)]
 pub enum ParseError {
     IO(io::Error),
@@ -567,18 +596,38 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// A single trace event.
 #[derive(Debug)]
 pub struct Event {
-    stream_id: u32,
-    #[allow(dead_code)] // Currently unused.
-    stream_counter: u32,
-    substream_id: u64,
-    start: SystemTime,
-    end: SystemTime,
-    description: String,
-    attributes: Vec<(String, Value)>,
+    /// Stream the event belongs to, e.g. a thread.
+    pub stream_id: u32,
+    /// Counter of the event within the stream.
+    pub stream_counter: u32,
+    /// Substream the event belongs to, e.g. an actor or future.
+    pub substream_id: u64,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub description: String,
+    pub attributes: Vec<(String, Value)>,
+}
+
+/// Name of the attribute used to link events into a flow, e.g. the sending and
+/// receiving of a message.
+pub const FLOW_ID_ATTRIBUTE: &str = "flow_id";
+
+impl Event {
+    /// Returns the flow id of the event, if any.
+    pub fn flow_id(&self) -> Option<u64> {
+        self.attributes
+            .iter()
+            .find_map(|(name, value)| match value {
+                Value::Unsigned(id) if name == FLOW_ID_ATTRIBUTE => Some(*id),
+                _ => None,
+            })
+    }
 }
 
+/// Value of an [`Event`] attribute.
 #[derive(Debug)]
 pub enum Value {
     Unsigned(u64),